@@ -28,6 +28,8 @@ fn get_code(e : &Error) -> ErrorCode {
         ErrorKind::RetrievalError(_err) => 15,
         ErrorKind::MissingValue(_err) => 16,
         ErrorKind::FileNotFoundError(_) => 17,
+        ErrorKind::RulesFileParseError(_err) => 18,
+        ErrorKind::DataFileError(_err) => 19,
     };
     ErrorCode::new(code)
 }
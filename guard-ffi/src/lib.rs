@@ -32,7 +32,7 @@ use errors::FfiError;
 #[no_mangle]
 pub extern "C" fn cfn_guard_run_checks<'a>(data: FfiValidateInput<'a>, rules: FfiValidateInput<'a>, verbose: c_char, err : &mut ExternError) -> *mut c_char {
     ffi_support::call_with_result(err, || {
-        match run_checks(data.into(), rules.into(), verbose == 1) {
+        match run_checks(data.into(), rules.into(), verbose == 1, &[], false) {
             Err(e) => Err(FfiError(e)),
             Ok(r) => Ok(r)
         }
@@ -19,6 +19,8 @@ pub struct CustomEvent {
     pub rules: Vec<String>,
     #[serde(rename = "verbose", default="default_as_true")] // for backward compatibility
     pub verbose: bool,
+    #[serde(rename = "type_coercion", default)]
+    pub type_coercion: bool,
 }
 
 #[derive(Serialize)]
@@ -46,7 +48,7 @@ pub async fn call_cfn_guard(e: CustomEvent, _c: Context) -> Result<CustomOutput,
         }, ValidateInput {
             content: &rule,
             file_name: "lambda-rule",
-        }, e.verbose) {
+        }, e.verbose, &[], e.type_coercion) {
             Ok(t) => t,
             Err(e) => (e.to_string()),
         };
@@ -18,6 +18,7 @@ mod tests {
                 RULE.parse().unwrap()
             ],
             verbose: false,
+            type_coercion: false,
         };
         println!("Request:\n{}", request);
 
@@ -38,6 +39,7 @@ mod tests {
                 RULE.parse().unwrap()
             ],
             verbose: true,
+            type_coercion: false,
         };
         println!("Request:\n{}", request);
 
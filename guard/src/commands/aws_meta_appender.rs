@@ -1,5 +1,5 @@
 use crate::rules::{EvaluationContext, Status, EvaluationType, Result};
-use crate::rules::path_value::{PathAwareValue, QueryResolver};
+use crate::rules::path_value::{EvaluationOptions, PathAwareValue, QueryResolver};
 use crate::rules::exprs::AccessQuery;
 use std::convert::TryFrom;
 use crate::rules::values::CmpOperator;
@@ -18,6 +18,14 @@ impl<'d> EvaluationContext for MetadataAppender<'d> {
         self.delegate.rule_status(rule_name)
     }
 
+    fn resource_count(&self, resource_type: &str) -> usize {
+        self.delegate.resource_count(resource_type)
+    }
+
+    fn options(&self) -> &EvaluationOptions {
+        self.delegate.options()
+    }
+
     fn end_evaluation(
         &self,
         eval_type: EvaluationType,
@@ -35,15 +43,13 @@ impl<'d> EvaluationContext for MetadataAppender<'d> {
                         if status == Status::FAIL {
                             if let Some(value) = &from {
                                 let path = value.self_path();
-                                if path.0.starts_with("/Resources") {
-                                    let parts = path.0.splitn(4, '/').collect::<Vec<&str>>();
-                                    if parts.len() == 4 {
-                                        let query = format!("Resources['{}'].Metadata[ keys == /^aws/ ]", parts[2]);
-                                        let AccessQuery { query: query, match_all: all } =
-                                            AccessQuery::try_from(query.as_str()).unwrap();
-                                        if let Ok(selected) = self.root_context.select(all, &query, self) {
-                                            break format!("{}\nMetadata: {:?}", msg, selected)
-                                        }
+                                let parts = path.components();
+                                if parts.len() > 2 && parts[0] == "Resources" {
+                                    let query = format!("Resources['{}'].Metadata[ keys == /^aws/ ]", parts[1]);
+                                    let AccessQuery { query, match_all: all } =
+                                        AccessQuery::try_from(query.as_str()).unwrap();
+                                    if let Ok(selected) = self.root_context.select(all, &query, self) {
+                                        break format!("{}\nMetadata: {:?}", msg, selected)
                                     }
                                 }
                             }
@@ -1,5 +1,6 @@
 use super::*;
 use super::super::common_test_helpers::DummyEval;
+use crate::rules::path_value::DEFAULT_EVALUATION_OPTIONS;
 
 #[test]
 fn append_cdk_metadata_test() -> Result<()> {
@@ -47,6 +48,14 @@ fn append_cdk_metadata_test() -> Result<()> {
             unimplemented!()
         }
 
+        fn resource_count(&self, _resource_type: &str) -> usize {
+            unimplemented!()
+        }
+
+        fn options(&self) -> &EvaluationOptions {
+            &DEFAULT_EVALUATION_OPTIONS
+        }
+
         fn end_evaluation(&self, eval_type: EvaluationType, context: &str, msg: String, from: Option<PathAwareValue>, to: Option<PathAwareValue>, status: Option<Status>, _cmp: Option<(CmpOperator, bool)>) {
             assert_ne!(msg.as_str(), "");
             assert_eq!(msg.starts_with("FIRST PART"), true);
@@ -1,6 +1,7 @@
 use crate::rules::{
     EvaluationContext, Result, Status, EvaluationType, path_value::PathAwareValue
 };
+use crate::rules::path_value::{EvaluationOptions, DEFAULT_EVALUATION_OPTIONS};
 use crate::rules::values::CmpOperator;
 
 pub(super) struct DummyEval{}
@@ -13,6 +14,14 @@ impl EvaluationContext for DummyEval {
         unimplemented!()
     }
 
+    fn resource_count(&self, _resource_type: &str) -> usize {
+        unimplemented!()
+    }
+
+    fn options(&self) -> &EvaluationOptions {
+        &DEFAULT_EVALUATION_OPTIONS
+    }
+
     fn end_evaluation(&self, _eval_type: EvaluationType, _context: &str, _msg: String, _from: Option<PathAwareValue>, _to: Option<PathAwareValue>, _status: Option<Status>, _cmp: Option<(CmpOperator, bool)>) {
     }
 
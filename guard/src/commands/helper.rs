@@ -2,16 +2,21 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::rules::errors::{Error, ErrorKind};
-use crate::rules::evaluate::RootScope;
-use crate::rules::path_value::PathAwareValue;
-use crate::commands::tracker::StackTracker;
-use crate::commands::validate::{ConsoleReporter, OutputFormatType, Reporter};
-use crate::rules::{Evaluate, Result};
+use crate::rules::path_value::{EvaluationOptions, PathAwareValue};
+use crate::commands::validate::{OutputFormatType, Reporter};
+use crate::rules::{Result, Status};
+use crate::commands::tracker::Failure;
+use crate::commands::validate::common::failures_from_record;
+use crate::rules::eval_context::EventRecord;
+use crate::rules::values::read_from;
+use crate::commands::validate::k8s_admission::{admission_request, admission_response, AdmissionResult, PREVIOUS_VARIABLE};
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::io::BufWriter;
 use crate::commands::validate::generic_summary::GenericSummary;
 use crate::rules::eval::eval_rules_file;
-use crate::rules::eval_context::root_scope;
+use crate::rules::eval_context::root_scope_with_defines;
+use crate::commands::validate::parse_defines;
 use crate::rules::path_value::traversal::Traversal;
 
 pub struct ValidateInput<'a> {
@@ -22,56 +27,121 @@ pub struct ValidateInput<'a> {
 pub fn validate_and_return_json(
     data: ValidateInput,
     rules: ValidateInput,
-    verbose: bool
+    verbose: bool,
+    defines: &[(&str, &str)],
+    type_coercion: bool
 ) -> Result<String> {
-    let input_data = match serde_json::from_str::<serde_json::Value>(&data.content) {
-       Ok(value) => PathAwareValue::try_from(value),
-       Err(e) => {
-           let value = serde_yaml::from_str::<serde_yaml::Value>(&data.content)?;
-           PathAwareValue::try_from(value)
-       }
-    };
+    let input_data = read_data(data.content);
+    let defines = parse_defines(defines)?;
+    evaluate(input_data, &rules, &defines, type_coercion, |status, root_record, root| {
+        if verbose {
+            return Ok(serde_json::to_string_pretty(root_record)?);
+        }
+
+        let mut write_output = BufWriter::new(Vec::new());
+        let traversal = Traversal::from(root);
+        let reporter = &GenericSummary::new() as &dyn Reporter;
+
+        reporter.report_eval(
+            &mut write_output,
+            status,
+            root_record,
+            false,
+            rules.file_name,
+            data.file_name,
+            data.content,
+            &traversal,
+            OutputFormatType::JSON
+        )?;
+
+        match String::from_utf8(write_output.buffer().to_vec()) {
+            Ok(val) => Ok(val),
+            Err(e) => Err(Error::new(ErrorKind::ParseError(e.to_string()))),
+        }
+    })
+}
 
-    let span = crate::rules::parser::Span::new_extra(&rules.content, rules.file_name);
+//
+// Same evaluation as validate_and_return_json, returning each failing clause as a flat
+// Failure instead of the evaluation tree
+//
+pub fn validate_and_return_failures(
+    data: ValidateInput,
+    rules: ValidateInput,
+    defines: &[(&str, &str)],
+    type_coercion: bool
+) -> Result<Vec<Failure>> {
+    let input_data = read_data(data.content);
+    let defines = parse_defines(defines)?;
+    evaluate(input_data, &rules, &defines, type_coercion, |_, root_record, _| failures_from_record(root_record))
+}
+
+//
+// Data is read with the loader used by validate, which accepts JSON and YAML and keeps the
+// line and column of each value for the reported failures
+//
+fn read_data(content: &str) -> Result<PathAwareValue> {
+    read_from(content).and_then(PathAwareValue::try_from)
+}
+
+//
+// Evaluates rules against request.object of a Kubernetes AdmissionReview, with
+// request.oldObject bound to %previous. The response allows the request unless a rule FAILs
+//
+pub fn validate_k8s_admission(
+    admission_review: &str,
+    rules: ValidateInput
+) -> Result<AdmissionResult> {
+    let review = PathAwareValue::try_from(read_from(admission_review)?)?;
+    let request = admission_request(&review, "AdmissionReview")?;
+    let variables = HashMap::from([(PREVIOUS_VARIABLE.to_string(), request.previous)]);
+    let uid = request.uid;
+    evaluate(Ok(request.object), &rules, &variables, false, |status, root_record, _| {
+        let failures = failures_from_record(root_record)?;
+        let response = admission_response(uid, status, &failures);
+        Ok(AdmissionResult { failures, response })
+    })
+}
+
+fn evaluate<T, F>(
+    input_data: Result<PathAwareValue>,
+    rules: &ValidateInput,
+    defines: &HashMap<String, PathAwareValue>,
+    type_coercion: bool,
+    report: F
+) -> Result<T>
+    where F: FnOnce(Status, &EventRecord<'_>, &PathAwareValue) -> Result<T>
+{
+    let span = crate::rules::parser::Span::new_extra(rules.content, rules.file_name);
 
-    let rules_file_name = rules.file_name;
     match crate::rules::parser::rules_file(span) {
 
         Ok(rules) => {
             match input_data {
                 Ok(root) => {
-                    let mut write_output = BufWriter::new(Vec::new());
-
-                    let traversal = Traversal::from(&root);
-                    let mut root_scope = root_scope(&rules, &root)?;
+                    let mut options = EvaluationOptions::new();
+                    options.type_coercion = type_coercion;
+                    let mut root_scope = root_scope_with_defines(&rules, &root, defines)?
+                        .with_options(options);
                     let status = eval_rules_file(&rules, &mut root_scope)?;
                     let root_record = root_scope.reset_recorder().extract();
-
-                    if verbose {
-                        return Ok(serde_json::to_string_pretty(&root_record)?);
-                    }
-
-                    let reporter = &GenericSummary::new() as &dyn Reporter;
-
-                    reporter.report_eval(
-                        &mut write_output,
-                        status,
-                        &root_record,
-                        rules_file_name,
-                        data.file_name,
-                        data.content,
-                        &traversal,
-                        OutputFormatType::JSON
-                    )?;
-
-                    match String::from_utf8(write_output.buffer().to_vec()) {
-                        Ok(val) => return Ok(val),
-                        Err(e) => return Err(Error::new(ErrorKind::ParseError(e.to_string()))),
-                    }
+                    report(status, &root_record, &root)
                 }
-                Err(e) => return Err(e),
+                Err(e) => Err(e),
             }
         }
-        Err(e) =>  return Err(Error::new(ErrorKind::ParseError(e.to_string()))),
+        Err(e) => Err(Error::new(ErrorKind::ParseError(e.to_string()))),
+    }
+}
+
+//
+// Checks that the rules parse, without needing any data to evaluate against. Errors carry
+// the line and column where parsing failed
+//
+pub fn parse_rules(rules: &str) -> std::result::Result<(), Vec<String>> {
+    let span = crate::rules::parser::Span::new_extra(rules, "");
+    match crate::rules::parser::rules_file(span) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(vec![e.to_string()]),
     }
 }
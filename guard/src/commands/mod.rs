@@ -6,10 +6,12 @@ pub(crate) mod helper;
 pub(crate) mod parse_tree;
 pub(crate) mod migrate;
 
-mod tracker;
+pub(crate) mod tracker;
 mod aws_meta_appender;
 mod common_test_helpers;
 
+use crate::rules::errors::{Error, ErrorKind};
+
 //
 // Constants
 //
@@ -23,18 +25,55 @@ pub(crate) const RULEGEN: &str = "rulegen";
 pub  const TEST: &str = "test";
 pub const VALIDATE: &str = "validate";
 // Arguments for validate
+pub(crate) const ALLOW_UNDEFINED_VARIABLES: &str = "allow-undefined-variables";
 pub(crate) const ALPHABETICAL: (&str, &str) = ("alphabetical", "a");
+pub(crate) const ASSERT_ALL_PASS: &str = "assert-all-pass";
 pub const DATA: (&str, &str) = ("data", "d");
+pub(crate) const CONDITION: &str = "condition";
+pub(crate) const BASELINE: &str = "baseline";
+pub(crate) const BASELINE_PATH: &str = "baseline-path";
+pub(crate) const CACHE_DIR: &str = "cache-dir";
+pub(crate) const CACHE_TTL: &str = "cache-ttl";
+pub(crate) const CASE_INSENSITIVE_KEYS: &str = "case-insensitive-keys";
+pub(crate) const COERCE_NUMERIC_STRINGS: &str = "coerce-numeric-strings";
+pub(crate) const DATA_FORMAT: &str = "data-format";
+pub(crate) const DEFINE: &str = "define";
+pub(crate) const EXCLUDE_TAGS: &str = "exclude-tags";
+pub(crate) const EXPLAIN: &str = "explain";
+pub(crate) const FAIL_FAST: &str = "fail-fast";
+pub(crate) const IGNORE_CF_EXTENSIONS: &str = "ignore-cf-extensions";
 pub(crate) const LAST_MODIFIED: (&str, &str) = ("last-modified", "m");
+pub(crate) const MAX_DEPTH: &str = "max-depth";
+pub(crate) const MAX_ERRORS: &str = "max-errors";
+pub(crate) const MERGE_RULES: &str = "merge-rules";
+pub(crate) const MISSING_AS: &str = "missing-as";
+pub(crate) const NO_COLOR: &str = "no-color";
+pub(crate) const NO_SUPPRESSIONS: &str = "no-suppressions";
+pub(crate) const OUTPUT_FILE: (&str, &str) = ("output-file", "O");
 pub(crate) const OUTPUT_FORMAT: (&str, &str) = ("output-format", "o");
 pub const INPUT_PARAMETERS: (&str, &str) = ("input-parameters", "i");
+pub(crate) const INCREMENTAL: &str = "incremental";
+pub(crate) const INPUT_TYPE: &str = "input-type";
+pub(crate) const PARAMETER: &str = "parameter";
+pub(crate) const PARAMETERS_FILE: &str = "parameters-file";
 pub(crate) const PAYLOAD: (&str, &str) = ("payload", "P");
 pub(crate) const PREVIOUS_ENGINE: (&str, &str) = ("previous-engine","E");
 pub(crate) const PRINT_JSON: (&str, &str) = ("print-json", "p");
+pub(crate) const REPORT_BY: &str = "report-by";
+pub(crate) const RESOLVE_CONDITIONS: &str = "resolve-conditions";
+pub(crate) const RESOLVE_PARAMETERS: &str = "resolve-parameters";
+pub(crate) const ROOT_PATH: &str = "root-path";
+pub(crate) const SCHEMA: &str = "schema";
 pub(crate) const SHOW_CLAUSE_FAILURES: (&str, &str) = ("show-clause-failures", "s");
 pub(crate) const SHOW_SUMMARY: (&str, &str) = ("show-summary", "S");
+pub(crate) const STRING_ORDER: &str = "string-order";
+pub(crate) const SUMMARY_ONLY: &str = "summary-only";
+pub(crate) const TAGS: &str = "tags";
 pub(crate) const TYPE: (&str, &str) = ("type", "t");
+pub(crate) const TYPE_COERCION: &str = "type-coercion";
 pub(crate) const VERBOSE: (&str, &str) = ("verbose", "v");
+pub(crate) const WATCH: &str = "watch";
+pub(crate) const WRITE_BASELINE: &str = "write-baseline";
 // Arguments for validate, migrate, parse tree
 pub const RULES: (&str, &str) = ("rules", "r");
 // Arguments for migrate, parse-tree, rulegen
@@ -61,3 +100,35 @@ pub(crate) const  DATA_FILE_SUPPORTED_EXTENSIONS: [&'static str; 5] = [".yaml",
                                                                       ".template"];
 pub(crate) const  RULE_FILE_SUPPORTED_EXTENSIONS: [&'static str; 2] = [".guard",
                                                                      ".ruleset"];
+
+// Exit codes for validate
+pub const SUCCESS_STATUS_CODE: i32 = 0;
+pub const DATA_FILE_ERROR_STATUS_CODE: i32 = 3;
+pub const FAILURE_STATUS_CODE: i32 = 5;
+pub const PARSING_ERROR_STATUS_CODE: i32 = 7;
+pub const UNKNOWN_ERROR_STATUS_CODE: i32 = -1;
+
+//
+// Maps an error returned from Command::execute to the process exit code. Data files that
+// could not be found, read or parsed map to DATA_FILE_ERROR_STATUS_CODE, rules files that
+// do not parse map to PARSING_ERROR_STATUS_CODE, everything else remains
+// UNKNOWN_ERROR_STATUS_CODE
+//
+pub fn exit_code_for_error(error: &Error) -> i32 {
+    match &error.0 {
+        ErrorKind::DataFileError(_) | ErrorKind::FileNotFoundError(_) => DATA_FILE_ERROR_STATUS_CODE,
+        ErrorKind::RulesFileParseError(_) => PARSING_ERROR_STATUS_CODE,
+        _ => UNKNOWN_ERROR_STATUS_CODE,
+    }
+}
+
+//
+// Colors are already left out when stdout is not a terminal. --no-color or a non empty NO_COLOR
+// environment variable (https://no-color.org) turn them off for the rest of the process
+//
+pub(crate) fn disable_colors(no_color: bool) {
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    if no_color || no_color_env {
+        colored::control::set_override(false);
+    }
+}
@@ -1,8 +1,10 @@
 use std::fs::{File};
 use clap::{App, Arg, ArgMatches};
 use crate::command::Command;
-use crate::commands::{OUTPUT, PARSE_TREE, PRINT_JSON, PRINT_YAML, RULES};
+use crate::commands::{ALLOW_UNDEFINED_VARIABLES, OUTPUT, PARSE_TREE, PRINT_JSON, PRINT_YAML, RULES};
 use crate::rules:: Result;
+use crate::rules::errors::{Error, ErrorKind};
+use crate::rules::references::{undefined_references, ReferenceKind};
 
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub(crate) struct ParseTree {}
@@ -29,6 +31,8 @@ impl Command for ParseTree {
                 .help("Print output in JSON format"))
             .arg(Arg::with_name(PRINT_YAML.0).long(PRINT_YAML.0).short(PRINT_YAML.1).required(false)
                 .help("Print output in YAML format"))
+            .arg(Arg::with_name(ALLOW_UNDEFINED_VARIABLES).long(ALLOW_UNDEFINED_VARIABLES).required(false)
+                .help("Report references to undeclared variables, e.g. ones provided with --define to validate, as warnings instead of errors"))
     }
 
     fn execute(&self, app: &ArgMatches<'_>) -> Result<i32> {
@@ -52,10 +56,23 @@ impl Command for ParseTree {
         match crate::rules::parser::rules_file(span) {
             Err(e) => {
                 println!("Parsing error handling rule, Error = {}", e);
-                return Err(e);
+                return Err(e.in_rules_file());
             },
 
             Ok(rules) => {
+                let allow_undefined_variables = app.is_present(ALLOW_UNDEFINED_VARIABLES);
+                let (warnings, errors): (Vec<_>, Vec<_>) = undefined_references(&rules, &[])
+                    .into_iter()
+                    .partition(|each| allow_undefined_variables && each.kind == ReferenceKind::Variable);
+                for each in &warnings {
+                    eprintln!("WARNING: {}", each);
+                }
+                if !errors.is_empty() {
+                    let errors = errors.iter().map(|each| format!("{}", each)).collect::<Vec<_>>();
+                    println!("Undefined references in rule, Error = {}", errors.join("; "));
+                    return Err(Error::new(ErrorKind::ParseError(errors.join("; "))));
+                }
+
                 if yaml {
                     serde_yaml::to_writer(out, &rules)?;
                 }
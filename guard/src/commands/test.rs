@@ -16,7 +16,7 @@ use crate::commands::files::{
 };
 use crate::commands::tracker::StackTracker;
 use crate::commands::{
-    validate, ALPHABETICAL, DIRECTORY, DIRECTORY_ONLY, LAST_MODIFIED, PREVIOUS_ENGINE,
+    disable_colors, validate, ALPHABETICAL, DIRECTORY, DIRECTORY_ONLY, LAST_MODIFIED, NO_COLOR, PREVIOUS_ENGINE,
     RULES_AND_TEST_FILE, RULES_FILE, TEST, TEST_DATA, VERBOSE,
 };
 use crate::rules::errors::{Error, ErrorKind};
@@ -75,10 +75,14 @@ or failure testing.
                 .help("Sort by last modified times within a directory"))
             .arg(Arg::with_name(VERBOSE.0).long(VERBOSE.0).short(VERBOSE.1).required(false)
                 .help("Verbose logging"))
+            .arg(Arg::with_name(NO_COLOR).long(NO_COLOR).required(false)
+                .help("Print plain output without colors. Colors are also left out when the NO_COLOR \
+                       environment variable is set or output is not a terminal"))
     }
 
     fn execute(&self, app: &ArgMatches<'_>) -> Result<i32> {
         let mut exit_code = 0;
+        disable_colors(app.is_present(NO_COLOR));
         let cmp = if let Some(_ignored) = app.value_of(ALPHABETICAL.0) {
             alpabetical
         } else if let Some(_ignored) = app.value_of(LAST_MODIFIED.0) {
@@ -434,7 +438,7 @@ fn test_with_data(
                                                 ));
                                         }
                                         if verbose {
-                                            validate::print_context(each, 1);
+                                            validate::print_context(&mut std::io::stdout(), each, 1)?;
                                         }
                                     }
                                 },
@@ -1,18 +1,85 @@
 use crate::rules::{EvaluationContext, Result, Status, EvaluationType, path_value::PathAwareValue};
 use nom::lib::std::fmt::Formatter;
-use serde::{Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use crate::rules::values::CmpOperator;
+use std::convert::{TryFrom, TryInto};
+use crate::rules::path_value::{EvaluationOptions, Path};
+use crate::commands::validate::suppressions::{take_failures_from_context, Excluded, TakeFailure};
 
-#[derive(Serialize, Debug)]
+///
+/// A failing clause as a flat record, for embedders that do not want to walk the evaluation
+/// tree. `from` is the value the clause was checked against and `to` the expected value, both
+/// as JSON. `location` is the (line, column) of `from` in the data file
+///
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub rule: String,
+    pub path: String,
+    pub from: Option<serde_json::Value>,
+    pub to: Option<serde_json::Value>,
+    pub message: Option<String>,
+    pub location: Option<(usize, usize)>,
+}
+
+impl Failure {
+    pub(crate) fn new(rule: &str, from: Option<&PathAwareValue>, to: Option<&PathAwareValue>, message: Option<String>) -> Result<Failure> {
+        let (path, location, from) = match from {
+            Some(value) => {
+                let (path, json): (String, serde_json::Value) = value.try_into()?;
+                let location = &value.self_path().1;
+                (path, Some((location.line, location.col)), Some(json))
+            },
+            None => (String::new(), None, None)
+        };
+        let to = match to {
+            Some(value) => {
+                let (_, json): (String, serde_json::Value) = value.try_into()?;
+                Some(json)
+            },
+            None => None
+        };
+        Ok(Failure { rule: rule.to_string(), path, from, to, message, location })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 pub(crate) struct StatusContext {
     pub(crate) eval_type: EvaluationType,
     pub(crate) context: String,
     pub(crate) msg: Option<String>,
+    #[serde(deserialize_with = "serialized_value")]
     pub(crate) from: Option<PathAwareValue>,
+    #[serde(deserialize_with = "serialized_value")]
     pub(crate) to: Option<PathAwareValue>,
     pub(crate) status: Option<Status>,
     pub(crate) comparator: Option<(CmpOperator, bool)>,
     pub(crate) children: Vec<StatusContext>,
+    //
+    // Status of each branch evaluated for an or (disjunction) conjunction. Evaluation stops
+    // at the first branch that passes, so branches after it are not present
+    //
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) branches: Vec<Status>,
+}
+
+//
+// Reads a value back in the shape PathAwareValue serializes to, its path and the value as JSON.
+// The location in the data file is not part of it and is left at the default
+//
+fn serialized_value<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<PathAwareValue>, D::Error> {
+    #[derive(Deserialize)]
+    struct Serialized {
+        path: String,
+        value: serde_json::Value,
+    }
+
+    match Option::<Serialized>::deserialize(deserializer)? {
+        Some(Serialized { path, value }) => {
+            let path = Path::try_from(path.as_str()).map_err(serde::de::Error::custom)?;
+            PathAwareValue::try_from((&value, path)).map(Some).map_err(serde::de::Error::custom)
+        },
+        None => Ok(None)
+    }
 }
 
 impl StatusContext {
@@ -25,7 +92,8 @@ impl StatusContext {
             from: None,
             to: None,
             comparator: None,
-            children: vec![]
+            children: vec![],
+            branches: vec![],
         }
     }
 }
@@ -52,6 +120,77 @@ impl<'r> StackTracker<'r> {
     pub(super) fn stack(self) -> Vec<StatusContext> {
         self.stack.into_inner()
     }
+
+    pub(super) fn tree(&self) -> std::cell::Ref<'_, Vec<StatusContext>> {
+        self.stack.borrow()
+    }
+
+    pub(super) fn tree_mut(&self) -> std::cell::RefMut<'_, Vec<StatusContext>> {
+        self.stack.borrow_mut()
+    }
+
+    //
+    // Takes the tree of an earlier evaluation in place of evaluating again
+    //
+    pub(super) fn restore(&self, tree: Vec<StatusContext>) {
+        *self.stack.borrow_mut() = tree;
+    }
+
+    pub(super) fn has_failed(&self) -> bool {
+        self.stack.borrow().first().is_some_and(|top| {
+            top.status == Some(Status::FAIL) ||
+                top.children.iter().any(|each| each.status == Some(Status::FAIL))
+        })
+    }
+
+    //
+    // Depth first, clauses that FAIL with no failing clause beneath them. Filters and when
+    // conditions are not failures of the rule and are not descended into
+    //
+    pub(super) fn failures(&self) -> Result<Vec<Failure>> {
+        let mut failures = Vec::new();
+        if let Some(top) = self.stack.borrow().first() {
+            for rule in &top.children {
+                if rule.status == Some(Status::FAIL) {
+                    collect_failures(&rule.context, rule, &mut failures)?;
+                }
+            }
+        }
+        Ok(failures)
+    }
+
+    pub(super) fn take_failures(&self, take: &mut TakeFailure<'_>) -> Result<Excluded> {
+        match self.stack.borrow_mut().first_mut() {
+            Some(top) => take_failures_from_context(top, take),
+            None => Ok(Excluded::default()),
+        }
+    }
+
+    pub(super) fn has_skipped(&self) -> bool {
+        self.stack.borrow().first().is_some_and(|top| {
+            top.children.iter().any(|each| each.status == Some(Status::SKIP))
+        })
+    }
+}
+
+fn collect_failures(rule: &str, current: &StatusContext, failures: &mut Vec<Failure>) -> Result<()> {
+    let mut leaf = true;
+    for child in &current.children {
+        if child.status != Some(Status::FAIL) {
+            continue;
+        }
+        match child.eval_type {
+            EvaluationType::Filter | EvaluationType::Condition => continue,
+            _ => {
+                leaf = false;
+                collect_failures(rule, child, failures)?;
+            }
+        }
+    }
+    if leaf {
+        failures.push(Failure::new(rule, current.from.as_ref(), current.to.as_ref(), current.msg.clone())?);
+    }
+    Ok(())
 }
 
 impl<'r> EvaluationContext for StackTracker<'r> {
@@ -63,6 +202,14 @@ impl<'r> EvaluationContext for StackTracker<'r> {
         self.root_context.rule_status(rule_name)
     }
 
+    fn resource_count(&self, resource_type: &str) -> usize {
+        self.root_context.resource_count(resource_type)
+    }
+
+    fn options(&self) -> &EvaluationOptions {
+        self.root_context.options()
+    }
+
     fn end_evaluation(&self,
                       eval_type: EvaluationType,
                       context: &str,
@@ -89,6 +236,11 @@ impl<'r> EvaluationContext for StackTracker<'r> {
         let stack = self.stack.borrow_mut().pop();
         match stack {
             Some(mut stack) => {
+                if eval_type == EvaluationType::Conjunction && stack.children.len() > 1 {
+                    stack.branches = stack.children.iter()
+                        .map(|each| each.status.unwrap_or(Status::SKIP))
+                        .collect();
+                }
                 stack.status = status.clone();
                 stack.from = from.clone();
                 stack.to = to.clone();
@@ -118,3 +270,7 @@ impl<'r> EvaluationContext for StackTracker<'r> {
 
 }
 
+#[cfg(test)]
+#[path = "tracker_tests.rs"]
+mod tracker_tests;
+
@@ -0,0 +1,41 @@
+use super::*;
+use crate::rules::evaluate::RootScope;
+use crate::rules::exprs::RulesFile;
+use crate::rules::Evaluate;
+use std::convert::TryFrom;
+
+#[test]
+fn test_failures_flattens_failing_clauses() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule api_methods {
+        Resources.*[ Type == 'AWS::ApiGateway::Method' ] {
+            Properties.AuthorizationType == "NONE"
+            Properties.HttpMethod == "GET"
+        }
+    }
+    rule no_buckets {
+        Resources.*[ Type == 'AWS::S3::Bucket' ] empty
+    }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "method": {
+                "Type": "AWS::ApiGateway::Method",
+                "Properties": { "AuthorizationType": "AWS_IAM", "HttpMethod": "GET" }
+            }
+        }
+    }))?;
+    let root = RootScope::new(&rules, &value)?;
+    let tracker = StackTracker::new(&root);
+    assert_eq!(rules.evaluate(&value, &tracker)?, Status::FAIL);
+
+    let failures = tracker.failures()?;
+    assert_eq!(failures.len(), 1);
+    let failure = &failures[0];
+    assert_eq!(failure.rule, "api_methods");
+    assert_eq!(failure.path, "/Resources/method/Properties/AuthorizationType");
+    assert_eq!(failure.from, Some(serde_json::json!("AWS_IAM")));
+    assert_eq!(failure.to, Some(serde_json::json!("NONE")));
+    assert!(failure.message.is_some());
+    Ok(())
+}
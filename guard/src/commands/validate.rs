@@ -1,4 +1,5 @@
 use std::cmp;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt::Debug;
 use std::fs::File;
@@ -9,7 +10,7 @@ use std::str::FromStr;
 use clap::{App, Arg, ArgGroup, ArgMatches};
 use colored::*;
 use enumflags2::BitFlags;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use Type::CFNTemplate;
 
@@ -19,34 +20,53 @@ use crate::commands::files::{alpabetical, iterate_over, last_modified};
 use crate::commands::tracker::{StackTracker, StatusContext};
 use crate::commands::validate::summary_table::SummaryType;
 use crate::commands::validate::tf::TfAware;
+use crate::commands::validate::cdk_assembly::stack_templates;
+use crate::commands::validate::k8s_admission::{admission_request, PREVIOUS_VARIABLE};
+use crate::commands::validate::tf_plan::adapt_tf_plan;
 use crate::commands::{
-    ALPHABETICAL, DATA, DATA_FILE_SUPPORTED_EXTENSIONS, INPUT_PARAMETERS, LAST_MODIFIED,
-    OUTPUT_FORMAT, PAYLOAD, PREVIOUS_ENGINE, PRINT_JSON, REQUIRED_FLAGS, RULES,
-    RULE_FILE_SUPPORTED_EXTENSIONS, SHOW_CLAUSE_FAILURES, SHOW_SUMMARY, TYPE, VALIDATE, VERBOSE,
+    disable_colors, ALLOW_UNDEFINED_VARIABLES, ALPHABETICAL, ASSERT_ALL_PASS, BASELINE, BASELINE_PATH, CACHE_DIR, CACHE_TTL, CASE_INSENSITIVE_KEYS, COERCE_NUMERIC_STRINGS, CONDITION, DATA, DATA_FILE_SUPPORTED_EXTENSIONS, DATA_FORMAT, DEFINE, EXCLUDE_TAGS, FAILURE_STATUS_CODE, EXPLAIN, FAIL_FAST, IGNORE_CF_EXTENSIONS, INCREMENTAL, INPUT_PARAMETERS, INPUT_TYPE, LAST_MODIFIED, MERGE_RULES, MISSING_AS, NO_COLOR, NO_SUPPRESSIONS,
+    MAX_DEPTH, MAX_ERRORS, OUTPUT_FILE, OUTPUT_FORMAT, PARAMETER, PARAMETERS_FILE, PARSING_ERROR_STATUS_CODE, PAYLOAD, PREVIOUS_ENGINE, PRINT_JSON, REPORT_BY, REQUIRED_FLAGS, RESOLVE_CONDITIONS, RESOLVE_PARAMETERS, ROOT_PATH, RULES,
+    RULE_FILE_SUPPORTED_EXTENSIONS, SCHEMA, SHOW_CLAUSE_FAILURES, SHOW_SUMMARY, STRING_ORDER, SUCCESS_STATUS_CODE, SUMMARY_ONLY, TAGS, TYPE, TYPE_COERCION, VALIDATE, VERBOSE, WATCH, WRITE_BASELINE,
 };
+use crate::rules::display::ValueOnlyDisplay;
 use crate::rules::errors::{Error, ErrorKind};
 use crate::rules::eval::eval_rules_file;
-use crate::rules::eval_context::{root_scope, simplifed_json_from_root, EventRecord};
+use crate::rules::eval_context::{
+    root_scope, root_scope_with_defines, simplifed_json_from_root, EventRecord,
+};
 use crate::rules::evaluate::RootScope;
-use crate::rules::exprs::RulesFile;
+use crate::rules::exprs::{LetValue, Rule, RulesFile};
 use crate::rules::path_value::traversal::Traversal;
-use crate::rules::path_value::PathAwareValue;
-use crate::rules::values::CmpOperator;
-use crate::rules::{Evaluate, EvaluationContext, EvaluationType, Result, Status};
+use crate::rules::intrinsics::{resolve_conditions, resolve_parameters};
+use crate::rules::path_value::{MissingAs, EvaluationOptions, PathAwareValue, StringOrder, DEFAULT_MAX_DEPTH};
+use crate::rules::references::{invalid_regexes, select_rules, undefined_references, InvalidRegex, ReferenceKind, UndefinedReference};
+use crate::rules::values::{CmpOperator, Value};
+use crate::rules::{EvalContext, Evaluate, EvaluationContext, EvaluationType, NamedStatus, RecordType, Result, Status};
 
 mod cfn;
 mod cfn_reporter;
-mod common;
+pub(crate) mod common;
 mod console_reporter;
 pub(crate) mod generic_summary;
 mod summary_table;
 mod tf;
+mod tf_plan;
+mod cdk_assembly;
+pub(crate) mod k8s_admission;
+pub(crate) mod baseline;
+pub(crate) mod cache;
+pub(crate) mod suppressions;
+pub(crate) mod watch;
 
 #[derive(Eq, Clone, Debug, PartialEq)]
 pub(crate) struct DataFile {
     content: String,
     path_value: PathAwareValue,
     name: String,
+    //
+    // Variables bound for this data file only, e.g. %previous for Kubernetes admission reviews
+    //
+    variables: HashMap<String, PathAwareValue>,
 }
 
 #[derive(Copy, Eq, Clone, Debug, PartialEq)]
@@ -55,6 +75,14 @@ pub(crate) enum Type {
     Generic,
 }
 
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Eq, Clone, Debug, PartialEq)]
+pub(crate) enum DataFormat {
+    Auto,
+    JSON,
+    YAML,
+}
+
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Copy, Eq, Clone, Debug, PartialEq)]
 pub(crate) enum OutputFormatType {
@@ -70,6 +98,7 @@ pub(crate) trait Reporter: Debug {
         writer: &mut dyn Write,
         status: Option<Status>,
         failed_rules: &[&StatusContext],
+        truncated: bool,
         passed_or_skipped: &[&StatusContext],
         longest_rule_name: usize,
         rules_file: &str,
@@ -83,6 +112,7 @@ pub(crate) trait Reporter: Debug {
         _write: &mut dyn Write,
         _status: Status,
         _root_record: &EventRecord<'value>,
+        _truncated: bool,
         _rules_file: &str,
         _data_file: &str,
         _data_file_bytes: &str,
@@ -125,6 +155,8 @@ single file and it would work as well.
 Note - When pointing the command to a directory, the directory may not contain a mix of
 rules and data files. The directory being pointed to must contain only data files,
 or rules files.
+Exit codes - 0 when all rules PASS or SKIP, 5 when any rule FAILs (or SKIPs with
+--assert-all-pass), 7 when a rules file fails to parse and 3 when a data file cannot be found or read.
 "#)
             .arg(Arg::with_name(RULES.0).long(RULES.0).short(RULES.1).takes_value(true)
                 .help("Provide a rules file or a directory of rules files. Supports passing multiple values by using this option repeatedly.\
@@ -138,30 +170,182 @@ or rules files.
                 .multiple(true).conflicts_with("payload"))
             .arg(Arg::with_name(INPUT_PARAMETERS.0).long(INPUT_PARAMETERS.0).short(INPUT_PARAMETERS.1).takes_value(true)
                      .help("Provide a data file or directory of data files in JSON or YAML that specifies any additional parameters to use along with data files to be used as a combined context. \
-                           All the parameter files passed as input get merged and this combined context is again merged with each file passed as an argument for `data`. Due to this, every file is \
-                           expected to contain mutually exclusive properties, without any overlap. Supports passing multiple values by using this option repeatedly.\
+                           All the parameter files passed as input get merged, with later files winning on conflicting keys and maps under the same key merged. Files in a directory are merged in file name order, or by last modified time with --last-modified, and this combined context is again merged with each file passed as an argument for `data`. \
+                           Every top-level key is also available inside rules as a variable, e.g. %allowed_regions, unless the rules file assigns it with `let`. Supports passing multiple values by using this option repeatedly.\
                           \nExample:\n --input-parameters param1.yaml --input-parameters ./param-dir1 --input-parameters param2.yaml\
                           \nFor directory arguments such as `param-dir1` above, scanning is only supported for files with following extensions: .yaml, .yml, .json, .jsn, .template")
                      .multiple(true))
             .arg(Arg::with_name(TYPE.0).long(TYPE.0).short(TYPE.1).takes_value(true).possible_values(&["CFNTemplate"])
                 .help("Specify the type of data file used for improved messaging"))
+            .arg(Arg::with_name(INPUT_TYPE).long(INPUT_TYPE).takes_value(true).possible_values(&["tf-plan", "cdk-assembly", "k8s-admission"])
+                .help("Adapt data files before evaluation. tf-plan reads the output of `terraform show -json plan.out` \
+                       and evaluates each resource's planned attributes keyed by resource type, \
+                       e.g. aws_s3_bucket { server_side_encryption_configuration EXISTS }. Keys looked up on lists apply to each element, \
+                       so nested blocks can be queried without [*]. Resources being deleted are skipped. tf-plan can not be used with \
+                       --resolve-conditions, --resolve-parameters or --previous-engine. \
+                       cdk-assembly expects each --data directory to be a CDK cloud assembly (cdk.out) and evaluates every stack \
+                       template listed in its manifest.json, including nested stacks and stages, reporting results by stack name. \
+                       k8s-admission reads a Kubernetes AdmissionReview and evaluates request.object, request.oldObject is \
+                       available as %previous, an empty list when there is none"))
+            .arg(Arg::with_name(DATA_FORMAT).long(DATA_FORMAT).takes_value(true)
+                .possible_values(&["json", "yaml", "auto"])
+                .default_value("auto")
+                .help("Specify the format of data files. auto (default) accepts both JSON and YAML, \
+                       json or yaml force that parser and fail with its error instead of falling back"))
             .arg(Arg::with_name(OUTPUT_FORMAT.0).long(OUTPUT_FORMAT.0).short(OUTPUT_FORMAT.1).takes_value(true)
                 .possible_values(&["json","yaml","single-line-summary"])
                 .default_value("single-line-summary")
                 .help("Specify the format in which the output should be displayed"))
+            .arg(Arg::with_name(OUTPUT_FILE.0).long(OUTPUT_FILE.0).short(OUTPUT_FILE.1).takes_value(true)
+                .help("Write the report in the selected --output-format to this file instead of stdout. Parent directories \
+                       are created and an existing file is overwritten. Progress messages, e.g. rules files that could not be \
+                       parsed, are printed to stderr"))
             .arg(Arg::with_name(PREVIOUS_ENGINE.0).long(PREVIOUS_ENGINE.0).short(PREVIOUS_ENGINE.1).takes_value(false)
                 .help("Uses the old engine for evaluation. This parameter will allow customers to evaluate old changes before migrating"))
             .arg(Arg::with_name(SHOW_SUMMARY.0).long(SHOW_SUMMARY.0).short(SHOW_SUMMARY.1).takes_value(true).use_delimiter(true).multiple(true)
                 .possible_values(&["none", "all", "pass", "fail", "skip"])
                 .default_value("fail")
                 .help("Controls if the summary table needs to be displayed. --show-summary fail (default) or --show-summary pass,fail (only show rules that did pass/fail) or --show-summary none (to turn it off) or --show-summary all (to show all the rules that pass, fail or skip)"))
+            .arg(Arg::with_name(SUMMARY_ONLY).long(SUMMARY_ONLY).takes_value(false).required(false)
+                .conflicts_with_all(&[VERBOSE.0, SHOW_CLAUSE_FAILURES.0, EXPLAIN])
+                .help("Only print the PASS/FAIL/SKIP status of each rule, with the number of failing resources for failed rules. \
+                       Clause level output is suppressed"))
+            .arg(Arg::with_name(REPORT_BY).long(REPORT_BY).takes_value(true)
+                .visible_alias("group-by")
+                .possible_values(&["rule", "resource", "tag", "type"])
+                .conflicts_with_all(&[SUMMARY_ONLY, SHOW_CLAUSE_FAILURES.0, EXPLAIN])
+                .help("Group failures by rule (default), or by resource to list the failing rules and clauses for each \
+                       logical id under Resources. Failures outside of Resources are listed under document. \
+                       By tag lists the status of rules under each tag declared with @tags, rules without tags under untagged. \
+                       By type lists failures under the Type of each resource, then by logical id, e.g. --group-by type"))
+            .arg(Arg::with_name(TAGS).long(TAGS).takes_value(true).use_delimiter(true).multiple(true)
+                .help("Only evaluate rules with one of these tags, declared with rule name @tags(\"tag\", ...) \
+                       or a # @tags: tag, ... comment before the rule. Join tags with + to select rules that have \
+                       all of them, e.g. security+cost. Rules that a selected rule depends on are evaluated as well"))
+            .arg(Arg::with_name(EXCLUDE_TAGS).long(EXCLUDE_TAGS).takes_value(true).use_delimiter(true).multiple(true)
+                .help("Do not evaluate rules with any of these tags, unless a selected rule depends on them"))
+            .arg(Arg::with_name(NO_SUPPRESSIONS).long(NO_SUPPRESSIONS).takes_value(false).required(false)
+                .help("Ignore rules suppressed for a resource with Metadata.cfn_guard.suppressed_rules in the template, \
+                       for audit runs. Without it failures of a suppressed rule on that resource are reported as \
+                       suppressed and do not fail validation"))
+            .arg(Arg::with_name(BASELINE).long(BASELINE).takes_value(true).required(false)
+                .help("Baseline file of known failures. Failures in the baseline are reported as known and do not fail \
+                       validation, only new failures do. Baseline findings that no longer fail are reported as stale"))
+            .arg(Arg::with_name(WRITE_BASELINE).long(WRITE_BASELINE).takes_value(false).required(false)
+                .requires(BASELINE)
+                .help("Write every failure of this run to the --baseline file instead of comparing against it"))
+            .arg(Arg::with_name(BASELINE_PATH).long(BASELINE_PATH).takes_value(true)
+                .possible_values(&["relative", "name", "none"])
+                .default_value("relative")
+                .help("How rules and data files are recorded in baseline findings, relative (default) to the --rules and \
+                       --data directories, by file name only or not at all, to match a finding in any file. Applies with --baseline"))
+            .arg(Arg::with_name(INCREMENTAL).long(INCREMENTAL).takes_value(false).required(false)
+                .requires_all(&[CACHE_DIR, PREVIOUS_ENGINE.0])
+                .help("Keep evaluation results in --cache-dir and report a rules file and data file that have not changed \
+                       since an earlier run from there instead of evaluating them again. Requires --previous-engine"))
+            .arg(Arg::with_name(CACHE_DIR).long(CACHE_DIR).takes_value(true)
+                .requires(INCREMENTAL)
+                .help("Directory for results kept with --incremental, created if it does not exist"))
+            .arg(Arg::with_name(CACHE_TTL).long(CACHE_TTL).takes_value(true)
+                .requires(INCREMENTAL)
+                .validator(|v| v.parse::<u64>().map(|_| ()).map_err(|_| format!("{} is not a valid number of seconds", v)))
+                .help("Seconds that a result kept with --incremental is used for before it is evaluated again. Defaults to 86400"))
+            .arg(Arg::with_name(ROOT_PATH).long(ROOT_PATH).takes_value(true)
+                .validator(|v| if v.is_empty() || v.starts_with('/') { Ok(()) } else { Err(format!("{} does not start with /", v)) })
+                .help("Evaluate rules against the value at this path in each data file instead of the whole file, \
+                       e.g. /Resources. Keys are separated by /, a list element is selected by its index, \
+                       ~1 is a / and ~0 a ~ inside a key"))
+            .arg(Arg::with_name(WATCH).long(WATCH).takes_value(false).required(false)
+                .requires_all(&[RULES.0, DATA.0])
+                .help("Keep running, validate again each time a file under --rules or --data is created, modified \
+                       or removed. Press Ctrl-C to stop"))
+            .arg(Arg::with_name(ASSERT_ALL_PASS).long(ASSERT_ALL_PASS).takes_value(false).required(false)
+                .help("Treat rules that SKIP as failures, every rule must PASS for validate to succeed. \
+                       This is strict, a template that legitimately has no resources of the type a rule targets will fail"))
             .arg(Arg::with_name(SHOW_CLAUSE_FAILURES.0).long(SHOW_CLAUSE_FAILURES.0).short(SHOW_CLAUSE_FAILURES.1).takes_value(false).required(false)
                 .help("Show clause failure along with summary"))
+            .arg(Arg::with_name(EXPLAIN).long(EXPLAIN).takes_value(false).required(false)
+                .requires(PREVIOUS_ENGINE.0)
+                .help("For each failing clause, show the data path, the operator, the full value the query resolved to \
+                       and the expected value. Requires --previous-engine"))
             .arg(Arg::with_name(ALPHABETICAL.0).long(ALPHABETICAL.0).short(ALPHABETICAL.1).required(false).help("Validate files in a directory ordered alphabetically"))
             .arg(Arg::with_name(LAST_MODIFIED.0).long(LAST_MODIFIED.0).short(LAST_MODIFIED.1).required(false).conflicts_with(ALPHABETICAL.0)
                 .help("Validate files in a directory ordered by last modified times"))
             .arg(Arg::with_name(VERBOSE.0).long(VERBOSE.0).short(VERBOSE.1).required(false)
                 .help("Verbose logging"))
+            .arg(Arg::with_name(NO_COLOR).long(NO_COLOR).required(false)
+                .help("Print plain output without colors. Colors are also left out when the NO_COLOR \
+                       environment variable is set, output is not a terminal or the output format is json or yaml"))
+            .arg(Arg::with_name(DEFINE).long(DEFINE).takes_value(true).multiple(true).number_of_values(1)
+                .help("Define a variable available to all rules as %name. Supports passing multiple values by using this option repeatedly. \
+                       Values are parsed like rule literals, so lists and maps are supported. A define overrides a file level let of the same name.\
+                       \nExample:\n --define env=\"prod\" --define allowed_amis='[\"ami-123\", \"ami-456\"]'"))
+            .arg(Arg::with_name(RESOLVE_PARAMETERS).long(RESOLVE_PARAMETERS).required(false)
+                .help("Replace Ref to CloudFormation template parameters with the value given with --parameter, or else the \
+                       parameter's Default. Failures on substituted values name the parameter. Refs to resources are left as is. \
+                       Fn::FindInMap is replaced with the value from the Mappings section once its keys are known"))
+            .arg(Arg::with_name(PARAMETER).long(PARAMETER).takes_value(true).multiple(true).number_of_values(1)
+                .requires(RESOLVE_PARAMETERS)
+                .help("Set the value of a template parameter used with --resolve-parameters. Supports setting multiple parameters \
+                       by using this option repeatedly. Lists are comma separated.\nExample:\n --parameter Env=prod --parameter Subnets=subnet-1,subnet-2"))
+            .arg(Arg::with_name(PARAMETERS_FILE).long(PARAMETERS_FILE).takes_value(true)
+                .requires(RESOLVE_PARAMETERS)
+                .help("Read template parameter values used with --resolve-parameters from a JSON or YAML file, either a map of \
+                       parameter names to values or the list of ParameterKey and ParameterValue pairs used by the AWS CLI. \
+                       Values given with --parameter take precedence.\nExample:\n --parameters-file params/prod.json"))
+            .arg(Arg::with_name(RESOLVE_CONDITIONS).long(RESOLVE_CONDITIONS).required(false)
+                .help("Evaluate the Conditions section of CloudFormation templates and replace Fn::If with the selected value. \
+                       Resources whose Condition is false are removed. When a condition cannot be determined, for instance \
+                       because it depends on a parameter, the template is validated once for each outcome"))
+            .arg(Arg::with_name(CONDITION).long(CONDITION).takes_value(true).multiple(true).number_of_values(1)
+                .requires(RESOLVE_CONDITIONS)
+                .help("Set the value of a template condition used with --resolve-conditions. Supports setting multiple conditions \
+                       by using this option repeatedly.\nExample:\n --condition IsProd=true --condition UseKms=false"))
+            .arg(Arg::with_name(MAX_ERRORS).long(MAX_ERRORS).takes_value(true)
+                .validator(|v| v.parse::<usize>().map(|_| ()).map_err(|_| format!("{} is not a valid number", v)))
+                .default_value("0")
+                .help("Maximum number of clause failures to report per rules file. All rules are still evaluated. 0 (default) reports all failures"))
+            .arg(Arg::with_name(MAX_DEPTH).long(MAX_DEPTH).takes_value(true)
+                .validator(|v| v.parse::<usize>().map(|_| ()).map_err(|_| format!("{} is not a valid number", v)))
+                .default_value("64")
+                .help("Maximum depth of nested values that wildcard queries like Resources.*.Properties[*] descend into. \
+                       Data nested deeper than this is reported as an error. Defaults to 64"))
+            .arg(Arg::with_name(MISSING_AS).long(MISSING_AS).takes_value(true)
+                .possible_values(&["skip", "fail"])
+                .default_value("skip")
+                .help("How EMPTY treats a query that does not resolve because a key is missing. skip (default) reports \
+                       the clause as SKIP, fail reports it as FAIL. A key that is present with an empty list or struct \
+                       is always EMPTY, and NOT EMPTY on a missing key always FAILs"))
+            .arg(Arg::with_name(STRING_ORDER).long(STRING_ORDER).takes_value(true)
+                .possible_values(&["lexical", "semver"])
+                .default_value("lexical")
+                .help("How <, >, <= and >= order strings. lexical (default) compares them character by character. \
+                       semver compares strings that are both semantic versions like 1.10.0 by version precedence, \
+                       and any other strings lexically"))
+            .arg(Arg::with_name(MERGE_RULES).long(MERGE_RULES).required(false)
+                .help("Evaluate all rules files as one rule set, so that rules can refer to rules defined in other files. \
+                       Repeated let assignments must have the same value and rule names must be unique across files"))
+            .arg(Arg::with_name(FAIL_FAST).long(FAIL_FAST).required(false)
+                .help("Stop evaluating further data and rules files as soon as a rule evaluates to FAIL"))
+            .arg(Arg::with_name(IGNORE_CF_EXTENSIONS).long(IGNORE_CF_EXTENSIONS).required(false)
+                .help("Skip resources whose Type starts with Custom:: or is AWS::CloudFormation::Stack or AWS::CloudFormation::StackSet"))
+            .arg(Arg::with_name(ALLOW_UNDEFINED_VARIABLES).long(ALLOW_UNDEFINED_VARIABLES).required(false)
+                .help("Report references to variables that are not declared in the rules file, --define or input parameters as \
+                       warnings instead of errors. References to undefined rules are always errors"))
+            .arg(Arg::with_name(TYPE_COERCION).long(TYPE_COERCION).required(false)
+                .help("Compare string values \"true\"/\"false\" with booleans and numeric strings with numbers, \
+                       instead of treating them as not comparable. Off by default"))
+            .arg(Arg::with_name(COERCE_NUMERIC_STRINGS).long(COERCE_NUMERIC_STRINGS).required(false)
+                .help("Compare numeric strings like \"443\" with numbers for <, <=, > and >=, instead of treating them as \
+                       not comparable. Equality and other types are unaffected. Off by default"))
+            .arg(Arg::with_name(CASE_INSENSITIVE_KEYS).long(CASE_INSENSITIVE_KEYS).required(false)
+                .help("Match property names in rules with keys in the data ignoring case, when no key matches exactly. \
+                       Off by default, CloudFormation templates are case sensitive"))
+            .arg(Arg::with_name(SCHEMA).long(SCHEMA).required(false).requires(RULES.0)
+                .help("Check the rules files themselves instead of validating data. After parsing, reports rule clauses \
+                       that refer to rules not in the file, %variables that are never assigned and regular expressions \
+                       that do not compile, with their line and column. Prints JSON with --output-format json. \
+                       Exits with 7 when any file fails to parse or has errors"))
             .arg(Arg::with_name(PRINT_JSON.0).long(PRINT_JSON.0).short(PRINT_JSON.1).required(false)
                 .help("Print output in json format"))
             .arg(Arg::with_name(PAYLOAD.0).long(PAYLOAD.0).short(PAYLOAD.1)
@@ -173,12 +357,65 @@ or rules files.
     }
 
     fn execute(&self, app: &ArgMatches<'_>) -> Result<i32> {
+        if app.is_present(WATCH) {
+            let paths = app
+                .values_of(RULES.0)
+                .into_iter()
+                .flatten()
+                .chain(app.values_of(DATA.0).into_iter().flatten())
+                .map(Path::new)
+                .collect::<Vec<&Path>>();
+            return watch::watch(&paths, || self.validate(app));
+        }
+        self.validate(app)
+    }
+}
+
+impl Validate {
+    fn validate(&self, app: &ArgMatches<'_>) -> Result<i32> {
         let cmp = if app.is_present(LAST_MODIFIED.0) {
             last_modified
         } else {
             alpabetical
         };
 
+        let data_format = match app.value_of(DATA_FORMAT) {
+            Some("json") => DataFormat::JSON,
+            Some("yaml") => DataFormat::YAML,
+            _ => DataFormat::Auto,
+        };
+
+        let tf_plan = app.value_of(INPUT_TYPE) == Some("tf-plan");
+        let cdk_assembly = app.value_of(INPUT_TYPE) == Some("cdk-assembly");
+        let k8s_admission = app.value_of(INPUT_TYPE) == Some("k8s-admission");
+        let data_variables: &[&str] = if k8s_admission { &[PREVIOUS_VARIABLE] } else { &[] };
+        if tf_plan && [RESOLVE_CONDITIONS, RESOLVE_PARAMETERS, PREVIOUS_ENGINE.0].iter().any(|arg| app.is_present(arg)) {
+            return Err(Error::new(ErrorKind::IncompatibleError(
+                "--input-type tf-plan can not be used with --resolve-conditions, --resolve-parameters or --previous-engine".to_string()
+            )));
+        }
+
+        let defines = match app.values_of(DEFINE) {
+            Some(values) => {
+                let pairs = values.map(split_define).collect::<Result<Vec<(&str, &str)>>>()?;
+                parse_defines(&pairs)?
+            }
+            None => HashMap::new(),
+        };
+        let allow_undefined_variables = app.is_present(ALLOW_UNDEFINED_VARIABLES);
+        if app.is_present(SCHEMA) {
+            let mut known = defines.keys().map(String::as_str).collect::<Vec<&str>>();
+            known.extend(data_variables);
+            let json = app.value_of(OUTPUT_FORMAT.0) == Some("json");
+            return lint_rules_files(
+                &rule_files(app.values_of(RULES.0).unwrap(), cmp)?,
+                &known,
+                allow_undefined_variables,
+                json,
+                &mut std::io::stdout(),
+            );
+        }
+
         let empty_path = Path::new("");
         let mut streams: Vec<DataFile> = Vec::new();
         let data_files: Vec<DataFile> = match app.values_of(DATA.0) {
@@ -186,6 +423,12 @@ or rules files.
                 for file_or_dir in list_of_file_or_dir {
                     validate_path(file_or_dir)?;
                     let base = PathBuf::from_str(file_or_dir)?;
+                    if cdk_assembly {
+                        for template in stack_templates(&base)? {
+                            streams.push(read_data_file(&template.path, template.name, data_format)?);
+                        }
+                        continue;
+                    }
                     for file in walkdir::WalkDir::new(base.clone()).into_iter().flatten() {
                         if file.path().is_file() {
                             let name = file
@@ -193,9 +436,6 @@ or rules files.
                                 .to_str()
                                 .map_or("".to_string(), String::from);
                             if has_a_supported_extension(&name, &DATA_FILE_SUPPORTED_EXTENSIONS) {
-                                let mut content = String::new();
-                                let mut reader = BufReader::new(File::open(file.path())?);
-                                reader.read_to_string(&mut content)?;
                                 let path = file.path();
                                 let relative = match path.strip_prefix(base.as_path()) {
                                     Ok(p) => {
@@ -207,15 +447,7 @@ or rules files.
                                     }
                                     Err(_) => format!("{}", path.display()),
                                 };
-                                let path_value = match get_path_aware_value_from_data(&content) {
-                                    Ok(t) => t,
-                                    Err(e) => return Err(e),
-                                };
-                                streams.push(DataFile {
-                                    name: relative,
-                                    path_value,
-                                    content,
-                                });
+                                streams.push(read_data_file(path, relative, data_format)?);
                             }
                         }
                     }
@@ -227,14 +459,12 @@ or rules files.
                     let mut content = String::new();
                     let mut reader = BufReader::new(std::io::stdin());
                     reader.read_to_string(&mut content)?;
-                    let path_value = match get_path_aware_value_from_data(&content) {
-                        Ok(t) => t,
-                        Err(e) => return Err(e),
-                    };
+                    let path_value = get_path_aware_value_from_data(&content, "STDIN", data_format)?;
                     streams.push(DataFile {
                         name: "STDIN".to_string(),
                         path_value,
                         content,
+                        variables: HashMap::new(),
                     });
                     streams
                 } else {
@@ -249,24 +479,18 @@ or rules files.
                 for file_or_dir in list_of_file_or_dir {
                     validate_path(file_or_dir)?;
                     let base = PathBuf::from_str(file_or_dir)?;
-                    for file in walkdir::WalkDir::new(base.clone()).into_iter().flatten() {
+                    for file in walkdir::WalkDir::new(base.clone()).sort_by(cmp).into_iter().flatten() {
                         if file.path().is_file() {
                             let name = file
                                 .file_name()
                                 .to_str()
                                 .map_or("".to_string(), String::from);
                             if has_a_supported_extension(&name, &DATA_FILE_SUPPORTED_EXTENSIONS) {
-                                let mut content = String::new();
-                                let mut reader = BufReader::new(File::open(file.path())?);
-                                reader.read_to_string(&mut content)?;
-                                let path_value = match get_path_aware_value_from_data(&content) {
-                                    Ok(t) => t,
-                                    Err(e) => return Err(e),
-                                };
-                                primary_path_value = match primary_path_value {
-                                    Some(current) => Some(current.merge(path_value)?),
-                                    None => Some(path_value),
-                                };
+                                let path_value = read_data_file(file.path(), name, data_format)?.path_value;
+                                match &mut primary_path_value {
+                                    Some(current) => current.deep_merge(&path_value)?,
+                                    None => primary_path_value = Some(path_value),
+                                }
                             }
                         }
                     }
@@ -276,19 +500,6 @@ or rules files.
             None => None,
         };
 
-        let verbose = app.is_present(VERBOSE.0);
-
-        let data_type = match app.value_of(TYPE.0) {
-            Some(t) => {
-                if t == "CFNTemplate" {
-                    CFNTemplate
-                } else {
-                    Type::Generic
-                }
-            }
-            None => Type::Generic,
-        };
-
         let output_type = match app.value_of(OUTPUT_FORMAT.0) {
             Some(o) => {
                 if o == "single-line-summary" {
@@ -301,59 +512,123 @@ or rules files.
             }
             None => OutputFormatType::SingleLineSummary,
         };
+        //
+        // JSON and YAML are read by other tools, they are always written without colors
+        //
+        let output_file = app.value_of(OUTPUT_FILE.0);
+        disable_colors(app.is_present(NO_COLOR) || output_type != OutputFormatType::SingleLineSummary || output_file.is_some());
+        let mut writer = match output_file {
+            Some(file) => create_output_file(file)?,
+            None => Box::new(std::io::stdout()) as Box<dyn Write>,
+        };
+        let options = ValidateOptions::from_args(app, output_type, defines);
 
-        let summary_type: BitFlags<SummaryType> =
-            app.values_of(SHOW_SUMMARY.0)
-                .map_or(SummaryType::FAIL.into(), |v| {
-                    v.fold(BitFlags::empty(), |mut st, elem| {
-                        match elem {
-                            "pass" => st.insert(SummaryType::PASS),
-                            "fail" => st.insert(SummaryType::FAIL),
-                            "skip" => st.insert(SummaryType::SKIP),
-                            "none" => return BitFlags::empty(),
-                            "all" => {
-                                st.insert(SummaryType::PASS | SummaryType::FAIL | SummaryType::SKIP)
-                            }
-                            _ => unreachable!(),
-                        };
-                        st
-                    })
-                });
-
-        let print_json = app.is_present(PRINT_JSON.0);
-        let show_clause_failures = app.is_present(SHOW_CLAUSE_FAILURES.0);
-        let new_version_eval_engine = !app.is_present(PREVIOUS_ENGINE.0);
+        let tag_filter = TagFilter {
+            include: app.values_of(TAGS).map_or(vec![], |tags| tags.map(String::from).collect()),
+            exclude: app.values_of(EXCLUDE_TAGS).map_or(vec![], |tags| tags.map(String::from).collect()),
+        };
+        let baseline_file = app.value_of(BASELINE).map(PathBuf::from);
+        let mut baseline = match &baseline_file {
+            Some(file) => {
+                let file_paths = match app.value_of(BASELINE_PATH) {
+                    Some("name") => baseline::FilePaths::Name,
+                    Some("none") => baseline::FilePaths::None,
+                    _ => baseline::FilePaths::Relative,
+                };
+                if app.is_present(WRITE_BASELINE) {
+                    baseline::Baseline::capture(file_paths)
+                } else {
+                    baseline::Baseline::read(file, file_paths)?
+                }
+            }
+            None => baseline::Baseline::default(),
+        };
+        let cache = match app.value_of(CACHE_DIR) {
+            Some(dir) if app.is_present(INCREMENTAL) => {
+                let ttl = app
+                    .value_of(CACHE_TTL)
+                    .map_or(cache::DEFAULT_CACHE_TTL, |v| v.parse::<u64>().unwrap_or(cache::DEFAULT_CACHE_TTL));
+                cache::Cache::open(Path::new(dir), ttl)?
+            }
+            _ => cache::Cache::default(),
+        };
+        let merge_rules = app.is_present(MERGE_RULES);
+        let substitute = app.is_present(RESOLVE_PARAMETERS);
+        let mut parameters = match app.value_of(PARAMETERS_FILE) {
+            Some(file) => read_parameters_file(Path::new(file))?,
+            None => HashMap::new(),
+        };
+        if let Some(values) = app.values_of(PARAMETER) {
+            for each in values {
+                let (name, value) = split_define(each)?;
+                parameters.insert(name.to_string(), value.to_string());
+            }
+        }
+        let resolve = app.is_present(RESOLVE_CONDITIONS);
+        let root_path = app.value_of(ROOT_PATH);
+        let conditions = match app.values_of(CONDITION) {
+            Some(values) => values.map(parse_condition).collect::<Result<HashMap<String, bool>>>()?,
+            None => HashMap::new(),
+        };
+        let data_files = if tf_plan {
+            adapt_tf_plan_data_files(data_files)?
+        } else if k8s_admission {
+            adapt_k8s_admission_data_files(data_files)?
+        } else {
+            data_files
+        };
+        let data_files = if substitute {
+            resolve_data_file_parameters(data_files, &parameters)
+        } else {
+            data_files
+        };
+        let data_files = if resolve {
+            resolve_data_file_conditions(data_files, &conditions)?
+        } else {
+            data_files
+        };
+        let data_files = match root_path {
+            Some(pointer) => select_data_file_root(data_files, pointer, options.ignore_cf_extensions)?,
+            None => data_files,
+        };
 
-        let mut exit_code = 0;
+        let mut exit_code = SUCCESS_STATUS_CODE;
+        let mut totals = common::EvaluationTotals::with_max_errors(options.max_errors);
         if app.is_present(RULES.0) {
-            let list_of_file_or_dir = app.values_of(RULES.0).unwrap();
-            let mut rules = Vec::new();
-            for file_or_dir in list_of_file_or_dir {
-                validate_path(file_or_dir)?;
-                let base = PathBuf::from_str(file_or_dir)?;
-                if base.is_file() {
-                    rules.push(base.clone())
-                } else {
-                    for entry in walkdir::WalkDir::new(base.clone())
-                        .sort_by(cmp)
-                        .into_iter()
-                        .flatten()
-                    {
-                        if entry.path().is_file()
-                            && entry
-                                .path()
-                                .file_name()
-                                .and_then(|s| s.to_str())
-                                .map_or(false, |s| {
-                                    has_a_supported_extension(s, &RULE_FILE_SUPPORTED_EXTENSIONS)
-                                })
-                        {
-                            rules.push(entry.path().to_path_buf());
+            let rules = rule_files(app.values_of(RULES.0).unwrap(), cmp)?;
+            let mut evaluate_rules = |rules: &RulesFile<'_>, rule_file_name: &str, exit_code: &mut i32| -> Result<bool> {
+                if tag_filter.excludes_all(rules) {
+                    return Ok(false)
+                }
+                check_references(
+                    rules,
+                    rule_file_name,
+                    extra_data.as_ref(),
+                    &options.defines,
+                    data_variables,
+                    allow_undefined_variables,
+                )?;
+                match evaluate_against_data_input(
+                    &options,
+                    extra_data.clone(),
+                    &data_files,
+                    rules,
+                    rule_file_name,
+                    writer.as_mut(),
+                    &mut totals,
+                    &mut baseline,
+                    &cache,
+                )? {
+                    Status::SKIP | Status::PASS => Ok(false),
+                    Status::FAIL => {
+                        if *exit_code == SUCCESS_STATUS_CODE {
+                            *exit_code = FAILURE_STATUS_CODE;
                         }
+                        Ok(options.evaluation.fail_fast)
                     }
                 }
-            }
-            for each_file_content in iterate_over(&rules, |content, file| {
+            };
+            let rule_file_contents = iterate_over(&rules, |content, file| {
                 Ok((
                     content,
                     match file.strip_prefix(&file) {
@@ -367,48 +642,46 @@ or rules files.
                         Err(_) => format!("{}", file.display()),
                     },
                 ))
-            }) {
-                match each_file_content {
-                    Err(e) => println!("Unable read content from file {}", e),
-                    Ok((file_content, rule_file_name)) => {
-                        let span =
-                            crate::rules::parser::Span::new_extra(&file_content, &rule_file_name);
-                        match crate::rules::parser::rules_file(span) {
-                            Err(e) => {
-                                println!(
-                                    "Parsing error handling rule file = {}, Error = {}",
-                                    rule_file_name.underline(),
-                                    e
-                                );
-                                println!("---");
-                                exit_code = 5;
-                                continue;
-                            }
+            }).filter_map(|each_file_content| match each_file_content {
+                Err(e) => {
+                    print_progress(output_file.is_some(), &format!("Unable read content from file {}", e));
+                    None
+                }
+                Ok(file_content) => Some(file_content),
+            }).collect::<Vec<(String, String)>>();
 
-                            Ok(rules) => {
-                                match evaluate_against_data_input(
-                                    data_type,
-                                    output_type,
-                                    extra_data.clone(),
-                                    &data_files,
-                                    &rules,
-                                    &rule_file_name,
-                                    verbose,
-                                    print_json,
-                                    show_clause_failures,
-                                    new_version_eval_engine,
-                                    summary_type,
-                                )? {
-                                    Status::SKIP | Status::PASS => continue,
-                                    Status::FAIL => {
-                                        exit_code = 5;
-                                    }
-                                }
-                            }
+            let mut merged = Vec::with_capacity(rule_file_contents.len());
+            for (file_content, rule_file_name) in &rule_file_contents {
+                let span = crate::rules::parser::Span::new_extra(file_content, rule_file_name);
+                match crate::rules::parser::rules_file(span) {
+                    Err(e) => {
+                        print_progress(output_file.is_some(), &format!(
+                            "Parsing error handling rule file = {}, Error = {}\n---",
+                            rule_file_name.underline(),
+                            e
+                        ));
+                        exit_code = PARSING_ERROR_STATUS_CODE;
+                        continue;
+                    }
+
+                    Ok(rules) => {
+                        if merge_rules {
+                            merged.push((rule_file_name.as_str(), rules));
+                        } else if evaluate_rules(&tag_filter.apply(rules), rule_file_name, &mut exit_code)? {
+                            break;
                         }
                     }
                 }
             }
+
+            //
+            // With --merge-rules a rules file that does not parse leaves the rule set incomplete,
+            // rules that depend on it would be reported as undefined, so nothing is evaluated
+            //
+            if merge_rules && exit_code != PARSING_ERROR_STATUS_CODE && !merged.is_empty() {
+                let rules = tag_filter.apply(merge_rules_files(merged)?);
+                evaluate_rules(&rules, &merged_rules_name(app.values_of(RULES.0).unwrap()), &mut exit_code)?;
+            }
         } else {
             let mut context = String::new();
             let mut reader = BufReader::new(std::io::stdin());
@@ -417,16 +690,36 @@ or rules files.
             let mut data_collection: Vec<DataFile> = Vec::new();
             for (i, data) in payload.list_of_data.iter().enumerate() {
                 let content = data.to_string();
-                let path_value = match get_path_aware_value_from_data(&content) {
-                    Ok(t) => t,
-                    Err(e) => return Err(e),
-                };
+                let name = format!("DATA_STDIN[{}]", i + 1);
+                let path_value = get_path_aware_value_from_data(&content, &name, data_format)?;
                 data_collection.push(DataFile {
-                    name: format!("DATA_STDIN[{}]", i + 1),
+                    name,
                     path_value,
                     content,
+                    variables: HashMap::new(),
                 });
             }
+            let data_collection = if tf_plan {
+                adapt_tf_plan_data_files(data_collection)?
+            } else if k8s_admission {
+                adapt_k8s_admission_data_files(data_collection)?
+            } else {
+                data_collection
+            };
+            let data_collection = if substitute {
+                resolve_data_file_parameters(data_collection, &parameters)
+            } else {
+                data_collection
+            };
+            let data_collection = if resolve {
+                resolve_data_file_conditions(data_collection, &conditions)?
+            } else {
+                data_collection
+            };
+            let data_collection = match root_path {
+                Some(pointer) => select_data_file_root(data_collection, pointer, options.ignore_cf_extensions)?,
+                None => data_collection,
+            };
             let rules_collection: Vec<(String, String)> = payload
                 .list_of_rules
                 .iter()
@@ -437,43 +730,235 @@ or rules files.
             for (each_rules, location) in rules_collection {
                 match parse_rules(&each_rules, &location) {
                     Err(e) => {
-                        println!(
-                            "Parsing error handling rules = {}, Error = {}",
+                        print_progress(output_file.is_some(), &format!(
+                            "Parsing error handling rules = {}, Error = {}\n---",
                             location.underline(),
                             e
-                        );
-                        println!("---");
-                        exit_code = 5;
+                        ));
+                        exit_code = PARSING_ERROR_STATUS_CODE;
                         continue;
                     }
 
                     Ok(rules) => {
+                        let rules = tag_filter.apply(rules);
+                        if tag_filter.excludes_all(&rules) {
+                            continue;
+                        }
+                        check_references(
+                            &rules,
+                            &location,
+                            None,
+                            &options.defines,
+                            data_variables,
+                            allow_undefined_variables,
+                        )?;
                         match evaluate_against_data_input(
-                            data_type,
-                            output_type,
+                            &options,
                             None,
                             &data_collection,
                             &rules,
                             &location,
-                            verbose,
-                            print_json,
-                            show_clause_failures,
-                            new_version_eval_engine,
-                            summary_type,
+                            writer.as_mut(),
+                            &mut totals,
+                            &mut baseline,
+                            &cache,
                         )? {
                             Status::SKIP | Status::PASS => continue,
                             Status::FAIL => {
-                                exit_code = 5;
+                                if exit_code == SUCCESS_STATUS_CODE {
+                                    exit_code = FAILURE_STATUS_CODE;
+                                }
+                                if options.evaluation.fail_fast {
+                                    break;
+                                }
                             }
                         }
                     }
                 }
             }
         }
+        totals.report(writer.as_mut(), output_type)?;
+        if let Some(file) = &baseline_file {
+            baseline.finish(file, writer.as_mut(), output_type)?;
+        }
+        writer.flush()?;
         Ok(exit_code)
     }
 }
 
+//
+// Report written with --output-file, parent directories are created and an existing file is
+// truncated
+//
+fn create_output_file(file: &str) -> Result<Box<dyn Write>> {
+    let path = Path::new(file);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)))
+}
+
+//
+// Rules files passed with --rules, directories are scanned for files with a supported extension
+// in the order cmp gives
+//
+fn rule_files(
+    list_of_file_or_dir: clap::Values<'_>,
+    cmp: fn(&walkdir::DirEntry, &walkdir::DirEntry) -> cmp::Ordering,
+) -> Result<Vec<PathBuf>> {
+    let mut rules = Vec::new();
+    for file_or_dir in list_of_file_or_dir {
+        validate_path(file_or_dir)?;
+        let base = PathBuf::from_str(file_or_dir)?;
+        if base.is_file() {
+            rules.push(base.clone())
+        } else {
+            for entry in walkdir::WalkDir::new(base.clone())
+                .sort_by(cmp)
+                .into_iter()
+                .flatten()
+            {
+                if entry.path().is_file()
+                    && entry
+                        .path()
+                        .file_name()
+                        .and_then(|s| s.to_str())
+                        .is_some_and(|s| {
+                            has_a_supported_extension(s, &RULE_FILE_SUPPORTED_EXTENSIONS)
+                        })
+                {
+                    rules.push(entry.path().to_path_buf());
+                }
+            }
+        }
+    }
+    Ok(rules)
+}
+
+//
+// A problem --schema found in a rules file. Line and column are absent for parse errors, whose
+// message carries the position, and for references or literals inside let assignments
+//
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct LintError {
+    pub(crate) file: String,
+    pub(crate) severity: &'static str,
+    pub(crate) kind: &'static str,
+    pub(crate) rule: Option<String>,
+    pub(crate) line: Option<u32>,
+    pub(crate) column: Option<u32>,
+    pub(crate) message: String,
+}
+
+impl std::fmt::Display for LintError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, ":{}:{}", line, column)?;
+        }
+        write!(f, ": {}[{}]: {}", self.severity, self.kind, self.message)
+    }
+}
+
+//
+// Structural checks on a parsed rules file, every rule clause refers to a rule in the file, every
+// %variable is assigned or known and every regular expression literal compiles. With
+// allow_undefined_variables undefined variables are warnings
+//
+pub(crate) fn lint_rules_file(
+    rules: &RulesFile<'_>,
+    rules_file_name: &str,
+    known_variables: &[&str],
+    allow_undefined_variables: bool,
+) -> Vec<LintError> {
+    let mut errors = Vec::new();
+    for each in undefined_references(rules, known_variables) {
+        let (kind, severity) = match each.kind {
+            ReferenceKind::Variable if allow_undefined_variables => ("undefined-variable", "warning"),
+            ReferenceKind::Variable => ("undefined-variable", "error"),
+            ReferenceKind::Rule => ("undefined-rule", "error"),
+        };
+        let location = each.location.clone();
+        errors.push(LintError {
+            file: rules_file_name.to_string(),
+            severity,
+            kind,
+            rule: each.rule_name.clone(),
+            line: location.as_ref().map(|l| l.line),
+            column: location.as_ref().map(|l| l.column),
+            message: format!("{}", UndefinedReference { location: None, ..each }),
+        });
+    }
+    for each in invalid_regexes(rules) {
+        let location = each.location.clone();
+        errors.push(LintError {
+            file: rules_file_name.to_string(),
+            severity: "error",
+            kind: "invalid-regex",
+            rule: each.rule_name.clone(),
+            line: location.as_ref().map(|l| l.line),
+            column: location.as_ref().map(|l| l.column),
+            message: format!("{}", InvalidRegex { location: None, ..each }),
+        });
+    }
+    errors
+}
+
+//
+// validate --schema, parses and lints each rules file without reading any data. Exits with the
+// parsing error code when any file fails to parse or has lint errors, warnings do not count
+//
+fn lint_rules_files(
+    files: &[PathBuf],
+    known_variables: &[&str],
+    allow_undefined_variables: bool,
+    json: bool,
+    writer: &mut dyn Write,
+) -> Result<i32> {
+    let mut all = Vec::new();
+    for file in files {
+        let mut content = String::new();
+        File::open(file)?.read_to_string(&mut content)?;
+        let name = format!("{}", file.display());
+        match parse_rules(&content, &name) {
+            Ok(rules) => all.extend(lint_rules_file(&rules, &name, known_variables, allow_undefined_variables)),
+            Err(e) => all.push(LintError {
+                file: name.clone(),
+                severity: "error",
+                kind: "parse",
+                rule: None,
+                line: None,
+                column: None,
+                message: e.to_string().replace('\n', " "),
+            }),
+        }
+    }
+    if json {
+        writeln!(writer, "{}", serde_json::to_string_pretty(&all)?)?;
+    } else {
+        for each in &all {
+            writeln!(writer, "{}", each)?;
+        }
+    }
+    Ok(if all.iter().any(|each| each.severity == "error") {
+        PARSING_ERROR_STATUS_CODE
+    } else {
+        SUCCESS_STATUS_CODE
+    })
+}
+
+//
+// Messages about the progress of validate, e.g. rules files that could not be parsed. They go
+// to stderr when the report is written to --output-file, so that the file only has the report
+//
+fn print_progress(to_stderr: bool, message: &str) {
+    if to_stderr {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
 pub(crate) fn validate_path(base: &str) -> Result<()> {
     match Path::new(base).exists() {
         true => Ok(()),
@@ -501,8 +986,365 @@ pub fn validate_and_return_json(data: &str, rules: &str) -> Result<String> {
             }
             Err(e) => Err(e),
         },
-        Err(e) => Err(Error::new(ErrorKind::ParseError(e.to_string()))),
+        Err(e) => Err(Error::new(ErrorKind::ParseError(e.to_string())).in_rules_file()),
+    }
+}
+
+fn split_define(define: &str) -> Result<(&str, &str)> {
+    match define.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim(), value)),
+        _ => Err(Error::new(ErrorKind::ParseError(format!(
+            "Variable definition {} is not in the form name=value",
+            define
+        )))),
+    }
+}
+
+fn parse_condition(condition: &str) -> Result<(String, bool)> {
+    let (name, value) = split_define(condition)?;
+    match value.trim() {
+        "true" => Ok((name.to_string(), true)),
+        "false" => Ok((name.to_string(), false)),
+        _ => Err(Error::new(ErrorKind::ParseError(format!(
+            "Condition {} must be set to true or false",
+            condition
+        )))),
+    }
+}
+
+fn read_parameters_file(path: &Path) -> Result<HashMap<String, String>> {
+    let mut content = String::new();
+    let mut reader = BufReader::new(File::open(path)?);
+    reader.read_to_string(&mut content)?;
+    let values = serde_yaml::from_str::<serde_yaml::Value>(&content).map_err(|e| Error::new(ErrorKind::ParseError(
+        format!("Could not read parameters file {}, {}", path.display(), e)
+    )))?;
+    parameter_values(&values).map_err(|e| Error::new(ErrorKind::ParseError(
+        format!("Parameters file {} is not valid, {}", path.display(), e)
+    )))
+}
+
+//
+// Parameter values as a map, {"Env": "prod", "Subnets": ["subnet-1", "subnet-2"]}, or as the AWS
+// CLI list, [{"ParameterKey": "Env", "ParameterValue": "prod"}]. Lists are joined with commas as
+// they are for --parameter
+//
+fn parameter_values(values: &serde_yaml::Value) -> Result<HashMap<String, String>> {
+    let pairs = match values {
+        serde_yaml::Value::Mapping(map) => map.iter().collect::<Vec<_>>(),
+        serde_yaml::Value::Sequence(list) => list.iter()
+            .map(|each| match (each.get("ParameterKey"), each.get("ParameterValue")) {
+                (Some(key), Some(value)) => Ok((key, value)),
+                _ => Err(Error::new(ErrorKind::ParseError(
+                    "list entries must have a ParameterKey and a ParameterValue".to_string()
+                ))),
+            })
+            .collect::<Result<Vec<_>>>()?,
+        _ => return Err(Error::new(ErrorKind::ParseError(
+            "expected a map of parameter values or a list of ParameterKey and ParameterValue pairs".to_string()
+        ))),
+    };
+    let mut parameters = HashMap::with_capacity(pairs.len());
+    for (key, value) in pairs {
+        let name = match scalar_parameter_value(key) {
+            Some(name) => name,
+            None => return Err(Error::new(ErrorKind::ParseError(format!("parameter name {:?} is not a string", key)))),
+        };
+        let value = match value {
+            serde_yaml::Value::Sequence(list) => list.iter().map(scalar_parameter_value).collect::<Option<Vec<String>>>()
+                .map(|each| each.join(",")),
+            rest => scalar_parameter_value(rest),
+        };
+        match value {
+            Some(value) => parameters.insert(name, value),
+            None => return Err(Error::new(ErrorKind::ParseError(format!("parameter {} has no scalar or list value", name)))),
+        };
+    }
+    Ok(parameters)
+}
+
+fn scalar_parameter_value(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn read_data_file(path: &Path, name: String, data_format: DataFormat) -> Result<DataFile> {
+    let mut content = String::new();
+    File::open(path)
+        .and_then(|file| BufReader::new(file).read_to_string(&mut content))
+        .map_err(|e| Error::new(ErrorKind::DataFileError(format!("{}, {}", name, e))))?;
+    let path_value = get_path_aware_value_from_data(&content, &name, data_format)?;
+    Ok(DataFile {
+        name,
+        path_value,
+        content,
+        variables: HashMap::new(),
+    })
+}
+
+fn adapt_k8s_admission_data_files(data_files: Vec<DataFile>) -> Result<Vec<DataFile>> {
+    data_files
+        .into_iter()
+        .map(|file| {
+            let request = admission_request(&file.path_value, &file.name)?;
+            Ok(DataFile {
+                path_value: request.object,
+                variables: HashMap::from([(PREVIOUS_VARIABLE.to_string(), request.previous)]),
+                ..file
+            })
+        })
+        .collect()
+}
+
+fn adapt_tf_plan_data_files(data_files: Vec<DataFile>) -> Result<Vec<DataFile>> {
+    data_files
+        .into_iter()
+        .map(|file| {
+            Ok(DataFile {
+                path_value: adapt_tf_plan(&file.path_value, &file.name)?,
+                ..file
+            })
+        })
+        .collect()
+}
+
+fn resolve_data_file_parameters(
+    data_files: Vec<DataFile>,
+    parameters: &HashMap<String, String>,
+) -> Vec<DataFile> {
+    data_files
+        .into_iter()
+        .map(|file| DataFile {
+            path_value: resolve_parameters(&file.path_value, parameters),
+            ..file
+        })
+        .collect()
+}
+
+//
+// Replaces each data file with one per combination of undetermined template conditions, named
+// after the outcomes chosen, e.g. "template.yaml [IsProd=true]"
+//
+fn resolve_data_file_conditions(
+    data_files: Vec<DataFile>,
+    conditions: &HashMap<String, bool>,
+) -> Result<Vec<DataFile>> {
+    let mut resolved = Vec::with_capacity(data_files.len());
+    for file in data_files {
+        let variants = resolve_conditions(&file.path_value, conditions)
+            .map_err(|e| e.with_data_file(&file.name))?;
+        for (label, path_value) in variants {
+            let name = if label.is_empty() {
+                file.name.clone()
+            } else {
+                format!("{} [{}]", file.name, label)
+            };
+            resolved.push(DataFile {
+                content: file.content.clone(),
+                path_value,
+                name,
+                variables: file.variables.clone(),
+            });
+        }
+    }
+    Ok(resolved)
+}
+
+//
+// Values are parsed the same way as literals in a rules file. Anything that does not
+// parse as a literal, like an unquoted word, is treated as a plain string
+//
+pub(crate) fn parse_defines(defines: &[(&str, &str)]) -> Result<HashMap<String, PathAwareValue>> {
+    let mut parsed = HashMap::with_capacity(defines.len());
+    for (name, value) in defines {
+        let value = match Value::try_from(*value) {
+            Ok(v) => v,
+            Err(_) => Value::String(value.to_string()),
+        };
+        parsed.insert(name.to_string(), PathAwareValue::try_from(value)?);
+    }
+    Ok(parsed)
+}
+
+//
+// Variables resolvable from rules: top-level keys from --input-parameters, unless the rules
+// file assigns them with let, overridden by any --define values
+//
+fn rule_variables(
+    rules: &RulesFile<'_>,
+    parameters: Option<&PathAwareValue>,
+    defines: &HashMap<String, PathAwareValue>,
+) -> HashMap<String, PathAwareValue> {
+    let mut variables = HashMap::new();
+    if let Some(PathAwareValue::Map((_, map))) = parameters {
+        for (key, value) in &map.values {
+            if !rules.assignments.iter().any(|each| &each.var == key) {
+                variables.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    variables.extend(defines.iter().map(|(k, v)| (k.clone(), v.clone())));
+    variables
+}
+
+//
+// Resolves variable and rule references in the rules file before any data file is evaluated.
+// Variables may be provided by --define, input parameters or bound for each data file by
+// --input-type, hence those names are known too
+//
+fn check_references(
+    rules: &RulesFile<'_>,
+    rules_file_name: &str,
+    parameters: Option<&PathAwareValue>,
+    defines: &HashMap<String, PathAwareValue>,
+    data_variables: &[&str],
+    allow_undefined_variables: bool,
+) -> Result<()> {
+    let mut known = defines.keys().map(String::as_str).collect::<Vec<&str>>();
+    known.extend(data_variables);
+    if let Some(PathAwareValue::Map((_, map))) = parameters {
+        known.extend(map.values.keys().map(String::as_str));
+    }
+    let (warnings, errors): (Vec<_>, Vec<_>) = undefined_references(rules, &known)
+        .into_iter()
+        .partition(|each| allow_undefined_variables && each.kind == ReferenceKind::Variable);
+    for each in &warnings {
+        eprintln!("WARNING: {} in rules file {}", each, rules_file_name);
+    }
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let errors = errors.iter().map(|each| format!("{}", each)).collect::<Vec<_>>();
+    Err(Error::new(ErrorKind::ParseError(format!(
+        "Undefined references in rules file {}: {}",
+        rules_file_name,
+        errors.join("; ")
+    ))))
+}
+
+//
+// Rules selected with --tags and --exclude-tags. Rules that a selected rule depends on are kept
+// whatever their tags
+//
+#[derive(Debug, Default)]
+struct TagFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl TagFilter {
+    fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    //
+    // A rule is included if it has any of the included tags, an included entry joining tags
+    // with + like security+cost needs all of them
+    //
+    fn selects(&self, rule: &Rule<'_>) -> bool {
+        let included = |entry: &String| entry.split('+').all(|tag| rule.tags.iter().any(|each| each == tag));
+        (self.include.is_empty() || self.include.iter().any(included)) &&
+            !rule.tags.iter().any(|tag| self.exclude.contains(tag))
+    }
+
+    fn apply<'loc>(&self, rules: RulesFile<'loc>) -> RulesFile<'loc> {
+        if self.is_empty() {
+            return rules
+        }
+        select_rules(rules, |rule| self.selects(rule))
+    }
+
+    //
+    // Rules files left without rules are not evaluated
+    //
+    fn excludes_all(&self, rules: &RulesFile<'_>) -> bool {
+        !self.is_empty() && rules.guard_rules.is_empty()
+    }
+}
+
+//
+// Combines rules files into one rule set so that rules in one file can refer to rules defined
+// in another. A let assignment repeated across files is kept once, assigning a different
+// value to it or defining a rule with the same name in more than one file is an error
+//
+fn merge_rules_files<'loc>(files: Vec<(&str, RulesFile<'loc>)>) -> Result<RulesFile<'loc>> {
+    let mut merged = RulesFile {
+        assignments: vec![],
+        guard_rules: vec![],
+        parameterized_rules: vec![],
+    };
+    let mut let_files: HashMap<String, &str> = HashMap::new();
+    let mut rule_files: HashMap<String, &str> = HashMap::new();
+    for (file_name, rules) in files {
+        for each in rules.assignments {
+            match let_files.get(&each.var) {
+                Some(defined_in) => {
+                    let existing = merged.assignments.iter().find(|assignment| assignment.var == each.var).unwrap();
+                    if !same_let_value(&existing.value, &each.value)? {
+                        return Err(Error::new(ErrorKind::IncompatibleError(format!(
+                            "Variable {} is assigned different values in rules files {} and {}",
+                            each.var, defined_in, file_name
+                        ))));
+                    }
+                }
+                None => {
+                    let_files.insert(each.var.clone(), file_name);
+                    merged.assignments.push(each);
+                }
+            }
+        }
+
+        let names = rules.guard_rules.iter().map(|rule| &rule.rule_name)
+            .chain(rules.parameterized_rules.iter().map(|rule| &rule.rule.rule_name));
+        for name in names {
+            if let Some(defined_in) = rule_files.insert(name.clone(), file_name) {
+                return Err(Error::new(ErrorKind::IncompatibleError(format!(
+                    "Rule {} is defined in rules files {} and {}",
+                    name, defined_in, file_name
+                ))));
+            }
+        }
+        merged.guard_rules.extend(rules.guard_rules);
+        merged.parameterized_rules.extend(rules.parameterized_rules);
     }
+    Ok(merged)
+}
+
+//
+// The same assignment in two files differs only in where it was written
+//
+fn same_let_value(first: &LetValue<'_>, second: &LetValue<'_>) -> Result<bool> {
+    fn without_locations(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                map.remove("location");
+                map.values_mut().for_each(without_locations);
+            }
+            serde_json::Value::Array(list) => list.iter_mut().for_each(without_locations),
+            _ => {}
+        }
+    }
+    let mut first = serde_json::to_value(first)?;
+    let mut second = serde_json::to_value(second)?;
+    without_locations(&mut first);
+    without_locations(&mut second);
+    Ok(first == second)
+}
+
+fn merged_rules_name<'a, I: Iterator<Item = &'a str>>(rules: I) -> String {
+    rules
+        .map(|each| {
+            Path::new(each)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or(each)
+        })
+        .collect::<Vec<&str>>()
+        .join(",")
 }
 
 fn deserialize_payload(payload: &str) -> Result<Payload> {
@@ -523,15 +1365,9 @@ pub(crate) struct ConsoleReporter<'r> {
     reporters: &'r Vec<&'r dyn Reporter>,
     rules_file_name: &'r str,
     data_file_name: &'r str,
-    verbose: bool,
-    print_json: bool,
-    show_clause_failures: bool,
-}
-
-fn indent_spaces(indent: usize) {
-    for _idx in 0..indent {
-        print!("{}", INDENT)
-    }
+    options: &'r ValidateOptions,
+    suppressed: suppressions::Excluded,
+    known: suppressions::Excluded,
 }
 
 //
@@ -555,7 +1391,11 @@ pub(crate) fn print_verbose_tree(root: &EventRecord<'_>) {
     pprint_tree(root, "".to_string(), true);
 }
 
-pub(super) fn print_context(cxt: &StatusContext, depth: usize) {
+//
+// The from value of a FAIL is always preceded by its resolved path, numbers and booleans carry
+// no hint of where they came from otherwise
+//
+pub(super) fn print_context(writer: &mut dyn Write, cxt: &StatusContext, depth: usize) -> Result<()> {
     let header = format!(
         "{}({}, {})",
         cxt.eval_type,
@@ -563,38 +1403,43 @@ pub(super) fn print_context(cxt: &StatusContext, depth: usize) {
         common::colored_string(cxt.status)
     )
     .underline();
-    //let depth = cxt.indent;
-    let _sub_indent = depth + 1;
-    indent_spaces(depth - 1);
-    println!("{}", header);
-    match &cxt.from {
-        Some(v) => {
-            indent_spaces(depth);
-            print!("|  ");
-            println!("From: {:?}", v);
+    let indent = INDENT.repeat(depth);
+    writeln!(writer, "{}{}", INDENT.repeat(depth - 1), header)?;
+    if let Some(v) = &cxt.from {
+        if cxt.status == Some(Status::FAIL) {
+            writeln!(writer, "{}|  Path: {}", indent, v.self_path().0)?;
         }
-        None => {}
+        writeln!(writer, "{}|  From: {}", indent, ValueOnlyDisplay(v))?;
     }
-    match &cxt.to {
-        Some(v) => {
-            indent_spaces(depth);
-            print!("|  ");
-            println!("To: {:?}", v);
-        }
-        None => {}
+    if let Some(v) = &cxt.to {
+        writeln!(writer, "{}|  To: {}", indent, ValueOnlyDisplay(v))?;
     }
-    match &cxt.msg {
-        Some(message) => {
-            indent_spaces(depth);
-            print!("|  ");
-            println!("Message: {}", message);
-        }
-        None => {}
+    if let Some(message) = &cxt.msg {
+        writeln!(writer, "{}|  Message: {}", indent, message)?;
+    }
+    if let Some(summary) = disjunction_summary(cxt) {
+        writeln!(writer, "{}|  {}", indent, summary)?;
     }
 
     for child in &cxt.children {
-        print_context(child, depth + 1)
+        print_context(writer, child, depth + 1)?;
+    }
+    Ok(())
+}
+
+fn disjunction_summary(cxt: &StatusContext) -> Option<String> {
+    if cxt.branches.is_empty() {
+        return None
     }
+    let satisfied = cxt.branches.iter().position(|status| *status == Status::PASS);
+    let branches = cxt.branches.iter().enumerate().map(|(idx, status)| {
+        if Some(idx) == satisfied {
+            format!("Branch {} {} (satisfied)", idx + 1, status)
+        } else {
+            format!("Branch {} {}", idx + 1, status)
+        }
+    }).collect::<Vec<String>>();
+    Some(format!("Or: {}", branches.join(", ")))
 }
 
 fn print_failing_clause(rules_file_name: &str, rule: &StatusContext, longest: usize) {
@@ -622,14 +1467,14 @@ fn print_failing_clause(rules_file_name: &str, rule: &StatusContext, longest: us
         match &matched.from {
             Some(from) => {
                 print!("{space:>longest$}", space = " ", longest = longest + 4);
-                let content = format!("Comparing {:?}", from);
+                let content = format!("Comparing Path=[{}] {}", from.self_path().0, ValueOnlyDisplay(from));
                 print!("{header:<20}{content}", header = " ", content = content);
             }
             None => {}
         }
         match &matched.to {
             Some(to) => {
-                println!(" with {:?} failed", to);
+                println!(" with {} failed", ValueOnlyDisplay(to));
             }
             None => {
                 println!()
@@ -652,27 +1497,205 @@ fn print_failing_clause(rules_file_name: &str, rule: &StatusContext, longest: us
     }
 }
 
+const MAX_DISPLAY_VALUE_LEN: usize = 80;
+
+fn truncate_for_display(value: String, max_len: usize) -> String {
+    if value.chars().count() <= max_len {
+        return value;
+    }
+    let mut truncated = value.chars().take(max_len).collect::<String>();
+    truncated.push_str("...");
+    truncated
+}
+
+fn display_value(value: &Option<PathAwareValue>) -> String {
+    match value {
+        Some(v) => truncate_for_display(
+            format!("{}", ValueOnlyDisplay(v)),
+            MAX_DISPLAY_VALUE_LEN,
+        ),
+        None => "NULL".to_string(),
+    }
+}
+
+fn print_failed_clause_details(
+    writer: &mut dyn Write,
+    rules_file_name: &str,
+    failed_rules: &[&StatusContext],
+) -> Result<()> {
+    for rule in failed_rules {
+        writeln!(
+            writer,
+            "Rule [{}/{}] {}",
+            rules_file_name,
+            rule.context,
+            common::colored_string(rule.status)
+        )?;
+        for clause in common::find_all_failing_clauses(rule) {
+            let comparator = clause.comparator.map_or("".to_string(), |(cmp, not)| {
+                format!("{}{}", if not { "NOT " } else { "" }, cmp)
+            });
+            let message = match &clause.msg {
+                Some(m) if !m.contains("DEFAULT") => format!(" Message=[{}]", m.replace('\n', ";")),
+                _ => "".to_string(),
+            };
+            match clause.from.as_ref().map(|v| v.self_path().0.as_str()) {
+                //
+                // The query did not resolve and the clause was checked against the whole
+                // document, show the clause instead of the document
+                //
+                Some("") | None => writeln!(
+                    writer,
+                    "    Clause=[{clause}] Comparator=[{cmp}] To=[{to}]{msg}",
+                    clause = clause.context.trim(),
+                    cmp = comparator,
+                    to = display_value(&clause.to),
+                    msg = message
+                )?,
+                Some(path) => writeln!(
+                    writer,
+                    "    Path=[{path}] Comparator=[{cmp}] From=[{from}] To=[{to}]{msg}",
+                    path = path,
+                    cmp = comparator,
+                    from = display_value(&clause.from),
+                    to = display_value(&clause.to),
+                    msg = message
+                )?,
+            }
+        }
+    }
+    Ok(())
+}
+
+//
+// Same as print_failed_clause_details for the evaluation record of the default engine
+//
+fn print_failed_record_details(
+    writer: &mut dyn Write,
+    rules_file_name: &str,
+    root_record: &EventRecord<'_>,
+) -> Result<()> {
+    for rule in &root_record.children {
+        let rule_name = match &rule.container {
+            Some(RecordType::RuleCheck(NamedStatus { name, status: Status::FAIL, .. })) => *name,
+            _ => continue,
+        };
+        writeln!(
+            writer,
+            "Rule [{}/{}] {}",
+            rules_file_name,
+            rule_name,
+            common::colored_string(Some(Status::FAIL))
+        )?;
+        for clause in common::find_failing_clauses(rule) {
+            let failure = common::failure_from_clause(rule_name, clause)?;
+            let comparator = common::extract_name_info_from_record(rule_name, clause)?
+                .comparison
+                .map_or("".to_string(), |cmp| {
+                    format!("{}{}", if cmp.not_operator_exists { "NOT " } else { "" }, cmp.operator)
+                });
+            let message = failure.message.as_ref().map_or("".to_string(), |m| {
+                format!(" Message=[{}]", m.replace('\n', ";"))
+            });
+            let json_value = |value: &Option<serde_json::Value>| match value {
+                Some(v) => truncate_for_display(v.to_string(), MAX_DISPLAY_VALUE_LEN),
+                None => "NULL".to_string(),
+            };
+            writeln!(
+                writer,
+                "    Path=[{path}] Comparator=[{cmp}] From=[{from}] To=[{to}]{msg}",
+                path = failure.path,
+                cmp = comparator,
+                from = json_value(&failure.from),
+                to = json_value(&failure.to),
+                msg = message
+            )?;
+        }
+    }
+    Ok(())
+}
+
+//
+// Unlike print_failed_clause_details values are shown in full and each detail is on its own
+// line, so that a single failing clause can be understood without the evaluation tree
+//
+fn print_explanation(
+    writer: &mut dyn Write,
+    rules_file_name: &str,
+    failed_rules: &[&StatusContext],
+) -> Result<()> {
+    for rule in failed_rules {
+        writeln!(
+            writer,
+            "Rule [{}/{}] {}",
+            rules_file_name,
+            rule.context,
+            common::colored_string(rule.status)
+        )?;
+        for (index, clause) in common::find_all_failing_clauses(rule).iter().enumerate() {
+            writeln!(writer, "  Clause #{} {}", index + 1, clause.context)?;
+            if let Some(from) = &clause.from {
+                writeln!(writer, "    Path:     {}", from.self_path().0)?;
+            }
+            if let Some((cmp, not)) = clause.comparator {
+                writeln!(writer, "    Operator: {}{}", if not { "NOT " } else { "" }, cmp)?;
+            }
+            if let Some(from) = &clause.from {
+                writeln!(writer, "    Found:    {}", ValueOnlyDisplay(from))?;
+            }
+            if let Some(to) = &clause.to {
+                writeln!(writer, "    Expected: {}", ValueOnlyDisplay(to))?;
+            }
+            if let Some(msg) = &clause.msg {
+                for (line, each) in msg.lines().enumerate() {
+                    let header = if line == 0 { "Message: " } else { "         " };
+                    writeln!(writer, "    {} {}", header, each)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 impl<'r> ConsoleReporter<'r> {
-    pub(crate) fn new(
+    fn has_failed(&self) -> bool {
+        self.root_context.has_failed() ||
+            (self.options.assert_all_pass && self.root_context.has_skipped())
+    }
+
+    fn new(
         root: StackTracker<'r>,
         renderers: &'r Vec<&'r dyn Reporter>,
         rules_file_name: &'r str,
         data_file_name: &'r str,
-        verbose: bool,
-        print_json: bool,
-        show_clause_failures: bool,
+        options: &'r ValidateOptions,
     ) -> Self {
         ConsoleReporter {
             root_context: root,
             reporters: renderers,
             rules_file_name,
             data_file_name,
-            verbose,
-            print_json,
-            show_clause_failures,
+            options,
+            suppressed: suppressions::Excluded::default(),
+            known: suppressions::Excluded::default(),
         }
     }
 
+    fn suppress(&mut self, suppressions: &suppressions::Suppressions) -> Result<()> {
+        self.suppressed = self.root_context.take_failures(
+            &mut |rule, _, failure| suppressions.suppresses(rule, &failure.path)
+        )?;
+        Ok(())
+    }
+
+    fn take_known(&mut self, baseline: &mut baseline::Baseline) -> Result<()> {
+        let (rules_file, data_file) = (self.rules_file_name, self.data_file_name);
+        self.known = self.root_context.take_failures(
+            &mut |rule, clause, failure| baseline.is_known(baseline.fingerprint(rules_file, data_file, rule, clause, failure))
+        )?;
+        Ok(())
+    }
+
     pub fn get_result_json(
         self,
         root: &PathAwareValue,
@@ -680,7 +1703,7 @@ impl<'r> ConsoleReporter<'r> {
     ) -> Result<String> {
         let stack = self.root_context.stack();
         let top = stack.first().unwrap();
-        if self.verbose {
+        if self.options.verbose {
             Ok(serde_json::to_string_pretty(&top.children).unwrap())
         } else {
             let mut output = Vec::new();
@@ -695,6 +1718,7 @@ impl<'r> ConsoleReporter<'r> {
                     &mut output,
                     top.status,
                     &failed,
+                    false,
                     &rest,
                     longest,
                     self.rules_file_name,
@@ -711,12 +1735,52 @@ impl<'r> ConsoleReporter<'r> {
         }
     }
 
-    fn report(self, root: &PathAwareValue, output_format_type: OutputFormatType) -> Result<()> {
+    fn report(
+        self,
+        root: &PathAwareValue,
+        output_format_type: OutputFormatType,
+        output: &mut dyn Write,
+        totals: &mut common::EvaluationTotals,
+        rules: &RulesFile<'_>,
+    ) -> Result<()> {
+        {
+            let tree = self.root_context.tree();
+            let top = tree.first().unwrap();
+            let counts = common::RuleCounts::from_context(top)
+                .with_suppressed_rules(self.suppressed.rules)
+                .with_known_rules(self.known.rules);
+            let counts = if self.options.assert_all_pass { counts.skips_as_failures() } else { counts };
+            totals.add(
+                self.data_file_name,
+                counts,
+                common::RuleCounts::clauses_from_context(top)
+                    .with_suppressed_clauses(self.suppressed.failures.len())
+                    .with_known_clauses(self.known.failures.len()),
+            );
+        }
+        let truncated = totals.truncate_context(self.root_context.tree_mut().first_mut().unwrap());
+
+        let by_resource = if self.options.report_by == common::ReportBy::Resource {
+            Some(common::ResourceReport::new(self.rules_file_name, self.data_file_name, self.root_context.failures()?))
+        } else {
+            None
+        };
+        let by_type = if self.options.report_by == common::ReportBy::Type {
+            Some(common::TypeReport::new(self.rules_file_name, self.data_file_name, root, self.root_context.failures()?))
+        } else {
+            None
+        };
         let stack = self.root_context.stack();
         let top = stack.first().unwrap();
-        let mut output = Box::new(std::io::stdout()) as Box<dyn Write>;
+        let by_tag = if self.options.report_by == common::ReportBy::Tag {
+            let statuses = top.children.iter()
+                .filter_map(|rule| rule.status.map(|status| (rule.context.as_str(), status)));
+            Some(common::TagReport::new(self.rules_file_name, self.data_file_name, rules, statuses))
+        } else {
+            None
+        };
 
-        if self.verbose && self.print_json {
+        if self.options.verbose && self.options.print_json {
             let serialized_user = serde_json::to_string_pretty(&top.children).unwrap();
             println!("{}", serialized_user);
         } else {
@@ -727,33 +1791,52 @@ impl<'r> ConsoleReporter<'r> {
 
             let traversal = Traversal::from(root);
 
-            for each_reporter in self.reporters {
-                each_reporter.report(
-                    &mut output,
-                    top.status,
-                    &failed,
-                    &rest,
-                    longest,
-                    self.rules_file_name,
-                    self.data_file_name,
-                    &traversal,
-                    output_format_type,
-                )?;
+            if let Some(by_resource) = &by_resource {
+                by_resource.report(output, output_format_type)?;
+            } else if let Some(by_type) = &by_type {
+                by_type.report(output, output_format_type)?;
+            } else if let Some(by_tag) = &by_tag {
+                by_tag.report(output, output_format_type)?;
+            } else if !self.options.failing_clauses_only() {
+                for each_reporter in self.reporters {
+                    each_reporter.report(
+                        output,
+                        top.status,
+                        &failed,
+                        truncated,
+                        &rest,
+                        longest,
+                        self.rules_file_name,
+                        self.data_file_name,
+                        &traversal,
+                        output_format_type,
+                    )?;
+                }
             }
 
-            if self.show_clause_failures {
+            if self.options.show_clause_failures {
                 println!("{}", "Clause Failure Summary".bold());
                 for each in failed {
                     print_failing_clause(self.rules_file_name, each, longest);
                 }
+            } else if self.options.explain {
+                print_explanation(output, self.rules_file_name, &failed)?;
+            } else if output_format_type == OutputFormatType::SingleLineSummary && !self.options.summary_only && self.options.report_by == common::ReportBy::Rule {
+                print_failed_clause_details(output, self.rules_file_name, &failed)?;
             }
 
-            if self.verbose {
+            if !self.options.summary_only {
+                self.suppressed.report(output, "suppressed", self.data_file_name, output_format_type)?;
+                self.known.report(output, "known", self.data_file_name, output_format_type)?;
+            }
+
+            if self.options.verbose {
                 println!("Evaluation Tree");
                 for each in &top.children {
-                    print_context(each, 1);
+                    print_context(&mut std::io::stdout(), each, 1)?;
                 }
             }
+
         }
 
         Ok(())
@@ -771,6 +1854,14 @@ impl<'r> EvaluationContext for ConsoleReporter<'r> {
         self.root_context.rule_status(rule_name)
     }
 
+    fn resource_count(&self, resource_type: &str) -> usize {
+        self.root_context.resource_count(resource_type)
+    }
+
+    fn options(&self) -> &EvaluationOptions {
+        self.root_context.options()
+    }
+
     fn end_evaluation(
         &self,
         eval_type: EvaluationType,
@@ -790,102 +1881,459 @@ impl<'r> EvaluationContext for ConsoleReporter<'r> {
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-fn evaluate_against_data_input<'r>(
+//
+// Options for evaluating rules files against data files, set once from the command line
+//
+#[derive(Debug)]
+struct ValidateOptions {
     _data_type: Type,
     output: OutputFormatType,
-    extra_data: Option<PathAwareValue>,
-    data_files: &'r Vec<DataFile>,
-    rules: &RulesFile<'_>,
-    rules_file_name: &'r str,
     verbose: bool,
     print_json: bool,
     show_clause_failures: bool,
-    new_engine_version: bool,
+    explain: bool,
+    summary_only: bool,
+    assert_all_pass: bool,
+    report_by: common::ReportBy,
     summary_table: BitFlags<SummaryType>,
+    apply_suppressions: bool,
+    new_engine_version: bool,
+    ignore_cf_extensions: bool,
+    max_errors: usize,
+    defines: HashMap<String, PathAwareValue>,
+    evaluation: EvaluationOptions,
+}
+
+impl ValidateOptions {
+    //
+    // With --fail-fast the console output is only the context of the failing clauses, the
+    // evaluation stops at the first data file with failures
+    //
+    fn failing_clauses_only(&self) -> bool {
+        self.evaluation.fail_fast && self.output == OutputFormatType::SingleLineSummary && !self.summary_only
+    }
+
+    fn from_args(app: &ArgMatches<'_>, output: OutputFormatType, defines: HashMap<String, PathAwareValue>) -> ValidateOptions {
+        let summary_table: BitFlags<SummaryType> =
+            app.values_of(SHOW_SUMMARY.0)
+                .map_or(SummaryType::FAIL.into(), |v| {
+                    v.fold(BitFlags::empty(), |mut st, elem| {
+                        match elem {
+                            "pass" => st.insert(SummaryType::PASS),
+                            "fail" => st.insert(SummaryType::FAIL),
+                            "skip" => st.insert(SummaryType::SKIP),
+                            "none" => return BitFlags::empty(),
+                            "all" => {
+                                st.insert(SummaryType::PASS | SummaryType::FAIL | SummaryType::SKIP)
+                            }
+                            _ => unreachable!(),
+                        };
+                        st
+                    })
+                });
+        let report_by = match app.value_of(REPORT_BY) {
+            Some("resource") => common::ReportBy::Resource,
+            Some("tag") => common::ReportBy::Tag,
+            Some("type") => common::ReportBy::Type,
+            _ => common::ReportBy::Rule,
+        };
+        let mut evaluation = EvaluationOptions::new();
+        evaluation.max_depth = app
+            .value_of(MAX_DEPTH)
+            .map_or(DEFAULT_MAX_DEPTH, |v| v.parse::<usize>().unwrap_or(DEFAULT_MAX_DEPTH));
+        evaluation.type_coercion = app.is_present(TYPE_COERCION);
+        evaluation.numeric_string_coercion = app.is_present(COERCE_NUMERIC_STRINGS);
+        evaluation.case_insensitive_keys = app.is_present(CASE_INSENSITIVE_KEYS);
+        evaluation.missing_as = match app.value_of(MISSING_AS) {
+            Some("fail") => MissingAs::Fail,
+            _ => MissingAs::Skip,
+        };
+        evaluation.string_order = match app.value_of(STRING_ORDER) {
+            Some("semver") => StringOrder::Semver,
+            _ => StringOrder::Lexical,
+        };
+        evaluation.list_key_expansion = app.value_of(INPUT_TYPE) == Some("tf-plan");
+        evaluation.fail_fast = app.is_present(FAIL_FAST);
+        ValidateOptions {
+            _data_type: match app.value_of(TYPE.0) {
+                Some("CFNTemplate") => CFNTemplate,
+                _ => Type::Generic,
+            },
+            output,
+            verbose: app.is_present(VERBOSE.0),
+            print_json: app.is_present(PRINT_JSON.0),
+            show_clause_failures: app.is_present(SHOW_CLAUSE_FAILURES.0),
+            explain: app.is_present(EXPLAIN),
+            summary_only: app.is_present(SUMMARY_ONLY),
+            assert_all_pass: app.is_present(ASSERT_ALL_PASS),
+            report_by,
+            summary_table,
+            apply_suppressions: !app.is_present(NO_SUPPRESSIONS),
+            new_engine_version: !app.is_present(PREVIOUS_ENGINE.0),
+            ignore_cf_extensions: app.is_present(IGNORE_CF_EXTENSIONS),
+            max_errors: app
+                .value_of(MAX_ERRORS)
+                .map_or(0, |v| v.parse::<usize>().unwrap_or(0)),
+            defines,
+            evaluation,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn evaluate_against_data_input<'r>(
+    options: &ValidateOptions,
+    extra_data: Option<PathAwareValue>,
+    data_files: &'r Vec<DataFile>,
+    rules: &RulesFile<'_>,
+    rules_file_name: &'r str,
+    write_output: &mut dyn Write,
+    totals: &mut common::EvaluationTotals,
+    baseline: &mut baseline::Baseline,
+    cache: &cache::Cache,
 ) -> Result<Status> {
+    for each in &rules.assignments {
+        if options.defines.contains_key(&each.var) {
+            eprintln!(
+                "WARNING: variable {} from --define overrides the let assignment in rules file {}",
+                each.var, rules_file_name
+            );
+        }
+    }
+    let variables = rule_variables(rules, extra_data.as_ref(), &options.defines);
     let mut overall = Status::PASS;
-    let mut write_output = Box::new(std::io::stdout()) as Box<dyn Write>;
     let generic: Box<dyn Reporter> =
         Box::new(generic_summary::GenericSummary::new()) as Box<dyn Reporter>;
     let tf: Box<dyn Reporter> = Box::new(TfAware::new_with(generic.as_ref())) as Box<dyn Reporter>;
     let cfn: Box<dyn Reporter> =
         Box::new(cfn::CfnAware::new_with(tf.as_ref())) as Box<dyn Reporter>;
-    let reporter: Box<dyn Reporter> = if summary_table.is_empty() {
+    let reporter: Box<dyn Reporter> = if options.summary_only {
+        Box::new(summary_table::SummaryTable::summary_only(cfn.as_ref())) as Box<dyn Reporter>
+    } else if options.summary_table.is_empty() {
         cfn
     } else {
         Box::new(summary_table::SummaryTable::new(
-            summary_table,
+            options.summary_table,
             cfn.as_ref(),
         )) as Box<dyn Reporter>
     };
     for file in data_files {
-        if new_engine_version {
+        if options.new_engine_version {
             let each = match &extra_data {
                 Some(data) => data.clone().merge(file.path_value.clone())?,
                 None => file.path_value.clone(),
             };
+            let each = if options.ignore_cf_extensions {
+                remove_cf_extensions(each)
+            } else {
+                each
+            };
             let traversal = Traversal::from(&each);
-            let mut root_scope = root_scope(rules, &each)?;
-            let status = eval_rules_file(rules, &mut root_scope)?;
-            let root_record = root_scope.reset_recorder().extract();
-            reporter.report_eval(
-                &mut write_output,
-                status,
-                &root_record,
-                rules_file_name,
-                &file.name,
-                &file.content,
-                &traversal,
-                output,
-            )?;
-            if verbose {
+            let file_variables = with_data_file_variables(&variables, file);
+            let mut root_scope = root_scope_with_defines(rules, &each, &file_variables)
+                .map_err(|e| e.with_data_file(&file.name))?
+                .with_options(options.evaluation.clone());
+            let status = eval_rules_file(rules, &mut root_scope);
+            let status = status.map_err(|e| e.with_data_file(&file.name))?;
+            let coerced = root_scope.options().coerced_matches();
+            let mut root_record = root_scope.reset_recorder().extract();
+            let suppressions = if options.apply_suppressions {
+                suppressions::Suppressions::from_template(&each)
+            } else {
+                suppressions::Suppressions::default()
+            };
+            let (status, metadata_suppressed) = suppressions.suppress_in_record(&mut root_record, status)?;
+            baseline.evaluated(rules_file_name, &file.name);
+            let (status, known) = if baseline.is_enabled() {
+                suppressions::take_failures_from_record(&mut root_record, status, &mut |rule, clause, failure| {
+                    baseline.is_known(baseline.fingerprint(rules_file_name, &file.name, rule, clause, failure))
+                })?
+            } else {
+                (status, suppressions::Excluded::default())
+            };
+            let truncated = totals.truncate_record(&root_record);
+            let reported = truncated.as_ref().unwrap_or(&root_record);
+            if options.report_by == common::ReportBy::Resource {
+                common::ResourceReport::new(rules_file_name, &file.name, common::failures_from_record(reported)?)
+                    .report(write_output, options.output)?;
+            } else if options.report_by == common::ReportBy::Type {
+                common::TypeReport::new(rules_file_name, &file.name, &each, common::failures_from_record(reported)?)
+                    .report(write_output, options.output)?;
+            } else if options.report_by == common::ReportBy::Tag {
+                let statuses = reported.children.iter().filter_map(|rule| match &rule.container {
+                    Some(RecordType::RuleCheck(NamedStatus{name, status, ..})) => Some((*name, *status)),
+                    _ => None,
+                });
+                common::TagReport::new(rules_file_name, &file.name, rules, statuses).report(write_output, options.output)?;
+            } else if options.failing_clauses_only() {
+                print_failed_record_details(write_output, rules_file_name, reported)?;
+            } else {
+                reporter.report_eval(
+                    write_output,
+                    status,
+                    reported,
+                    truncated.is_some(),
+                    rules_file_name,
+                    &file.name,
+                    &file.content,
+                    &traversal,
+                    options.output,
+                )?;
+            }
+            if !options.summary_only {
+                metadata_suppressed.report(write_output, "suppressed", &file.name, options.output)?;
+                known.report(write_output, "known", &file.name, options.output)?;
+            }
+            report_coerced_matches(write_output, coerced, &file.name, options.output)?;
+            if options.verbose {
                 print_verbose_tree(&root_record);
             }
-            if print_json {
+            if options.print_json {
                 println!("{}", serde_json::to_string_pretty(&root_record)?)
             }
+            let counts = common::RuleCounts::from_record(&root_record)
+                .with_suppressed_rules(metadata_suppressed.rules)
+                .with_known_rules(known.rules);
+            let (status, counts) = if options.assert_all_pass && counts.skipped > 0 {
+                (Status::FAIL, counts.skips_as_failures())
+            } else {
+                (status, counts)
+            };
+            totals.add(
+                &file.name,
+                counts,
+                common::RuleCounts::clauses_from_record(&root_record)
+                    .with_suppressed_clauses(metadata_suppressed.failures.len())
+                    .with_known_clauses(known.failures.len()),
+            );
             if status == Status::FAIL {
-                overall = Status::FAIL
+                overall = Status::FAIL;
+                if options.evaluation.fail_fast {
+                    break;
+                }
             }
         } else {
-            let each = &file.path_value;
-            let root_context = RootScope::new(rules, each)?;
+            let filtered;
+            let each = if options.ignore_cf_extensions {
+                filtered = remove_cf_extensions(file.path_value.clone());
+                &filtered
+            } else {
+                &file.path_value
+            };
+            let file_variables = with_data_file_variables(&variables, file);
+            let root_context = RootScope::new(rules, each)
+                .map_err(|e| e.with_data_file(&file.name))?
+                .with_defines(&file_variables)
+                .with_options(options.evaluation.clone());
             let stacker = StackTracker::new(&root_context);
             let renderers = vec![reporter.as_ref()];
-            let reporter = ConsoleReporter::new(
+            let mut reporter = ConsoleReporter::new(
                 stacker,
                 &renderers,
                 rules_file_name,
                 &file.name,
-                verbose,
-                print_json,
-                show_clause_failures,
+                options,
             );
-            let appender = MetadataAppender {
-                delegate: &reporter,
-                root_context: each,
+            //
+            // The data as evaluated is part of the key along with the file content, it differs
+            // from the content with --resolve-parameters and --ignore-cf-extensions
+            //
+            let key = if cache.is_enabled() {
+                let mut variables = file_variables.iter().collect::<Vec<_>>();
+                variables.sort_by(|a, b| a.0.cmp(b.0));
+                let evaluation = &options.evaluation;
+                let options = format!(
+                    "{:?}",
+                    (
+                        evaluation.type_coercion,
+                        evaluation.numeric_string_coercion,
+                        evaluation.case_insensitive_keys,
+                        evaluation.missing_as,
+                        evaluation.string_order,
+                        evaluation.max_depth,
+                        evaluation.fail_fast,
+                        variables,
+                    )
+                );
+                Some(cache::Cache::key(&[&format!("{:?}", rules), &file.content, &format!("{:?}", each), &options]))
+            } else {
+                None
             };
-            let status = rules.evaluate(each, &appender)?;
-            reporter.report(each, output)?;
-            if status == Status::FAIL {
-                overall = Status::FAIL
+            let (status, coerced) = match key.as_ref().and_then(|key| cache.load(key, each)) {
+                Some(cached) => {
+                    reporter.root_context.restore(cached.tree);
+                    (cached.status, cached.coerced)
+                }
+                None => {
+                    let appender = MetadataAppender {
+                        delegate: &reporter,
+                        root_context: each,
+                    };
+                    let status = rules.evaluate(each, &appender);
+                    let status = status.map_err(|e| e.with_data_file(&file.name))?;
+                    let coerced = root_context.options().coerced_matches();
+                    if let Some(key) = &key {
+                        cache.store(key, status, coerced, &reporter.root_context.tree())?;
+                    }
+                    (status, coerced)
+                }
+            };
+            if options.apply_suppressions {
+                reporter.suppress(&suppressions::Suppressions::from_template(each))?;
+            }
+            baseline.evaluated(rules_file_name, &file.name);
+            if baseline.is_enabled() {
+                reporter.take_known(baseline)?;
+            }
+            //
+            // the status from evaluation does not know about suppressed or known rules
+            //
+            let failed = reporter.has_failed() ||
+                (status == Status::FAIL && reporter.suppressed.rules + reporter.known.rules == 0);
+            reporter.report(each, options.output, write_output, totals, rules)?;
+            report_coerced_matches(write_output, coerced, &file.name, options.output)?;
+            if failed {
+                overall = Status::FAIL;
+                if options.evaluation.fail_fast {
+                    break;
+                }
             }
         }
     }
     Ok(overall)
 }
 
-fn get_path_aware_value_from_data(content: &String) -> Result<PathAwareValue> {
+fn with_data_file_variables(
+    variables: &HashMap<String, PathAwareValue>,
+    file: &DataFile,
+) -> HashMap<String, PathAwareValue> {
+    let mut merged = variables.clone();
+    merged.extend(file.variables.iter().map(|(k, v)| (k.clone(), v.clone())));
+    merged
+}
+
+fn report_coerced_matches(
+    writer: &mut dyn Write,
+    coerced: usize,
+    data_file_name: &str,
+    output: OutputFormatType,
+) -> Result<()> {
+    if coerced > 0 && output == OutputFormatType::SingleLineSummary {
+        writeln!(
+            writer,
+            "NOTE: {} comparison(s) against data [{}] succeeded only via type coercion",
+            coerced, data_file_name
+        )?;
+    }
+    Ok(())
+}
+
+//
+// With --root-path rules are evaluated against the value at pointer. It keeps its path in the
+// data file, so failures are reported at the same path as without --root-path. Extensions are
+// removed before, they are found from the Resources section of the whole file
+//
+fn select_data_file_root(data_files: Vec<DataFile>, pointer: &str, ignore_cf_extensions: bool) -> Result<Vec<DataFile>> {
+    let mut selected = Vec::with_capacity(data_files.len());
+    for DataFile { content, path_value, name, variables } in data_files {
+        let root = if ignore_cf_extensions {
+            remove_cf_extensions(path_value)
+        } else {
+            path_value
+        };
+        let path_value = value_at_pointer(&root, pointer).cloned().ok_or_else(|| {
+            Error::new(ErrorKind::RetrievalError(format!(
+                "--root-path {} does not resolve to a value in data file {}",
+                pointer, name
+            )))
+        })?;
+        selected.push(DataFile { content, path_value, name, variables });
+    }
+    Ok(selected)
+}
+
+fn value_at_pointer<'v>(root: &'v PathAwareValue, pointer: &str) -> Option<&'v PathAwareValue> {
+    if pointer.is_empty() || pointer == "/" {
+        return Some(root);
+    }
+    pointer.split('/').skip(1).try_fold(root, |current, token| {
+        let key = token.replace("~1", "/").replace("~0", "~");
+        match current {
+            PathAwareValue::Map((_, map)) => map.values.get(&key),
+            PathAwareValue::List((_, list)) => key.parse::<usize>().ok().and_then(|idx| list.get(idx)),
+            _ => None,
+        }
+    })
+}
+
+//
+// Removes custom resources and nested stacks from the Resources section. These do not have
+// standard properties and only produce noise for rules written against AWS resource types
+//
+fn remove_cf_extensions(mut root: PathAwareValue) -> PathAwareValue {
+    if let PathAwareValue::Map((_, root_map)) = &mut root {
+        if let Some(PathAwareValue::Map((_, resources))) = root_map.values.get_mut("Resources") {
+            let extensions = resources
+                .values
+                .iter()
+                .filter(|(_, resource)| is_cf_extension(resource))
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<String>>();
+            for name in &extensions {
+                resources.values.shift_remove(name);
+            }
+            resources.keys.retain(|key| match key {
+                PathAwareValue::String((_, name)) => !extensions.contains(name),
+                _ => true,
+            });
+        }
+    }
+    root
+}
+
+fn is_cf_extension(resource: &PathAwareValue) -> bool {
+    if let PathAwareValue::Map((_, resource)) = resource {
+        if let Some(PathAwareValue::String((_, type_name))) = resource.values.get("Type") {
+            return type_name.starts_with("Custom::")
+                || type_name == "AWS::CloudFormation::Stack"
+                || type_name == "AWS::CloudFormation::StackSet";
+        }
+    }
+    false
+}
+
+fn get_path_aware_value_from_data(
+    content: &str,
+    name: &str,
+    data_format: DataFormat,
+) -> Result<PathAwareValue> {
     if content.trim().is_empty() {
-        Err(Error::new(ErrorKind::ParseError("blank data".to_string())))
+        Err(Error::new(ErrorKind::DataFileError(format!("{}, blank data", name))))
     } else {
+        //
+        // The loader accepts any YAML, which includes JSON, and keeps line information. When a
+        // format is forced, check the content with that parser first so that its error surfaces
+        //
+        let forced = match data_format {
+            DataFormat::Auto => Ok(()),
+            DataFormat::JSON => serde_json::from_str::<serde_json::Value>(content)
+                .map(|_| ())
+                .map_err(|e| format!("{}, not parsable as JSON: {}", name, e)),
+            DataFormat::YAML => serde_yaml::from_str::<serde_yaml::Value>(content)
+                .map(|_| ())
+                .map_err(|e| format!("{}, not parsable as YAML: {}", name, e)),
+        };
+        if let Err(msg) = forced {
+            return Err(Error::new(ErrorKind::DataFileError(msg)));
+        }
         let path_value = match crate::rules::values::read_from(content) {
-            Ok(value) => PathAwareValue::try_from(value)?,
+            Ok(value) => PathAwareValue::try_from(value)
+                .map_err(|e| Error::new(ErrorKind::DataFileError(format!("{}, {}", name, e))))?,
             Err(_) => {
                 let str_len: usize = cmp::min(content.len(), 100);
-                return Err(Error::new(ErrorKind::ParseError(format!(
-                    "data beginning with \n{}\n ...",
+                return Err(Error::new(ErrorKind::DataFileError(format!(
+                    "{}, data beginning with \n{}\n ...",
+                    name,
                     &content[..str_len]
                 ))));
             }
@@ -0,0 +1,203 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::commands::tracker::Failure;
+use crate::commands::validate::OutputFormatType;
+use crate::rules::errors::{Error, ErrorKind};
+use crate::rules::Result;
+
+const BASELINE_VERSION: u32 = 1;
+
+//
+// How rules and data file names appear in fingerprints, with --baseline-path. Names are
+// relative to the directories passed to --rules and --data, name drops the directories and
+// none leaves files out so that a finding matches in any file
+//
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FilePaths {
+    Relative,
+    Name,
+    None,
+}
+
+///
+/// A failure as recorded in a baseline. The clause is its text in the rules file rather than
+/// its location, so that fingerprints do not change when clauses are moved around
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Fingerprint {
+    pub(crate) rules_file: String,
+    pub(crate) data_file: String,
+    pub(crate) rule: String,
+    pub(crate) clause: String,
+    pub(crate) path: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BaselineFile {
+    version: u32,
+    findings: BTreeSet<Fingerprint>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Mode {
+    Off,
+    Compare,
+    Write,
+}
+
+///
+/// Failures known from a baseline file (--baseline). Known failures are taken out of the
+/// evaluation and reported separately, findings in the baseline that no longer fail are
+/// reported as stale. With --write-baseline every failure is known and is written out
+///
+#[derive(Debug)]
+pub(crate) struct Baseline {
+    mode: Mode,
+    file_paths: FilePaths,
+    findings: BTreeSet<Fingerprint>,
+    matched: BTreeSet<Fingerprint>,
+    evaluated: BTreeSet<(String, String)>,
+}
+
+impl Default for Baseline {
+    fn default() -> Self {
+        Baseline {
+            mode: Mode::Off,
+            file_paths: FilePaths::Relative,
+            findings: BTreeSet::new(),
+            matched: BTreeSet::new(),
+            evaluated: BTreeSet::new(),
+        }
+    }
+}
+
+impl Baseline {
+    pub(crate) fn read(file: &Path, file_paths: FilePaths) -> Result<Baseline> {
+        let reader = BufReader::new(File::open(file)?);
+        let baseline: BaselineFile = serde_json::from_reader(reader).map_err(|e| Error::new(ErrorKind::ParseError(
+            format!("Could not read baseline file {}, {}", file.display(), e)
+        )))?;
+        if baseline.version != BASELINE_VERSION {
+            return Err(Error::new(ErrorKind::IncompatibleError(format!(
+                "Baseline file {} has version {}, expected {}", file.display(), baseline.version, BASELINE_VERSION
+            ))))
+        }
+        Ok(Baseline { mode: Mode::Compare, file_paths, findings: baseline.findings, ..Default::default() })
+    }
+
+    pub(crate) fn capture(file_paths: FilePaths) -> Baseline {
+        Baseline { mode: Mode::Write, file_paths, ..Default::default() }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.mode != Mode::Off
+    }
+
+    fn file_name(&self, name: &str) -> String {
+        match self.file_paths {
+            FilePaths::Relative => name.replace('\\', "/"),
+            FilePaths::Name => Path::new(name).file_name()
+                .and_then(|name| name.to_str())
+                .map_or(name.to_string(), String::from),
+            FilePaths::None => String::new(),
+        }
+    }
+
+    pub(crate) fn fingerprint(&self, rules_file: &str, data_file: &str, rule: &str, clause: &str, failure: &Failure) -> Fingerprint {
+        Fingerprint {
+            rules_file: self.file_name(rules_file),
+            data_file: self.file_name(data_file),
+            rule: rule.to_string(),
+            clause: clause.split_whitespace().collect::<Vec<&str>>().join(" "),
+            path: failure.path.clone(),
+        }
+    }
+
+    //
+    // Must be called for each pair evaluated, findings are only stale for pairs that were
+    //
+    pub(crate) fn evaluated(&mut self, rules_file: &str, data_file: &str) {
+        let evaluated = (self.file_name(rules_file), self.file_name(data_file));
+        self.evaluated.insert(evaluated);
+    }
+
+    pub(crate) fn is_known(&mut self, fingerprint: Fingerprint) -> bool {
+        match self.mode {
+            Mode::Off => false,
+            Mode::Write => {
+                self.findings.insert(fingerprint);
+                true
+            },
+            Mode::Compare => {
+                let known = self.findings.contains(&fingerprint);
+                if known {
+                    self.matched.insert(fingerprint);
+                }
+                known
+            },
+        }
+    }
+
+    pub(crate) fn stale(&self) -> Vec<&Fingerprint> {
+        if self.mode != Mode::Compare {
+            return vec![]
+        }
+        self.findings.iter()
+            .filter(|each| !self.matched.contains(*each))
+            .filter(|each| self.evaluated.contains(&(each.rules_file.clone(), each.data_file.clone())))
+            .collect()
+    }
+
+    pub(crate) fn write(&self, file: &Path) -> Result<()> {
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let writer = BufWriter::new(File::create(file)?);
+        let baseline = BaselineFile { version: BASELINE_VERSION, findings: self.findings.clone() };
+        serde_json::to_writer_pretty(writer, &baseline)?;
+        Ok(())
+    }
+
+    //
+    // Writes the baseline with --write-baseline, otherwise reports stale findings
+    //
+    pub(crate) fn finish(&self, file: &Path, writer: &mut dyn Write, output: OutputFormatType) -> Result<()> {
+        match self.mode {
+            Mode::Off => {},
+            Mode::Write => {
+                self.write(file)?;
+                if output == OutputFormatType::SingleLineSummary {
+                    writeln!(writer, "Baseline with {} findings written to [{}]", self.findings.len(), file.display())?;
+                }
+            },
+            Mode::Compare => {
+                let stale = self.stale();
+                if stale.is_empty() {
+                    return Ok(())
+                }
+                let report = serde_json::json!({ "stale_baseline": &stale });
+                match output {
+                    OutputFormatType::SingleLineSummary => {
+                        writeln!(writer, "Stale baseline findings that no longer fail, remove them from [{}]", file.display())?;
+                        for each in stale {
+                            writeln!(writer, "    Rule=[{}/{}] Clause=[{}] Path=[{}] in [{}]",
+                                     each.rules_file, each.rule, each.clause, each.path, each.data_file)?;
+                        }
+                    },
+                    OutputFormatType::JSON => writeln!(writer, "{}", serde_json::to_string_pretty(&report)?)?,
+                    OutputFormatType::YAML => write!(writer, "{}", serde_yaml::to_string(&report)?)?,
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "baseline_tests.rs"]
+mod baseline_tests;
@@ -0,0 +1,186 @@
+use super::*;
+use std::convert::TryFrom;
+
+use crate::commands::tracker::StackTracker;
+use crate::commands::validate::suppressions::{take_failures_from_record, Excluded};
+use crate::rules::eval::eval_rules_file;
+use crate::rules::eval_context::root_scope;
+use crate::rules::exprs::RulesFile;
+use crate::rules::path_value::PathAwareValue;
+use crate::rules::{Evaluate, Status};
+
+const RULES: &str = r#"
+rule buckets_encrypted {
+    Resources.*[ Type == 'AWS::S3::Bucket' ] {
+        Properties.BucketEncryption exists
+        Properties.VersioningConfiguration.Status == 'Enabled'
+    }
+}
+"#;
+
+const REORDERED_RULES: &str = r#"
+let buckets = Resources.*[ Type == 'AWS::S3::Bucket' ]
+
+rule buckets_encrypted {
+    %buckets {
+        Properties.VersioningConfiguration.Status   ==   'Enabled'
+    }
+    Resources.*[ Type == 'AWS::S3::Bucket' ] {
+        Properties.BucketEncryption exists
+    }
+}
+"#;
+
+fn template(encrypted: bool) -> Result<PathAwareValue> {
+    let properties = if encrypted {
+        serde_json::json!({ "BucketEncryption": {} })
+    } else {
+        serde_json::json!({})
+    };
+    PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "logs": { "Type": "AWS::S3::Bucket", "Properties": properties },
+        }
+    }))
+}
+
+fn take_known(rules: &str, value: &PathAwareValue, data_file: &str, baseline: &mut Baseline) -> Result<(Status, Excluded)> {
+    let rules = RulesFile::try_from(rules)?;
+    let mut root = root_scope(&rules, value)?;
+    let status = eval_rules_file(&rules, &mut root)?;
+    let mut record = root.reset_recorder().extract();
+    baseline.evaluated("buckets.guard", data_file);
+    take_failures_from_record(&mut record, status, &mut |rule, clause, failure| {
+        baseline.is_known(baseline.fingerprint("buckets.guard", data_file, rule, clause, failure))
+    })
+}
+
+fn write_baseline(file_paths: FilePaths, data_file: &str) -> Result<Baseline> {
+    let mut baseline = Baseline::capture(file_paths);
+    let (status, known) = take_known(RULES, &template(false)?, data_file, &mut baseline)?;
+    assert_eq!(status, Status::PASS);
+    assert_eq!(known.failures.len(), 2);
+    Ok(baseline)
+}
+
+fn compare_with(written: &Baseline, file_paths: FilePaths) -> Baseline {
+    Baseline { mode: Mode::Compare, file_paths, findings: written.findings.clone(), ..Default::default() }
+}
+
+#[test]
+fn test_known_failures_do_not_fail() -> Result<()> {
+    let written = write_baseline(FilePaths::Relative, "templates/bucket.yaml")?;
+    let mut baseline = compare_with(&written, FilePaths::Relative);
+    let (status, known) = take_known(RULES, &template(false)?, "templates/bucket.yaml", &mut baseline)?;
+    assert_eq!(status, Status::PASS);
+    assert_eq!(known.rules, 1);
+    assert_eq!(known.failures.len(), 2);
+    assert!(baseline.stale().is_empty());
+
+    //
+    // the same findings in another data file are new
+    //
+    let mut baseline = compare_with(&written, FilePaths::Relative);
+    let (status, known) = take_known(RULES, &template(false)?, "templates/other.yaml", &mut baseline)?;
+    assert_eq!(status, Status::FAIL);
+    assert!(known.failures.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_fingerprints_ignore_clause_order() -> Result<()> {
+    let written = write_baseline(FilePaths::Relative, "bucket.yaml")?;
+    let mut baseline = compare_with(&written, FilePaths::Relative);
+    let (status, known) = take_known(REORDERED_RULES, &template(false)?, "bucket.yaml", &mut baseline)?;
+    assert_eq!(status, Status::PASS);
+    assert_eq!(known.failures.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_fingerprints_path_prefix() -> Result<()> {
+    let written = write_baseline(FilePaths::Name, "ci/templates/bucket.yaml")?;
+    let mut baseline = compare_with(&written, FilePaths::Name);
+    let (status, _) = take_known(RULES, &template(false)?, "bucket.yaml", &mut baseline)?;
+    assert_eq!(status, Status::PASS);
+
+    let written = write_baseline(FilePaths::None, "bucket.yaml")?;
+    let mut baseline = compare_with(&written, FilePaths::None);
+    let (status, _) = take_known(RULES, &template(false)?, "other.yaml", &mut baseline)?;
+    assert_eq!(status, Status::PASS);
+    Ok(())
+}
+
+#[test]
+fn test_fixed_failure_is_stale() -> Result<()> {
+    let written = write_baseline(FilePaths::Relative, "bucket.yaml")?;
+    let mut baseline = compare_with(&written, FilePaths::Relative);
+    let (status, known) = take_known(RULES, &template(true)?, "bucket.yaml", &mut baseline)?;
+    assert_eq!(status, Status::PASS);
+    assert_eq!(known.failures.len(), 1);
+    let stale = baseline.stale();
+    assert_eq!(stale.len(), 1);
+    assert_eq!(stale[0].rule, "buckets_encrypted");
+    assert_eq!(stale[0].path, "/Resources/logs/Properties");
+    assert!(stale[0].clause.contains("Properties.BucketEncryption EXISTS"), "{}", stale[0].clause);
+
+    let mut output = Vec::new();
+    baseline.finish(Path::new("baseline.json"), &mut output, OutputFormatType::JSON)?;
+    let report: serde_json::Value = serde_json::from_slice(&output)?;
+    assert_eq!(report["stale_baseline"][0]["path"], "/Resources/logs/Properties");
+
+    //
+    // findings are not stale for data files that were not evaluated
+    //
+    let baseline = compare_with(&written, FilePaths::Relative);
+    assert!(baseline.stale().is_empty());
+    Ok(())
+}
+
+//
+// The previous engine (-E) reports missing properties at the value it last resolved, baselines
+// are written and compared with the same engine
+//
+fn take_known_previous_engine(value: &PathAwareValue, baseline: &mut Baseline) -> Result<(bool, Excluded)> {
+    let rules = RulesFile::try_from(RULES)?;
+    let scope = crate::rules::evaluate::RootScope::new(&rules, value)?;
+    let tracker = StackTracker::new(&scope);
+    rules.evaluate(value, &tracker)?;
+    let known = tracker.take_failures(&mut |rule, clause, failure| {
+        baseline.is_known(baseline.fingerprint("buckets.guard", "bucket.yaml", rule, clause, failure))
+    })?;
+    Ok((tracker.has_failed(), known))
+}
+
+#[test]
+fn test_known_failures_previous_engine() -> Result<()> {
+    let mut written = Baseline::capture(FilePaths::Relative);
+    take_known_previous_engine(&template(false)?, &mut written)?;
+    assert_eq!(written.findings.len(), 2);
+
+    let mut baseline = compare_with(&written, FilePaths::Relative);
+    baseline.evaluated("buckets.guard", "bucket.yaml");
+    let (failed, known) = take_known_previous_engine(&template(false)?, &mut baseline)?;
+    assert!(!failed);
+    assert_eq!(known.rules, 1);
+    assert!(baseline.stale().is_empty());
+
+    let mut baseline = compare_with(&written, FilePaths::Relative);
+    baseline.evaluated("buckets.guard", "bucket.yaml");
+    let (failed, known) = take_known_previous_engine(&template(true)?, &mut baseline)?;
+    assert!(!failed);
+    assert_eq!(known.failures.len(), 1);
+    assert_eq!(baseline.stale().len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_read_written_baseline() -> Result<()> {
+    let written = write_baseline(FilePaths::Relative, "bucket.yaml")?;
+    let file = std::env::temp_dir().join(format!("cfn-guard-baseline-{}.json", std::process::id()));
+    written.write(&file)?;
+    let read = Baseline::read(&file, FilePaths::Relative);
+    std::fs::remove_file(&file)?;
+    assert_eq!(read?.findings, written.findings);
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::commands::tracker::StatusContext;
+use crate::rules::path_value::{compare_eq, PathAwareValue, DEFAULT_EVALUATION_OPTIONS};
+use crate::rules::{Result, Status};
+
+const CACHE_VERSION: u32 = 1;
+
+pub(crate) const DEFAULT_CACHE_TTL: u64 = 86400;
+
+#[derive(Serialize)]
+struct EntryFile<'a> {
+    version: u32,
+    created: u64,
+    status: Status,
+    coerced: usize,
+    tree: &'a [StatusContext],
+}
+
+#[derive(Deserialize)]
+struct CachedEntryFile {
+    version: u32,
+    created: u64,
+    status: Status,
+    coerced: usize,
+    tree: Vec<StatusContext>,
+}
+
+///
+/// The result of evaluating a rules file against a data file in an earlier run, the status, the
+/// number of matches that needed type coercion and the evaluation tree before suppressions and
+/// baselines were applied
+///
+#[derive(Debug)]
+pub(crate) struct Entry {
+    pub(crate) status: Status,
+    pub(crate) coerced: usize,
+    pub(crate) tree: Vec<StatusContext>,
+}
+
+///
+/// Evaluation results kept between runs (--incremental). Each result is a JSON file in the
+/// cache directory named by the SHA-256 of what was evaluated, so a rules file and data file
+/// that have not changed since the last run are reported from the file instead of being
+/// evaluated again. Results older than the TTL (--cache-ttl) are evaluated again
+///
+#[derive(Debug, Default)]
+pub(crate) struct Cache {
+    dir: Option<PathBuf>,
+    ttl: u64,
+}
+
+impl Cache {
+    pub(crate) fn open(dir: &Path, ttl: u64) -> Result<Cache> {
+        fs::create_dir_all(dir)?;
+        Ok(Cache { dir: Some(dir.to_path_buf()), ttl })
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.dir.is_some()
+    }
+
+    //
+    // Each part is hashed with its length, so that moving text from one part to the next
+    // does not produce the same key
+    //
+    pub(crate) fn key(parts: &[&str]) -> String {
+        let mut hasher = Sha256::new();
+        for part in parts {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    fn entry_file(&self, key: &str) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(format!("{}.json", key)))
+    }
+
+    //
+    // The result for key, unless it has expired. Expired results and ones that cannot be read,
+    // e.g. written by another version, are removed and evaluated again
+    //
+    pub(crate) fn load(&self, key: &str, data: &PathAwareValue) -> Option<Entry> {
+        let file = self.entry_file(key)?;
+        let content = fs::read_to_string(&file).ok()?;
+        match serde_json::from_str::<CachedEntryFile>(&content) {
+            Ok(entry) if entry.version == CACHE_VERSION && now().saturating_sub(entry.created) <= self.ttl => {
+                let mut tree = entry.tree;
+                for each in &mut tree {
+                    relink(each, data);
+                }
+                Some(Entry { status: entry.status, coerced: entry.coerced, tree })
+            },
+            _ => {
+                let _ = fs::remove_file(&file);
+                None
+            }
+        }
+    }
+
+    //
+    // Written to a temporary file first, so that a concurrent run never reads half a result
+    //
+    pub(crate) fn store(&self, key: &str, status: Status, coerced: usize, tree: &[StatusContext]) -> Result<()> {
+        if let Some(file) = self.entry_file(key) {
+            let entry = EntryFile { version: CACHE_VERSION, created: now(), status, coerced, tree };
+            let partial = file.with_extension(format!("{}.tmp", std::process::id()));
+            fs::write(&partial, serde_json::to_string(&entry)?)?;
+            fs::rename(&partial, &file)?;
+        }
+        Ok(())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+//
+// Values are cached without their location in the data file. The data file is the same as
+// when the result was cached, so values from it are taken from it again by path
+//
+fn relink(context: &mut StatusContext, data: &PathAwareValue) {
+    let relinked = |value: &mut Option<PathAwareValue>| {
+        if let Some(cached) = value {
+            if let Some(found) = value_at(data, &cached.self_path().0) {
+                if compare_eq(found, cached, &DEFAULT_EVALUATION_OPTIONS).unwrap_or(false) {
+                    *cached = found.clone();
+                }
+            }
+        }
+    };
+    relinked(&mut context.from);
+    relinked(&mut context.to);
+    for child in &mut context.children {
+        relink(child, data);
+    }
+}
+
+//
+// data is not the root of the file with --root-path, path is looked up relative to it
+//
+fn value_at<'v>(data: &'v PathAwareValue, path: &str) -> Option<&'v PathAwareValue> {
+    let relative = path.strip_prefix(data.self_path().0.as_str())?;
+    relative.split('/').skip(1).try_fold(data, |current, part| match current {
+        PathAwareValue::Map((_, map)) => map.values.get(part),
+        PathAwareValue::List((_, list)) => part.parse::<usize>().ok().and_then(|idx| list.get(idx)),
+        _ => None,
+    }).filter(|found| found.self_path().0 == path)
+}
+
+#[cfg(test)]
+#[path = "cache_tests.rs"]
+mod cache_tests;
@@ -0,0 +1,67 @@
+use super::*;
+use std::convert::TryFrom;
+
+use crate::commands::tracker::StackTracker;
+use crate::rules::evaluate::RootScope;
+use crate::rules::exprs::RulesFile;
+use crate::rules::values::read_from;
+use crate::rules::Evaluate;
+
+#[test]
+fn test_key_depends_on_every_part() {
+    let key = Cache::key(&["rules", "data"]);
+    assert_eq!(key.len(), 64);
+    assert_eq!(key, Cache::key(&["rules", "data"]));
+    assert_ne!(key, Cache::key(&["rules", "data", ""]));
+    assert_ne!(key, Cache::key(&["rulesd", "ata"]));
+    assert_ne!(key, Cache::key(&["data", "rules"]));
+}
+
+#[test]
+fn test_store_and_load() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule sized { Resources.*.Properties.Size <= 100 }
+    "#)?;
+    let data = PathAwareValue::try_from(read_from("Resources:\n  volume:\n    Properties:\n      Size: 200\n")?)?;
+    let root = RootScope::new(&rules, &data)?;
+    let tracker = StackTracker::new(&root);
+    let status = rules.evaluate(&data, &tracker)?;
+    assert_eq!(status, Status::FAIL);
+
+    let dir = std::env::temp_dir().join(format!("cfn-guard-cache-tests-{}", std::process::id()));
+    let cache = Cache::open(&dir, DEFAULT_CACHE_TTL)?;
+    assert!(cache.is_enabled());
+    assert!(cache.load("key", &data).is_none());
+    cache.store("key", status, 2, &tracker.tree())?;
+
+    let entry = cache.load("key", &data).unwrap();
+    assert_eq!((entry.status, entry.coerced), (Status::FAIL, 2));
+    let restored = StackTracker::new(&root);
+    restored.restore(entry.tree);
+    assert_eq!(restored.failures()?, tracker.failures()?);
+
+    //
+    // the failing value is the one in the data, with its location
+    //
+    let failures = restored.failures()?;
+    assert_eq!(failures[0].path, "/Resources/volume/Properties/Size");
+    assert_eq!(failures[0].location, Some((3, 12)));
+
+    //
+    // entries that cannot be read are removed
+    //
+    std::fs::write(dir.join("broken.json"), "{")?;
+    assert!(cache.load("broken", &data).is_none());
+    assert!(!dir.join("broken.json").exists());
+
+    let expired = Cache::open(&dir, 0)?;
+    let mut entry: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("key.json"))?)?;
+    entry["created"] = serde_json::json!(1);
+    std::fs::write(dir.join("key.json"), entry.to_string())?;
+    assert!(expired.load("key", &data).is_none());
+    assert!(!dir.join("key.json").exists());
+
+    std::fs::remove_dir_all(&dir)?;
+    assert!(!Cache::default().is_enabled());
+    Ok(())
+}
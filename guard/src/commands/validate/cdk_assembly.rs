@@ -0,0 +1,114 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::rules::errors::{Error, ErrorKind};
+use crate::rules::Result;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const STACK_ARTIFACT: &str = "aws:cloudformation:stack";
+const ASSET_MANIFEST_ARTIFACT: &str = "cdk:asset-manifest";
+const NESTED_ASSEMBLY_ARTIFACT: &str = "cdk:cloud-assembly";
+const NESTED_TEMPLATE_SUFFIX: &str = ".nested.template.json";
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct StackTemplate {
+    pub(crate) name: String,
+    pub(crate) path: PathBuf,
+}
+
+///
+/// Lists the CloudFormation templates in a CDK cloud assembly, the `cdk.out` directory written
+/// by `cdk synth`. Templates are discovered from `manifest.json` rather than by extension, so
+/// that asset, tree and manifest JSON files are not evaluated as data. Each template is named
+/// after its stack, e.g. `MyStack`. Nested stack templates are file assets of their parent
+/// stack and are named `MyStack/<nested template>`, stages are nested assemblies and are
+/// read the same way.
+///
+pub(crate) fn stack_templates(assembly: &Path) -> Result<Vec<StackTemplate>> {
+    let manifest = read_json(&assembly.join(MANIFEST_FILE))?;
+    let artifacts = match manifest.get("artifacts") {
+        Some(Value::Object(artifacts)) => artifacts,
+        _ => return Err(not_an_assembly(assembly)),
+    };
+
+    let mut templates = Vec::new();
+    for (id, artifact) in artifacts {
+        match artifact.get("type").and_then(Value::as_str) {
+            Some(STACK_ARTIFACT) => {
+                if let Some(template) = property(artifact, "templateFile") {
+                    let name = artifact.get("displayName").and_then(Value::as_str).unwrap_or(id);
+                    templates.push(StackTemplate { name: name.to_string(), path: assembly.join(template) });
+                }
+            }
+            Some(NESTED_ASSEMBLY_ARTIFACT) => {
+                if let Some(directory) = property(artifact, "directoryName") {
+                    templates.extend(stack_templates(&assembly.join(directory))?);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    //
+    // Nested stack templates are file assets listed in the asset manifest of their parent
+    // stack, the asset manifest artifact is named <stack id>.assets
+    //
+    for (id, artifact) in artifacts {
+        if artifact.get("type").and_then(Value::as_str) != Some(ASSET_MANIFEST_ARTIFACT) {
+            continue;
+        }
+        let file = match property(artifact, "file") {
+            Some(file) => file,
+            None => continue,
+        };
+        let stack_id = id.strip_suffix(".assets").unwrap_or(id);
+        let stack = artifacts.get(stack_id)
+            .and_then(|stack| stack.get("displayName"))
+            .and_then(Value::as_str)
+            .unwrap_or(stack_id);
+        for nested in nested_templates(&assembly.join(file))? {
+            let name = nested.trim_end_matches(NESTED_TEMPLATE_SUFFIX);
+            templates.push(StackTemplate { name: format!("{}/{}", stack, name), path: assembly.join(nested) });
+        }
+    }
+    Ok(templates)
+}
+
+fn property<'a>(artifact: &'a Value, name: &str) -> Option<&'a str> {
+    artifact.get("properties").and_then(|properties| properties.get(name)).and_then(Value::as_str)
+}
+
+fn nested_templates(asset_manifest: &Path) -> Result<Vec<String>> {
+    let manifest = read_json(asset_manifest)?;
+    let mut nested = Vec::new();
+    if let Some(Value::Object(files)) = manifest.get("files") {
+        for asset in files.values() {
+            if let Some(path) = asset.get("source").and_then(|s| s.get("path")).and_then(Value::as_str) {
+                if path.ends_with(NESTED_TEMPLATE_SUFFIX) {
+                    nested.push(path.to_string());
+                }
+            }
+        }
+    }
+    Ok(nested)
+}
+
+fn read_json(file: &Path) -> Result<Value> {
+    let in_file = |msg: String| Error::new(ErrorKind::DataFileError(format!("{}, {}", file.display(), msg)));
+    let reader = BufReader::new(File::open(file).map_err(|e| in_file(e.to_string()))?);
+    serde_json::from_reader(reader).map_err(|e| in_file(e.to_string()))
+}
+
+fn not_an_assembly(assembly: &Path) -> Error {
+    Error::new(ErrorKind::DataFileError(format!(
+        "{}, not a CDK cloud assembly, expected artifacts in {}",
+        assembly.display(), MANIFEST_FILE
+    )))
+}
+
+#[cfg(test)]
+#[path = "cdk_assembly_tests.rs"]
+mod cdk_assembly_tests;
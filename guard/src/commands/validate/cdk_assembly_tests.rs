@@ -0,0 +1,38 @@
+use super::*;
+
+fn assembly() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/cdk-assembly")
+}
+
+#[test]
+fn test_stack_templates_from_manifest() -> Result<()> {
+    let assembly = assembly();
+    let templates = stack_templates(&assembly)?;
+    assert_eq!(
+        templates,
+        vec![
+            StackTemplate {
+                name: "AppStack".to_string(),
+                path: assembly.join("AppStack.template.json"),
+            },
+            StackTemplate {
+                name: "Prod/ApiStack".to_string(),
+                path: assembly.join("assembly-Prod").join("ProdApiStack.template.json"),
+            },
+            StackTemplate {
+                name: "AppStack/AppStackStorageNestedStackStorageNestedStackResource5A3C2B1D".to_string(),
+                path: assembly.join("AppStackStorageNestedStackStorageNestedStackResource5A3C2B1D.nested.template.json"),
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_stack_templates_not_an_assembly() {
+    let not_assembly = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/data-dir");
+    match stack_templates(&not_assembly) {
+        Err(Error(ErrorKind::DataFileError(msg))) => assert!(msg.contains("manifest.json"), "{}", msg),
+        rest => unreachable!("{:?}", rest),
+    }
+}
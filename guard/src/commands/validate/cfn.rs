@@ -58,16 +58,37 @@ impl<'reporter> CfnAware<'reporter> {
 impl<'reporter> Reporter for CfnAware<'reporter> {
     fn report(
         &self,
-        _writer: &mut dyn Write,
-        _status: Option<Status>,
-        _failed_rules: &[&StatusContext],
-        _passed_or_skipped: &[&StatusContext],
-        _longest_rule_name: usize,
-        _rules_file: &str,
-        _data_file: &str,
-        _data: &Traversal<'_>,
-        _output_format_type: OutputFormatType) -> rules::Result<()> {
-        Ok(())
+        writer: &mut dyn Write,
+        status: Option<Status>,
+        failed_rules: &[&StatusContext],
+        truncated: bool,
+        passed_or_skipped: &[&StatusContext],
+        longest_rule_name: usize,
+        rules_file: &str,
+        data_file: &str,
+        data: &Traversal<'_>,
+        output_format_type: OutputFormatType) -> rules::Result<()> {
+        //
+        // The previous engine shows failures as the summary table and clause details, only the
+        // structured formats are rendered, by the generic reporter
+        //
+        if output_format_type == OutputFormatType::SingleLineSummary {
+            return Ok(())
+        }
+        self.next.map_or(
+            Ok(()), |next|
+                next.report(
+                    writer,
+                    status,
+                    failed_rules,
+                    truncated,
+                    passed_or_skipped,
+                    longest_rule_name,
+                    rules_file,
+                    data_file,
+                    data,
+                    output_format_type)
+        )
     }
 
     fn report_eval<'value>(
@@ -75,6 +96,7 @@ impl<'reporter> Reporter for CfnAware<'reporter> {
         write: &mut dyn Write,
         status: Status,
         root_record: &EventRecord<'value>,
+        truncated: bool,
         rules_file: &str,
         data_file: &str,
         data_file_bytes: &str,
@@ -82,7 +104,8 @@ impl<'reporter> Reporter for CfnAware<'reporter> {
         output_type: OutputFormatType) -> rules::Result<()> {
         let root = data.root().unwrap();
         if let Ok(_) = data.at("/Resources", root) {
-            let failure_report = simplifed_json_from_root(root_record)?;
+            let mut failure_report = simplifed_json_from_root(root_record)?;
+            failure_report.truncated = truncated;
             Ok(match output_type {
                 OutputFormatType::YAML => serde_yaml::to_writer(write, &failure_report)?,
                 OutputFormatType::JSON => serde_json::to_writer_pretty(write, &failure_report)?,
@@ -97,6 +120,7 @@ impl<'reporter> Reporter for CfnAware<'reporter> {
                         write,
                         status,
                         root_record,
+                        truncated,
                         rules_file,
                         data_file,
                         data_file_bytes,
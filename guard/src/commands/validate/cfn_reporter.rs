@@ -34,6 +34,7 @@ impl Reporter for CfnReporter {
               writer: &mut dyn Write,
               _status: Option<Status>,
               failed_rules: &[&StatusContext],
+              truncated: bool,
               passed_or_skipped: &[&StatusContext],
               longest_rule_name: usize,
               rules_file: &str,
@@ -43,8 +44,8 @@ impl Reporter for CfnReporter {
 
         let renderer = match output_format_type {
             OutputFormatType::SingleLineSummary => Box::new(SingleLineReporter {}) as Box<dyn GenericReporter>,
-            OutputFormatType::JSON => Box::new(StructuredSummary::new(StructureType::JSON)) as Box<dyn GenericReporter>,
-            OutputFormatType::YAML => Box::new(StructuredSummary::new(StructureType::YAML)) as Box<dyn GenericReporter>,
+            OutputFormatType::JSON => Box::new(StructuredSummary::new(StructureType::JSON).with_truncated(truncated)) as Box<dyn GenericReporter>,
+            OutputFormatType::YAML => Box::new(StructuredSummary::new(StructureType::YAML).with_truncated(truncated)) as Box<dyn GenericReporter>,
         };
         let failed = if !failed_rules.is_empty() {
             let mut by_resource_name = HashMap::new();
@@ -105,6 +106,7 @@ impl Reporter for CfnReporter {
         _write: &mut dyn Write,
         _status: Status,
         _root_record: &EventRecord<'value>,
+        _truncated: bool,
         _rules_file: &str,
         _data_file: &str,
         _data_file_bytes: &str,
@@ -112,8 +114,8 @@ impl Reporter for CfnReporter {
         _output_type: OutputFormatType) -> crate::rules::Result<()> {
         let renderer = match _output_type {
             OutputFormatType::SingleLineSummary => Box::new(SingleLineReporter {}) as Box<dyn GenericReporter>,
-            OutputFormatType::JSON => Box::new(StructuredSummary::new(StructureType::JSON)) as Box<dyn GenericReporter>,
-            OutputFormatType::YAML => Box::new(StructuredSummary::new(StructureType::YAML)) as Box<dyn GenericReporter>,
+            OutputFormatType::JSON => Box::new(StructuredSummary::new(StructureType::JSON).with_truncated(_truncated)) as Box<dyn GenericReporter>,
+            OutputFormatType::YAML => Box::new(StructuredSummary::new(StructureType::YAML).with_truncated(_truncated)) as Box<dyn GenericReporter>,
         };
         super::common::report_from_events(
             _root_record, _write, _data_file, _rules_file, renderer.as_ref())
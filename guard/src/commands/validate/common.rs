@@ -1,9 +1,12 @@
 use colored::*;
 use serde::Serialize;
 
-use crate::commands::tracker::StatusContext;
-use crate::rules::{EvaluationType, Status, RecordType, ClauseCheck, QueryResult, NamedStatus, UnResolved, InComparisonCheck};
+use crate::commands::tracker::{Failure, StatusContext};
+use crate::rules::{EvaluationType, Status, RecordType, ClauseCheck, QueryResult, NamedStatus, UnResolved, InComparisonCheck,
+                   ComparisonClauseCheck, UnaryValueCheck, ValueCheck, MissingValueCheck};
 use crate::rules::values::CmpOperator;
+use crate::rules::exprs::RulesFile;
+use crate::rules::path_value::PathAwareValue;
 use std::fmt::Debug;
 use std::io::Write;
 use std::collections::{HashMap, HashSet, BTreeSet, BTreeMap};
@@ -73,15 +76,22 @@ pub(super) enum StructureType {
 
 #[derive(Debug)]
 pub(super) struct StructuredSummary {
-    hierarchy_type: StructureType
+    hierarchy_type: StructureType,
+    truncated: bool,
 }
 
 impl StructuredSummary {
     pub(super) fn new(hierarchy_type: StructureType) -> Self {
         StructuredSummary {
-            hierarchy_type
+            hierarchy_type,
+            truncated: false,
         }
     }
+
+    pub(super) fn with_truncated(mut self, truncated: bool) -> Self {
+        self.truncated = truncated;
+        self
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -91,6 +101,8 @@ struct DataOutput<'a> {
     not_compliant: HashMap<String, Vec<NameInfo<'a>>>,
     not_applicable: HashSet<String>,
     compliant: HashSet<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
 }
 
 impl GenericReporter for StructuredSummary {
@@ -107,7 +119,8 @@ impl GenericReporter for StructuredSummary {
             data_from: data_file_name,
             not_compliant: failed,
             compliant: passed,
-            not_applicable: skipped
+            not_applicable: skipped,
+            truncated: self.truncated,
         };
 
         match &self.hierarchy_type {
@@ -122,6 +135,13 @@ lazy_static! {
     static ref PATH_FROM_MSG: Regex = Regex::new(r"path\s+=\s+(?P<path>[^ ]+)").ok().unwrap();
 }
 
+//
+// Retrieval errors of the previous engine (-E) carry the path they stopped at in the message
+//
+pub(super) fn path_from_message(msg: &str) -> Option<&str> {
+    PATH_FROM_MSG.captures(msg).and_then(|cap| cap.name("path")).map(|path| path.as_str())
+}
+
 pub(super) fn find_failing_clauses<'record, 'value>(
     current: &'record EventRecord<'value>) -> Vec<&'record EventRecord<'value>>
 {
@@ -143,6 +163,96 @@ pub(super) fn find_failing_clauses<'record, 'value>(
     }
 }
 
+pub(super) fn is_failing_clause(record: &EventRecord<'_>) -> bool {
+    match &record.container {
+        Some(RecordType::ClauseValueCheck(ClauseCheck::Success)) => false,
+        Some(RecordType::ClauseValueCheck(_)) => true,
+        Some(RecordType::RuleCheck(NamedStatus{message: Some(_), status: Status::FAIL, ..})) => true,
+        _ => false
+    }
+}
+
+//
+// Keeps the first remaining failing clauses (in evaluation order) and drops the rest. Blocks
+// that are left with no failing clauses are dropped as well so that reporters do not
+// mistake them for blocks that failed to retrieve values. Rules are always kept to
+// preserve accurate summary counts. Filters are kept whole, like find_failing_clauses the
+// clauses that do not select a value are not failures. Returns whether to keep current and
+// the number of omitted failures.
+//
+fn prune_failing_clauses(current: &mut EventRecord<'_>, remaining: &mut usize) -> (bool, usize) {
+    if matches!(&current.container, Some(RecordType::Filter(_))) {
+        return (true, 0)
+    }
+
+    if is_failing_clause(current) {
+        return if *remaining > 0 {
+            *remaining -= 1;
+            (true, 0)
+        } else {
+            (false, 1)
+        }
+    }
+
+    if current.children.is_empty() {
+        return (true, 0)
+    }
+
+    let mut omitted = 0;
+    let mut any_kept = false;
+    let children = std::mem::take(&mut current.children);
+    for mut child in children {
+        let (keep, dropped) = prune_failing_clauses(&mut child, remaining);
+        omitted += dropped;
+        if keep {
+            any_kept = true;
+            current.children.push(child);
+        }
+    }
+
+    let is_rule_or_file = matches!(
+        &current.container, Some(RecordType::RuleCheck(_)) | Some(RecordType::FileCheck(_)));
+    (any_kept || omitted == 0 || is_rule_or_file, omitted)
+}
+
+//
+// Same as prune_failing_clauses for the evaluation tree of the previous engine, filters and
+// when conditions are not failures of the rule
+//
+fn prune_failing_contexts(current: &mut StatusContext, remaining: &mut usize) -> (bool, usize) {
+    if matches!(current.eval_type, EvaluationType::Filter | EvaluationType::Condition) {
+        return (true, 0)
+    }
+
+    if current.eval_type == EvaluationType::Clause && current.status == Some(Status::FAIL) {
+        return if *remaining > 0 {
+            *remaining -= 1;
+            (true, 0)
+        } else {
+            (false, 1)
+        }
+    }
+
+    if current.children.is_empty() {
+        return (true, 0)
+    }
+
+    let mut omitted = 0;
+    let mut any_kept = false;
+    let children = std::mem::take(&mut current.children);
+    for mut child in children {
+        let (keep, dropped) = prune_failing_contexts(&mut child, remaining);
+        omitted += dropped;
+        if keep {
+            any_kept = true;
+            current.children.push(child);
+        }
+    }
+
+    let is_rule_or_file = matches!(current.eval_type, EvaluationType::Rule | EvaluationType::File);
+    (any_kept || omitted == 0 || is_rule_or_file, omitted)
+}
+
 pub(super) fn extract_name_info_from_record<'record, 'value>(
     rule_name: &'record str,
     clause: &'record EventRecord<'value>) -> crate::rules::Result<NameInfo<'record>>
@@ -295,6 +405,53 @@ pub(super) fn extract_name_info_from_record<'record, 'value>(
     })
 }
 
+fn clause_from<'value>(clause: &EventRecord<'value>) -> Option<&'value PathAwareValue> {
+    let from = match &clause.container {
+        Some(RecordType::ClauseValueCheck(ClauseCheck::Comparison(check))) => &check.from,
+        Some(RecordType::ClauseValueCheck(ClauseCheck::InComparison(check))) => &check.from,
+        Some(RecordType::ClauseValueCheck(ClauseCheck::Unary(check))) => &check.value.from,
+        Some(RecordType::ClauseValueCheck(ClauseCheck::MissingBlockValue(check))) => &check.from,
+        _ => return None,
+    };
+    from.resolved().or_else(|| from.unresolved_traversed_to())
+}
+
+//
+// Flattens the failing clauses of each failed rule, in evaluation order. The custom message
+// from the rules file is preferred over the generated one
+//
+pub(crate) fn failures_from_record(root: &EventRecord<'_>) -> crate::rules::Result<Vec<Failure>> {
+    let mut failures = Vec::new();
+    for rule in &root.children {
+        let rule_name = match &rule.container {
+            Some(RecordType::RuleCheck(NamedStatus{name, status: Status::FAIL, ..})) => *name,
+            _ => continue,
+        };
+        for clause in find_failing_clauses(rule) {
+            failures.push(failure_from_clause(rule_name, clause)?);
+        }
+    }
+    Ok(failures)
+}
+
+pub(super) fn failure_from_clause(rule_name: &str, clause: &EventRecord<'_>) -> crate::rules::Result<Failure> {
+    let info = extract_name_info_from_record(rule_name, clause)?;
+    let from = clause_from(clause);
+    let path = match from {
+        Some(value) if info.path.is_empty() => value.self_path().0.clone(),
+        _ => info.path,
+    };
+    let message = if info.message.is_empty() { info.error } else { Some(info.message) };
+    Ok(Failure {
+        rule: info.rule.to_string(),
+        path,
+        from: info.provided,
+        to: info.expected,
+        message,
+        location: from.map(|value| (value.self_path().1.line, value.self_path().1.col)),
+    })
+}
+
 pub(crate) fn extract_event_records<'value>(root_record: EventRecord<'value>)
                                             -> (Vec<EventRecord<'value>>, Vec<EventRecord<'value>>, Vec<EventRecord<'value>>)
 {
@@ -307,7 +464,7 @@ pub(crate) fn extract_event_records<'value>(root_record: EventRecord<'value>)
                 let mut failed = EventRecord {
                     container: Some(RecordType::RuleCheck(NamedStatus{status: Status::FAIL, name, message: message.clone()})),
                     children: vec![],
-                    context: each_rule.context
+                    context: each_rule.context,
                 };
                 //add_failed_children(&mut failed, each_rule.children)
             },
@@ -411,8 +568,8 @@ pub(super) fn extract_name_info<'a>(rule_name: &'a str,
         // No from is how we indicate retrieval errors.
         //
         let (path, error) = each_failing_clause.msg.as_ref().map_or(("".to_string(), "".to_string()), |msg| {
-            match PATH_FROM_MSG.captures(msg) {
-                Some(cap) => (cap["path"].to_string(), msg.clone()),
+            match path_from_message(msg) {
+                Some(path) => (path.to_string(), msg.clone()),
                 None => ("".to_string(), msg.clone())
             }
         });
@@ -463,6 +620,496 @@ pub(super) fn find_all_failing_clauses(context: &StatusContext) -> Vec<&StatusCo
     failed
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub(super) struct RuleCounts {
+    pub(super) passed: usize,
+    pub(super) failed: usize,
+    pub(super) skipped: usize,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub(super) suppressed: usize,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub(super) known: usize,
+}
+
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+impl RuleCounts {
+    pub(super) fn from_statuses<I: Iterator<Item=Status>>(statuses: I) -> RuleCounts {
+        let mut counts = RuleCounts::default();
+        for status in statuses {
+            match status {
+                Status::PASS => counts.passed += 1,
+                Status::FAIL => counts.failed += 1,
+                Status::SKIP => counts.skipped += 1,
+            }
+        }
+        counts
+    }
+
+    //
+    // Rules that share a name are counted once, FAIL taking precedence over PASS over SKIP
+    //
+    pub(super) fn from_record(root: &EventRecord<'_>) -> RuleCounts {
+        let mut by_name: BTreeMap<&str, Status> = BTreeMap::new();
+        for each in &root.children {
+            if let Some(RecordType::RuleCheck(NamedStatus{status, name, ..})) = &each.container {
+                let merged = match (by_name.get(name), *status) {
+                    (Some(Status::FAIL), _) | (_, Status::FAIL) => Status::FAIL,
+                    (Some(Status::PASS), _) | (_, Status::PASS) => Status::PASS,
+                    _ => Status::SKIP
+                };
+                by_name.insert(*name, merged);
+            }
+        }
+        RuleCounts::from_statuses(by_name.into_values())
+    }
+
+    pub(super) fn from_context(root: &StatusContext) -> RuleCounts {
+        RuleCounts::from_statuses(root.children.iter().filter_map(|each| each.status))
+    }
+
+    //
+    // Clauses are counted once for each value they were checked against. Clauses of when
+    // conditions and filters decide what is checked and are not counted
+    //
+    pub(super) fn clauses_from_record(root: &EventRecord<'_>) -> RuleCounts {
+        fn statuses(current: &EventRecord<'_>, acc: &mut Vec<Status>) {
+            match &current.container {
+                Some(RecordType::ClauseValueCheck(check)) => acc.push(match check {
+                    ClauseCheck::Success => Status::PASS,
+                    ClauseCheck::Comparison(ComparisonClauseCheck{status, ..}) |
+                    ClauseCheck::InComparison(InComparisonCheck{status, ..}) |
+                    ClauseCheck::Unary(UnaryValueCheck{value: ValueCheck{status, ..}, ..}) |
+                    ClauseCheck::DependentRule(MissingValueCheck{status, ..}) |
+                    ClauseCheck::MissingBlockValue(ValueCheck{status, ..}) => *status,
+                    ClauseCheck::NoValueForEmptyCheck(_) => Status::FAIL,
+                }),
+                Some(RecordType::RuleCondition(_)) |
+                Some(RecordType::TypeCondition(_)) |
+                Some(RecordType::WhenCondition(_)) |
+                Some(RecordType::Filter(_)) => {},
+                _ => current.children.iter().for_each(|each| statuses(each, acc)),
+            }
+        }
+        let mut acc = Vec::new();
+        statuses(root, &mut acc);
+        RuleCounts::from_statuses(acc.into_iter())
+    }
+
+    pub(super) fn clauses_from_context(root: &StatusContext) -> RuleCounts {
+        fn statuses(current: &StatusContext, acc: &mut Vec<Status>) {
+            match current.eval_type {
+                EvaluationType::Clause => acc.extend(current.status),
+                EvaluationType::Condition |
+                EvaluationType::Filter => {},
+                _ => current.children.iter().for_each(|each| statuses(each, acc)),
+            }
+        }
+        let mut acc = Vec::new();
+        statuses(root, &mut acc);
+        RuleCounts::from_statuses(acc.into_iter())
+    }
+
+    fn add(&mut self, other: RuleCounts) {
+        self.passed += other.passed;
+        self.failed += other.failed;
+        self.skipped += other.skipped;
+        self.suppressed += other.suppressed;
+        self.known += other.known;
+    }
+
+    //
+    // Suppressed rules PASS once their suppressed failures are taken out, they are counted as
+    // suppressed instead. Suppressed clauses are taken out and are not counted otherwise
+    //
+    pub(super) fn with_suppressed_rules(self, suppressed: usize) -> RuleCounts {
+        RuleCounts { passed: self.passed.saturating_sub(suppressed), suppressed: self.suppressed + suppressed, ..self }
+    }
+
+    pub(super) fn with_suppressed_clauses(self, suppressed: usize) -> RuleCounts {
+        RuleCounts { suppressed: self.suppressed + suppressed, ..self }
+    }
+
+    //
+    // Same as suppressed for failures known from a --baseline
+    //
+    pub(super) fn with_known_rules(self, known: usize) -> RuleCounts {
+        RuleCounts { passed: self.passed.saturating_sub(known), known: self.known + known, ..self }
+    }
+
+    pub(super) fn with_known_clauses(self, known: usize) -> RuleCounts {
+        RuleCounts { known: self.known + known, ..self }
+    }
+
+    //
+    // Used with --assert-all-pass, skipped rules are reported as failed
+    //
+    pub(super) fn skips_as_failures(self) -> RuleCounts {
+        RuleCounts { failed: self.failed + self.skipped, skipped: 0, ..self }
+    }
+}
+
+impl std::fmt::Display for RuleCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Rules: {} total, {} passed, {} failed, {} skipped",
+               self.passed + self.failed + self.skipped + self.suppressed + self.known, self.passed, self.failed, self.skipped)?;
+        if self.suppressed > 0 {
+            write!(f, ", {} suppressed", self.suppressed)?;
+        }
+        if self.known > 0 {
+            write!(f, ", {} known", self.known)?;
+        }
+        Ok(())
+    }
+}
+
+//
+// Rule and clause counts across every data and rules file evaluated by a validate run,
+// reported once after the sections for each data file. With --max-errors it also keeps how many
+// more failing clauses can be shown in the run and how many were omitted
+//
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct EvaluationTotals {
+    pub(super) rules: RuleCounts,
+    pub(super) clauses: RuleCounts,
+    pub(super) data_files: BTreeSet<String>,
+    remaining_failures: Option<usize>,
+    pub(super) omitted_failures: usize,
+}
+
+#[derive(Serialize)]
+struct TotalsSummary<'a> {
+    rules: &'a RuleCounts,
+    clauses: &'a RuleCounts,
+    files: usize,
+}
+
+impl EvaluationTotals {
+    pub(super) fn with_max_errors(max_errors: usize) -> EvaluationTotals {
+        EvaluationTotals {
+            remaining_failures: if max_errors > 0 { Some(max_errors) } else { None },
+            ..Default::default()
+        }
+    }
+
+    //
+    // Returns a copy of root with the failing clauses over the --max-errors limit taken out, or
+    // None when nothing had to be omitted
+    //
+    pub(super) fn truncate_record<'value>(&mut self, root: &EventRecord<'value>) -> Option<EventRecord<'value>> {
+        let remaining = self.remaining_failures.as_mut()?;
+        let mut truncated = root.clone();
+        let (_, omitted) = prune_failing_clauses(&mut truncated, remaining);
+        self.omitted_failures += omitted;
+        if omitted > 0 { Some(truncated) } else { None }
+    }
+
+    //
+    // Same as truncate_record for the previous engine, top is truncated in place. Returns
+    // whether any failing clause was omitted
+    //
+    pub(super) fn truncate_context(&mut self, top: &mut StatusContext) -> bool {
+        let remaining = match self.remaining_failures.as_mut() {
+            Some(remaining) => remaining,
+            None => return false,
+        };
+        let (_, omitted) = prune_failing_contexts(top, remaining);
+        self.omitted_failures += omitted;
+        omitted > 0
+    }
+
+    pub(super) fn add(&mut self, data_file: &str, rules: RuleCounts, clauses: RuleCounts) {
+        self.rules.add(rules);
+        self.clauses.add(clauses);
+        self.data_files.insert(data_file.to_string());
+    }
+
+    //
+    // The summary is the last line of the text report, and the last document after the per-file
+    // documents of the JSON and YAML reports
+    //
+    pub(super) fn report(&self, writer: &mut dyn Write, output: OutputFormatType) -> crate::rules::Result<()> {
+        if self.data_files.is_empty() {
+            return Ok(())
+        }
+        let totals = HashMap::from([("summary", TotalsSummary {
+            rules: &self.rules,
+            clauses: &self.clauses,
+            files: self.data_files.len(),
+        })]);
+        match output {
+            OutputFormatType::SingleLineSummary => {
+                writeln!(writer, "{}", self)?;
+                if self.omitted_failures > 0 {
+                    writeln!(writer, "... and {} more failures omitted. Use --max-errors 0 for all.", self.omitted_failures)?;
+                }
+            },
+            OutputFormatType::JSON => writeln!(writer, "{}", serde_json::to_string_pretty(&totals)?)?,
+            OutputFormatType::YAML => write!(writer, "{}", serde_yaml::to_string(&totals)?)?,
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for EvaluationTotals {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let files = self.data_files.len();
+        write!(f, "{} across {} data file{}; Clauses: {} passed, {} failed", self.rules, files,
+               if files == 1 { "" } else { "s" }, self.clauses.passed, self.clauses.failed)?;
+        if self.clauses.suppressed > 0 {
+            write!(f, ", {} suppressed", self.clauses.suppressed)?;
+        }
+        if self.clauses.known > 0 {
+            write!(f, ", {} known", self.clauses.known)?;
+        }
+        Ok(())
+    }
+}
+
+//
+// Failing resources for a rule are the distinct resources under /Resources the failing clauses
+// point into. Failures on paths outside of Resources are each counted on their own
+//
+fn failing_resource(path: &str) -> &str {
+    resource_id(path).unwrap_or(path)
+}
+
+pub(super) fn resource_id(path: &str) -> Option<&str> {
+    let mut components = path.split('/').filter(|each| !each.is_empty());
+    match (components.next(), components.next()) {
+        (Some("Resources"), Some(name)) => Some(name),
+        _ => None
+    }
+}
+
+//
+// Failures pivoted by the resource they were found in, used with --report-by resource. The
+// resource is the logical id from the leading /Resources/<id> of the failing value's path,
+// resources without failures are not listed and failures outside of Resources are listed
+// under document
+//
+#[derive(Debug, PartialEq, Serialize)]
+pub(super) struct ResourceReport<'a> {
+    pub(super) rules_file: &'a str,
+    pub(super) data_file: &'a str,
+    pub(super) resources: BTreeMap<String, Vec<Failure>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(super) document: Vec<Failure>,
+}
+
+impl<'a> ResourceReport<'a> {
+    pub(super) fn new(rules_file: &'a str, data_file: &'a str, failures: Vec<Failure>) -> Self {
+        let mut resources: BTreeMap<String, Vec<Failure>> = BTreeMap::new();
+        let mut document = Vec::new();
+        for each in failures {
+            match resource_id(&each.path) {
+                Some(id) => resources.entry(id.to_string()).or_default().push(each),
+                None => document.push(each),
+            }
+        }
+        ResourceReport { rules_file, data_file, resources, document }
+    }
+
+    pub(super) fn report(&self, writer: &mut dyn Write, output: OutputFormatType) -> crate::rules::Result<()> {
+        match output {
+            OutputFormatType::SingleLineSummary => {
+                for (id, failures) in &self.resources {
+                    self.print_bucket(writer, &format!("Resource [{}]", id), failures)?;
+                }
+                if !self.document.is_empty() {
+                    self.print_bucket(writer, "Document", &self.document)?;
+                }
+            },
+            OutputFormatType::JSON => writeln!(writer, "{}", serde_json::to_string_pretty(self)?)?,
+            OutputFormatType::YAML => write!(writer, "{}", serde_yaml::to_string(self)?)?,
+        }
+        Ok(())
+    }
+
+    fn print_bucket(&self, writer: &mut dyn Write, bucket: &str, failures: &[Failure]) -> crate::rules::Result<()> {
+        writeln!(writer, "{} in [{}] {}", bucket, self.data_file, colored_string(Some(Status::FAIL)))?;
+        for each in failures {
+            let message = each.message.as_ref().map_or("".to_string(), |m| {
+                format!(" Message=[{}]", m.trim().replace('\n', ";"))
+            });
+            writeln!(writer, "    Rule=[{}/{}] Path=[{}]{}", self.rules_file, each.rule, each.path, message)?;
+        }
+        Ok(())
+    }
+}
+
+//
+// Failures pivoted by the Type of the resource they were found in, used with --report-by type.
+// Within each type failures are listed by the logical id from the failing value's path, like
+// ResourceReport. Resources without a Type are listed under untyped and failures outside of
+// Resources under document
+//
+#[derive(Debug, PartialEq, Serialize)]
+pub(super) struct TypeReport<'a> {
+    pub(super) rules_file: &'a str,
+    pub(super) data_file: &'a str,
+    pub(super) types: BTreeMap<String, BTreeMap<String, Vec<Failure>>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(super) document: Vec<Failure>,
+}
+
+pub(super) const UNTYPED: &str = "untyped";
+
+fn map_entry<'v>(value: Option<&'v PathAwareValue>, key: &str) -> Option<&'v PathAwareValue> {
+    match value {
+        Some(PathAwareValue::Map((_, map))) => map.values.get(key),
+        _ => None,
+    }
+}
+
+impl<'a> TypeReport<'a> {
+    pub(super) fn new(rules_file: &'a str, data_file: &'a str, root: &PathAwareValue, failures: Vec<Failure>) -> Self {
+        let ResourceReport { resources, document, .. } = ResourceReport::new(rules_file, data_file, failures);
+        let mut types: BTreeMap<String, BTreeMap<String, Vec<Failure>>> = BTreeMap::new();
+        for (id, failures) in resources {
+            let resource = map_entry(map_entry(Some(root), "Resources"), &id);
+            let resource_type = match map_entry(resource, "Type") {
+                Some(PathAwareValue::String((_, resource_type))) => resource_type.as_str(),
+                _ => UNTYPED,
+            };
+            types.entry(resource_type.to_string()).or_default().insert(id, failures);
+        }
+        TypeReport { rules_file, data_file, types, document }
+    }
+
+    pub(super) fn report(&self, writer: &mut dyn Write, output: OutputFormatType) -> crate::rules::Result<()> {
+        match output {
+            OutputFormatType::SingleLineSummary => {
+                for (resource_type, resources) in &self.types {
+                    writeln!(writer, "Type [{}] in [{}] {}", resource_type, self.data_file, colored_string(Some(Status::FAIL)))?;
+                    for (id, failures) in resources {
+                        writeln!(writer, "    Resource [{}]", id)?;
+                        self.print_failures(writer, "        ", failures)?;
+                    }
+                }
+                if !self.document.is_empty() {
+                    writeln!(writer, "Document in [{}] {}", self.data_file, colored_string(Some(Status::FAIL)))?;
+                    self.print_failures(writer, "    ", &self.document)?;
+                }
+            },
+            OutputFormatType::JSON => writeln!(writer, "{}", serde_json::to_string_pretty(self)?)?,
+            OutputFormatType::YAML => write!(writer, "{}", serde_yaml::to_string(self)?)?,
+        }
+        Ok(())
+    }
+
+    fn print_failures(&self, writer: &mut dyn Write, indent: &str, failures: &[Failure]) -> crate::rules::Result<()> {
+        for each in failures {
+            let message = each.message.as_ref().map_or("".to_string(), |m| {
+                format!(" Message=[{}]", m.trim().replace('\n', ";"))
+            });
+            writeln!(writer, "{}Rule=[{}/{}] Path=[{}]{}", indent, self.rules_file, each.rule, each.path, message)?;
+        }
+        Ok(())
+    }
+}
+
+//
+// How the results for each data file are grouped with --report-by
+//
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ReportBy {
+    Rule,
+    Resource,
+    Tag,
+    Type,
+}
+
+pub(super) const UNTAGGED: &str = "untagged";
+
+//
+// Rule statuses grouped by the tags declared on rules with @tags, for --report-by tag. A rule
+// is listed under each of its tags, rules without tags are listed under untagged. Rules that
+// share a name are listed once, FAIL taking precedence over PASS over SKIP
+//
+#[derive(Debug, PartialEq, Serialize)]
+pub(super) struct TagReport<'a> {
+    pub(super) rules_file: &'a str,
+    pub(super) data_file: &'a str,
+    pub(super) tags: BTreeMap<String, BTreeMap<String, Status>>,
+}
+
+impl<'a> TagReport<'a> {
+    pub(super) fn new<'r, I>(rules_file: &'a str, data_file: &'a str, rules: &RulesFile<'_>, statuses: I) -> Self
+        where I: Iterator<Item = (&'r str, Status)>
+    {
+        let mut tags: BTreeMap<String, BTreeMap<String, Status>> = BTreeMap::new();
+        for (name, status) in statuses {
+            let mut rule_tags = rules.guard_rules.iter()
+                .filter(|rule| rule.rule_name == name)
+                .flat_map(|rule| rule.tags.iter().map(String::as_str))
+                .collect::<BTreeSet<&str>>();
+            if rule_tags.is_empty() {
+                rule_tags.insert(UNTAGGED);
+            }
+            for tag in rule_tags {
+                let merged = tags.entry(tag.to_string()).or_default().entry(name.to_string()).or_insert(status);
+                *merged = match (*merged, status) {
+                    (Status::FAIL, _) | (_, Status::FAIL) => Status::FAIL,
+                    (Status::PASS, _) | (_, Status::PASS) => Status::PASS,
+                    _ => Status::SKIP,
+                };
+            }
+        }
+        TagReport { rules_file, data_file, tags }
+    }
+
+    pub(super) fn report(&self, writer: &mut dyn Write, output: OutputFormatType) -> crate::rules::Result<()> {
+        match output {
+            OutputFormatType::SingleLineSummary => {
+                for (tag, rules) in &self.tags {
+                    let counts = RuleCounts::from_statuses(rules.values().copied());
+                    let status = if counts.failed > 0 {
+                        Status::FAIL
+                    } else if counts.passed > 0 {
+                        Status::PASS
+                    } else {
+                        Status::SKIP
+                    };
+                    writeln!(writer, "Tag [{}] in [{}] {}", tag, self.data_file, colored_string(Some(status)))?;
+                    for (rule, status) in rules {
+                        writeln!(writer, "    Rule=[{}/{}] {}", self.rules_file, rule, colored_string(Some(*status)))?;
+                    }
+                }
+            },
+            OutputFormatType::JSON => writeln!(writer, "{}", serde_json::to_string_pretty(self)?)?,
+            OutputFormatType::YAML => write!(writer, "{}", serde_yaml::to_string(self)?)?,
+        }
+        Ok(())
+    }
+}
+
+pub(super) fn count_failing_resources(rule: &StatusContext) -> usize {
+    find_all_failing_clauses(rule).iter()
+        .filter_map(|clause| clause.from.as_ref())
+        .map(|from| failing_resource(from.self_path().0.as_str()))
+        .collect::<HashSet<&str>>()
+        .len()
+}
+
+pub(super) fn count_failing_resources_from_record(rule_name: &str, rule: &EventRecord<'_>) -> crate::rules::Result<usize> {
+    let mut paths = HashSet::new();
+    for clause in find_failing_clauses(rule) {
+        let path = match &clause.container {
+            Some(RecordType::ClauseValueCheck(ClauseCheck::InComparison(incomp))) =>
+                incomp.from.resolved().or_else(|| incomp.from.unresolved_traversed_to())
+                    .map_or("".to_string(), |value| value.self_path().0.clone()),
+            _ => extract_name_info_from_record(rule_name, clause)?.path
+        };
+        if !path.is_empty() {
+            paths.insert(failing_resource(&path).to_string());
+        }
+    }
+    Ok(paths.len())
+}
+
 pub(super) fn print_compliant_skipped_info(writer: &mut dyn Write,
                                            passed: &HashSet<String>,
                                            skipped: &HashSet<String>,
@@ -483,6 +1130,17 @@ pub(super) fn print_compliant_skipped_info(writer: &mut dyn Write,
     Ok(())
 }
 
+fn json_type_name(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "bool",
+        serde_json::Value::Number(n) => if n.is_f64() { "float" } else { "int" },
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "list",
+        serde_json::Value::Object(_) => "struct",
+    }
+}
+
 pub(super) fn print_name_info<R, U, B>(
     writer: &mut dyn Write,
     info: &[NameInfo<'_>],
@@ -525,21 +1183,29 @@ pub(super) fn print_name_info<R, U, B>(
                 };
                 if cmp.is_unary() {
                     use CmpOperator::*;
+                    let op_msg = match cmp {
+                        Exists => if !not { "did not exist" } else { "existed" },
+                        Empty => if !not { "was not empty"} else { "was empty" },
+                        IsList => if !not { "was not a list " } else { "was list" },
+                        IsMap => if !not { "was not a struct" } else { "was struct" },
+                        IsString => if !not { "was not a string " } else { "was string" },
+                        IsBool => if !not { "was not a bool" } else { "was bool" },
+                        IsInt => if !not { "was not an int" } else { "was int" },
+                        IsNumber => if !not { "was not a number" } else { "was number" },
+                        Unique => if !not { "was not unique" } else { "was unique" },
+                        IsFloat => if !not { "was not a float" } else { "was float" },
+                        Eq | EqUnordered | In | AllIn | Gt | Lt | Le | Ge | Subset | Superset | InCidr => unreachable!()
+                    };
+                    let op_msg = match &each.provided {
+                        Some(provided) if cmp.is_type_check() && !not =>
+                            format!("{}, found {}", op_msg.trim_end(), json_type_name(provided)),
+                        _ => op_msg.to_string()
+                    };
                     writeln!(writer, "{}",
                              unary_message(
                                  rules_file_name,
                                  data_file_name,
-                                 match cmp {
-                                     Exists => if !not { "did not exist" } else { "existed" },
-                                     Empty => if !not { "was not empty"} else { "was empty" },
-                                     IsList => if !not { "was not a list " } else { "was list" },
-                                     IsMap => if !not { "was not a struct" } else { "was struct" },
-                                     IsString => if !not { "was not a string " } else { "was string" },
-                                     IsBool => if !not { "was not a bool" } else { "was bool" },
-                                     IsInt => if !not { "was not an int" } else { "was int" },
-                                     IsFloat => if !not { "was not a float" } else { "was float" },
-                                     Eq | In | Gt | Lt | Le | Ge => unreachable!()
-                                 },
+                                 &op_msg,
                                  each)?,
                     )?;
 
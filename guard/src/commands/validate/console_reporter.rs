@@ -106,6 +106,8 @@ fn pprint_failed_sub_tree(current: &EventRecord<'_>,
                         CmpOperator::IsList => if *not { "was a list " } else { "was not list" },
                         CmpOperator::IsMap => if *not { "was a struct" } else { "was not struct" },
                         CmpOperator::IsString => if *not { "was a string " } else { "was not string" },
+                        CmpOperator::IsNumber => if *not { "was a number" } else { "was not a number" },
+                        CmpOperator::Unique => if *not { "was unique" } else { "was not unique" },
                         _ => unreachable!()
                     };
 
@@ -217,11 +219,16 @@ fn pprint_failed_sub_tree(current: &EventRecord<'_>,
                                 to=to_result.map_or("NULL".to_string(), |t| format!("{}", t)),
                                 op_msg=match cmp {
                                     CmpOperator::Eq => if *not { "equal to" } else { "not equal to" },
+                                    CmpOperator::EqUnordered => if *not { "equal in any order to" } else { "not equal in any order to" },
                                     CmpOperator::Le => if *not { "less than equal to" } else { "less than equal to" },
                                     CmpOperator::Lt => if *not { "less than" } else { "not less than" },
                                     CmpOperator::Ge => if *not { "greater than equal to" } else { "not greater than equal" },
                                     CmpOperator::Gt => if *not { "greater than" } else { "not greater than" },
                                     CmpOperator::In => if *not { "in" } else { "not in" },
+                                    CmpOperator::AllIn => if *not { "in" } else { "not in" },
+                                    CmpOperator::Subset => if *not { "a subset of" } else { "not a subset of" },
+                                    CmpOperator::Superset => if *not { "a superset of" } else { "not a superset of" },
+                                    CmpOperator::InCidr => if *not { "within CIDR" } else { "not within CIDR" },
                                     _ => unreachable!()
                                 },
                                 err=error_message,
@@ -265,6 +272,7 @@ impl Reporter for ConsoleReporter {
         _writer: &mut dyn Write,
         _status: Option<Status>,
         _failed_rules: &[&StatusContext],
+        _truncated: bool,
         _passed_or_skipped: &[&StatusContext],
         _longest_rule_name: usize,
         _rules_file: &str,
@@ -279,6 +287,7 @@ impl Reporter for ConsoleReporter {
         _write: &mut dyn Write,
         _status: Status,
         _root_record: &EventRecord<'value>,
+        _truncated: bool,
         _rules_file: &str,
         _data_file: &str,
         _data_file_bytes: &str,
@@ -28,6 +28,7 @@ impl Reporter for GenericSummary {
         writer: &mut dyn Write,
         status: Option<Status>,
         failed_rules: &[&StatusContext],
+        truncated: bool,
         passed_or_skipped: &[&StatusContext],
         longest_rule_name: usize,
         rules_file: &str,
@@ -37,8 +38,8 @@ impl Reporter for GenericSummary {
     {
         let renderer = match output_format_type {
             OutputFormatType::SingleLineSummary => Box::new(SingleLineSummary{}) as Box<dyn GenericReporter>,
-            OutputFormatType::JSON => Box::new(StructuredSummary::new(StructureType::JSON)) as Box<dyn GenericReporter>,
-            OutputFormatType::YAML => Box::new(StructuredSummary::new(StructureType::YAML)) as Box<dyn GenericReporter>,
+            OutputFormatType::JSON => Box::new(StructuredSummary::new(StructureType::JSON).with_truncated(truncated)) as Box<dyn GenericReporter>,
+            OutputFormatType::YAML => Box::new(StructuredSummary::new(StructureType::YAML).with_truncated(truncated)) as Box<dyn GenericReporter>,
         };
         let failed = if !failed_rules.is_empty() {
             let mut by_rule = HashMap::with_capacity(failed_rules.len());
@@ -93,6 +94,7 @@ impl Reporter for GenericSummary {
         _write: &mut dyn Write,
         _status: Status,
         _root_record: &EventRecord<'value>,
+        _truncated: bool,
         _rules_file: &str,
         _data_file: &str,
         _data_file_bytes: &str,
@@ -100,8 +102,8 @@ impl Reporter for GenericSummary {
         _output_type: OutputFormatType) -> crate::rules::Result<()> {
         let renderer = match _output_type {
             OutputFormatType::SingleLineSummary => Box::new(SingleLineSummary{}) as Box<dyn GenericReporter>,
-            OutputFormatType::JSON => Box::new(StructuredSummary::new(StructureType::JSON)) as Box<dyn GenericReporter>,
-            OutputFormatType::YAML => Box::new(StructuredSummary::new(StructureType::YAML)) as Box<dyn GenericReporter>,
+            OutputFormatType::JSON => Box::new(StructuredSummary::new(StructureType::JSON).with_truncated(_truncated)) as Box<dyn GenericReporter>,
+            OutputFormatType::YAML => Box::new(StructuredSummary::new(StructureType::YAML).with_truncated(_truncated)) as Box<dyn GenericReporter>,
         };
         super::common::report_from_events(_root_record, _write, _data_file, _rules_file, renderer.as_ref())
     }
@@ -146,7 +148,7 @@ fn binary_error_message(rules_file: &str, data_file: &str, op_msg: &str, info: &
                msg=info.message.replace("\n", ";"),
                expected=info.expected.as_ref().map_or(&serde_json::Value::Null, |v| v),
         cmp_msg=info.comparison.as_ref().map_or("", |c| {
-            if c.operator == CmpOperator::In {
+            if matches!(c.operator, CmpOperator::In | CmpOperator::AllIn | CmpOperator::Subset | CmpOperator::Superset | CmpOperator::InCidr) {
                 "match expected value in"
             }
             else {
@@ -0,0 +1,102 @@
+use serde::Serialize;
+
+use crate::commands::tracker::Failure;
+use crate::rules::errors::{Error, ErrorKind};
+use crate::rules::path_value::{Path, PathAwareValue};
+use crate::rules::{Result, Status};
+
+pub(crate) const PREVIOUS_VARIABLE: &str = "previous";
+
+const DENIED_STATUS_CODE: u16 = 403;
+
+pub(crate) struct AdmissionRequest {
+    pub(crate) uid: String,
+    pub(crate) object: PathAwareValue,
+    pub(crate) previous: PathAwareValue,
+}
+
+///
+/// Response for a validating admission webhook, serialized as the `response` of an
+/// AdmissionReview. The status message lists the messages of the failing clauses
+///
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AdmissionResponse {
+    pub uid: String,
+    pub allowed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<AdmissionStatus>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AdmissionStatus {
+    pub code: u16,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AdmissionResult {
+    pub failures: Vec<Failure>,
+    pub response: AdmissionResponse,
+}
+
+///
+/// Extracts `request.object` from a Kubernetes AdmissionReview, which is what rules are written
+/// against. `request.oldObject` is returned as previous and is bound to `%previous`, it is an
+/// empty list when there is no old object, e.g. for CREATE, so that `%previous empty` can be
+/// checked. Values keep their paths in the review, e.g. /request/object/spec/replicas.
+///
+pub(crate) fn admission_request(review: &PathAwareValue, data_file: &str) -> Result<AdmissionRequest> {
+    let request = match review {
+        PathAwareValue::Map((_, map)) => match map.values.get("request") {
+            Some(PathAwareValue::Map((_, request))) => request,
+            _ => return Err(not_a_review(data_file)),
+        },
+        _ => return Err(not_a_review(data_file)),
+    };
+    let object = match request.values.get("object") {
+        Some(object @ PathAwareValue::Map(_)) => object.clone(),
+        _ => return Err(not_a_review(data_file)),
+    };
+    //
+    // oldObject is null when absent, which the YAML loader reads as a plain string
+    //
+    let previous = match request.values.get("oldObject") {
+        Some(previous @ PathAwareValue::Map(_)) => previous.clone(),
+        _ => PathAwareValue::List((Path::root(), vec![])),
+    };
+    let uid = request.values.get("uid")
+        .and_then(PathAwareValue::as_string)
+        .map_or(String::new(), String::from);
+    Ok(AdmissionRequest { uid, object, previous })
+}
+
+pub(crate) fn admission_response(uid: String, status: Status, failures: &[Failure]) -> AdmissionResponse {
+    if status != Status::FAIL {
+        return AdmissionResponse { uid, allowed: true, status: None };
+    }
+    let mut messages: Vec<&str> = Vec::with_capacity(failures.len());
+    for each in failures {
+        if let Some(message) = each.message.as_deref() {
+            let message = message.trim();
+            if !message.is_empty() && !messages.contains(&message) {
+                messages.push(message);
+            }
+        }
+    }
+    AdmissionResponse {
+        uid,
+        allowed: false,
+        status: Some(AdmissionStatus { code: DENIED_STATUS_CODE, message: messages.join("; ") }),
+    }
+}
+
+fn not_a_review(data_file: &str) -> Error {
+    Error::new(ErrorKind::DataFileError(format!(
+        "{}, not a Kubernetes AdmissionReview, expected request.object",
+        data_file
+    )))
+}
+
+#[cfg(test)]
+#[path = "k8s_admission_tests.rs"]
+mod k8s_admission_tests;
@@ -0,0 +1,64 @@
+use super::*;
+use std::convert::TryFrom;
+
+fn review(old_object: serde_json::Value) -> Result<PathAwareValue> {
+    PathAwareValue::try_from(serde_json::json!({
+        "apiVersion": "admission.k8s.io/v1",
+        "kind": "AdmissionReview",
+        "request": {
+            "uid": "1234",
+            "operation": "UPDATE",
+            "object": { "spec": { "replicas": 1 } },
+            "oldObject": old_object
+        }
+    }))
+}
+
+#[test]
+fn test_admission_request_extracts_object_and_previous() -> Result<()> {
+    let request = admission_request(&review(serde_json::json!({ "spec": { "replicas": 3 } }))?, "review.json")?;
+    assert_eq!(request.uid, "1234");
+    assert_eq!(request.object.self_path().0, "/request/object");
+    assert_eq!(request.previous.self_path().0, "/request/oldObject");
+
+    //
+    // CREATE requests have no oldObject
+    //
+    let request = admission_request(&review(serde_json::Value::Null)?, "review.json")?;
+    assert_eq!(request.previous.as_list().map(|list| list.len()), Some(0));
+
+    let template = PathAwareValue::try_from(serde_json::json!({ "Resources": {} }))?;
+    match admission_request(&template, "template.json") {
+        Err(Error(ErrorKind::DataFileError(msg))) => {
+            assert_eq!(msg, "template.json, not a Kubernetes AdmissionReview, expected request.object");
+        },
+        rest => unreachable!("{:?}", rest.map(|request| request.uid)),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_admission_response() {
+    let failure = |message: &str| Failure {
+        rule: "rule".to_string(),
+        path: "/request/object/spec/replicas".to_string(),
+        from: None,
+        to: None,
+        message: Some(message.to_string()),
+        location: None,
+    };
+    let failures = vec![failure("Replicas must not be reduced\n"), failure("Replicas must not be reduced"), failure("Pin images")];
+
+    let response = admission_response("1234".to_string(), Status::FAIL, &failures);
+    assert_eq!(response, AdmissionResponse {
+        uid: "1234".to_string(),
+        allowed: false,
+        status: Some(AdmissionStatus { code: 403, message: "Replicas must not be reduced; Pin images".to_string() }),
+    });
+
+    for status in [Status::PASS, Status::SKIP] {
+        let response = admission_response("1234".to_string(), status, &[]);
+        assert!(response.allowed);
+        assert_eq!(response.status, None);
+    }
+}
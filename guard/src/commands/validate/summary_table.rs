@@ -4,6 +4,7 @@ use crate::commands::tracker::StatusContext;
 use crate::rules::{Status, NamedStatus};
 use colored::*;
 use itertools::Itertools;
+use std::collections::HashMap;
 use enumflags2::{bitflags, BitFlags};
 use crate::commands::validate::common::colored_string;
 use crate::rules::eval_context::EventRecord;
@@ -24,26 +25,49 @@ pub(super) enum SummaryType {
 pub(super) struct SummaryTable<'reporter> {
     summary_type: BitFlags<SummaryType>,
     next: &'reporter dyn Reporter,
+    summary_only: bool,
 }
 
 impl<'a> SummaryTable<'a> {
     pub(crate) fn new<'r>(summary_type: BitFlags<SummaryType>,
                           next: &'r dyn Reporter) -> SummaryTable<'r> {
-        SummaryTable {summary_type, next }
+        SummaryTable {summary_type, next, summary_only: false }
+    }
+
+    //
+    // Reports every rule along with the number of failing resources for failed rules, and
+    // nothing else. Reporters after this one are not called
+    //
+    pub(crate) fn summary_only<'r>(next: &'r dyn Reporter) -> SummaryTable<'r> {
+        SummaryTable {summary_type: BitFlags::all(), next, summary_only: true }
+    }
+}
+
+fn failing_resources(count: Option<usize>) -> String {
+    match count {
+        Some(count) => format!("    {} failing resource(s)", count),
+        None => "".to_string()
     }
 }
 
 fn print_partition(writer: &mut dyn Write,
                    rules_file_name: &str,
                    part: &[&StatusContext],
-                   longest: usize) -> crate::rules::Result<()> {
+                   longest: usize,
+                   with_counts: bool) -> crate::rules::Result<()> {
     for container in part {
+        let count = if with_counts && container.status == Some(Status::FAIL) {
+            Some(super::common::count_failing_resources(container))
+        } else {
+            None
+        };
         writeln!(writer,
-                 "{filename}/{context:<0$}{status}",
+                 "{filename}/{context:<0$}{status}{count}",
                  longest+4,
                  filename=rules_file_name,
                  context=container.context,
-                 status=super::common::colored_string(container.status)
+                 status=super::common::colored_string(container.status),
+                 count=failing_resources(count)
         )?;
     }
     Ok(())
@@ -53,14 +77,16 @@ fn print_summary(
     writer: &mut dyn Write,
     rules_file_name: &str,
     longest: usize,
-    rules: &indexmap::IndexMap<&str, Status>) -> crate::rules::Result<()> {
+    rules: &indexmap::IndexMap<&str, Status>,
+    counts: &HashMap<&str, usize>) -> crate::rules::Result<()> {
     for (rule_name, status) in rules.iter() {
         writeln!(writer,
-                 "{filename}/{context:<0$}{status}",
+                 "{filename}/{context:<0$}{status}{count}",
                  longest+4,
                  filename=rules_file_name,
                  context=rule_name,
-                 status=super::common::colored_string(Some(*status)))?;
+                 status=super::common::colored_string(Some(*status)),
+                 count=failing_resources(counts.get(rule_name).copied()))?;
     }
     Ok(())
 }
@@ -71,6 +97,7 @@ impl<'r> Reporter for SummaryTable<'r> {
               writer: &mut dyn Write,
               status: Option<Status>,
               failed_rules: &[&StatusContext],
+              truncated: bool,
               passed_or_skipped: &[&StatusContext],
               longest_rule_name: usize,
               rules_file_name: &str,
@@ -91,7 +118,7 @@ impl<'r> Reporter for SummaryTable<'r> {
             writeln!(writer, "{} Status = {}", data_file_name, colored_string(status))?;
             wrote_header_line = true;
             writeln!(writer, "{}", "SKIP rules".bold())?;
-            print_partition(writer, rules_file_name, &skipped, longest_rule_name)?;
+            print_partition(writer, rules_file_name, &skipped, longest_rule_name, self.summary_only)?;
 
         }
 
@@ -99,23 +126,27 @@ impl<'r> Reporter for SummaryTable<'r> {
             writeln!(writer, "{} Status = {}", data_file_name, colored_string(status))?;
             wrote_header_line = true;
             writeln!(writer, "{}", "PASS rules".bold())?;
-            print_partition(writer, rules_file_name, &passed, longest_rule_name)?;
+            print_partition(writer, rules_file_name, &passed, longest_rule_name, self.summary_only)?;
         }
 
         if self.summary_type.contains(SummaryType::FAIL) && !failed_rules.is_empty() {
             writeln!(writer, "{} Status = {}", data_file_name, colored_string(status))?;
             wrote_header_line = true;
             writeln!(writer, "{}", "FAILED rules".bold())?;
-            print_partition(writer, rules_file_name, failed_rules, longest_rule_name)?;
+            print_partition(writer, rules_file_name, failed_rules, longest_rule_name, self.summary_only)?;
         }
 
         if wrote_header_line {
             writeln!(writer, "---")?;
         }
+        if self.summary_only {
+            return Ok(())
+        }
         self.next.report(
                   writer,
                   status,
                   failed_rules,
+                  truncated,
                   passed_or_skipped,
                   longest_rule_name,
                   rules_file_name,
@@ -129,6 +160,7 @@ impl<'r> Reporter for SummaryTable<'r> {
         _write: &mut dyn Write,
         _status: Status,
         _root_record: &EventRecord<'value>,
+        _truncated: bool,
         _rules_file: &str,
         _data_file: &str,
         _data_file_bytes: &str,
@@ -138,10 +170,14 @@ impl<'r> Reporter for SummaryTable<'r> {
         let mut passed = indexmap::IndexMap::with_capacity(_root_record.children.len());
         let mut skipped = indexmap::IndexMap::with_capacity(_root_record.children.len());
         let mut failed = indexmap::IndexMap::with_capacity(_root_record.children.len());
+        let mut counts = HashMap::new();
         let mut longest = 0;
         for each_rule in &_root_record.children {
             if let Some(RecordType::RuleCheck(NamedStatus {status, name, ..})) =
                 &each_rule.container {
+                if self.summary_only && *status == Status::FAIL {
+                    counts.insert(*name, super::common::count_failing_resources_from_record(name, each_rule)?);
+                }
                 match status {
                     Status::PASS => passed.insert(*name, *status),
                     Status::FAIL => failed.insert(*name, *status),
@@ -160,7 +196,7 @@ impl<'r> Reporter for SummaryTable<'r> {
             writeln!(_write, "{} Status = {}", _data_file, colored_string(Some(_status)))?;
             wrote_header_line = true;
             writeln!(_write, "{}", "SKIP rules".bold())?;
-            print_summary(_write, _rules_file, longest, &skipped)?;
+            print_summary(_write, _rules_file, longest, &skipped, &counts)?;
         }
 
         if self.summary_type.contains(SummaryType::PASS) && !passed.is_empty() {
@@ -169,7 +205,7 @@ impl<'r> Reporter for SummaryTable<'r> {
                 writeln!(_write, "{} Status = {}", _data_file, colored_string(Some(_status)))?;
             }
             writeln!(_write, "{}", "PASS rules".bold())?;
-            print_summary(_write, _rules_file, longest, &passed)?;
+            print_summary(_write, _rules_file, longest, &passed, &counts)?;
         }
 
         if self.summary_type.contains(SummaryType::FAIL) && !failed.is_empty() {
@@ -178,17 +214,21 @@ impl<'r> Reporter for SummaryTable<'r> {
                 writeln!(_write, "{} Status = {}", _data_file, colored_string(Some(_status)))?;
             }
             writeln!(_write, "{}", "FAILED rules".bold())?;
-            print_summary(_write, _rules_file, longest, &failed)?;
+            print_summary(_write, _rules_file, longest, &failed, &counts)?;
         }
 
         if wrote_header_line {
             writeln!(_write, "---")?;
         }
+        if self.summary_only {
+            return Ok(())
+        }
 
         self.next.report_eval(
             _write,
             _status,
             _root_record,
+            _truncated,
             _rules_file,
             _data_file,
             _data_file_bytes,
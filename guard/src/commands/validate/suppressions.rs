@@ -0,0 +1,262 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+use crate::commands::tracker::{Failure, StatusContext};
+use crate::commands::validate::common::{
+    failure_from_clause, find_failing_clauses, is_failing_clause, path_from_message, resource_id,
+};
+use crate::commands::validate::OutputFormatType;
+use crate::rules::eval_context::EventRecord;
+use crate::rules::path_value::PathAwareValue;
+use crate::rules::{EvaluationType, NamedStatus, RecordType, Result, Status};
+
+const METADATA_KEY: &str = "cfn_guard";
+const SUPPRESSED_RULES: &str = "suppressed_rules";
+
+///
+/// Rules suppressed for a resource with `Metadata.cfn_guard.suppressed_rules`, a list of rule
+/// names. Failing clauses of a suppressed rule that point into the resource are taken out of
+/// the evaluation and reported separately, a rule with no other failures PASSes
+///
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Suppressions {
+    by_resource: HashMap<String, HashSet<String>>,
+}
+
+//
+// Failures taken out of an evaluation, by suppressions or a baseline. rules is the number of
+// rules that PASS as a result
+//
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Excluded {
+    pub(crate) rules: usize,
+    pub(crate) failures: Vec<Failure>,
+}
+
+//
+// Called with the rule name, the clause and the failure of each failing clause, returns true
+// for the failure to be taken out
+//
+pub(crate) type TakeFailure<'a> = dyn FnMut(&str, &str, &Failure) -> bool + 'a;
+
+impl Suppressions {
+    pub(crate) fn from_template(root: &PathAwareValue) -> Suppressions {
+        let mut by_resource = HashMap::new();
+        if let Ok(PathAwareValue::Map((_, resources))) = root.at_path("Resources") {
+            for (id, resource) in &resources.values {
+                let rules: HashSet<String> = match resource.at_path(&format!("Metadata/{}/{}", METADATA_KEY, SUPPRESSED_RULES)) {
+                    Ok(PathAwareValue::List((_, rules))) => rules.iter()
+                        .filter_map(PathAwareValue::as_string)
+                        .map(String::from)
+                        .collect(),
+                    _ => continue,
+                };
+                by_resource.insert(id.to_string(), rules);
+            }
+        }
+        Suppressions { by_resource }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.by_resource.is_empty()
+    }
+
+    pub(crate) fn suppresses(&self, rule: &str, path: &str) -> bool {
+        match resource_id(path).and_then(|id| self.by_resource.get(id)) {
+            Some(rules) => rules.contains(rule),
+            None => false,
+        }
+    }
+
+    pub(crate) fn suppress_in_record(&self, root: &mut EventRecord<'_>, status: Status) -> Result<(Status, Excluded)> {
+        if self.is_empty() {
+            return Ok((status, Excluded::default()))
+        }
+        take_failures_from_record(root, status, &mut |rule, _, failure| self.suppresses(rule, &failure.path))
+    }
+}
+
+//
+// Taken out clauses are dropped from the record along with blocks they leave empty, as with
+// --max-errors. Returns the file status once rules left without failures PASS
+//
+pub(crate) fn take_failures_from_record(
+    root: &mut EventRecord<'_>,
+    status: Status,
+    take: &mut TakeFailure<'_>,
+) -> Result<(Status, Excluded)> {
+    let mut excluded = Excluded::default();
+    for rule in &mut root.children {
+        let name = match &rule.container {
+            Some(RecordType::RuleCheck(NamedStatus{name, status: Status::FAIL, ..})) => *name,
+            _ => continue,
+        };
+        let before = excluded.failures.len();
+        prune_children(name, rule, take, &mut excluded.failures)?;
+        if excluded.failures.len() > before && find_failing_clauses(rule).is_empty() {
+            if let Some(RecordType::RuleCheck(named)) = &mut rule.container {
+                named.status = Status::PASS;
+            }
+            excluded.rules += 1;
+        }
+    }
+    if excluded.rules == 0 {
+        return Ok((status, excluded))
+    }
+    let status = overall(root.children.iter().filter_map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus{status, ..})) => Some(*status),
+        _ => None,
+    }));
+    if let Some(RecordType::FileCheck(named)) = &mut root.container {
+        named.status = status;
+    }
+    Ok((status, excluded))
+}
+
+fn prune_record(
+    rule: &str,
+    current: &mut EventRecord<'_>,
+    take: &mut TakeFailure<'_>,
+    failures: &mut Vec<Failure>,
+) -> Result<bool> {
+    if is_failing_clause(current) {
+        let failure = failure_from_clause(rule, current)?;
+        return Ok(if take(rule, &current.context, &failure) {
+            failures.push(failure);
+            false
+        } else {
+            true
+        })
+    }
+    if current.children.is_empty() || matches!(&current.container, Some(RecordType::Filter(_))) {
+        return Ok(true)
+    }
+    let before = failures.len();
+    prune_children(rule, current, take, failures)?;
+    Ok(!current.children.is_empty() || failures.len() == before)
+}
+
+fn prune_children(
+    rule: &str,
+    current: &mut EventRecord<'_>,
+    take: &mut TakeFailure<'_>,
+    failures: &mut Vec<Failure>,
+) -> Result<()> {
+    for mut child in std::mem::take(&mut current.children) {
+        if prune_record(rule, &mut child, take, failures)? {
+            current.children.push(child);
+        }
+    }
+    Ok(())
+}
+
+//
+// Same as take_failures_from_record for the previous engine (-E). Taken out clauses are
+// dropped and the blocks above them PASS once they have no failing clause left
+//
+pub(crate) fn take_failures_from_context(top: &mut StatusContext, take: &mut TakeFailure<'_>) -> Result<Excluded> {
+    let mut excluded = Excluded::default();
+    for rule in &mut top.children {
+        if rule.status != Some(Status::FAIL) {
+            continue;
+        }
+        let name = rule.context.clone();
+        let before = excluded.failures.len();
+        if !prune_context(&name, rule, take, &mut excluded.failures)? && excluded.failures.len() > before {
+            rule.status = Some(Status::PASS);
+            excluded.rules += 1;
+        }
+    }
+    if excluded.rules > 0 {
+        top.status = Some(overall(top.children.iter().filter_map(|rule| rule.status)));
+    }
+    Ok(excluded)
+}
+
+//
+// Returns whether the context still FAILs
+//
+fn prune_context(
+    rule: &str,
+    current: &mut StatusContext,
+    take: &mut TakeFailure<'_>,
+    failures: &mut Vec<Failure>,
+) -> Result<bool> {
+    if !current.children.iter().any(is_failing_context) {
+        let mut failure = Failure::new(rule, current.from.as_ref(), current.to.as_ref(), current.msg.clone())?;
+        if failure.path.is_empty() {
+            if let Some(path) = current.msg.as_deref().and_then(path_from_message) {
+                failure.path = path.split('[').next().unwrap_or(path).to_string();
+            }
+        }
+        if take(rule, &current.context, &failure) {
+            failures.push(failure);
+            return Ok(false)
+        }
+        return Ok(true)
+    }
+    let mut failing = false;
+    for mut child in std::mem::take(&mut current.children) {
+        if !is_failing_context(&child) {
+            current.children.push(child);
+            continue;
+        }
+        let leaf = !child.children.iter().any(is_failing_context);
+        if prune_context(rule, &mut child, take, failures)? {
+            failing = true;
+        } else if leaf {
+            continue;
+        }
+        current.children.push(child);
+    }
+    if !failing {
+        current.status = Some(Status::PASS);
+    }
+    Ok(failing)
+}
+
+impl Excluded {
+    //
+    // kind is how the failures were taken out, e.g. suppressed
+    //
+    pub(crate) fn report(&self, writer: &mut dyn Write, kind: &str, data_file: &str, output: OutputFormatType) -> Result<()> {
+        if self.failures.is_empty() {
+            return Ok(())
+        }
+        let report = serde_json::json!({ "data_file": data_file, kind: &self.failures });
+        match output {
+            OutputFormatType::SingleLineSummary => {
+                writeln!(writer, "Failures {} in [{}]", kind, data_file)?;
+                for each in &self.failures {
+                    writeln!(writer, "    Resource=[{}] Rule=[{}] Path=[{}] Message=[{}]",
+                             resource_id(&each.path).unwrap_or(""), each.rule, each.path,
+                             each.message.as_deref().unwrap_or("").replace('\n', ";"))?;
+                }
+            },
+            OutputFormatType::JSON => writeln!(writer, "{}", serde_json::to_string_pretty(&report)?)?,
+            OutputFormatType::YAML => write!(writer, "{}", serde_yaml::to_string(&report)?)?,
+        }
+        Ok(())
+    }
+}
+
+fn is_failing_context(context: &StatusContext) -> bool {
+    context.status == Some(Status::FAIL) &&
+        !matches!(context.eval_type, EvaluationType::Filter | EvaluationType::Condition)
+}
+
+fn overall<I: Iterator<Item = Status>>(statuses: I) -> Status {
+    let mut overall = Status::SKIP;
+    for status in statuses {
+        match status {
+            Status::FAIL => return Status::FAIL,
+            Status::PASS => overall = Status::PASS,
+            Status::SKIP => {}
+        }
+    }
+    overall
+}
+
+#[cfg(test)]
+#[path = "suppressions_tests.rs"]
+mod suppressions_tests;
@@ -0,0 +1,86 @@
+use super::*;
+use std::convert::TryFrom;
+
+use crate::commands::tracker::StackTracker;
+use crate::rules::eval::eval_rules_file;
+use crate::rules::eval_context::root_scope;
+use crate::rules::exprs::RulesFile;
+use crate::rules::Evaluate;
+
+const RULES: &str = r#"
+rule buckets_encrypted {
+    Resources.*[ Type == 'AWS::S3::Bucket' ] {
+        Properties.BucketEncryption exists
+    }
+}
+
+rule buckets_versioned {
+    Resources.*[ Type == 'AWS::S3::Bucket' ] {
+        Properties.VersioningConfiguration.Status == 'Enabled'
+    }
+}
+"#;
+
+fn template() -> Result<PathAwareValue> {
+    PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "logs": {
+                "Type": "AWS::S3::Bucket",
+                "Metadata": {
+                    "cfn_guard": { "suppressed_rules": ["buckets_encrypted", "buckets_versioned"] }
+                },
+                "Properties": {}
+            },
+            "data": {
+                "Type": "AWS::S3::Bucket",
+                "Metadata": { "cfn_guard": { "suppressed_rules": ["buckets_versioned"] } },
+                "Properties": {}
+            }
+        }
+    }))
+}
+
+#[test]
+fn test_suppressions_from_template() -> Result<()> {
+    let suppressions = Suppressions::from_template(&template()?);
+    assert!(suppressions.suppresses("buckets_encrypted", "/Resources/logs/Properties"));
+    assert!(suppressions.suppresses("buckets_versioned", "/Resources/data"));
+    assert!(!suppressions.suppresses("buckets_encrypted", "/Resources/data/Properties"));
+    assert!(!suppressions.suppresses("buckets_encrypted", "/Outputs/logs"));
+
+    let template = PathAwareValue::try_from(serde_json::json!({
+        "Resources": { "logs": { "Type": "AWS::S3::Bucket", "Metadata": { "cfn_guard": {} } } }
+    }))?;
+    assert!(Suppressions::from_template(&template).is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_suppress_failures_for_both_engines() -> Result<()> {
+    let rules = RulesFile::try_from(RULES)?;
+    let value = template()?;
+    let suppressions = Suppressions::from_template(&value);
+
+    let mut root = root_scope(&rules, &value)?;
+    let status = eval_rules_file(&rules, &mut root)?;
+    assert_eq!(status, Status::FAIL);
+    let mut record = root.reset_recorder().extract();
+    let (status, suppressed) = suppressions.suppress_in_record(&mut record, status)?;
+    //
+    // buckets_encrypted still fails for data, buckets_versioned is suppressed for both
+    //
+    assert_eq!(status, Status::FAIL);
+    assert_eq!(suppressed.rules, 1);
+    assert_eq!(suppressed.failures.len(), 3);
+    assert_eq!(find_failing_clauses(&record).len(), 1);
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    let tracker = StackTracker::new(&scope);
+    rules.evaluate(&value, &tracker)?;
+    let suppressed = tracker.take_failures(&mut |rule, _, failure| suppressions.suppresses(rule, &failure.path))?;
+    assert_eq!(suppressed.rules, 1);
+    assert_eq!(suppressed.failures.len(), 3);
+    assert!(tracker.has_failed());
+    assert_eq!(tracker.failures()?.len(), 1);
+    Ok(())
+}
@@ -26,16 +26,37 @@ impl<'reporter> TfAware<'reporter> {
 impl<'reporter> Reporter for TfAware<'reporter> {
     fn report(
         &self,
-        _writer: &mut dyn Write,
-        _status: Option<Status>,
-        _failed_rules: &[&StatusContext],
-        _passed_or_skipped: &[&StatusContext],
-        _longest_rule_name: usize,
-        _rules_file: &str,
-        _data_file: &str,
-        _data: &Traversal<'_>,
-        _output_type: OutputFormatType) -> crate::rules::Result<()> {
-        Ok(())
+        writer: &mut dyn Write,
+        status: Option<Status>,
+        failed_rules: &[&StatusContext],
+        truncated: bool,
+        passed_or_skipped: &[&StatusContext],
+        longest_rule_name: usize,
+        rules_file: &str,
+        data_file: &str,
+        data: &Traversal<'_>,
+        output_type: OutputFormatType) -> crate::rules::Result<()> {
+        //
+        // The previous engine shows failures as the summary table and clause details, only the
+        // structured formats are rendered, by the generic reporter
+        //
+        if output_type == OutputFormatType::SingleLineSummary {
+            return Ok(())
+        }
+        self.next.map_or(
+            Ok(()), |next|
+                next.report(
+                    writer,
+                    status,
+                    failed_rules,
+                    truncated,
+                    passed_or_skipped,
+                    longest_rule_name,
+                    rules_file,
+                    data_file,
+                    data,
+                    output_type)
+        )
     }
 
     fn report_eval<'value>(
@@ -43,6 +64,7 @@ impl<'reporter> Reporter for TfAware<'reporter> {
         write: &mut dyn Write,
         status: Status,
         root_record: &EventRecord<'value>,
+        truncated: bool,
         rules_file: &str,
         data_file: &str,
         data_file_bytes: &str,
@@ -59,7 +81,8 @@ impl<'reporter> Reporter for TfAware<'reporter> {
         };
 
         if is_tf_plan {
-            let failure_report = simplifed_json_from_root(root_record)?;
+            let mut failure_report = simplifed_json_from_root(root_record)?;
+            failure_report.truncated = truncated;
             Ok(match output_type {
                 OutputFormatType::YAML => serde_yaml::to_writer(write, &failure_report)?,
                 OutputFormatType::JSON => serde_json::to_writer_pretty(write, &failure_report)?,
@@ -74,6 +97,7 @@ impl<'reporter> Reporter for TfAware<'reporter> {
                     write,
                     status,
                     root_record,
+                    truncated,
                     rules_file,
                     data_file,
                     data_file_bytes,
@@ -0,0 +1,73 @@
+use crate::rules::errors::{Error, ErrorKind};
+use crate::rules::path_value::{MapValue, PathAwareValue};
+use crate::rules::Result;
+
+///
+/// Adapts the JSON output of `terraform show -json plan.out` into the shape rules are written
+/// against, a map keyed by resource type whose values are the list of planned attributes
+/// (`change.after`) for each resource of that type, e.g.
+///
+/// ```json
+/// { "aws_s3_bucket": [ { "bucket": "logs", "server_side_encryption_configuration": [...] } ] }
+/// ```
+///
+/// so that `aws_s3_bucket { server_side_encryption_configuration EXISTS }` checks every bucket.
+/// Values are not copied into new locations, each one keeps the path it had in the plan,
+/// e.g. /resource_changes/2/change/after/bucket. Resources being deleted, where after is null,
+/// are skipped.
+///
+pub(crate) fn adapt_tf_plan(plan: &PathAwareValue, data_file: &str) -> Result<PathAwareValue> {
+    let (root, changes) = match plan {
+        PathAwareValue::Map((root, map)) => match map.values.get("resource_changes") {
+            Some(PathAwareValue::List((_, changes))) => (root, changes),
+            _ => return Err(not_a_plan(data_file)),
+        },
+        _ => return Err(not_a_plan(data_file)),
+    };
+
+    let mut by_type: indexmap::IndexMap<&str, (&PathAwareValue, Vec<PathAwareValue>)> = indexmap::IndexMap::new();
+    for each in changes {
+        let change = match each {
+            PathAwareValue::Map((_, change)) => change,
+            _ => continue,
+        };
+        let (type_key, type_name) = match change.values.get("type") {
+            Some(key @ PathAwareValue::String((_, name))) => (key, name.as_str()),
+            _ => continue,
+        };
+        let after = match change.values.get("change") {
+            Some(PathAwareValue::Map((_, planned))) => planned.values.get("after"),
+            _ => None,
+        };
+        //
+        // after is null for resources being deleted, which the YAML loader reads as a plain
+        // string, anything other than a struct is skipped
+        //
+        if let Some(after @ PathAwareValue::Map(_)) = after {
+            by_type
+                .entry(type_name)
+                .or_insert_with(|| (type_key, Vec::new()))
+                .1
+                .push(after.clone());
+        }
+    }
+
+    let mut adapted = MapValue { keys: Vec::with_capacity(by_type.len()), values: indexmap::IndexMap::with_capacity(by_type.len()) };
+    for (type_name, (type_key, resources)) in by_type {
+        let path = type_key.self_path().clone();
+        adapted.keys.push(type_key.clone());
+        adapted.values.insert(type_name.to_string(), PathAwareValue::List((path, resources)));
+    }
+    Ok(PathAwareValue::Map((root.clone(), adapted)))
+}
+
+fn not_a_plan(data_file: &str) -> Error {
+    Error::new(ErrorKind::DataFileError(format!(
+        "{}, not a Terraform plan, expected resource_changes from the output of `terraform show -json`",
+        data_file
+    )))
+}
+
+#[cfg(test)]
+#[path = "tf_plan_tests.rs"]
+mod tf_plan_tests;
@@ -0,0 +1,86 @@
+use super::*;
+use crate::rules::eval::eval_rules_file;
+use crate::rules::eval_context::root_scope;
+use crate::rules::exprs::RulesFile;
+use crate::rules::path_value::EvaluationOptions;
+use crate::rules::{ClauseCheck, RecordType, Status};
+use std::convert::TryFrom;
+
+fn read_plan() -> Result<PathAwareValue> {
+    let content = std::fs::read_to_string(
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/tf-s3-buckets-plan.json"))?;
+    PathAwareValue::try_from(crate::rules::values::read_from(&content)?)
+}
+
+#[test]
+fn test_adapt_tf_plan_keys_resources_by_type() -> Result<()> {
+    let adapted = adapt_tf_plan(&read_plan()?, "plan.json")?;
+    let by_type = match &adapted {
+        PathAwareValue::Map((_, map)) => map,
+        rest => unreachable!("{:?}", rest),
+    };
+    assert_eq!(by_type.values.keys().map(String::as_str).collect::<Vec<&str>>(),
+               vec!["aws_s3_bucket", "aws_s3_bucket_public_access_block"]);
+
+    //
+    // aws_s3_bucket.legacy is being deleted and is skipped
+    //
+    let buckets = match &by_type.values["aws_s3_bucket"] {
+        PathAwareValue::List((_, buckets)) => buckets,
+        rest => unreachable!("{:?}", rest),
+    };
+    assert_eq!(buckets.iter().map(|each| each.self_path().0.as_str()).collect::<Vec<&str>>(),
+               vec!["/resource_changes/0/change/after", "/resource_changes/2/change/after"]);
+
+    match adapt_tf_plan(&PathAwareValue::try_from(serde_json::json!({ "Resources": {} }))?, "template.json") {
+        Err(Error(ErrorKind::DataFileError(msg))) => assert!(msg.starts_with("template.json, not a Terraform plan")),
+        rest => unreachable!("{:?}", rest),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_rules_on_adapted_tf_plan_point_into_plan() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule s3_buckets_encrypted {
+        aws_s3_bucket {
+            server_side_encryption_configuration !EMPTY
+        }
+    }
+
+    rule s3_buckets_use_aes256 {
+        aws_s3_bucket {
+            server_side_encryption_configuration.rule.apply_server_side_encryption_by_default.sse_algorithm == "AES256"
+        }
+    }
+    "#)?;
+    let adapted = adapt_tf_plan(&read_plan()?, "plan.json")?;
+    let mut options = EvaluationOptions::new();
+    options.list_key_expansion = true;
+    let mut root = root_scope(&rules, &adapted)?.with_options(options);
+    let status = eval_rules_file(&rules, &mut root)?;
+    assert_eq!(status, Status::FAIL);
+
+    let top = root.reset_recorder().extract();
+    let mut failed = Vec::new();
+    let mut records = vec![&top];
+    while let Some(record) = records.pop() {
+        if let Some(RecordType::ClauseValueCheck(ClauseCheck::Unary(check))) = &record.container {
+            if check.value.status == Status::FAIL {
+                failed.push(check.value.from.resolved().unwrap().self_path().0.clone());
+            }
+        }
+        records.extend(record.children.iter());
+    }
+    assert_eq!(failed, vec!["/resource_changes/0/change/after/server_side_encryption_configuration".to_string()]);
+
+    let rules_status = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(check)) => (check.name, check.status),
+        rest => unreachable!("{:?}", rest),
+    }).collect::<Vec<(&str, Status)>>();
+    //
+    // the unencrypted bucket has no sse_algorithm to compare, the logs bucket passes
+    //
+    assert_eq!(rules_status, vec![("s3_buckets_encrypted", Status::FAIL), ("s3_buckets_use_aes256", Status::FAIL)]);
+    Ok(())
+}
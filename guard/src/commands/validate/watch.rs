@@ -0,0 +1,98 @@
+use std::io::Write;
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::commands::exit_code_for_error;
+use crate::rules::errors::{Error, ErrorKind};
+use crate::rules::Result;
+
+pub(crate) const DEBOUNCE_DELAY: Duration = Duration::from_millis(200);
+
+const CLEAR_SCREEN: &str = "\x1B[2J\x1B[1;1H";
+
+///
+/// Runs validate and then again each time a file under paths is created, modified or removed
+/// (--watch). The screen is cleared before each run. Saves that arrive within DEBOUNCE_DELAY of
+/// each other are one change. An error, e.g. a data file that does not parse, is printed and
+/// the next change is waited for. Returns the exit code of the last run once the paths can no
+/// longer be watched
+///
+pub(crate) fn watch<F>(paths: &[&Path], mut run: F) -> Result<i32>
+where
+    F: FnMut() -> Result<i32>,
+{
+    let (sender, receiver) = channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = sender.send(event);
+    })
+    .map_err(|e| watch_error("", e))?;
+    for path in paths {
+        watcher
+            .watch(path, RecursiveMode::Recursive)
+            .map_err(|e| watch_error(&path.display().to_string(), e))?;
+    }
+
+    loop {
+        print!("{}", CLEAR_SCREEN);
+        std::io::stdout().flush()?;
+        let exit_code = match run() {
+            Ok(code) => code,
+            Err(e) => {
+                println!("Error occurred {}", e);
+                exit_code_for_error(&e)
+            }
+        };
+        std::io::stdout().flush()?;
+
+        //
+        // Events for files the run itself wrote, e.g. --output-file or --cache-dir under a
+        // watched directory, would start the next run right away
+        //
+        while receiver.try_recv().is_ok() {}
+        eprintln!("Watching for changes, press Ctrl-C to stop");
+        if !wait_for_change(&receiver, DEBOUNCE_DELAY) {
+            return Ok(exit_code);
+        }
+    }
+}
+
+//
+// Waits for a file to be created, modified or removed, then for delay to pass without any
+// further event. Returns false when no more events can arrive
+//
+pub(crate) fn wait_for_change(receiver: &Receiver<notify::Result<Event>>, delay: Duration) -> bool {
+    loop {
+        match receiver.recv() {
+            Ok(event) if is_change(&event) => break,
+            Ok(_) => continue,
+            Err(_) => return false,
+        }
+    }
+    loop {
+        match receiver.recv_timeout(delay) {
+            Ok(_) => continue,
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => return true,
+        }
+    }
+}
+
+fn is_change(event: &notify::Result<Event>) -> bool {
+    matches!(
+        event,
+        Ok(Event { kind: EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_), .. })
+    )
+}
+
+fn watch_error(path: &str, error: notify::Error) -> Error {
+    Error::new(ErrorKind::IoError(std::io::Error::other(format!(
+        "Unable to watch {} for changes, {}",
+        path, error
+    ))))
+}
+
+#[cfg(test)]
+#[path = "watch_tests.rs"]
+mod watch_tests;
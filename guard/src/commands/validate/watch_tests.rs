@@ -0,0 +1,71 @@
+use super::*;
+use notify::event::{AccessKind, CreateKind, ModifyKind, RemoveKind};
+use std::sync::mpsc::Sender;
+use std::time::Instant;
+
+fn send(sender: &Sender<notify::Result<Event>>, kind: EventKind) {
+    sender.send(Ok(Event::new(kind))).unwrap();
+}
+
+#[test]
+fn test_rapid_saves_are_one_change() {
+    let (sender, receiver) = channel();
+    send(&sender, EventKind::Create(CreateKind::File));
+    send(&sender, EventKind::Modify(ModifyKind::Any));
+    send(&sender, EventKind::Modify(ModifyKind::Any));
+    assert!(wait_for_change(&receiver, Duration::from_millis(20)));
+    assert!(receiver.try_recv().is_err());
+
+    //
+    // a save that arrives during the delay starts it again
+    //
+    let later = sender.clone();
+    let saving = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        send(&later, EventKind::Modify(ModifyKind::Any));
+    });
+    let started = Instant::now();
+    send(&sender, EventKind::Remove(RemoveKind::File));
+    assert!(wait_for_change(&receiver, Duration::from_millis(100)));
+    assert!(started.elapsed() >= Duration::from_millis(150));
+    saving.join().unwrap();
+}
+
+#[test]
+fn test_reads_and_errors_are_not_changes() {
+    let (sender, receiver) = channel();
+    send(&sender, EventKind::Access(AccessKind::Any));
+    sender.send(Err(notify::Error::generic("watch failed"))).unwrap();
+    drop(sender);
+    assert!(!wait_for_change(&receiver, DEBOUNCE_DELAY));
+}
+
+#[test]
+fn test_watched_directory_changes() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("cfn-guard-watch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let (sender, receiver) = channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = sender.send(event);
+    })
+    .map_err(|e| watch_error("", e))?;
+    watcher.watch(&dir, RecursiveMode::Recursive).map_err(|e| watch_error("", e))?;
+    std::fs::write(dir.join("rules.guard"), "rule r { a == 1 }")?;
+    assert!(wait_for_change(&receiver, DEBOUNCE_DELAY));
+
+    drop(watcher);
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_unwatchable_path() {
+    let missing = std::env::temp_dir().join(format!("cfn-guard-watch-missing-{}", std::process::id()));
+    let mut runs = 0;
+    assert!(watch(&[missing.as_path()], || {
+        runs += 1;
+        Ok(0)
+    })
+    .is_err());
+    assert_eq!(runs, 0);
+}
@@ -49,4 +49,1046 @@ fn test_supported_extensions() {
     // unsupported
     assert!(!has_a_supported_extension(&"blah.txt".to_string(), &RULE_FILE_SUPPORTED_EXTENSIONS));
     assert!(!has_a_supported_extension(&"blah".to_string(), &RULE_FILE_SUPPORTED_EXTENSIONS));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_print_failed_clause_details() -> Result<()> {
+    let data = PathAwareValue::try_from(serde_json::json!({
+        "Resources": { "bucket": { "Properties": { "BucketName": "a-very-long-bucket-name" } } }
+    }))?;
+    let from = data.at_path("/Resources/bucket/Properties/BucketName")?.clone();
+    let to = PathAwareValue::try_from(serde_json::json!("expected"))?;
+    let clause = StatusContext {
+        eval_type: EvaluationType::Clause,
+        context: "BucketName".to_string(),
+        msg: Some("bucket name\nmust match".to_string()),
+        from: Some(from),
+        to: Some(to),
+        status: Some(Status::FAIL),
+        comparator: Some((CmpOperator::Eq, false)),
+        children: vec![],
+        branches: vec![],
+    };
+    let rule = StatusContext {
+        eval_type: EvaluationType::Rule,
+        context: "check_bucket".to_string(),
+        msg: None,
+        from: None,
+        to: None,
+        status: Some(Status::FAIL),
+        comparator: None,
+        children: vec![clause],
+        branches: vec![],
+    };
+    let mut writer = Vec::new();
+    print_failed_clause_details(&mut writer, "rules.guard", &[&rule])?;
+    let output = String::from_utf8(writer).unwrap();
+    assert!(output.contains("rules.guard/check_bucket"));
+    assert!(output.contains(concat!(
+        "    Path=[/Resources/bucket/Properties/BucketName] Comparator=[EQUALS] ",
+        "From=[\"a-very-long-bucket-name\"] To=[\"expected\"] Message=[bucket name;must match]\n"
+    )), "{}", output);
+    Ok(())
+}
+
+#[test]
+fn test_print_failed_clause_details_for_unresolved_query() -> Result<()> {
+    let data = PathAwareValue::try_from(serde_json::json!({
+        "Resources": { "bucket": { "Properties": { "BucketName": "a-very-long-bucket-name" } } }
+    }))?;
+    let clause = StatusContext {
+        eval_type: EvaluationType::Clause,
+        context: " Resources.*.Properties.Missing EXISTS  ".to_string(),
+        msg: Some("(DEFAULT: NO_MESSAGE)".to_string()),
+        from: Some(data),
+        to: None,
+        status: Some(Status::FAIL),
+        comparator: Some((CmpOperator::Exists, false)),
+        children: vec![],
+        branches: vec![],
+    };
+    let rule = StatusContext {
+        eval_type: EvaluationType::Rule,
+        context: "check_bucket".to_string(),
+        msg: None,
+        from: None,
+        to: None,
+        status: Some(Status::FAIL),
+        comparator: None,
+        children: vec![clause],
+        branches: vec![],
+    };
+    let mut writer = Vec::new();
+    print_failed_clause_details(&mut writer, "rules.guard", &[&rule])?;
+    let output = String::from_utf8(writer).unwrap();
+    assert!(output.contains(
+        "    Clause=[Resources.*.Properties.Missing EXISTS] Comparator=[EXISTS] To=[NULL]\n"), "{}", output);
+    assert!(!output.contains("BucketName"), "{}", output);
+    Ok(())
+}
+
+#[test]
+fn test_print_failed_record_details() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule check_bucket {
+        Resources.*.Properties.BucketName == "expected" <<bucket name
+must match>>
+    }
+    rule check_type { Resources.*.Type exists }
+    "#)?;
+    let data = PathAwareValue::try_from(serde_json::json!({
+        "Resources": { "bucket": { "Type": "AWS::S3::Bucket", "Properties": { "BucketName": "a-very-long-bucket-name" } } }
+    }))?;
+    let mut scope = root_scope(&rules, &data)?;
+    assert_eq!(eval_rules_file(&rules, &mut scope)?, Status::FAIL);
+    let root_record = scope.reset_recorder().extract();
+    let mut writer = Vec::new();
+    print_failed_record_details(&mut writer, "rules.guard", &root_record)?;
+    let output = String::from_utf8(writer).unwrap();
+    assert!(output.contains("rules.guard/check_bucket"), "{}", output);
+    assert!(!output.contains("check_type"), "{}", output);
+    assert!(output.contains(concat!(
+        "    Path=[/Resources/bucket/Properties/BucketName] Comparator=[EQUALS] ",
+        "From=[\"a-very-long-bucket-name\"] To=[\"expected\"] Message=[bucket name;must match]\n"
+    )), "{}", output);
+    Ok(())
+}
+
+#[test]
+fn test_print_explanation() -> Result<()> {
+    let data = PathAwareValue::try_from(serde_json::json!({
+        "Resources": { "bucket": { "Properties": { "BucketName": "a-very-long-bucket-name-that-is-not-truncated-when-explaining-failures-in-full" } } }
+    }))?;
+    let from = data.at_path("/Resources/bucket/Properties/BucketName")?.clone();
+    let clause = StatusContext {
+        eval_type: EvaluationType::Clause,
+        context: "BucketName".to_string(),
+        msg: Some("bucket name\nmust match".to_string()),
+        from: Some(from),
+        to: Some(PathAwareValue::try_from(serde_json::json!("expected"))?),
+        status: Some(Status::FAIL),
+        comparator: Some((CmpOperator::Eq, true)),
+        children: vec![],
+        branches: vec![],
+    };
+    let rule = StatusContext {
+        eval_type: EvaluationType::Rule,
+        context: "check_bucket".to_string(),
+        msg: None,
+        from: None,
+        to: None,
+        status: Some(Status::FAIL),
+        comparator: None,
+        children: vec![clause],
+        branches: vec![],
+    };
+    let mut writer = Vec::new();
+    print_explanation(&mut writer, "rules.guard", &[&rule])?;
+    let output = String::from_utf8(writer).unwrap();
+    assert!(output.contains("rules.guard/check_bucket"));
+    assert!(output.contains(concat!(
+        "  Clause #1 BucketName\n",
+        "    Path:     /Resources/bucket/Properties/BucketName\n",
+        "    Operator: NOT EQUALS\n",
+        "    Found:    \"a-very-long-bucket-name-that-is-not-truncated-when-explaining-failures-in-full\"\n",
+        "    Expected: \"expected\"\n",
+        "    Message:  bucket name\n",
+        "              must match\n",
+    )), "{}", output);
+    Ok(())
+}
+
+#[test]
+fn test_print_context_path_for_scalar_failures() -> Result<()> {
+    let data = PathAwareValue::try_from(serde_json::json!({
+        "Resources": { "VPC": { "Properties": { "Port": 22, "Enabled": false } } }
+    }))?;
+    let port = data.at_path("/Resources/VPC/Properties/Port")?.clone();
+    let enabled = data.at_path("/Resources/VPC/Properties/Enabled")?.clone();
+    let clause = |context: &str, from: PathAwareValue, to: serde_json::Value, status: Status| -> Result<StatusContext> {
+        Ok(StatusContext {
+            eval_type: EvaluationType::Clause,
+            context: context.to_string(),
+            msg: None,
+            from: Some(from),
+            to: Some(PathAwareValue::try_from(to)?),
+            status: Some(status),
+            comparator: Some((CmpOperator::Eq, false)),
+            children: vec![],
+            branches: vec![],
+        })
+    };
+    let rule = StatusContext {
+        eval_type: EvaluationType::Rule,
+        context: "check_vpc".to_string(),
+        msg: None,
+        from: None,
+        to: None,
+        status: Some(Status::FAIL),
+        comparator: None,
+        children: vec![
+            clause("Port", port.clone(), serde_json::json!(443), Status::FAIL)?,
+            clause("Enabled", enabled, serde_json::json!(true), Status::FAIL)?,
+            clause("PortPass", port, serde_json::json!(22), Status::PASS)?,
+        ],
+        branches: vec![],
+    };
+    let mut writer = Vec::new();
+    print_context(&mut writer, &rule, 1)?;
+    let output = String::from_utf8(writer).unwrap();
+    assert!(output.contains(concat!(
+        "        |  Path: /Resources/VPC/Properties/Port\n",
+        "        |  From: 22\n",
+        "        |  To: 443\n",
+    )), "{}", output);
+    assert!(output.contains(concat!(
+        "        |  Path: /Resources/VPC/Properties/Enabled\n",
+        "        |  From: false\n",
+        "        |  To: true\n",
+    )), "{}", output);
+    assert_eq!(output.matches("|  Path:").count(), 2, "{}", output);
+    Ok(())
+}
+
+#[test]
+fn test_truncate_for_display() {
+    assert_eq!(truncate_for_display("short".to_string(), 10), "short");
+    assert_eq!(truncate_for_display("0123456789ab".to_string(), 10), "0123456789...");
+}
+
+#[test]
+fn test_parameter_values() -> Result<()> {
+    let expected = HashMap::from([
+        ("Env".to_string(), "prod".to_string()),
+        ("Volumes".to_string(), "2".to_string()),
+        ("Subnets".to_string(), "subnet-1,subnet-2".to_string()),
+    ]);
+    let map = serde_yaml::from_str::<serde_yaml::Value>(r#"
+    Env: prod
+    Volumes: 2
+    Subnets: [subnet-1, subnet-2]
+    "#)?;
+    assert_eq!(parameter_values(&map)?, expected);
+
+    let list = serde_yaml::from_str::<serde_yaml::Value>(r#"[
+        { "ParameterKey": "Env", "ParameterValue": "prod" },
+        { "ParameterKey": "Volumes", "ParameterValue": "2" },
+        { "ParameterKey": "Subnets", "ParameterValue": "subnet-1,subnet-2" }
+    ]"#)?;
+    assert_eq!(parameter_values(&list)?, expected);
+
+    for invalid in [r#"[{ "ParameterKey": "Env" }]"#, r#"{ "Env": { "Value": "prod" } }"#, "prod"] {
+        assert!(parameter_values(&serde_yaml::from_str::<serde_yaml::Value>(invalid)?).is_err(), "{}", invalid);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_split_define() -> Result<()> {
+    assert_eq!(split_define("env=prod")?, ("env", "prod"));
+    assert_eq!(split_define("expr=a=b")?, ("expr", "a=b"));
+    assert!(split_define("env").is_err());
+    assert!(split_define("=prod").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_defines_override_file_level_let() -> Result<()> {
+    let defines = parse_defines(&[("allowed", r#"["a", "b"]"#), ("env", "prod")])?;
+    assert_eq!(defines.get("env"), Some(&PathAwareValue::try_from(serde_json::json!("prod"))?));
+
+    let rules = RulesFile::try_from(r#"
+    let allowed = ["c"]
+    rule check_name { Name IN %allowed }
+    rule check_env { Env == %env }
+    "#)?;
+    let data = PathAwareValue::try_from(serde_json::json!({"Name": "a", "Env": "prod"}))?;
+    let mut scope = root_scope_with_defines(&rules, &data, &defines)?;
+    assert_eq!(eval_rules_file(&rules, &mut scope)?, Status::PASS);
+
+    let mut scope = root_scope(&rules, &data)?;
+    assert!(eval_rules_file(&rules, &mut scope).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_max_errors_is_counted_across_data_files() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule check_names { Names[*] == "a" }
+    rule check_env { Env == "prod" }
+    "#)?;
+    let data = PathAwareValue::try_from(serde_json::json!({
+        "Names": ["b", "c", "d"],
+        "Env": "dev"
+    }))?;
+    let mut totals = common::EvaluationTotals::with_max_errors(3);
+
+    let mut scope = root_scope(&rules, &data)?;
+    assert_eq!(eval_rules_file(&rules, &mut scope)?, Status::FAIL);
+    let root_record = scope.reset_recorder().extract();
+    assert_eq!(common::find_failing_clauses(&root_record).len(), 4);
+    let first = totals.truncate_record(&root_record).unwrap();
+    assert_eq!(first.children.len(), 2);
+    assert_eq!(common::find_failing_clauses(&first).len(), 3);
+    assert_eq!(totals.omitted_failures, 1);
+
+    let mut scope = root_scope(&rules, &data)?;
+    assert_eq!(eval_rules_file(&rules, &mut scope)?, Status::FAIL);
+    let root_record = scope.reset_recorder().extract();
+    let second = totals.truncate_record(&root_record).unwrap();
+    assert_eq!(second.children.len(), 2);
+    assert_eq!(common::find_failing_clauses(&second).len(), 0);
+    assert_eq!(totals.omitted_failures, 5);
+
+    let mut writer = Vec::new();
+    generic_summary::GenericSummary::new().report_eval(
+        &mut writer,
+        Status::FAIL,
+        &second,
+        true,
+        "rules.guard",
+        "data.json",
+        "",
+        &Traversal::from(&data),
+        OutputFormatType::JSON,
+    )?;
+    let json: serde_json::Value = serde_json::from_slice(&writer)?;
+    assert_eq!(json.get("truncated"), Some(&serde_json::Value::Bool(true)));
+
+    let mut unlimited = common::EvaluationTotals::with_max_errors(0);
+    assert!(unlimited.truncate_record(&root_record).is_none());
+    assert_eq!(unlimited.omitted_failures, 0);
+    Ok(())
+}
+
+#[test]
+fn test_max_errors_does_not_count_filtered_out_values() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule check_volumes {
+        Resources.*[ Type == "AWS::EC2::Volume" ].Properties.Encrypted == true
+    }
+    "#)?;
+    let data = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "bucket": { "Type": "AWS::S3::Bucket", "Properties": {} },
+            "first": { "Type": "AWS::EC2::Volume", "Properties": { "Encrypted": false } },
+            "queue": { "Type": "AWS::SQS::Queue", "Properties": {} },
+            "second": { "Type": "AWS::EC2::Volume", "Properties": { "Encrypted": false } }
+        }
+    }))?;
+
+    let mut scope = root_scope(&rules, &data)?;
+    assert_eq!(eval_rules_file(&rules, &mut scope)?, Status::FAIL);
+    let root_record = scope.reset_recorder().extract();
+    assert_eq!(common::find_failing_clauses(&root_record).len(), 2);
+    let mut totals = common::EvaluationTotals::with_max_errors(2);
+    assert!(totals.truncate_record(&root_record).is_none());
+    assert_eq!(totals.omitted_failures, 0);
+    let mut totals = common::EvaluationTotals::with_max_errors(1);
+    let truncated = totals.truncate_record(&root_record).unwrap();
+    assert_eq!(common::find_failing_clauses(&truncated).len(), 1);
+    assert_eq!(totals.omitted_failures, 1);
+
+    let root = RootScope::new(&rules, &data)?;
+    let tracker = StackTracker::new(&root);
+    assert_eq!(rules.evaluate(&data, &tracker)?, Status::FAIL);
+    let mut totals = common::EvaluationTotals::with_max_errors(1);
+    assert!(totals.truncate_context(tracker.tree_mut().first_mut().unwrap()));
+    assert_eq!(totals.omitted_failures, 1);
+    assert_eq!(tracker.failures()?.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_summary_only_reports_failing_resource_counts() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule check_encryption {
+        Resources.*.Properties.Encrypted == true
+        Resources.*.Properties.KmsKeyId exists
+    }
+    rule check_tags {
+        Resources.*.Properties.Tags exists
+    }
+    "#)?;
+    let data = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "first": { "Type": "AWS::EC2::Volume", "Properties": { "Encrypted": false, "Tags": [] } },
+            "second": { "Type": "AWS::EC2::Volume", "Properties": { "Encrypted": false, "Tags": [] } },
+            "third": { "Type": "AWS::EC2::Volume", "Properties": { "Encrypted": true, "KmsKeyId": "key", "Tags": [] } }
+        }
+    }))?;
+    let mut scope = root_scope(&rules, &data)?;
+    let status = eval_rules_file(&rules, &mut scope)?;
+    let root_record = scope.reset_recorder().extract();
+    let traversal = Traversal::from(&data);
+
+    let generic = generic_summary::GenericSummary::new();
+    let reporter = summary_table::SummaryTable::summary_only(&generic);
+    let mut writer = Vec::new();
+    reporter.report_eval(
+        &mut writer,
+        status,
+        &root_record,
+        false,
+        "rules.guard",
+        "volumes.json",
+        "",
+        &traversal,
+        OutputFormatType::SingleLineSummary,
+    )?;
+    let output = String::from_utf8(writer).unwrap();
+    let check_encryption = output.lines().find(|line| line.contains("rules.guard/check_encryption")).unwrap();
+    assert!(check_encryption.ends_with("2 failing resource(s)"), "{}", output);
+    let check_tags = output.lines().find(|line| line.contains("rules.guard/check_tags")).unwrap();
+    assert!(!check_tags.contains("failing resource"), "{}", output);
+    assert!(!output.contains("Evaluating data"), "{}", output);
+    Ok(())
+}
+
+#[test]
+fn test_remove_cf_extensions() -> Result<()> {
+    let data = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "bucket": { "Type": "AWS::S3::Bucket" },
+            "custom": { "Type": "Custom::MyResource" },
+            "stack": { "Type": "AWS::CloudFormation::Stack" },
+            "stackSet": { "Type": "AWS::CloudFormation::StackSet" },
+            "waitHandle": { "Type": "AWS::CloudFormation::WaitConditionHandle" }
+        }
+    }))?;
+    let filtered = remove_cf_extensions(data);
+    let resources = match &filtered {
+        PathAwareValue::Map((_, root)) => match root.values.get("Resources") {
+            Some(PathAwareValue::Map((_, resources))) => resources,
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    };
+    let names = resources.values.keys().map(String::as_str).collect::<Vec<_>>();
+    assert_eq!(names, vec!["bucket", "waitHandle"]);
+    assert_eq!(resources.keys.len(), 2);
+
+    let rules = RulesFile::try_from(r#"
+    rule check_types { Resources.*.Type IN ["AWS::S3::Bucket", "AWS::CloudFormation::WaitConditionHandle"] }
+    "#)?;
+    let mut scope = root_scope(&rules, &filtered)?;
+    assert_eq!(eval_rules_file(&rules, &mut scope)?, Status::PASS);
+    Ok(())
+}
+
+#[test]
+fn test_get_path_aware_value_from_data_with_format() -> Result<()> {
+    //
+    // single quotes are not JSON, but the content is still valid YAML
+    //
+    let content = r#"{"Resources": {"bucket": {"Type": 'AWS::S3::Bucket'}}}"#.to_string();
+    let auto = get_path_aware_value_from_data(&content, "template.json", DataFormat::Auto)?;
+    let yaml = get_path_aware_value_from_data(&content, "template.json", DataFormat::YAML)?;
+    assert_eq!(auto, yaml);
+
+    match get_path_aware_value_from_data(&content, "template.json", DataFormat::JSON) {
+        Err(Error(ErrorKind::DataFileError(msg))) => {
+            assert!(msg.starts_with("template.json, not parsable as JSON: "), "{}", msg);
+            assert!(msg.contains("line 1 column"), "{}", msg);
+        }
+        rest => unreachable!("{:?}", rest),
+    }
+
+    let content = r#"{"Resources": {}}"#.to_string();
+    assert_eq!(
+        get_path_aware_value_from_data(&content, "template.json", DataFormat::JSON)?,
+        get_path_aware_value_from_data(&content, "template.json", DataFormat::Auto)?
+    );
+    Ok(())
+}
+
+#[test]
+fn test_evaluation_error_names_data_file() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule check_size { Resources.*.Properties.Size == %missing }
+    "#)?;
+    let content = r#"{"Resources": {"volume": {"Properties": {"Size": 10}}}}"#.to_string();
+    let data_files = vec![DataFile {
+        path_value: get_path_aware_value_from_data(&content, "volumes.json", DataFormat::Auto)?,
+        content,
+        name: "volumes.json".to_string(),
+        variables: HashMap::new(),
+    }];
+    let app = Validate::new().command().get_matches_from(vec!["validate", "--rules", "sizes.guard"]);
+    let mut options = ValidateOptions::from_args(&app, OutputFormatType::SingleLineSummary, HashMap::new());
+    for new_engine_version in [true, false].iter() {
+        options.new_engine_version = *new_engine_version;
+        let result = evaluate_against_data_input(
+            &options,
+            None,
+            &data_files,
+            &rules,
+            "sizes.guard",
+            &mut std::io::sink(),
+            &mut common::EvaluationTotals::default(),
+            &mut baseline::Baseline::default(),
+            &cache::Cache::default(),
+        );
+        match result {
+            Err(e) => {
+                let msg = format!("{}", e);
+                assert!(msg.contains("DataFile = volumes.json"), "{}", msg);
+            }
+            Ok(status) => unreachable!("{:?}", status),
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_disjunction_reports_branch_statuses() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule encrypted_or_private {
+        Properties.Encrypted == true or
+        Properties.Private == true
+    }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Properties": { "Encrypted": false, "Private": true }
+    }))?;
+    let root = RootScope::new(&rules, &value)?;
+    let tracker = StackTracker::new(&root);
+    assert_eq!(rules.evaluate(&value, &tracker)?, Status::PASS);
+    let stack = tracker.stack();
+
+    fn find_or(cxt: &StatusContext) -> Option<&StatusContext> {
+        if !cxt.branches.is_empty() {
+            return Some(cxt)
+        }
+        cxt.children.iter().find_map(find_or)
+    }
+    let disjunction = stack.iter().find_map(find_or).expect("or conjunction recorded");
+    assert_eq!(disjunction.eval_type, EvaluationType::Conjunction);
+    assert_eq!(disjunction.branches, vec![Status::FAIL, Status::PASS]);
+    let summary = disjunction_summary(disjunction).unwrap();
+    assert!(summary.contains("Branch 1"));
+    assert!(!summary.split(',').next().unwrap().contains("(satisfied)"));
+    assert!(summary.split(',').nth(1).unwrap().contains("Branch 2"));
+    assert!(summary.ends_with("(satisfied)"));
+    Ok(())
+}
+
+#[test]
+fn test_rule_counts_for_both_engines() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule has_resources {
+        Resources exists
+    }
+    rule encrypted {
+        Resources.*.Properties.Encrypted == true
+    }
+    rule tagged {
+        Resources.*.Properties.Tags exists
+    }
+    rule lambdas when Resources.*.Type == "AWS::Lambda::Function" {
+        Resources.*.Properties.Runtime exists
+    }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "volume": { "Type": "AWS::EC2::Volume", "Properties": { "Encrypted": false } }
+        }
+    }))?;
+    let expected = common::RuleCounts { passed: 1, failed: 2, skipped: 1, suppressed: 0, known: 0 };
+
+    let mut scope = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut scope)?;
+    let root_record = scope.reset_recorder().extract();
+    assert_eq!(common::RuleCounts::from_record(&root_record), expected);
+
+    let root = RootScope::new(&rules, &value)?;
+    let tracker = StackTracker::new(&root);
+    rules.evaluate(&value, &tracker)?;
+    let stack = tracker.stack();
+    assert_eq!(common::RuleCounts::from_context(&stack[0]), expected);
+
+    assert_eq!(format!("{}", expected), "Rules: 4 total, 1 passed, 2 failed, 1 skipped");
+    Ok(())
+}
+
+#[test]
+fn test_cached_rule_checks_not_counted() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule encrypted {
+        Resources.*.Properties.Encrypted == true
+    }
+    rule first_check {
+        encrypted
+    }
+    rule second_check {
+        encrypted
+    }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "volume": { "Type": "AWS::EC2::Volume", "Properties": { "Encrypted": false } }
+        }
+    }))?;
+
+    let mut scope = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut scope)?;
+    let root_record = scope.reset_recorder().extract();
+    assert_eq!(common::RuleCounts::from_record(&root_record),
+               common::RuleCounts { passed: 0, failed: 3, skipped: 0, suppressed: 0, known: 0 });
+    assert_eq!(common::RuleCounts::clauses_from_record(&root_record),
+               common::RuleCounts { passed: 0, failed: 3, skipped: 0, suppressed: 0, known: 0 });
+    assert!(common::find_failing_clauses(&root_record).iter().all(
+        |each| !matches!(each.container, Some(RecordType::CachedRuleCheck(_)))));
+    Ok(())
+}
+
+#[test]
+fn test_custom_message_interpolation_for_both_engines() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule buckets_encrypted {
+        Resources.*[ Type == 'AWS::S3::Bucket' ] {
+            Properties.BucketEncryption exists
+                << bucket ${Properties.BucketName} with tags ${Properties.Tags.0.Value} is not encrypted, ${Properties.Missing} >>
+        }
+    }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "bucket": {
+                "Type": "AWS::S3::Bucket",
+                "Properties": { "BucketName": "logs", "Tags": [{ "Key": "Owner", "Value": "team" }] }
+            }
+        }
+    }))?;
+    let expected = "bucket logs with tags team is not encrypted, ${Properties.Missing}";
+
+    let mut scope = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut scope)?;
+    let root_record = scope.reset_recorder().extract();
+    let failures = common::failures_from_record(&root_record)?;
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].message.as_deref().map(str::trim), Some(expected));
+
+    let root = RootScope::new(&rules, &value)?;
+    let tracker = StackTracker::new(&root);
+    rules.evaluate(&value, &tracker)?;
+    let failures = tracker.failures()?;
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].message.as_deref().map(str::trim), Some(expected));
+    Ok(())
+}
+
+#[test]
+fn test_escaped_message_delimiter_for_both_engines() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule role_arn {
+        Resources.*.Properties.RoleArn == /^arn:aws:iam::/ <<use ARN format arn:aws:iam::123:role/X \>> see wiki>>
+        Resources.*.Properties.Name exists <<name \>>\>> missing>>
+    }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": { "function": { "Properties": { "RoleArn": "role/X" } } }
+    }))?;
+    let expected = vec!["use ARN format arn:aws:iam::123:role/X >> see wiki", "name >>>> missing"];
+
+    let mut scope = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut scope)?;
+    let root_record = scope.reset_recorder().extract();
+    let failures = common::failures_from_record(&root_record)?;
+    assert_eq!(failures.iter().map(|each| each.message.as_deref().unwrap_or("")).collect::<Vec<_>>(), expected);
+
+    let root = RootScope::new(&rules, &value)?;
+    let tracker = StackTracker::new(&root);
+    rules.evaluate(&value, &tracker)?;
+    let failures = tracker.failures()?;
+    assert_eq!(failures.iter().map(|each| each.message.as_deref().unwrap_or("")).collect::<Vec<_>>(), expected);
+    Ok(())
+}
+
+#[test]
+fn test_resource_report_groups_failures_by_logical_id() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule encrypted {
+        Resources.*.Properties.Encrypted == true
+    }
+    rule sized {
+        Resources.*.Properties.Size <= 100
+    }
+    rule described {
+        Description exists
+    }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "volume": { "Type": "AWS::EC2::Volume", "Properties": { "Encrypted": false, "Size": 200 } },
+            "small": { "Type": "AWS::EC2::Volume", "Properties": { "Encrypted": true, "Size": 10 } },
+            "large": { "Type": "AWS::EC2::Volume", "Properties": { "Encrypted": true, "Size": 500 } }
+        }
+    }))?;
+
+    let mut scope = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut scope)?;
+    let root_record = scope.reset_recorder().extract();
+    let report = common::ResourceReport::new("volumes.guard", "volumes.json", common::failures_from_record(&root_record)?);
+    let rules_by_resource = report.resources.iter()
+        .map(|(id, failures)| (id.as_str(), failures.iter().map(|each| each.rule.as_str()).collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+    assert_eq!(rules_by_resource, vec![("large", vec!["sized"]), ("volume", vec!["encrypted", "sized"])]);
+    assert_eq!(report.document.iter().map(|each| each.rule.as_str()).collect::<Vec<_>>(), vec!["described"]);
+
+    let mut output = Vec::new();
+    report.report(&mut output, OutputFormatType::SingleLineSummary)?;
+    let output = String::from_utf8(output).unwrap();
+    let headers = output.lines().filter(|line| !line.starts_with(' ')).collect::<Vec<_>>();
+    assert_eq!(headers.len(), 3);
+    assert!(headers[0].starts_with("Resource [large] in [volumes.json]"));
+    assert!(headers[1].starts_with("Resource [volume] in [volumes.json]"));
+    assert!(headers[2].starts_with("Document in [volumes.json]"));
+    assert!(output.contains("    Rule=[volumes.guard/sized] Path=[/Resources/large/Properties/Size]"));
+
+    let root = RootScope::new(&rules, &value)?;
+    let tracker = StackTracker::new(&root);
+    rules.evaluate(&value, &tracker)?;
+    let report = common::ResourceReport::new("volumes.guard", "volumes.json", tracker.failures()?);
+    assert_eq!(report.resources.keys().map(String::as_str).collect::<Vec<_>>(), vec!["large", "volume"]);
+    Ok(())
+}
+
+#[test]
+fn test_type_report_groups_failures_by_resource_type() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule volumes_encrypted {
+        Resources.*[ Type == "AWS::EC2::Volume" ].Properties.Encrypted == true
+    }
+    rule buckets_versioned {
+        Resources.*[ Type == "AWS::S3::Bucket" ].Properties.VersioningConfiguration.Status == "Enabled"
+    }
+    rule described {
+        Description exists
+    }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "volume": { "Type": "AWS::EC2::Volume", "Properties": { "Encrypted": false } },
+            "encrypted": { "Type": "AWS::EC2::Volume", "Properties": { "Encrypted": true } },
+            "logs": { "Type": "AWS::S3::Bucket", "Properties": { "VersioningConfiguration": { "Status": "Suspended" } } },
+            "assets": { "Type": "AWS::S3::Bucket", "Properties": { "VersioningConfiguration": { "Status": "Suspended" } } }
+        }
+    }))?;
+
+    let mut scope = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut scope)?;
+    let root_record = scope.reset_recorder().extract();
+    let report = common::TypeReport::new("rules.guard", "template.json", &value, common::failures_from_record(&root_record)?);
+    let by_type = report.types.iter()
+        .map(|(resource_type, resources)| (resource_type.as_str(), resources.iter()
+            .map(|(id, failures)| (id.as_str(), failures.iter().map(|each| each.rule.as_str()).collect::<Vec<_>>()))
+            .collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+    assert_eq!(by_type, vec![
+        ("AWS::EC2::Volume", vec![("volume", vec!["volumes_encrypted"])]),
+        ("AWS::S3::Bucket", vec![("assets", vec!["buckets_versioned"]), ("logs", vec!["buckets_versioned"])]),
+    ]);
+    assert_eq!(report.document.iter().map(|each| each.rule.as_str()).collect::<Vec<_>>(), vec!["described"]);
+
+    let mut output = Vec::new();
+    report.report(&mut output, OutputFormatType::SingleLineSummary)?;
+    let output = String::from_utf8(output).unwrap();
+    let headers = output.lines().filter(|line| !line.starts_with(' ')).collect::<Vec<_>>();
+    assert_eq!(headers.len(), 3);
+    assert!(headers[0].starts_with("Type [AWS::EC2::Volume] in [template.json]"));
+    assert!(headers[1].starts_with("Type [AWS::S3::Bucket] in [template.json]"));
+    assert!(headers[2].starts_with("Document in [template.json]"));
+    assert!(output.contains("    Resource [logs]\n        Rule=[rules.guard/buckets_versioned] Path=[/Resources/logs/Properties/VersioningConfiguration/Status]"));
+
+    let root = RootScope::new(&rules, &value)?;
+    let tracker = StackTracker::new(&root);
+    rules.evaluate(&value, &tracker)?;
+    let report = common::TypeReport::new("rules.guard", "template.json", &value, tracker.failures()?);
+    assert_eq!(report.types.keys().map(String::as_str).collect::<Vec<_>>(), vec!["AWS::EC2::Volume", "AWS::S3::Bucket"]);
+
+    //
+    // resources that have no Type are grouped as untyped
+    //
+    let untyped = PathAwareValue::try_from(serde_json::json!({ "Resources": { "volume": { "Properties": {} } } }))?;
+    let failures = vec![crate::commands::tracker::Failure {
+        rule: "volumes_encrypted".to_string(),
+        path: "/Resources/volume/Properties/Encrypted".to_string(),
+        from: None,
+        to: None,
+        message: None,
+        location: None,
+    }];
+    let report = common::TypeReport::new("rules.guard", "template.json", &untyped, failures);
+    assert_eq!(report.types.keys().map(String::as_str).collect::<Vec<_>>(), vec![common::UNTYPED]);
+    Ok(())
+}
+
+#[test]
+fn test_evaluation_totals_across_files() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule encrypted when Resources.*.Type == "AWS::EC2::Volume" {
+        Resources.*.Properties.Encrypted == true
+    }
+    rule sized {
+        Resources.*.Properties.Size > 10
+    }
+    "#)?;
+    let first = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "data": { "Type": "AWS::EC2::Volume", "Properties": { "Encrypted": false, "Size": 100 } },
+            "logs": { "Type": "AWS::EC2::Volume", "Properties": { "Encrypted": true, "Size": 5 } }
+        }
+    }))?;
+    let second = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "bucket": { "Type": "AWS::S3::Bucket", "Properties": { "Size": 20 } }
+        }
+    }))?;
+
+    for new_engine in [true, false] {
+        let mut totals = common::EvaluationTotals::default();
+        for (name, value) in [("first.json", &first), ("second.json", &second), ("first.json", &first)] {
+            let (rules_counts, clauses) = if new_engine {
+                let mut scope = root_scope(&rules, value)?;
+                eval_rules_file(&rules, &mut scope)?;
+                let root_record = scope.reset_recorder().extract();
+                (common::RuleCounts::from_record(&root_record), common::RuleCounts::clauses_from_record(&root_record))
+            } else {
+                let root = RootScope::new(&rules, value)?;
+                let tracker = StackTracker::new(&root);
+                rules.evaluate(value, &tracker)?;
+                let stack = tracker.stack();
+                (common::RuleCounts::from_context(&stack[0]), common::RuleCounts::clauses_from_context(&stack[0]))
+            };
+            totals.add(name, rules_counts, clauses);
+        }
+        assert_eq!(totals.rules, common::RuleCounts { passed: 1, failed: 4, skipped: 1, suppressed: 0, known: 0 }, "new engine {}", new_engine);
+        assert_eq!(totals.clauses, common::RuleCounts { passed: 5, failed: 4, skipped: 0, suppressed: 0, known: 0 }, "new engine {}", new_engine);
+        assert_eq!(format!("{}", totals),
+                   "Rules: 6 total, 1 passed, 4 failed, 1 skipped across 2 data files; Clauses: 5 passed, 4 failed");
+
+        let mut json = Vec::new();
+        totals.report(&mut json, OutputFormatType::JSON)?;
+        let json: serde_json::Value = serde_json::from_slice(&json)?;
+        assert_eq!(json, serde_json::json!({
+            "summary": {
+                "rules": { "passed": 1, "failed": 4, "skipped": 1 },
+                "clauses": { "passed": 5, "failed": 4, "skipped": 0 },
+                "files": 2
+            }
+        }));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_merge_rules_files() -> Result<()> {
+    let a = RulesFile::try_from(r#"
+    let buckets = Resources.*[ Type == 'AWS::S3::Bucket' ]
+    let max = 10
+    rule logged when encrypted {
+        %buckets.Properties.LoggingConfiguration exists
+    }
+    "#)?;
+    let b = RulesFile::try_from(r#"
+
+    let max = 10
+    let buckets = Resources.*[ Type == 'AWS::S3::Bucket' ]
+    rule encrypted {
+        %buckets.Properties.BucketEncryption exists
+    }
+    "#)?;
+    let merged = merge_rules_files(vec![("a.guard", a.clone()), ("b.guard", b.clone())])?;
+    assert_eq!(merged.assignments.iter().map(|each| each.var.as_str()).collect::<Vec<_>>(), vec!["buckets", "max"]);
+    assert_eq!(merged.guard_rules.iter().map(|each| each.rule_name.as_str()).collect::<Vec<_>>(),
+               vec!["logged", "encrypted"]);
+    assert!(undefined_references(&merged, &[]).is_empty());
+
+    let conflicting = RulesFile::try_from(r#"
+    let max = 20
+    "#)?;
+    match merge_rules_files(vec![("a.guard", a.clone()), ("c.guard", conflicting)]) {
+        Err(Error(ErrorKind::IncompatibleError(msg))) =>
+            assert_eq!(msg, "Variable max is assigned different values in rules files a.guard and c.guard"),
+        rest => unreachable!("{:?}", rest),
+    }
+
+    match merge_rules_files(vec![("b.guard", b.clone()), ("d.guard", b)]) {
+        Err(Error(ErrorKind::IncompatibleError(msg))) =>
+            assert_eq!(msg, "Rule encrypted is defined in rules files b.guard and d.guard"),
+        rest => unreachable!("{:?}", rest),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_disable_colors() -> Result<()> {
+    colored::control::set_override(true);
+    disable_colors(false);
+    assert!(format!("{}", common::colored_string(Some(Status::FAIL))).contains('\x1b'));
+    disable_colors(true);
+    assert_eq!(format!("{}", common::colored_string(Some(Status::FAIL))), "FAIL");
+
+    colored::control::set_override(true);
+    std::env::set_var("NO_COLOR", "1");
+    disable_colors(false);
+    std::env::remove_var("NO_COLOR");
+    let failures = vec![crate::commands::tracker::Failure::new("described", None, None, Some("missing".to_string()))?];
+    let mut output = Vec::new();
+    common::ResourceReport::new("rules.guard", "data.json", failures)
+        .report(&mut output, OutputFormatType::SingleLineSummary)?;
+    let output = String::from_utf8(output).unwrap();
+    assert!(!output.contains('\x1b'));
+    assert!(output.starts_with("Document in [data.json] FAIL\n"));
+    colored::control::unset_override();
+    Ok(())
+}
+
+#[test]
+fn test_tag_filter_and_report() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule volumes_exist {
+        Resources.*[ Type == 'AWS::EC2::Volume' ] not empty
+    }
+    rule encrypted @tags("security", "ebs") when volumes_exist {
+        Resources.*.Properties.Encrypted == true
+    }
+    rule sized @tags("cost", "ebs") {
+        Resources.*.Properties.Size <= 100
+    }
+    rule described @tags("experimental") {
+        Description exists
+    }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "volume": { "Type": "AWS::EC2::Volume", "Properties": { "Encrypted": false, "Size": 10 } }
+        }
+    }))?;
+    let names = |rules: &RulesFile<'_>| rules.guard_rules.iter().map(|rule| rule.rule_name.clone()).collect::<Vec<_>>();
+
+    let filter = TagFilter { include: vec!["security".to_string()], exclude: vec![] };
+    assert_eq!(names(&filter.apply(rules.clone())), vec!["volumes_exist", "encrypted"]);
+    let filter = TagFilter { include: vec!["ebs".to_string()], exclude: vec!["cost".to_string()] };
+    assert_eq!(names(&filter.apply(rules.clone())), vec!["volumes_exist", "encrypted"]);
+    let filter = TagFilter { include: vec![], exclude: vec!["experimental".to_string()] };
+    assert_eq!(names(&filter.apply(rules.clone())), vec!["volumes_exist", "encrypted", "sized"]);
+    let filter = TagFilter { include: vec!["pci".to_string()], exclude: vec![] };
+    assert!(filter.excludes_all(&filter.apply(rules.clone())));
+    assert!(!TagFilter::default().excludes_all(&RulesFile::try_from("rule r { a exists }")?));
+
+    let mut scope = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut scope)?;
+    let root_record = scope.reset_recorder().extract();
+    let statuses = root_record.children.iter().filter_map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus{name, status, ..})) => Some((*name, *status)),
+        _ => None,
+    });
+    let report = common::TagReport::new("volumes.guard", "volumes.json", &rules, statuses);
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    let tracker = StackTracker::new(&scope);
+    rules.evaluate(&value, &tracker)?;
+    let stack = tracker.stack();
+    let statuses = stack[0].children.iter()
+        .filter_map(|rule| rule.status.map(|status| (rule.context.as_str(), status)));
+    assert_eq!(common::TagReport::new("volumes.guard", "volumes.json", &rules, statuses), report);
+
+    let by_tag = report.tags.iter()
+        .map(|(tag, rules)| (tag.as_str(), rules.iter().map(|(name, status)| (name.as_str(), *status)).collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+    assert_eq!(by_tag, vec![
+        ("cost", vec![("sized", Status::PASS)]),
+        ("ebs", vec![("encrypted", Status::FAIL), ("sized", Status::PASS)]),
+        ("experimental", vec![("described", Status::FAIL)]),
+        ("security", vec![("encrypted", Status::FAIL)]),
+        (common::UNTAGGED, vec![("volumes_exist", Status::PASS)]),
+    ]);
+
+    let mut output = Vec::new();
+    report.report(&mut output, OutputFormatType::JSON)?;
+    let output: serde_json::Value = serde_json::from_slice(&output)?;
+    assert_eq!(output["tags"]["ebs"]["encrypted"], "FAIL");
+    Ok(())
+}
+
+#[test]
+fn test_lint_rules_file() -> Result<()> {
+    let content = r###"let broken = /(unclosed/
+rule sizes {
+    Name == /[a-z/
+    Zone IN %zones
+    Name == /^[a-z]+$/
+}
+rule uses {
+    sizes
+    sizez
+}
+"###;
+    let rules = parse_rules(content, "lint.guard")?;
+    let lint = |allow_undefined_variables| lint_rules_file(&rules, "lint.guard", &[], allow_undefined_variables)
+        .into_iter()
+        .map(|each| (each.severity, each.kind, each.rule, each.line, each.column))
+        .collect::<Vec<_>>();
+    let sizes = Some("sizes".to_string());
+    assert_eq!(lint(false), vec![
+        ("error", "undefined-variable", sizes.clone(), Some(4), Some(5)),
+        ("error", "undefined-rule", Some("uses".to_string()), Some(9), Some(5)),
+        ("error", "invalid-regex", None, None, None),
+        ("error", "invalid-regex", sizes.clone(), Some(3), Some(5)),
+    ]);
+    assert_eq!(lint(true)[0], ("warning", "undefined-variable", sizes, Some(4), Some(5)));
+
+    let errors = lint_rules_file(&rules, "lint.guard", &[], false);
+    assert_eq!(format!("{}", errors[0]), "lint.guard:4:5: error[undefined-variable]: Undefined variable %zones in rule sizes");
+    assert_eq!(format!("{}", errors[2]),
+               "lint.guard: error[invalid-regex]: Invalid regular expression /(unclosed/, regex parse error: (unclosed ^ error: unclosed group");
+
+    let clean = parse_rules("let zones = [\"a\"]\nrule sizes { Zone IN %zones }", "clean.guard")?;
+    assert!(lint_rules_file(&clean, "clean.guard", &[], false).is_empty());
+    Ok(())
+}
+
+
+#[test]
+fn test_select_data_file_root() -> Result<()> {
+    let content = r#"{
+        "Resources": {
+            "bucket": { "Type": "AWS::S3::Bucket", "Properties": { "Tags": [{ "Key": "a/b~c", "Value": "v" }] } },
+            "stack": { "Type": "AWS::CloudFormation::Stack" }
+        }
+    }"#;
+    let root = PathAwareValue::try_from(serde_json::from_str::<serde_json::Value>(content)?)?;
+    let data_file = || DataFile {
+        content: content.to_string(),
+        path_value: root.clone(),
+        name: "template.json".to_string(),
+        variables: HashMap::new(),
+    };
+
+    for pointer in ["", "/"] {
+        assert_eq!(value_at_pointer(&root, pointer), Some(&root));
+    }
+    let tag = value_at_pointer(&root, "/Resources/bucket/Properties/Tags/0").unwrap();
+    assert_eq!(tag.self_path().0, "/Resources/bucket/Properties/Tags/0");
+    let key = PathAwareValue::try_from(serde_json::json!({ "a/b~c": 1 }))?;
+    assert!(value_at_pointer(&key, "/a~1b~0c").is_some());
+    assert!(value_at_pointer(&root, "/Resources/bucket/Properties/Tags/1").is_none());
+    assert!(value_at_pointer(&root, "/Resources/bucket/Type/Name").is_none());
+
+    //
+    // the selected value keeps its path, extensions are removed from the whole template first
+    //
+    let selected = select_data_file_root(vec![data_file()], "/Resources", true)?;
+    assert_eq!(selected[0].path_value.self_path().0, "/Resources");
+    match &selected[0].path_value {
+        PathAwareValue::Map((_, map)) => assert_eq!(map.values.keys().collect::<Vec<_>>(), vec!["bucket"]),
+        rest => unreachable!("{:?}", rest),
+    }
+    assert_eq!(selected[0].content, content);
+
+    let error = select_data_file_root(vec![data_file()], "/Outputs", false).unwrap_err();
+    assert!(error.to_string().contains("--root-path /Outputs does not resolve to a value in data file template.json"));
+    Ok(())
+}
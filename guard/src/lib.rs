@@ -11,4 +11,6 @@ mod migrate;
 mod utils;
 
 pub use crate::rules::errors::{Error, ErrorKind};
-pub use crate::commands::helper::{validate_and_return_json as run_checks, ValidateInput};
+pub use crate::commands::helper::{validate_and_return_json as run_checks, validate_and_return_failures as run_checks_structured, validate_k8s_admission as run_checks_k8s, parse_rules, ValidateInput};
+pub use crate::commands::tracker::Failure;
+pub use crate::commands::validate::k8s_admission::{AdmissionResponse, AdmissionResult, AdmissionStatus};
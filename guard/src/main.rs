@@ -11,7 +11,7 @@ mod utils;
 use crate::command::Command;
 use rules::errors::Error;
 use std::process::exit;
-use crate::commands::{APP_NAME, APP_VERSION};
+use crate::commands::{APP_NAME, APP_VERSION, exit_code_for_error};
 
 
 
@@ -54,7 +54,7 @@ fn main() -> Result<(), Error>{
                 match (*command).execute(value) {
                     Err(e) => {
                         println!("Error occurred {}", e);
-                        exit(-1);
+                        exit(exit_code_for_error(&e));
                     },
                     Ok(code) => {
                         exit(code)
@@ -2,13 +2,46 @@ use crate::rules::eval_context::EventRecord;
 use crate::rules::{RecordType, BlockCheck, ClauseCheck, Status, QueryResult};
 use std::fmt::{Formatter, Display};
 use crate::rules::values::{CmpOperator, RangeType, LOWER_INCLUSIVE, UPPER_INCLUSIVE};
-use crate::rules::path_value::PathAwareValue;
+use crate::rules::path_value::{EvaluationOptions, PathAwareValue};
 use crate::rules::exprs::SliceDisplay;
 
 pub(crate) fn display_comparison((cmp, not): (CmpOperator, bool)) -> String {
     format!("{} {}", if not { "not" } else { "" }, cmp)
 }
 
+//
+// Replaces ${path.to.property} tokens in a custom message with the value found at that path
+// from context, strings are written without quotes. Tokens that do not resolve are left as is
+//
+pub(crate) fn interpolate_message(msg: &str, context: &PathAwareValue, options: &EvaluationOptions) -> String {
+    let mut interpolated = String::with_capacity(msg.len());
+    let mut rest = msg;
+    while let Some(start) = rest.find("${") {
+        let token = &rest[start + 2..];
+        let end = match token.find('}') {
+            Some(end) => end,
+            None => break,
+        };
+        interpolated.push_str(&rest[..start]);
+        match lookup_path(context, token[..end].trim(), options) {
+            Some(PathAwareValue::String((_, value))) => interpolated.push_str(value),
+            Some(value) => interpolated.push_str(&format!("{}", ValueOnlyDisplay(value))),
+            None => interpolated.push_str(&rest[start..start + end + 3]),
+        }
+        rest = &token[end + 1..];
+    }
+    interpolated.push_str(rest);
+    interpolated
+}
+
+fn lookup_path<'value>(context: &'value PathAwareValue, path: &str, options: &EvaluationOptions) -> Option<&'value PathAwareValue> {
+    path.split('.').try_fold(context, |current, part| match current {
+        PathAwareValue::Map((_, map)) => map.get_key(part, options),
+        PathAwareValue::List((_, list)) => part.parse::<usize>().ok().and_then(|idx| list.get(idx)),
+        _ => None,
+    })
+}
+
 
 fn write_range<T: Display + PartialOrd>(
     formatter: &mut Formatter<'_>,
@@ -45,7 +78,7 @@ impl<'value> std::fmt::Debug for ValueOnlyDisplay<'value> {
 impl<'value> Display for ValueOnlyDisplay<'value> {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
         match self.0 {
-            PathAwareValue::Null(_path)                         => formatter.write_str("\"NULL\"")?,
+            PathAwareValue::Null(_path)                         => formatter.write_str("null")?,
             PathAwareValue::String((_path, value))      => formatter.write_fmt(
                 format_args!("\"{}\"", value))?,
             PathAwareValue::Regex((_path, value))       => formatter.write_fmt(
@@ -227,6 +260,13 @@ impl<'value> std::fmt::Display for RecordType<'value> {
                 f.write_fmt(format_args!("Rule({}, Status={})", status.name, status.status))?;
             },
 
+            //
+            // no child events, the rule was already evaluated for the document
+            //
+            RecordType::CachedRuleCheck(status) => {
+                f.write_fmt(format_args!("Rule({}, Status={}, cached)", status.name, status.status))?;
+            },
+
             //
             // has as many child events for each GuardClauseBlockCheck | Disjunction
             //
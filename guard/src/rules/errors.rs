@@ -12,6 +12,37 @@ impl Error {
     pub fn new(kind: ErrorKind) -> Error {
         Error(kind)
     }
+
+    //
+    // Records the data file that was being evaluated when the error occurred, so that
+    // failures across a directory of data files can be traced back to their source
+    //
+    pub(crate) fn with_data_file(self, data_file: &str) -> Error {
+        let in_file = |msg: String| format!("{}, DataFile = {}", msg, data_file);
+        Error(match self.0 {
+            ErrorKind::ParseError(msg) => ErrorKind::ParseError(in_file(msg)),
+            ErrorKind::MissingProperty(msg) => ErrorKind::MissingProperty(in_file(msg)),
+            ErrorKind::MissingValue(msg) => ErrorKind::MissingValue(in_file(msg)),
+            ErrorKind::RetrievalError(msg) => ErrorKind::RetrievalError(in_file(msg)),
+            ErrorKind::MissingVariable(msg) => ErrorKind::MissingVariable(in_file(msg)),
+            ErrorKind::MultipleValues(msg) => ErrorKind::MultipleValues(in_file(msg)),
+            ErrorKind::IncompatibleRetrievalError(msg) => ErrorKind::IncompatibleRetrievalError(in_file(msg)),
+            IncompatibleError(msg) => IncompatibleError(in_file(msg)),
+            ErrorKind::NotComparable(msg) => ErrorKind::NotComparable(in_file(msg)),
+            rest => rest,
+        })
+    }
+
+    //
+    // Marks an error from parsing a rules file, these are told apart from other parse errors
+    // for the exit code
+    //
+    pub(crate) fn in_rules_file(self) -> Error {
+        match self.0 {
+            ErrorKind::ParseError(msg) => Error(ErrorKind::RulesFileParseError(msg)),
+            rest => Error(rest),
+        }
+    }
 }
 
 fn error_kind_msg(kind: &ErrorKind) -> String {
@@ -100,6 +131,14 @@ fn error_kind_msg(kind: &ErrorKind) -> String {
         ErrorKind::FileNotFoundError(path) => {
             format!("The path {} does not exist", path)
         }
+
+        ErrorKind::RulesFileParseError(err) => {
+            format!("Parser Error when parsing rules file {}", err)
+        }
+
+        ErrorKind::DataFileError(err) => {
+            format!("Unable to read data file {}", err)
+        }
     }
 }
 
@@ -132,6 +171,8 @@ pub enum ErrorKind {
     ConversionError(Infallible),
     Errors(Vec<ErrorKind>),
     FileNotFoundError(String),
+    RulesFileParseError(String),
+    DataFileError(String),
 }
 
 impl From<std::fmt::Error> for Error {
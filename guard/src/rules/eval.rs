@@ -1,7 +1,8 @@
 use super::*;
 use super::exprs::*;
-use crate::rules::eval_context::{block_scope, ValueScope};
-use crate::rules::path_value::compare_eq;
+use crate::rules::eval_context::{block_scope, rule_scope, ValueScope};
+use crate::rules::display::{interpolate_message, ValueOnlyDisplay};
+use crate::rules::path_value::{compare_eq, compare_in, earlier_duplicates, EvaluationOptions, MissingAs};
 use std::collections::HashMap;
 use crate::rules::eval::operators::Comparator;
 
@@ -66,6 +67,10 @@ is_type_fn!(is_char_range_operation, PathAwareValue::RangeChar(_));
 is_type_fn!(is_int_range_operation, PathAwareValue::RangeInt(_));
 is_type_fn!(is_float_range_operation, PathAwareValue::RangeFloat(_));
 
+fn is_number_operation(value: &QueryResult<'_>) -> Result<bool> {
+    Ok(is_int_operation(value)? || is_float_operation(value)?)
+}
+
 fn not_operation<O>(operation: O) -> impl Fn(&QueryResult<'_>) -> Result<bool>
     where O: Fn(&QueryResult<'_>) -> Result<bool>
 {
@@ -172,6 +177,7 @@ fn unary_operation<'r, 'l: 'r, 'loc: 'l>(lhs_query: &'l [QueryPart<'loc>],
     //
     let empty_on_expr = match &lhs_query[lhs_query.len()-1] {
         QueryPart::Filter(_,_) |
+        QueryPart::FilterNot(_,_) |
         QueryPart::MapKeyFilter(_,_) => true,
         rest => rest.is_variable() && lhs_query.len() == 1
     };
@@ -182,17 +188,24 @@ fn unary_operation<'r, 'l: 'r, 'loc: 'l>(lhs_query: &'l [QueryPart<'loc>],
                 let mut results = Vec::with_capacity(lhs.len());
                 for each in lhs {
                     eval_context.start_record(&context)?;
+                    let literal = matches!(each, QueryResult::Literal(_));
                     let (result, status) = match each {
                         QueryResult::Literal(res) |
                         QueryResult::Resolved(res) => {
                             //
-                            // NULL == EMPTY
+                            // NULL == EMPTY, a literal variable value such as let x = [] is
+                            // also EMPTY when the list or map has no entries
                             //
+                            let empty = res.is_null() || (literal && match res {
+                                PathAwareValue::List((_, list)) => list.is_empty(),
+                                PathAwareValue::Map((_, map)) => map.is_empty(),
+                                _ => false,
+                            });
                             let status = if cmp.1 {
                                 // Not empty
-                                !res.is_null()
+                                !empty
                             } else {
-                                res.is_null()
+                                empty
                             };
                             (QueryResult::Resolved(res), match status {
                                 true => Status::PASS, // not_empty
@@ -259,6 +272,22 @@ fn unary_operation<'r, 'l: 'r, 'loc: 'l>(lhs_query: &'l [QueryPart<'loc>],
         })
     }
 
+    //
+    // A value the query could not resolve is left out of EMPTY with --missing-as skip, the
+    // clause SKIPs when no value resolved. With fail it FAILs EMPTY, !EMPTY FAILs either way.
+    // Selecting all entries of an empty list or map, Tags[*] for Tags: [], is EMPTY
+    //
+    let missing_is_not_empty = cmp.1 != inverse;
+    let is_missing = |value: &QueryResult<'_>| match value {
+        QueryResult::UnResolved(unresolved) => !unresolved.is_empty_collection(),
+        _ => false,
+    };
+    let lhs = if cmp.0 == CmpOperator::Empty && !missing_is_not_empty && eval_context.options().missing_as == MissingAs::Skip {
+        lhs.into_iter().filter(|each| !is_missing(each)).collect()
+    } else {
+        lhs
+    };
+
     //
     // This only happens when the query has filters in them
     //
@@ -266,6 +295,10 @@ fn unary_operation<'r, 'l: 'r, 'loc: 'l>(lhs_query: &'l [QueryPart<'loc>],
         return Ok(EvaluationResult::EmptyQueryResult(Status::SKIP))
     }
 
+    if cmp.0 == CmpOperator::Unique {
+        return unique_operation(lhs, cmp, inverse, context, custom_message, eval_context)
+    }
+
     use CmpOperator::*;
     let mut operation: Box<dyn FnMut(&QueryResult<'l>) -> Result<bool>> =
         match cmp {
@@ -279,16 +312,23 @@ fn unary_operation<'r, 'l: 'r, 'loc: 'l>(lhs_query: &'l [QueryPart<'loc>],
                     context,
                     custom_message
                 ),
-            (CmpOperator::Empty, not_empty) =>
+            (CmpOperator::Empty, not_empty) => {
+                let empty_operation = move |value: &QueryResult<'_>| -> Result<bool> {
+                    if is_missing(value) {
+                        return Ok(missing_is_not_empty)
+                    }
+                    element_empty_operation(value)
+                };
                 box_create_func!(
-                    element_empty_operation,
+                    empty_operation,
                     not_empty,
                     inverse,
                     cmp,
                     eval_context,
                     context,
                     custom_message
-                ),
+                )
+            },
             (CmpOperator::IsString, is_not_string) =>
                 box_create_func!(
                     is_string_operation,
@@ -334,7 +374,16 @@ fn unary_operation<'r, 'l: 'r, 'loc: 'l>(lhs_query: &'l [QueryPart<'loc>],
                     eval_context,
                     context,
                     custom_message),
-            (Eq | Gt | Ge | Lt | Le | In, _) => unreachable!(),
+            (CmpOperator::IsNumber, is_not_number) =>
+                box_create_func!(
+                    is_number_operation,
+                    is_not_number,
+                    inverse,
+                    cmp,
+                    eval_context,
+                    context,
+                    custom_message),
+            (Eq | EqUnordered | Gt | Ge | Lt | Le | In | AllIn | Unique | Subset | Superset | InCidr, _) => unreachable!(),
         };
     let mut status = Vec::with_capacity(lhs.len());
     for each in lhs {
@@ -351,6 +400,61 @@ fn unary_operation<'r, 'l: 'r, 'loc: 'l>(lhs_query: &'l [QueryPart<'loc>],
     Ok(EvaluationResult::QueryValueResult(status))
 }
 
+//
+// UNIQUE compares the values against each other rather than checking each one, a query that
+// resolves to a single list checks its elements. A value FAILs when it repeats an earlier one,
+// with !UNIQUE every value PASSes once any value repeats. Unresolved values never repeat
+//
+fn unique_operation<'r, 'l: 'r, 'loc: 'l>(lhs: Vec<QueryResult<'l>>,
+                    cmp: (CmpOperator, bool),
+                    inverse: bool,
+                    context: String,
+                    custom_message: Option<String>,
+                    eval_context: &'r mut dyn EvalContext<'l, 'loc>) -> Result<EvaluationResult<'l>> {
+    let values = match lhs.as_slice() {
+        [QueryResult::Resolved(PathAwareValue::List((_, list)))] =>
+            list.iter().map(QueryResult::Resolved).collect::<Vec<QueryResult<'l>>>(),
+        _ => lhs,
+    };
+    let resolved = values.iter().filter_map(|each| match each {
+        QueryResult::Literal(value) | QueryResult::Resolved(value) => Some(*value),
+        QueryResult::UnResolved(_) => None,
+    }).collect::<Vec<&PathAwareValue>>();
+    let duplicates = earlier_duplicates(&resolved, eval_context.options());
+    let repeated = duplicates.iter().any(Option::is_some);
+    let mut duplicates = duplicates.into_iter();
+
+    let not_unique = cmp.1 != inverse;
+    let mut results = Vec::with_capacity(values.len());
+    for each in values {
+        let duplicate_of = match each {
+            QueryResult::UnResolved(_) => None,
+            _ => duplicates.next().flatten(),
+        };
+        let pass = if not_unique { repeated } else { duplicate_of.is_none() };
+        eval_context.start_record(&context)?;
+        if pass {
+            eval_context.end_record(&context, RecordType::ClauseValueCheck(ClauseCheck::Success))?;
+        } else {
+            eval_context.end_record(&context, RecordType::ClauseValueCheck(ClauseCheck::Unary(
+                UnaryValueCheck {
+                    comparison: cmp,
+                    value: ValueCheck {
+                        status: Status::FAIL,
+                        message: duplicate_of.map(|first| format!(
+                            "value {} repeats the value at {}", ValueOnlyDisplay(first), first.self_path()
+                        )),
+                        custom_message: custom_message.clone(),
+                        from: each.clone()
+                    }
+                }
+            )))?;
+        }
+        results.push((each, if pass { Status::PASS } else { Status::FAIL }));
+    }
+    Ok(EvaluationResult::QueryValueResult(results))
+}
+
 enum ComparisonResult<'r> {
     Comparable(ComparisonWithRhs<'r>),
     NotComparable(NotComparableWithRhs<'r>),
@@ -510,7 +614,7 @@ fn not_cmp<F>(cmp: F) -> impl Fn(&PathAwareValue, &PathAwareValue) -> Result<boo
     }
 }
 
-fn in_cmp(not_in: bool) -> impl Fn(&PathAwareValue, &PathAwareValue) -> Result<bool>
+fn in_cmp(not_in: bool, options: &EvaluationOptions) -> impl Fn(&PathAwareValue, &PathAwareValue) -> Result<bool> + '_
 {
     move |lhs, rhs| {
         match (lhs, rhs) {
@@ -521,21 +625,30 @@ fn in_cmp(not_in: bool) -> impl Fn(&PathAwareValue, &PathAwareValue) -> Result<b
             },
 
             (_,
-             PathAwareValue::List((_, rhs_list))) => {
-                Ok({
-                    let mut tracking = Vec::with_capacity(rhs_list.len());
-                    for each_rhs in rhs_list {
-                        tracking.push(compare_eq(lhs, each_rhs)?);
-                    }
-                    match  tracking.iter().find(|s| **s) {
-                        Some(_) => if not_in { false } else { true },
-                        None => if not_in { true } else { false }
-                    }
-                })
+             PathAwareValue::List(_)) => {
+                let result = compare_in(lhs, rhs, options)?;
+                Ok(if not_in { !result } else { result })
+            },
+
+            //
+            // Used by KEYS filters, where the LHS are map keys, so membership is against the keys
+            //
+            (PathAwareValue::String(_),
+             PathAwareValue::Map((_, rhs_map))) => {
+                let result = rhs_map.keys.contains(lhs);
+                Ok(if not_in { !result } else { result })
+            },
+
+            (_,
+             PathAwareValue::Map(_)) => {
+                Err(Error::new(ErrorKind::NotComparable(
+                    format!("Map keys can only be compared with strings, found {}, {}",
+                            lhs.type_info(), rhs.self_path())
+                )))
             },
 
             (_, _) => {
-                let result = compare_eq(lhs, rhs)?;
+                let result = compare_eq(lhs, rhs, options)?;
                 Ok(if not_in { !result } else { result })
             }
         }
@@ -718,11 +831,11 @@ fn report_at_least_one<'r, 'value: 'r, 'loc: 'value>(
     Ok(statues)
 }
 
-fn not_compare<O>(cmp: O, invert: bool) -> impl Fn(&PathAwareValue, &PathAwareValue) -> Result<bool>
-    where O: Fn(&PathAwareValue, &PathAwareValue) -> Result<bool>
+fn not_compare<'o, O>(cmp: O, invert: bool, options: &'o EvaluationOptions) -> impl Fn(&PathAwareValue, &PathAwareValue) -> Result<bool> + 'o
+    where O: 'o + Fn(&PathAwareValue, &PathAwareValue, &EvaluationOptions) -> Result<bool>
 {
     move |lhs, rhs| {
-        let r = cmp(lhs, rhs)?;
+        let r = cmp(lhs, rhs, options)?;
         Ok(if invert { !r } else { r })
     }
 }
@@ -736,7 +849,7 @@ fn binary_operation<'value, 'loc: 'value>(
     eval_context: &mut dyn EvalContext<'value, 'loc>) -> Result<EvaluationResult<'value>> {
 
     let lhs = eval_context.query(lhs_query)?;
-    let results = cmp.compare(&lhs, rhs)?;
+    let results = cmp.compare(&lhs, rhs, eval_context.options())?;
     match results {
         operators::EvalResult::Skip => return Ok(EvaluationResult::EmptyQueryResult(Status::SKIP)),
         operators::EvalResult::Result(results) => {
@@ -814,6 +927,12 @@ fn binary_operation<'value, 'loc: 'value>(
                                 eval_context.start_record(&context)?;
                                 eval_context.end_record(&context, RecordType::ClauseValueCheck(ClauseCheck::Success))?;
                                 statues.push((QueryResult::Resolved(val.lhs), Status::PASS));
+                            },
+
+                            operators::Compare::SetIn(set_in) => {
+                                eval_context.start_record(&context)?;
+                                eval_context.end_record(&context, RecordType::ClauseValueCheck(ClauseCheck::Success))?;
+                                statues.push((QueryResult::Resolved(set_in.collection), Status::PASS));
                             }
                         }
                     },
@@ -882,6 +1001,45 @@ fn binary_operation<'value, 'loc: 'value>(
                                     ))?;
                                     statues.push((QueryResult::Resolved(lhs), Status::FAIL));
                                 }
+                            },
+
+                            //
+                            // SUBSET reports each value of the collection that is not in the RHS, SUPERSET
+                            // reports the collection with the RHS values missing from it. Negated, the values
+                            // that the relation held for are reported the same way
+                            //
+                            operators::Compare::SetIn(set_in) => {
+                                if cmp.0 == CmpOperator::Superset {
+                                    eval_context.start_record(&context)?;
+                                    eval_context.end_record(&context, RecordType::ClauseValueCheck(
+                                        ClauseCheck::InComparison(InComparisonCheck {
+                                            status: Status::FAIL,
+                                            message: None,
+                                            custom_message: custom_message.clone(),
+                                            comparison: cmp,
+                                            from: QueryResult::Resolved(set_in.collection),
+                                            to: set_in.diff.iter().map(|e| QueryResult::Resolved(e)).collect()
+                                        })
+                                    ))?;
+                                    statues.push((QueryResult::Resolved(set_in.collection), Status::FAIL));
+                                }
+                                else {
+                                    let rhs = set_in.rhs.iter().map(|e| QueryResult::Resolved(e)).collect::<Vec<_>>();
+                                    for lhs in set_in.diff {
+                                        eval_context.start_record(&context)?;
+                                        eval_context.end_record(&context, RecordType::ClauseValueCheck(
+                                            ClauseCheck::InComparison(InComparisonCheck {
+                                                status: Status::FAIL,
+                                                message: None,
+                                                custom_message: custom_message.clone(),
+                                                comparison: cmp,
+                                                from: QueryResult::Resolved(lhs),
+                                                to: rhs.clone()
+                                            })
+                                        ))?;
+                                        statues.push((QueryResult::Resolved(lhs), Status::FAIL));
+                                    }
+                                }
                             }
                         }
                     }
@@ -943,7 +1101,7 @@ pub(super) fn real_binary_operation<'value, 'loc: 'value>(
                 let r = match cmp {
                     (CmpOperator::Eq, is_not) => {
                         each_lhs_compare(
-                            not_compare(crate::rules::path_value::compare_eq, is_not),
+                            not_compare(crate::rules::path_value::compare_eq, is_not, eval_context.options()),
                             *l,
                             rhs
                         )?
@@ -951,7 +1109,7 @@ pub(super) fn real_binary_operation<'value, 'loc: 'value>(
 
                     (CmpOperator::Ge, is_not) => {
                         each_lhs_compare(
-                            not_compare(crate::rules::path_value::compare_ge, is_not),
+                            not_compare(crate::rules::path_value::compare_ge, is_not, eval_context.options()),
                             *l,
                             rhs
                         )?
@@ -959,7 +1117,7 @@ pub(super) fn real_binary_operation<'value, 'loc: 'value>(
 
                     (CmpOperator::Gt, is_not) => {
                         each_lhs_compare(
-                            not_compare(crate::rules::path_value::compare_gt, is_not),
+                            not_compare(crate::rules::path_value::compare_gt, is_not, eval_context.options()),
                             *l,
                             rhs
                         )?
@@ -967,7 +1125,7 @@ pub(super) fn real_binary_operation<'value, 'loc: 'value>(
 
                     (CmpOperator::Lt, is_not) => {
                         each_lhs_compare(
-                            not_compare(crate::rules::path_value::compare_lt, is_not),
+                            not_compare(crate::rules::path_value::compare_lt, is_not, eval_context.options()),
                             *l,
                             rhs
                         )?
@@ -975,15 +1133,19 @@ pub(super) fn real_binary_operation<'value, 'loc: 'value>(
 
                     (CmpOperator::Le, is_not) => {
                         each_lhs_compare(
-                            not_compare(crate::rules::path_value::compare_le, is_not),
+                            not_compare(crate::rules::path_value::compare_le, is_not, eval_context.options()),
                             *l,
                             rhs
                         )?
                     },
 
-                    (CmpOperator::In, is_not) => {
+                    //
+                    // map keys are scalars, ALL IN is the same as IN for them
+                    //
+                    (CmpOperator::In, is_not) |
+                    (CmpOperator::AllIn, is_not) => {
                         each_lhs_compare(
-                            in_cmp(is_not),
+                            in_cmp(is_not, eval_context.options()),
                             *l,
                             rhs
                         )?
@@ -993,7 +1155,7 @@ pub(super) fn real_binary_operation<'value, 'loc: 'value>(
                 };
 
                 match cmp.0 {
-                    CmpOperator::In => {
+                    CmpOperator::In | CmpOperator::AllIn => {
                         statues.extend(report_at_least_one(
                             r,
                             cmp,
@@ -1028,13 +1190,15 @@ pub(in crate::rules) fn eval_guard_access_clause<'value, 'loc: 'value>(
     let all = gac.access_clause.query.match_all;
     let blk_context = format!("GuardAccessClause#block{}", gac);
     resolver.start_record(&blk_context)?;
+    let custom_message = gac.access_clause.custom_message.as_ref()
+        .map(|msg| interpolate_message(msg, resolver.root(), resolver.options()));
 
     let statues = if gac.access_clause.comparator.0.is_unary() {
         unary_operation(&gac.access_clause.query.query,
                         gac.access_clause.comparator,
                         gac.negation,
                         format!("{}", gac),
-                        gac.access_clause.custom_message.clone(),
+                        custom_message,
                         resolver)
     }
     else {
@@ -1075,7 +1239,7 @@ pub(in crate::rules) fn eval_guard_access_clause<'value, 'loc: 'value>(
             &rhs,
             gac.access_clause.comparator,
             format!("{}", gac),
-            gac.access_clause.custom_message.clone(),
+            custom_message,
             resolver
         )
     };
@@ -1138,6 +1302,8 @@ pub(in crate::rules) fn eval_guard_named_clause<'value, 'loc: 'value>(
 {
     let context = format!("{}", gnc);
     resolver.start_record(&context)?;
+    let custom_message = gnc.custom_message.as_ref()
+        .map(|msg| interpolate_message(msg, resolver.root(), resolver.options()));
 
     match resolver.rule_status(&gnc.dependent_rule) {
         Ok(status) => {
@@ -1159,7 +1325,7 @@ pub(in crate::rules) fn eval_guard_named_clause<'value, 'loc: 'value>(
                                     rule: &gnc.dependent_rule,
                                     status: Status::FAIL,
                                     message: None,
-                                    custom_message: gnc.custom_message.clone()
+                                    custom_message: custom_message.clone()
                                 }
                             )
                         )
@@ -1180,7 +1346,7 @@ pub(in crate::rules) fn eval_guard_named_clause<'value, 'loc: 'value>(
                             rule: &gnc.dependent_rule,
                             status: Status::FAIL,
                             message: Some(format!("{} failed due to error {}", context, e)),
-                            custom_message: gnc.custom_message.clone(),
+                            custom_message: custom_message.clone(),
                         }
                     )
                 )
@@ -1356,6 +1522,10 @@ impl<'eval, 'value, 'loc: 'value> EvalContext<'value, 'loc> for ResolvedParamete
         self.parent.root()
     }
 
+    fn options(&self) -> &EvaluationOptions {
+        self.parent.options()
+    }
+
     fn rule_status(&mut self, rule_name: &'value str) -> Result<Status> {
         self.parent.rule_status(rule_name)
     }
@@ -1469,10 +1639,11 @@ pub (in crate::rules) fn eval_type_block_clause<'value, 'loc: 'value>(
     type_block: &'value TypeBlock<'loc>,
     resolver: &mut dyn EvalContext<'value, 'loc>) -> Result<Status>
 {
-    let context = format!("TypeBlock#{}", type_block.type_name);
+    let context = format!("TypeBlock#{}{}",
+                          if type_block.negation { "not " } else { "" }, type_block.type_name);
     resolver.start_record(&context)?;
     let block = if let Some(conditions) = &type_block.conditions {
-        let when_context = format!("TypeBlock#{}/When", type_block.type_name);
+        let when_context = format!("{}/When", context);
         resolver.start_record(&when_context)?;
         match eval_conjunction_clauses(conditions, resolver, eval_when_clause) {
             Ok(status) => {
@@ -1525,6 +1696,10 @@ pub (in crate::rules) fn eval_type_block_clause<'value, 'loc: 'value>(
             return Err(e)
         }
     };
+    if let Some(exists) = type_block.exists {
+        return eval_type_exists(type_block, exists != type_block.negation, &context, values, resolver)
+    }
+
     if values.is_empty() {
         resolver.end_record(&context, RecordType::TypeCheck(
             TypeBlockCheck {
@@ -1581,6 +1756,11 @@ pub (in crate::rules) fn eval_type_block_clause<'value, 'loc: 'value>(
         if fails > 0 { Status::FAIL }
         else if passes > 0 { Status::PASS }
         else { Status::SKIP };
+    let status = match status {
+        Status::PASS if type_block.negation => Status::FAIL,
+        Status::FAIL if type_block.negation => Status::PASS,
+        rest => rest
+    };
 
     resolver.end_record(&context, RecordType::TypeCheck(
         TypeBlockCheck {
@@ -1594,6 +1774,63 @@ pub (in crate::rules) fn eval_type_block_clause<'value, 'loc: 'value>(
     Ok(status)
 }
 
+//
+// Type EXISTS and Type !EXISTS pass or fail based on the number of resources of the type that
+// are present. Failures are recorded as unary checks so that reporters show how many matched
+//
+fn eval_type_exists<'value, 'loc: 'value>(
+    type_block: &'value TypeBlock<'loc>,
+    expect_exists: bool,
+    context: &str,
+    values: Vec<QueryResult<'value>>,
+    resolver: &mut dyn EvalContext<'value, 'loc>) -> Result<Status>
+{
+    let matched = values.into_iter().filter_map(|each| match each {
+        QueryResult::Literal(rv) | QueryResult::Resolved(rv) => Some(rv),
+        QueryResult::UnResolved(_) => None,
+    }).collect::<Vec<_>>();
+    let message = format!("{} resource(s) of type {} matched", matched.len(), type_block.type_name);
+    let status = if matched.is_empty() != expect_exists { Status::PASS } else { Status::FAIL };
+
+    if status == Status::FAIL {
+        let failures = if expect_exists {
+            vec![QueryResult::UnResolved(UnResolved {
+                traversed_to: resolver.root(),
+                remaining_query: format!("Resources.*[ Type == \"{}\" ]", type_block.type_name),
+                reason: Some(message.clone()),
+            })]
+        } else {
+            matched.iter().map(|each| QueryResult::Resolved(each)).collect()
+        };
+        for (idx, each) in failures.into_iter().enumerate() {
+            let check_context = format!("{}/{}", context, idx);
+            resolver.start_record(&check_context)?;
+            resolver.end_record(&check_context, RecordType::ClauseValueCheck(ClauseCheck::Unary(
+                UnaryValueCheck {
+                    comparison: (CmpOperator::Exists, !expect_exists),
+                    value: ValueCheck {
+                        from: each,
+                        message: Some(message.clone()),
+                        custom_message: None,
+                        status: Status::FAIL,
+                    }
+                }
+            )))?;
+        }
+    }
+
+    resolver.end_record(context, RecordType::TypeCheck(
+        TypeBlockCheck {
+            type_name: &type_block.type_name,
+            block: BlockCheck {
+                status,
+                message: Some(message),
+                at_least_one_matches: !matched.is_empty()
+            }
+        }))?;
+    Ok(status)
+}
+
 pub(in crate::rules) fn eval_rule_clause<'value, 'loc: 'value>(
     rule_clause: &'value RuleClause<'loc>,
     resolver: &mut dyn EvalContext<'value, 'loc>) -> Result<Status>
@@ -1609,6 +1846,14 @@ pub(in crate::rules) fn eval_rule_clause<'value, 'loc: 'value>(
 pub(in crate::rules) fn eval_rule<'value, 'loc: 'value>(
     rule: &'value Rule<'loc>,
     resolver: &mut dyn EvalContext<'value, 'loc>) -> Result<Status>
+{
+    let mut scope = rule_scope(resolver);
+    eval_rule_in_scope(rule, &mut scope)
+}
+
+fn eval_rule_in_scope<'value, 'loc: 'value>(
+    rule: &'value Rule<'loc>,
+    resolver: &mut dyn EvalContext<'value, 'loc>) -> Result<Status>
 {
     let context = format!("{}", rule.rule_name);
     resolver.start_record(&context)?;
@@ -1681,7 +1926,12 @@ pub(in crate) fn eval_rules_file<'value, 'loc: 'value>(
             Ok(status) => {
                 match status {
                     Status::PASS => { passes += 1; },
-                    Status::FAIL => { fails += 1; },
+                    Status::FAIL => {
+                        fails += 1;
+                        if resolver.options().fail_fast {
+                            break
+                        }
+                    },
                     Status::SKIP => {}
                 }
             },
@@ -52,6 +52,21 @@ impl<'value> ListIn<'value> {
     }
 }
 
+#[derive(Clone, Debug)]
+pub(crate) struct SetIn<'value> {
+    pub(crate) diff: Vec<&'value PathAwareValue>,
+    pub(crate) collection: &'value PathAwareValue,
+    pub(crate) lhs: Vec<&'value PathAwareValue>,
+    pub(crate) rhs: Vec<&'value PathAwareValue>,
+}
+
+impl<'value> SetIn<'value> {
+    fn new<'r>(diff: Vec<&'r PathAwareValue>, collection: &'r PathAwareValue, lhs: Vec<&'r PathAwareValue>, rhs: Vec<&'r PathAwareValue>) -> SetIn<'r> {
+        SetIn {
+            diff, collection, lhs, rhs
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub(crate) enum Compare<'r> {
@@ -59,6 +74,7 @@ pub(crate) enum Compare<'r> {
     QueryIn(QueryIn<'r>),
     ListIn(ListIn<'r>),
     ValueIn(LhsRhsPair<'r>),
+    SetIn(SetIn<'r>),
 }
 
 #[derive(Clone, Debug)]
@@ -128,7 +144,8 @@ pub(crate) trait Comparator {
     fn compare<'value>(
         &self,
         lhs: &[QueryResult<'value>],
-        rhs: &[QueryResult<'value>]) -> crate::rules::Result<EvalResult<'value>>;
+        rhs: &[QueryResult<'value>],
+        options: &EvaluationOptions) -> crate::rules::Result<EvalResult<'value>>;
 }
 
 pub(crate) trait UnaryComparator {
@@ -138,11 +155,14 @@ pub(crate) trait UnaryComparator {
 }
 
 struct CommonOperator {
-    comparator: fn (&PathAwareValue, &PathAwareValue) -> crate::rules::Result<bool>
+    comparator: fn (&PathAwareValue, &PathAwareValue, &EvaluationOptions) -> crate::rules::Result<bool>
 }
 
-struct EqOperation{}
+struct EqOperation{ unordered: bool }
 struct InOperation{}
+struct SetOperation{ superset: bool }
+struct AllInOperation{}
+struct CidrOperation{}
 
 fn selected<'value, U, R>(
     query_results: &[QueryResult<'value>],
@@ -189,7 +209,8 @@ impl Comparator for CommonOperator {
     fn compare<'value>(
         &self,
         lhs: &[QueryResult<'value>],
-        rhs: &[QueryResult<'value>]) -> crate::rules::Result<EvalResult<'value>> {
+        rhs: &[QueryResult<'value>],
+        options: &EvaluationOptions) -> crate::rules::Result<EvalResult<'value>> {
         let mut results = Vec::with_capacity(lhs.len());
         let lhs_flattened = flattened(
             lhs, |ur| results.push(ValueEvalResult::LhsUnresolved(ur.clone())));
@@ -204,7 +225,7 @@ impl Comparator for CommonOperator {
         for each_lhs in lhs_flattened {
             for each_rhs in rhs {
                 results.push(
-                    match_value(each_lhs, each_rhs, self.comparator)
+                    match_value(each_lhs, each_rhs, |first, second| (self.comparator)(first, second, options))
                 );
             }
         }
@@ -314,7 +335,8 @@ fn fail<'value>(
 
 fn contained_in<'value>(
     lhs_value: &'value PathAwareValue,
-    rhs_value: &'value PathAwareValue) -> ValueEvalResult<'value>
+    rhs_value: &'value PathAwareValue,
+    options: &EvaluationOptions) -> ValueEvalResult<'value>
 {
     match lhs_value {
         PathAwareValue::List((_, lhsl)) =>
@@ -357,6 +379,24 @@ fn contained_in<'value>(
                     }
                 },
 
+                PathAwareValue::Map((_, rhsm)) => {
+                    let diff = lhsl.iter().filter(|each| !rhsm.values.values().any(|v| v == *each))
+                        .collect::<Vec<_>>();
+                    if diff.is_empty() {
+                        ValueEvalResult::ComparisonResult(
+                            ComparisonResult::Success(
+                                Compare::ListIn(ListIn::new(diff, lhs_value, rhs_value))
+                            )
+                        )
+                    } else {
+                        ValueEvalResult::ComparisonResult(
+                            ComparisonResult::Fail(
+                                Compare::ListIn(ListIn::new(diff, lhs_value, rhs_value))
+                            )
+                        )
+                    }
+                },
+
                 _ =>
                     ValueEvalResult::ComparisonResult(
                         ComparisonResult::NotComparable(
@@ -388,7 +428,28 @@ fn contained_in<'value>(
                     )
                 },
 
-                rhs_rest=> match_value(rest, rhs_rest, compare_eq)
+                //
+                // Membership against a map is checked over its values
+                //
+                PathAwareValue::Map((_, rhsm)) => if rhsm.values.values().any(|v| v == rest) {
+                    ValueEvalResult::ComparisonResult(
+                        ComparisonResult::Success(
+                            Compare::ValueIn(
+                                LhsRhsPair::new(rest, rhs_value)
+                            )
+                        )
+                    )
+                } else {
+                    ValueEvalResult::ComparisonResult(
+                        ComparisonResult::Fail(
+                            Compare::ValueIn(
+                                LhsRhsPair::new(rest, rhs_value)
+                            )
+                        )
+                    )
+                },
+
+                rhs_rest=> match_value(rest, rhs_rest, |first, second| compare_eq(first, second, options))
             }
         }
     }
@@ -398,12 +459,13 @@ impl Comparator for InOperation {
     fn compare<'value>(
         &self,
         lhs: &[QueryResult<'value>],
-        rhs: &[QueryResult<'value>]) -> crate::rules::Result<EvalResult<'value>> {
+        rhs: &[QueryResult<'value>],
+        options: &EvaluationOptions) -> crate::rules::Result<EvalResult<'value>> {
         let mut results = Vec::with_capacity(lhs.len());
         match (is_literal(lhs), is_literal(rhs)) {
             (Some(l), Some(r)) => {
                 results.push( string_in(l, r)
-                    .fail(|_| contained_in(l, r)));
+                    .fail(|_| contained_in(l, r, options)));
             },
 
             (Some(l), None) => {
@@ -416,7 +478,7 @@ impl Comparator for InOperation {
 
                 if rhs.iter().any(|elem| elem.is_list()) {
                     rhs.into_iter().for_each(|r|
-                        results.push(contained_in(l, r))
+                        results.push(contained_in(l, r, options))
                     );
                 }
                 else {
@@ -442,7 +504,7 @@ impl Comparator for InOperation {
                     }
                     else {
                         rhs.iter().for_each(|rhs_elem|
-                            results.push(contained_in(l, rhs_elem)));
+                            results.push(contained_in(l, rhs_elem, options)));
                     }
                 }
             },
@@ -468,7 +530,7 @@ impl Comparator for InOperation {
                         },
 
                         rest => results.push(
-                            contained_in(l, rest)
+                            contained_in(l, rest, options)
                         ),
                     }
                 );
@@ -491,11 +553,9 @@ impl Comparator for InOperation {
                 let mut diff = Vec::with_capacity(lhs_selected.len());
                 'each_lhs: for eachl in &lhs_selected {
                     for eachr in &rhs_selected {
-                        match contained_in(*eachl, *eachr) {
-                            ValueEvalResult::ComparisonResult(ComparisonResult::Success(_)) => {
-                                continue 'each_lhs
-                            },
-                            _ => {}
+                        if let ValueEvalResult::ComparisonResult(ComparisonResult::Success(_)) =
+                            contained_in(eachl, eachr, options) {
+                            continue 'each_lhs
                         }
                     }
                     diff.push(*eachl);
@@ -526,12 +586,18 @@ impl Comparator for EqOperation {
     fn compare<'value>(
         &self,
         lhs: &[QueryResult<'value>],
-        rhs: &[QueryResult<'value>]) -> crate::rules::Result<EvalResult<'value>> {
+        rhs: &[QueryResult<'value>],
+        options: &EvaluationOptions) -> crate::rules::Result<EvalResult<'value>> {
         let mut results = Vec::with_capacity(lhs.len());
+        let equals = |first: &PathAwareValue, second: &PathAwareValue| if self.unordered {
+            compare_list_set_eq(first, second, options)
+        } else {
+            compare_eq(first, second, options)
+        };
         match (is_literal(lhs), is_literal(rhs)) {
             (Some(l), Some(r)) => {
                 results.push(
-                    match_value(l, r, compare_eq)
+                    match_value(l, r, equals)
                 );
             },
 
@@ -549,7 +615,7 @@ impl Comparator for EqOperation {
                     PathAwareValue::List(_) => {
                         for each in rhs {
                             results.push(
-                                match_value(l, each, compare_eq)
+                                match_value(l, each, equals)
                             );
                         }
                     },
@@ -560,14 +626,14 @@ impl Comparator for EqOperation {
                                 PathAwareValue::List((_, rhsl)) => {
                                     for each_rhs in rhsl {
                                         results.push(
-                                            match_value(single_value, each_rhs, compare_eq)
+                                            match_value(single_value, each_rhs, equals)
                                         );
                                     }
                                 },
 
                                 rest_rhs => {
                                     results.push(
-                                        match_value(single_value, rest_rhs, compare_eq)
+                                        match_value(single_value, rest_rhs, equals)
                                     );
                                 }
                             }
@@ -584,12 +650,12 @@ impl Comparator for EqOperation {
                         for each in lhs_flattened {
                             if each.is_scalar() && rhsl.len() == 1 {
                                 results.push(
-                                    match_value(each, &rhsl[0], compare_eq)
+                                    match_value(each, &rhsl[0], equals)
                                 )
                             }
                             else {
                                 results.push(
-                                    match_value(each, r, compare_eq)
+                                    match_value(each, r, equals)
                                 );
                             }
                         }
@@ -599,12 +665,12 @@ impl Comparator for EqOperation {
                         for each in lhs_flattened {
                             if let PathAwareValue::List((_, lhs_list)) = each {
                                 for each_lhs in lhs_list {
-                                    results.push(match_value(each_lhs, single_value, compare_eq));
+                                    results.push(match_value(each_lhs, single_value, equals));
                                 }
                             }
                             else {
                                 results.push(
-                                    match_value(each, r, compare_eq)
+                                    match_value(each, r, equals)
                                 );
                             }
                         }
@@ -626,13 +692,18 @@ impl Comparator for EqOperation {
                     Vec::push
                 );
 
+                let contains = |values: &Vec<&PathAwareValue>, each: &PathAwareValue| if self.unordered {
+                    values.iter().any(|value| compare_list_set_eq(value, each, options).unwrap_or(false))
+                } else {
+                    values.contains(&each)
+                };
                 let diff = if lhs_selected.len() > rhs_selected.len() {
-                    lhs_selected.iter().filter(|e| !rhs_selected.contains(*e))
-                        .map(|e| *e)
+                    lhs_selected.iter().filter(|e| !contains(&rhs_selected, e))
+                        .copied()
                         .collect::<Vec<_>>()
                 } else {
-                    rhs_selected.iter().filter(|e| !lhs_selected.contains(*e))
-                        .map(|e| *e)
+                    rhs_selected.iter().filter(|e| !contains(&lhs_selected, e))
+                        .copied()
                         .collect::<Vec<_>>()
                 };
 
@@ -661,22 +732,136 @@ impl Comparator for EqOperation {
     }
 }
 
+//
+// Each element of every LHS list, and each scalar, is checked on its own against all the RHS
+// values, lists on the RHS are flattened. Unlike IN, a list is never checked as a whole
+//
+impl Comparator for AllInOperation {
+    fn compare<'value>(
+        &self,
+        lhs: &[QueryResult<'value>],
+        rhs: &[QueryResult<'value>],
+        options: &EvaluationOptions) -> crate::rules::Result<EvalResult<'value>> {
+        let mut results = Vec::with_capacity(lhs.len());
+        let lhs_flattened = flattened(
+            lhs, |ur| results.push(ValueEvalResult::LhsUnresolved(ur.clone())));
+        let rhs_flattened = flattened(
+            rhs, |ur| results.extend(
+                lhs_flattened.iter().map(|lhs|
+                    ValueEvalResult::ComparisonResult(
+                        ComparisonResult::RhsUnresolved(ur.clone(), lhs))))
+        );
+        for each in lhs_flattened {
+            let diff = set_difference(&[each], &rhs_flattened, false, options);
+            let holds = diff.is_empty();
+            let query_in = Compare::QueryIn(QueryIn::new(diff, vec![each], rhs_flattened.clone()));
+            results.push(ValueEvalResult::ComparisonResult(
+                if holds { ComparisonResult::Success(query_in) } else { ComparisonResult::Fail(query_in) }
+            ));
+        }
+        Ok(EvalResult::Result(results))
+    }
+}
+
+//
+// Each address or block on the LHS is checked on its own against all the RHS blocks, it holds
+// when any of them contains it. Values that are not valid addresses or blocks are not comparable,
+// which reports them as failures whether or not the operator is negated
+//
+impl Comparator for CidrOperation {
+    fn compare<'value>(
+        &self,
+        lhs: &[QueryResult<'value>],
+        rhs: &[QueryResult<'value>],
+        _options: &EvaluationOptions) -> crate::rules::Result<EvalResult<'value>> {
+        let mut results = Vec::with_capacity(lhs.len());
+        let lhs_flattened = flattened(
+            lhs, |ur| results.push(ValueEvalResult::LhsUnresolved(ur.clone())));
+        let rhs_flattened = flattened(
+            rhs, |ur| results.extend(
+                lhs_flattened.iter().map(|lhs|
+                    ValueEvalResult::ComparisonResult(
+                        ComparisonResult::RhsUnresolved(ur.clone(), lhs))))
+        );
+        'lhs: for each in lhs_flattened {
+            for each_rhs in &rhs_flattened {
+                match compare_in_cidr(each, each_rhs) {
+                    Ok(true) => {
+                        results.push(ValueEvalResult::ComparisonResult(ComparisonResult::Success(
+                            Compare::QueryIn(QueryIn::new(vec![], vec![each], rhs_flattened.clone()))
+                        )));
+                        continue 'lhs
+                    },
+                    Ok(false) => {},
+                    Err(Error(ErrorKind::NotComparable(reason))) => {
+                        results.push(ValueEvalResult::ComparisonResult(ComparisonResult::NotComparable(
+                            NotComparable { reason, pair: LhsRhsPair::new(each, each_rhs) }
+                        )));
+                        continue 'lhs
+                    },
+                    Err(e) => return Err(e),
+                }
+            }
+            results.push(ValueEvalResult::ComparisonResult(ComparisonResult::Fail(
+                Compare::QueryIn(QueryIn::new(vec![each], vec![each], rhs_flattened.clone()))
+            )));
+        }
+        Ok(EvalResult::Result(results))
+    }
+}
+
+impl Comparator for SetOperation {
+    fn compare<'value>(
+        &self,
+        lhs: &[QueryResult<'value>],
+        rhs: &[QueryResult<'value>],
+        options: &EvaluationOptions) -> crate::rules::Result<EvalResult<'value>> {
+        let mut results = Vec::with_capacity(lhs.len());
+        let lhs_selected = selected(
+            lhs,
+            |ur| results.push(ValueEvalResult::LhsUnresolved(ur.clone())),
+            Vec::push
+        );
+        let rhs_flattened = flattened(
+            rhs, |ur| results.extend(
+                lhs_selected.iter().map(|lhs|
+                    ValueEvalResult::ComparisonResult(
+                        ComparisonResult::RhsUnresolved(ur.clone(), lhs))))
+        );
+        for (collection, elements) in set_collections(&lhs_selected) {
+            let diff = set_difference(&elements, &rhs_flattened, self.superset, options);
+            let holds = diff.is_empty();
+            let set_in = Compare::SetIn(SetIn::new(diff, collection, elements, rhs_flattened.clone()));
+            results.push(ValueEvalResult::ComparisonResult(
+                if holds { ComparisonResult::Success(set_in) } else { ComparisonResult::Fail(set_in) }
+            ));
+        }
+        Ok(EvalResult::Result(results))
+    }
+}
+
 impl Comparator for crate::rules::CmpOperator {
     fn compare<'value>(
         &self,
         lhs: &[QueryResult<'value>],
-        rhs: &[QueryResult<'value>]) -> crate::rules::Result<EvalResult<'value>> {
+        rhs: &[QueryResult<'value>],
+        options: &EvaluationOptions) -> crate::rules::Result<EvalResult<'value>> {
         if lhs.is_empty() || rhs.is_empty() {
             return Ok(EvalResult::Skip)
         }
 
         match self {
-            CmpOperator::Eq => EqOperation{}.compare(lhs, rhs),
-            CmpOperator::In => InOperation{}.compare(lhs, rhs),
-            CmpOperator::Lt => CommonOperator{ comparator: compare_lt }.compare(lhs, rhs),
-            CmpOperator::Gt => CommonOperator{ comparator: compare_gt }.compare(lhs, rhs),
-            CmpOperator::Le => CommonOperator{ comparator: compare_le }.compare(lhs, rhs),
-            CmpOperator::Ge => CommonOperator{ comparator: compare_ge }.compare(lhs, rhs),
+            CmpOperator::Eq => EqOperation{ unordered: false }.compare(lhs, rhs, options),
+            CmpOperator::EqUnordered => EqOperation{ unordered: true }.compare(lhs, rhs, options),
+            CmpOperator::In => InOperation{}.compare(lhs, rhs, options),
+            CmpOperator::AllIn => AllInOperation{}.compare(lhs, rhs, options),
+            CmpOperator::InCidr => CidrOperation{}.compare(lhs, rhs, options),
+            CmpOperator::Subset => SetOperation{ superset: false }.compare(lhs, rhs, options),
+            CmpOperator::Superset => SetOperation{ superset: true }.compare(lhs, rhs, options),
+            CmpOperator::Lt => CommonOperator{ comparator: compare_lt }.compare(lhs, rhs, options),
+            CmpOperator::Gt => CommonOperator{ comparator: compare_gt }.compare(lhs, rhs, options),
+            CmpOperator::Le => CommonOperator{ comparator: compare_le }.compare(lhs, rhs, options),
+            CmpOperator::Ge => CommonOperator{ comparator: compare_ge }.compare(lhs, rhs, options),
             _ => return Err(crate::rules::Error::new(ErrorKind::IncompatibleError(
                 format!("Operation {} NOT PERMITTED", self)
             ))),
@@ -688,10 +873,32 @@ impl Comparator for (crate::rules::CmpOperator, bool) {
     fn compare<'value>(
         &self,
         lhs: &[QueryResult<'value>],
-        rhs: &[QueryResult<'value>]) -> crate::rules::Result<EvalResult<'value>> {
-        let results = self.0.compare(lhs, rhs)?;
+        rhs: &[QueryResult<'value>],
+        options: &EvaluationOptions) -> crate::rules::Result<EvalResult<'value>> {
+        let results = self.0.compare(lhs, rhs, options)?;
         Ok(match results {
             EvalResult::Skip => EvalResult::Skip,
+            //
+            // NOT SUBSET and NOT SUPERSET negate the relation for the collection as a whole, the
+            // elements reported when it fails are all of those that the relation held for
+            //
+            EvalResult::Result(r) if self.1 && matches!(self.0, CmpOperator::Subset | CmpOperator::Superset) => {
+                EvalResult::Result(r.into_iter().map(|e| match e {
+                    ValueEvalResult::ComparisonResult(ComparisonResult::Fail(Compare::SetIn(set_in))) =>
+                        ValueEvalResult::ComparisonResult(ComparisonResult::Success(
+                            Compare::SetIn(SetIn::new(vec![], set_in.collection, set_in.lhs, set_in.rhs))
+                        )),
+
+                    ValueEvalResult::ComparisonResult(ComparisonResult::Success(Compare::SetIn(set_in))) => {
+                        let held = if self.0 == CmpOperator::Superset { set_in.rhs.clone() } else { set_in.lhs.clone() };
+                        ValueEvalResult::ComparisonResult(ComparisonResult::Fail(
+                            Compare::SetIn(SetIn::new(held, set_in.collection, set_in.lhs, set_in.rhs))
+                        ))
+                    },
+
+                    rest => rest,
+                }).collect())
+            },
             EvalResult::Result(r) => {
                 if self.1 {
                     EvalResult::Result(r.into_iter().map(|e| match e {
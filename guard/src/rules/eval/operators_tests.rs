@@ -97,7 +97,7 @@ fn test_operator_eq_literal() -> crate::rules::Result<()> {
     // != '*'
     //
 
-    let result = (CmpOperator::Eq, true).compare(&answers, &literal_string)?;
+    let result = (CmpOperator::Eq, true).compare(&answers, &literal_string, &DEFAULT_EVALUATION_OPTIONS)?;
     let result = match result {
         EvalResult::Result(v) => v,
         _ => unreachable!()
@@ -114,7 +114,7 @@ fn test_operator_eq_literal() -> crate::rules::Result<()> {
     //
     // == '*'
     //
-    let result = (CmpOperator::Eq, false).compare(&answers, &literal_string)?;
+    let result = (CmpOperator::Eq, false).compare(&answers, &literal_string, &DEFAULT_EVALUATION_OPTIONS)?;
     let result = match result {
         EvalResult::Result(v) => v,
         _ => unreachable!()
@@ -147,7 +147,7 @@ fn test_operator_eq_queries() -> crate::rules::Result<()> {
 
     let s3_keys_query_results: Vec<QueryResult<'_>> = s3_keys.iter().map(QueryResult::Resolved).collect();
     let s3_bucket_policy_results: Vec<QueryResult<'_>> = s3_bucket_refs.iter().map(QueryResult::Resolved).collect();
-    let result = (CmpOperator::Eq, false).compare(&s3_keys_query_results, &s3_bucket_policy_results)?;
+    let result = (CmpOperator::Eq, false).compare(&s3_keys_query_results, &s3_bucket_policy_results, &DEFAULT_EVALUATION_OPTIONS)?;
     let result = match result {
         EvalResult::Result(v) => v,
         _ => unreachable!()
@@ -197,7 +197,7 @@ fn test_operator_eq_query_to_scalar_literal_ok() -> crate::rules::Result<()> {
     //
     // Checking something like Resources[ Type == "AWS::IAM::Role" ].Properties.Policy.Statement[*].Action != '*'
     //
-    let eval = match (CmpOperator::Eq, true).compare(&lhs_queries, &rhs_queries)? {
+    let eval = match (CmpOperator::Eq, true).compare(&lhs_queries, &rhs_queries, &DEFAULT_EVALUATION_OPTIONS)? {
         EvalResult::Result(v) => v,
         _ => unreachable!()
     };
@@ -237,7 +237,7 @@ fn test_operator_eq_query_to_scalar_literal_ok() -> crate::rules::Result<()> {
     //
     // Checking something like '*' != Resources[ Type == "AWS::IAM::Role" ].Properties.Policy.Statement[*].Action
     //
-    let eval = match (CmpOperator::Eq, true).compare(&rhs_queries, &lhs_queries)? {
+    let eval = match (CmpOperator::Eq, true).compare(&rhs_queries, &lhs_queries, &DEFAULT_EVALUATION_OPTIONS)? {
         EvalResult::Result(v) => v,
         _ => unreachable!()
     };
@@ -296,7 +296,7 @@ fn test_operator_in_scalar_literal_to_query_ok() -> crate::rules::Result<()> {
     //
     // Literal to query results
     //
-    let eval = match CmpOperator::In.compare(&scalar_literal, &query_results)? {
+    let eval = match CmpOperator::In.compare(&scalar_literal, &query_results, &DEFAULT_EVALUATION_OPTIONS)? {
         EvalResult::Result(s) => s,
         _ => unreachable!()
     };
@@ -353,7 +353,7 @@ fn test_operator_in_list_literal_to_query_ok() -> crate::rules::Result<()> {
     //
     // Literal to query results
     //
-    let eval = match CmpOperator::In.compare(&list_literal, &query_results)? {
+    let eval = match CmpOperator::In.compare(&list_literal, &query_results, &DEFAULT_EVALUATION_OPTIONS)? {
         EvalResult::Result(s) => s,
         _ => unreachable!()
     };
@@ -401,7 +401,7 @@ fn test_operator_in_query_to_scalar_ok() -> crate::rules::Result<()> {
     // Query results to Literal. This returns 3 results as we flatten the list to compare with
     // scalar
     //
-    let eval = match CmpOperator::In.compare(&query_results, &scalar_literal)? {
+    let eval = match CmpOperator::In.compare(&query_results, &scalar_literal, &DEFAULT_EVALUATION_OPTIONS)? {
         EvalResult::Result(s) => s,
         _ => unreachable!()
     };
@@ -444,7 +444,7 @@ fn test_operator_in_query_to_scalar_ok() -> crate::rules::Result<()> {
     //
     // Literal to query check
     //
-    let eval = match CmpOperator::In.compare(&scalar_literal, &query_results)? {
+    let eval = match CmpOperator::In.compare(&scalar_literal, &query_results, &DEFAULT_EVALUATION_OPTIONS)? {
         EvalResult::Result(s) => s,
         _ => unreachable!()
     };
@@ -495,7 +495,7 @@ fn test_operator_in_query_to_scalar_in_string_ok() -> crate::rules::Result<()> {
     // Query results to Literal. This returns 4 results as we flatten the list to compare with
     // scalar
     //
-    let eval = match CmpOperator::In.compare(&query_results, &scalar_literal)? {
+    let eval = match CmpOperator::In.compare(&query_results, &scalar_literal, &DEFAULT_EVALUATION_OPTIONS)? {
         EvalResult::Result(s) => s,
         _ => unreachable!()
     };
@@ -572,7 +572,7 @@ fn test_operator_in_query_to_scalar_in_string_not_ok() -> crate::rules::Result<(
     // Query results to Literal. This returns 6 results as we flatten the list to compare with
     // scalar
     //
-    let eval = match CmpOperator::In.compare(&query_results, &scalar_literal)? {
+    let eval = match CmpOperator::In.compare(&query_results, &scalar_literal, &DEFAULT_EVALUATION_OPTIONS)? {
         EvalResult::Result(s) => s,
         _ => unreachable!()
     };
@@ -676,7 +676,7 @@ fn test_operator_in_query_to_query_ok() -> crate::rules::Result<()> {
         QueryResult::Resolved(&rhs_scalar_query_list_value),
     ];
 
-    let eval = match CmpOperator::In.compare(&lhs_query_results, &rhs_query_results)? {
+    let eval = match CmpOperator::In.compare(&lhs_query_results, &rhs_query_results, &DEFAULT_EVALUATION_OPTIONS)? {
         EvalResult::Result(s) => s,
         _ => unreachable!()
     };
@@ -725,7 +725,7 @@ fn test_operator_in_query_to_query_ok() -> crate::rules::Result<()> {
     // Query results to Literal. This returns 6 results as we flatten the list to compare with
     // scalar
     //
-    let eval = match CmpOperator::In.compare(&lhs_query_results, &rhs_query_results)? {
+    let eval = match CmpOperator::In.compare(&lhs_query_results, &rhs_query_results, &DEFAULT_EVALUATION_OPTIONS)? {
         EvalResult::Result(s) => s,
         _ => unreachable!()
     };
@@ -807,7 +807,7 @@ fn test_operator_in_query_to_query_not_ok() -> crate::rules::Result<()> {
         QueryResult::Resolved(&rhs_scalar_query_list_value),
     ];
 
-    let eval = match CmpOperator::In.compare(&lhs_query_results, &rhs_query_results)? {
+    let eval = match CmpOperator::In.compare(&lhs_query_results, &rhs_query_results, &DEFAULT_EVALUATION_OPTIONS)? {
         EvalResult::Result(s) => s,
         _ => unreachable!()
     };
@@ -845,7 +845,7 @@ fn test_operator_in_query_to_query_not_ok() -> crate::rules::Result<()> {
         QueryResult::Resolved(&rhs_scalar_query_list_value),
     ];
 
-    let eval = match CmpOperator::In.compare(&lhs_query_results, &rhs_query_results)? {
+    let eval = match CmpOperator::In.compare(&lhs_query_results, &rhs_query_results, &DEFAULT_EVALUATION_OPTIONS)? {
         EvalResult::Result(s) => s,
         _ => unreachable!()
     };
@@ -877,7 +877,7 @@ fn test_operator_in_query_to_query_not_ok() -> crate::rules::Result<()> {
     //
     // Checking !IN
     //
-    let eval = match (CmpOperator::In, true).compare(&lhs_query_results, &rhs_query_results)? {
+    let eval = match (CmpOperator::In, true).compare(&lhs_query_results, &rhs_query_results, &DEFAULT_EVALUATION_OPTIONS)? {
         EvalResult::Result(s) => s,
         _ => unreachable!()
     };
@@ -918,7 +918,7 @@ fn test_operator_in_literal_list_in_query_ok() -> crate::rules::Result<()> {
     let lhs = QueryResult::Literal(&lhs_value);
     let rhs_value = PathAwareValue::String((Path::root(), String::from("Environment")));
     let rhs = QueryResult::Resolved(&rhs_value);
-    match CmpOperator::In.compare(&[lhs], &[rhs]) {
+    match CmpOperator::In.compare(&[lhs], &[rhs], &DEFAULT_EVALUATION_OPTIONS) {
         Ok(EvalResult::Result(result)) => {
             for each in result {
                 match each {
@@ -962,7 +962,7 @@ fn test_operator_in_scalar_literal_to_query_ok_with_unresolved() -> crate::rules
         QueryResult::UnResolved(ur.clone()),
     ];
 
-    let eval = match CmpOperator::In.compare(&scalar_literal, &query_results)? {
+    let eval = match CmpOperator::In.compare(&scalar_literal, &query_results, &DEFAULT_EVALUATION_OPTIONS)? {
         EvalResult::Result(s) => s,
         _ => unreachable!()
     };
@@ -1003,7 +1003,7 @@ fn test_operator_eq_vs_in_from_queries() -> crate::rules::Result<()> {
     let lhs_answers = evaluator.query(&custom)?;
     let custom_data = AccessQuery::try_from(r#"Resources[ Type == "Custom::Data" ].Properties.ge"#)?.query;
     let rhs_answers = evaluator.query(&custom_data)?;
-    let result = (CmpOperator::Eq, false).compare(&lhs_answers, &rhs_answers)?;
+    let result = (CmpOperator::Eq, false).compare(&lhs_answers, &rhs_answers, &DEFAULT_EVALUATION_OPTIONS)?;
     let result = match result {
         EvalResult::Result(v) => v,
         _ => unreachable!()
@@ -1012,7 +1012,7 @@ fn test_operator_eq_vs_in_from_queries() -> crate::rules::Result<()> {
     let eval_result = &result[0];
     assert_eq!(matches!(eval_result, ValueEvalResult::ComparisonResult(ComparisonResult::Fail(_))), true);
 
-    let result = (CmpOperator::In, false).compare(&lhs_answers, &rhs_answers)?;
+    let result = (CmpOperator::In, false).compare(&lhs_answers, &rhs_answers, &DEFAULT_EVALUATION_OPTIONS)?;
     let result = match result {
         EvalResult::Result(v) => v,
         _ => unreachable!()
@@ -1025,3 +1025,59 @@ fn test_operator_eq_vs_in_from_queries() -> crate::rules::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_operator_in_map_values() -> crate::rules::Result<()> {
+    let map = PathAwareValue::try_from(
+        r#"{ prod: "us-east-1", dev: "us-west-2", port: 443 }"#)?;
+    let rhs = [QueryResult::Literal(&map)];
+
+    let string_value = PathAwareValue::try_from(r#""us-west-2""#)?;
+    let int_value = PathAwareValue::try_from("443")?;
+    let regex_value = PathAwareValue::try_from("/^us-east/")?;
+    for each in &[&string_value, &int_value, &regex_value] {
+        match CmpOperator::In.compare(&[QueryResult::Literal(*each)], &rhs, &DEFAULT_EVALUATION_OPTIONS)? {
+            EvalResult::Result(results) => {
+                assert_eq!(results.len(), 1);
+                match &results[0] {
+                    ValueEvalResult::ComparisonResult(
+                        ComparisonResult::Success(Compare::ValueIn(pair))) => {
+                        assert_eq!(pair.lhs, *each);
+                    },
+                    rest => unreachable!("{:?}", rest)
+                }
+            },
+            _ => unreachable!()
+        }
+    }
+
+    //
+    // keys are not part of the membership check
+    //
+    let key = PathAwareValue::try_from(r#""prod""#)?;
+    match CmpOperator::In.compare(&[QueryResult::Literal(&key)], &rhs, &DEFAULT_EVALUATION_OPTIONS)? {
+        EvalResult::Result(results) => {
+            assert_eq!(results.len(), 1);
+            assert!(matches!(&results[0],
+                ValueEvalResult::ComparisonResult(ComparisonResult::Fail(Compare::ValueIn(_)))));
+        },
+        _ => unreachable!()
+    }
+
+    let list = PathAwareValue::try_from(r#"["us-east-1", 8080]"#)?;
+    match CmpOperator::In.compare(&[QueryResult::Literal(&list)], &rhs, &DEFAULT_EVALUATION_OPTIONS)? {
+        EvalResult::Result(results) => {
+            assert_eq!(results.len(), 1);
+            match &results[0] {
+                ValueEvalResult::ComparisonResult(
+                    ComparisonResult::Fail(Compare::ListIn(lin))) => {
+                    assert_eq!(lin.diff.len(), 1);
+                    assert_eq!(lin.diff[0], &PathAwareValue::try_from("8080")?);
+                },
+                rest => unreachable!("{:?}", rest)
+            }
+        },
+        _ => unreachable!()
+    }
+    Ok(())
+}
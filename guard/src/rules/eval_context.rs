@@ -1,5 +1,6 @@
-use crate::rules::exprs::{RulesFile, AccessQuery, Rule, LetExpr, LetValue, QueryPart, SliceDisplay, Block, GuardClause, Conjunctions, ParameterizedRule};
-use crate::rules::path_value::{PathAwareValue, MapValue};
+use crate::rules::exprs::{RulesFile, AccessQuery, ROOT_VARIABLE, Rule, LetExpr, LetValue, QueryPart, SliceDisplay, Block, GuardClause, Conjunctions, ParameterizedRule};
+use crate::rules::path_value::{capture_value, check_max_depth, cidr_prefix_value, count_value, EvaluationOptions, PathAwareValue, MapValue};
+use crate::rules::functions::collections::count;
 use std::collections::{HashMap, HashSet};
 use crate::rules::{QueryResult, Status, EvalContext, UnResolved, RecordType, NamedStatus, TypeBlockCheck, BlockCheck, ClauseCheck, UnaryValueCheck, ValueCheck, ComparisonClauseCheck, RecordTracer, InComparisonCheck};
 use crate::rules::Result;
@@ -29,18 +30,26 @@ pub(crate) struct RootScope<'value, 'loc: 'value> {
     scope: Scope<'value, 'loc>,
     rules: HashMap<&'value str, Vec<&'value Rule<'loc>>>,
     rules_status: HashMap<&'value str, Status>,
+    rules_in_progress: Vec<&'value str>,
     parameterized_rules: HashMap<&'value str, &'value ParameterizedRule<'loc>>,
     recorder: RecordTracker<'value>,
+    options: EvaluationOptions,
 }
 
 impl<'value, 'loc: 'value> RootScope<'value, 'loc> {
     pub fn reset_root(self, new_root: &'value PathAwareValue) -> Result<RootScope<'value, 'loc>> {
+        let options = self.options;
         root_scope_with(
             self.scope.literals,
             self.scope.variable_queries,
             self.rules,
             self.parameterized_rules,
-            new_root)
+            new_root).map(|scope| scope.with_options(options))
+    }
+
+    pub(crate) fn with_options(mut self, options: EvaluationOptions) -> Self {
+        self.options = options;
+        self
     }
 
     pub(crate) fn reset_recorder(&mut self) -> RecordTracker<'value> {
@@ -77,10 +86,12 @@ pub(crate) fn reset_with<'value, 'loc: 'value>(
     };
     RootScope {
         scope, rules, parameterized_rules, rules_status: HashMap::new(),
+        rules_in_progress: Vec::new(),
         recorder: RecordTracker {
             final_event: None,
             events: vec![]
-        }
+        },
+        options: root_scope.options,
     }
 }
 
@@ -89,6 +100,16 @@ pub(crate) struct BlockScope<'value, 'loc: 'value, 'eval> {
     parent: &'eval mut dyn EvalContext<'value, 'loc>,
 }
 
+//
+// Scope of a single rule evaluation. Keys captured by queries in the rule, e.g. name in
+// Resources[ name | Type == 'AWS::S3::Bucket' ], are kept here instead of in the file scope so
+// that they are not visible to, or accumulated with, other rules capturing the same name
+//
+pub(crate) struct RuleScope<'value, 'loc: 'value, 'eval> {
+    captured: HashMap<&'value str, Vec<QueryResult<'value>>>,
+    parent: &'eval mut dyn EvalContext<'value, 'loc>,
+}
+
 pub(crate) struct ValueScope<'value, 'eval, 'loc: 'value> {
     pub(crate) root: &'value PathAwareValue,
     pub(crate) parent: &'eval mut dyn EvalContext<'value, 'loc>,
@@ -159,6 +180,7 @@ fn accumulate<'value, 'loc: 'value>(
         );
     }
 
+    check_max_depth(resolver.options().max_depth, parent, &query[query_index..])?;
     let mut accumulated = Vec::with_capacity(elements.len());
     for (_index, each) in elements.iter().enumerate() {
         accumulated.extend(query_retrieval_with_converter(query_index+1, query, each, resolver, converter)?);
@@ -190,6 +212,7 @@ fn accumulate_map<'value, 'loc: 'value, F>(
         );
     }
 
+    check_max_depth(resolver.options().max_depth, parent, &query[query_index..])?;
     let mut resolved = Vec::with_capacity(map.values.len());
     for (key, each) in map.keys.iter().zip(map.values.values()) {
         let mut val_resolver = ValueScope{ root: each, parent: resolver };
@@ -232,7 +255,14 @@ fn map_resolved<'value, F>(
     }
 }
 
-fn check_and_delegate<'value, 'loc: 'value>(conjunctions: &'value Conjunctions<GuardClause<'loc>>, name: &'value Option<String>)
+//
+// A Filter selects values for which its clauses PASS, a FilterNot those for which they do not
+//
+fn is_selected(status: Status, negated: bool) -> bool {
+    (status == Status::PASS) != negated
+}
+
+fn check_and_delegate<'value, 'loc: 'value>(conjunctions: &'value Conjunctions<GuardClause<'loc>>, name: &'value Option<String>, negated: bool)
     -> impl Fn(usize, &'value [QueryPart<'loc>], &'value PathAwareValue, &'value PathAwareValue, &mut dyn EvalContext<'value, 'loc>, Option<&dyn Fn(&str) -> String>) -> Result<Vec<QueryResult<'value>>>
 {
     move |index, query, key, value, eval_context, converter| {
@@ -242,16 +272,16 @@ fn check_and_delegate<'value, 'loc: 'value>(conjunctions: &'value Conjunctions<G
             conjunctions, eval_context, super::eval::eval_guard_clause) {
             Ok(status) => {
                 eval_context.end_record(&context, RecordType::Filter(status))?;
+                let selected = is_selected(status, negated);
                 if let Some(key_name) = name {
-                    if status == Status::PASS {
+                    if selected {
                         eval_context.add_variable_capture_key(key_name.as_ref(), key)?;
                     }
                 }
-                match status {
-                    Status::PASS => {
-                        query_retrieval_with_converter(index, query, value, eval_context, converter)
-                    },
-                    _ => Ok(vec![])
+                if selected {
+                    query_retrieval_with_converter(index, query, value, eval_context, converter)
+                } else {
+                    Ok(vec![])
                 }
             },
 
@@ -276,11 +306,80 @@ lazy_static! {
         ];
 }
 
+//
+// Resources.*[ Type == resource_type ], counted
+//
+fn resource_count(root: &PathAwareValue, resource_type: &str) -> usize {
+    let resources = match root {
+        PathAwareValue::Map((_, map)) => match map.values.get("Resources") {
+            Some(PathAwareValue::Map((_, resources))) => resources,
+            _ => return 0,
+        },
+        _ => return 0,
+    };
+    resources.values.values().filter(|resource| match resource {
+        PathAwareValue::Map((_, resource)) => matches!(
+            resource.values.get("Type"), Some(PathAwareValue::String((_, type_name))) if type_name == resource_type),
+        _ => false,
+    }).count()
+}
+
 fn query_retrieval<'value, 'loc: 'value>(
     query_index: usize,
     query: &'value [QueryPart<'loc>],
     current: &'value PathAwareValue,
     resolver: &mut dyn EvalContext<'value, 'loc>) -> Result<Vec<QueryResult<'value>>> {
+    //
+    // Resources are counted across the whole template, not the value the clause is evaluated in
+    //
+    if let [QueryPart::ResourceCount(resource_type)] = query {
+        let mut resources = 0;
+        for each in resolver.resolve_variable(ROOT_VARIABLE)? {
+            if let QueryResult::Literal(root) | QueryResult::Resolved(root) = each {
+                resources += resource_count(root, resource_type);
+            }
+        }
+        return Ok(vec![QueryResult::Resolved(count_value(resources))])
+    }
+    if let Some((QueryPart::Count, query)) = query.split_last() {
+        let matched = query_retrieval_with_converter(query_index, query, current, resolver, None)?;
+        return Ok(vec![QueryResult::Resolved(count_value(count(&matched) as usize))])
+    }
+    if let Some((QueryPart::Capture(regex), query)) = query.split_last() {
+        let matched = query_retrieval_with_converter(query_index, query, current, resolver, None)?;
+        let mut captured = Vec::with_capacity(matched.len());
+        for each in matched {
+            if let QueryResult::Literal(value) | QueryResult::Resolved(value) = each {
+                captured.extend(capture_value(value, regex)?.map(QueryResult::Resolved));
+            }
+        }
+        return Ok(captured)
+    }
+    //
+    // Values that are not addresses or CIDR blocks have no prefix, they stay unresolved so that
+    // the clause comparing the prefix fails for them
+    //
+    if let Some((QueryPart::CidrPrefix, query)) = query.split_last() {
+        let matched = query_retrieval_with_converter(query_index, query, current, resolver, None)?;
+        let mut prefixes = Vec::with_capacity(matched.len());
+        for each in matched {
+            match each {
+                QueryResult::Literal(value) | QueryResult::Resolved(value) => prefixes.push(
+                    match cidr_prefix_value(value) {
+                        Ok(prefix) => QueryResult::Resolved(prefix),
+                        Err(Error(ErrorKind::NotComparable(reason))) => QueryResult::UnResolved(UnResolved {
+                            traversed_to: value,
+                            remaining_query: "cidr_prefix()".to_string(),
+                            reason: Some(reason),
+                        }),
+                        Err(e) => return Err(e),
+                    }
+                ),
+                unresolved => prefixes.push(unresolved),
+            }
+        }
+        return Ok(prefixes)
+    }
     query_retrieval_with_converter(
         query_index, query, current, resolver, None,
     )
@@ -332,6 +431,26 @@ fn query_retrieval_with_converter<'value, 'loc: 'value>(
             query_retrieval_with_converter(query_index+1, query, current, resolver, converter)
         },
 
+        QueryPart::Count => {
+            Err(Error::new(ErrorKind::IncompatibleError(
+                format!("count() can only be applied to a whole query, found it inside {}", SliceDisplay(query)))))
+        },
+
+        QueryPart::Capture(_) => {
+            Err(Error::new(ErrorKind::IncompatibleError(
+                format!("MATCH can only be applied to a whole query, found it inside {}", SliceDisplay(query)))))
+        },
+
+        QueryPart::CidrPrefix => {
+            Err(Error::new(ErrorKind::IncompatibleError(
+                format!("cidr_prefix() can only be applied to a whole query, found it inside {}", SliceDisplay(query)))))
+        },
+
+        QueryPart::ResourceCount(_) => {
+            Err(Error::new(ErrorKind::IncompatibleError(
+                format!("RESOURCE_COUNT can only follow a resource type, found it inside {}", SliceDisplay(query)))))
+        },
+
         QueryPart::Key(key) => {
             match key.parse::<i32>() {
                 Ok(idx) => {
@@ -457,7 +576,7 @@ fn query_retrieval_with_converter<'value, 'loc: 'value>(
                             }
                             Ok(acc)
                         } else {
-                            match map.values.get(key) {
+                            match map.get_key(key, resolver.options()) {
                                 Some(val) =>
                                     return query_retrieval_with_converter(query_index+1, query, val, resolver, converter),
 
@@ -487,6 +606,23 @@ fn query_retrieval_with_converter<'value, 'loc: 'value>(
                                 &query[query_index..]
                             )
                         }
+                    } else if let (PathAwareValue::List((_, list)), true) = (current, resolver.options().list_key_expansion) {
+                        //
+                        // Same as an implicit [*], it is an error if there are no elements
+                        //
+                        if list.is_empty() {
+                            return to_unresolved_result(
+                                current,
+                                format!("No more entries for value at path = {} on type = {} ",
+                                        current.self_path(), current.type_info()),
+                                &query[query_index..]
+                            );
+                        }
+                        let mut acc = Vec::with_capacity(list.len());
+                        for each in list {
+                            acc.extend(query_retrieval_with_converter(query_index, query, each, resolver, converter)?);
+                        }
+                        Ok(acc)
                     } else {
                         to_unresolved_result(
                             current,
@@ -497,6 +633,33 @@ fn query_retrieval_with_converter<'value, 'loc: 'value>(
             }
         },
 
+        QueryPart::KeyRegex(regex) => {
+            match current {
+                PathAwareValue::Map((path, map)) => {
+                    let matched = map.values_matching(regex, resolver.options())?;
+                    if matched.is_empty() {
+                        return to_unresolved_result(
+                            current,
+                            format!("Could not find a key matching /{}/ inside struct at path {}", regex, path),
+                            &query[query_index..]
+                        )
+                    }
+                    let mut acc = Vec::with_capacity(matched.len());
+                    for each in matched {
+                        acc.extend(query_retrieval_with_converter(query_index+1, query, each, resolver, converter)?);
+                    }
+                    Ok(acc)
+                },
+
+                _ =>
+                    to_unresolved_result(
+                        current,
+                        format!("Attempting to retrieve keys matching /{}/ but type is not an struct type at path {}, Type = {}",
+                                regex, current.self_path(), current.type_info()),
+                        &query[query_index..])
+            }
+        },
+
         QueryPart::Index(index) => {
             match current {
                 PathAwareValue::List((_, list)) => {
@@ -591,16 +754,35 @@ fn query_retrieval_with_converter<'value, 'loc: 'value>(
             }
         },
 
-        QueryPart::Filter(name, conjunctions) => {
+        QueryPart::RecursiveDescent(key) => {
+            let found = current.descendants_with_key(key);
+            if found.is_empty() {
+                return to_unresolved_result(
+                    current,
+                    format!("Could not find key {} at any depth inside path {}", key, current.self_path()),
+                    &query[query_index..]
+                )
+            }
+            let mut acc = Vec::with_capacity(found.len());
+            for each in found {
+                acc.extend(query_retrieval_with_converter(query_index+1, query, each, resolver, converter)?);
+            }
+            Ok(acc)
+        },
+
+        QueryPart::Filter(name, conjunctions) |
+        QueryPart::FilterNot(name, conjunctions) => {
+            let negated = matches!(&query[query_index], QueryPart::FilterNot(..));
             match current {
                 PathAwareValue::Map((_path, map)) => {
                     match &query[query_index-1] {
                         QueryPart::AllValues(_name) |
                         QueryPart::AllIndices(_name) => {
-                            check_and_delegate(conjunctions, &None)(query_index+1, query, current, current, resolver, converter)
+                            check_and_delegate(conjunctions, &None, negated)(query_index+1, query, current, current, resolver, converter)
                         },
 
-                        QueryPart::Key(_) => {
+                        QueryPart::Key(_) |
+                        QueryPart::RecursiveDescent(_) => {
 //
 //                            Ideal solution, see https://github.com/rust-lang/rust/issues/41078
 //
@@ -615,7 +797,7 @@ fn query_retrieval_with_converter<'value, 'loc: 'value>(
 //                                })
                             if !map.is_empty() {
                                 accumulate_map(
-                                    current, map, query_index, query, resolver, converter, check_and_delegate(conjunctions, name)
+                                    current, map, query_index, query, resolver, converter, check_and_delegate(conjunctions, name, negated)
                                 )
                             } else {
                                 Ok(vec![])
@@ -636,11 +818,10 @@ fn query_retrieval_with_converter<'value, 'loc: 'value>(
                             conjunctions, &mut val_resolver, super::eval::eval_guard_clause) {
                             Ok(status) => {
                                 resolver.end_record(&context, RecordType::Filter(status))?;
-                                match status {
-                                    Status::PASS => {
-                                        query_retrieval_with_converter(query_index + 1, query, each, resolver, converter)?
-                                    },
-                                    _ => vec![]
+                                if is_selected(status, negated) {
+                                    query_retrieval_with_converter(query_index + 1, query, each, resolver, converter)?
+                                } else {
+                                    vec![]
                                 }
                             },
 
@@ -659,11 +840,10 @@ fn query_retrieval_with_converter<'value, 'loc: 'value>(
                     match super::eval::eval_conjunction_clauses(
                             conjunctions, &mut val_resolver, super::eval::eval_guard_clause) {
                         Ok(status) => {
-                            match status {
-                                Status::PASS => {
-                                    query_retrieval_with_converter(query_index + 1, query, current, resolver, converter)
-                                },
-                                _ => Ok(vec![])
+                            if is_selected(status, negated) {
+                                query_retrieval_with_converter(query_index + 1, query, current, resolver, converter)
+                            } else {
+                                Ok(vec![])
                             }
                         },
                         Err(e) => {
@@ -767,6 +947,33 @@ pub(crate) fn root_scope<'value, 'loc: 'value>(
 {
     let (literals, queries) =
         extract_variables(&rules_file.assignments)?;
+    root_scope_with_variables(rules_file, literals, queries, root)
+}
+
+//
+// Variables defined from outside the rules file (e.g. --define on the command line) take
+// precedence over file level let assignments of the same name
+//
+pub(crate) fn root_scope_with_defines<'value, 'loc: 'value>(
+    rules_file: &'value RulesFile<'loc>,
+    root: &'value PathAwareValue,
+    defines: &'value HashMap<String, PathAwareValue>) -> Result<RootScope<'value, 'loc>>
+{
+    let (mut literals, mut queries) =
+        extract_variables(&rules_file.assignments)?;
+    for (name, value) in defines {
+        queries.remove(name.as_str());
+        literals.insert(name.as_str(), value);
+    }
+    root_scope_with_variables(rules_file, literals, queries, root)
+}
+
+fn root_scope_with_variables<'value, 'loc: 'value>(
+    rules_file: &'value RulesFile<'loc>,
+    literals: HashMap<&'value str, &'value PathAwareValue>,
+    queries: HashMap<&'value str, &'value AccessQuery<'loc>>,
+    root: &'value PathAwareValue) -> Result<RootScope<'value, 'loc>>
+{
     let mut lookup_cache = HashMap::with_capacity(rules_file.guard_rules.len());
     for rule in &rules_file.guard_rules {
         lookup_cache.entry(rule.rule_name.as_str()).or_insert(vec![]).push(rule);
@@ -799,10 +1006,12 @@ pub(crate) fn root_scope_with<'value, 'loc: 'value>(
         rules: lookup_cache,
         parameterized_rules,
         rules_status: HashMap::new(),
+        rules_in_progress: Vec::new(),
         recorder: RecordTracker {
             final_event: None,
             events: vec![]
-        }
+        },
+        options: EvaluationOptions::default(),
     })
 }
 
@@ -825,6 +1034,14 @@ pub(crate) fn block_scope<'value, 'block, 'loc: 'value, 'eval, T>(
     })
 }
 
+pub(crate) fn rule_scope<'value, 'loc: 'value, 'eval>(
+    parent: &'eval mut dyn EvalContext<'value, 'loc>) -> RuleScope<'value, 'loc, 'eval> {
+    RuleScope {
+        captured: HashMap::new(),
+        parent
+    }
+}
+
 pub(crate) struct RecordTracker<'value> {
     pub(crate) events: Vec<EventRecord<'value>>,
     pub(crate) final_event: Option<EventRecord<'value>>,
@@ -847,7 +1064,7 @@ impl<'value> RecordTracer<'value> for RecordTracker<'value> {
         self.events.push(EventRecord {
             context: context.to_string(),
             container: None,
-            children: vec![]
+            children: vec![],
         });
         Ok(())
     }
@@ -906,9 +1123,31 @@ impl<'value, 'loc: 'value> EvalContext<'value, 'loc> for RootScope<'value, 'loc>
         self.scope.root
     }
 
+    fn options(&self) -> &EvaluationOptions {
+        &self.options
+    }
+
     fn rule_status(&mut self, rule_name: &'value str) -> Result<Status> {
         if let Some(status) = self.rules_status.get(rule_name) {
-            return Ok(*status)
+            //
+            // Status was already computed for this document, record a lightweight
+            // node instead of repeating the rule's entire evaluation subtree
+            //
+            let status = *status;
+            self.start_record(rule_name)?;
+            self.end_record(rule_name, RecordType::CachedRuleCheck(NamedStatus {
+                name: rule_name,
+                status,
+                message: None,
+            }))?;
+            return Ok(status)
+        }
+
+        if let Some(pos) = self.rules_in_progress.iter().position(|each| *each == rule_name) {
+            let mut cycle = self.rules_in_progress[pos..].to_vec();
+            cycle.push(rule_name);
+            return Err(Error::new(ErrorKind::IncompatibleError(
+                format!("Cycle detected between rules {}", cycle.join(" -> ")))))
         }
 
         let rule = match self.rules.get(rule_name) {
@@ -918,15 +1157,16 @@ impl<'value, 'loc: 'value> EvalContext<'value, 'loc> for RootScope<'value, 'loc>
                         rule_name, self.rules.keys()))))
         };
 
-        let status = 'done: loop {
-            for each_rule in rule {
-                let status = super::eval::eval_rule(each_rule, self)?;
-                if status != SKIP {
-                    break 'done status;
-                }
+        self.rules_in_progress.push(rule_name);
+        let mut status = Ok(SKIP);
+        for each_rule in rule {
+            status = super::eval::eval_rule(each_rule, self);
+            if !matches!(status, Ok(SKIP)) {
+                break
             }
-            break SKIP
-        };
+        }
+        self.rules_in_progress.pop();
+        let status = status?;
 
         // let status = super::eval::eval_rule(rule, self)?;
         self.rules_status.insert(rule_name, status);
@@ -942,6 +1182,10 @@ impl<'value, 'loc: 'value> EvalContext<'value, 'loc> for RootScope<'value, 'loc>
     }
 
     fn resolve_variable(&mut self, variable_name: &'value str) -> Result<Vec<QueryResult<'value>>> {
+        if variable_name == ROOT_VARIABLE {
+            return Ok(vec![QueryResult::Resolved(self.scope.root)])
+        }
+
         if let Some(val) = self.scope.literals.get(variable_name) {
             return Ok(vec![QueryResult::Literal(*val)])
         }
@@ -982,6 +1226,11 @@ impl<'value, 'loc: 'value> RecordTracer<'value> for RootScope<'value, 'loc> {
     }
 
     fn end_record(&mut self, context: &str, record: RecordType<'value>) -> Result<()> {
+        if let RecordType::RuleCheck(NamedStatus { name, status, .. }) = &record {
+            if self.rules.contains_key(name) {
+                self.rules_status.entry(*name).or_insert(*status);
+            }
+        }
         self.recorder.end_record(context, record)
     }
 }
@@ -1000,6 +1249,10 @@ impl<'value, 'loc: 'value, 'eval> EvalContext<'value, 'loc> for ValueScope<'valu
         self.root
     }
 
+    fn options(&self) -> &EvaluationOptions {
+        self.parent.options()
+    }
+
     fn rule_status(&mut self, rule_name: &'value str) -> Result<Status> {
         self.parent.rule_status(rule_name)
     }
@@ -1025,6 +1278,52 @@ impl<'value, 'loc: 'value, 'eval> RecordTracer<'value> for ValueScope<'value, 'e
 }
 
 
+impl<'value, 'loc: 'value, 'eval> EvalContext<'value, 'loc> for RuleScope<'value, 'loc, 'eval> {
+    fn query(&mut self, query: &'value [QueryPart<'loc>]) -> Result<Vec<QueryResult<'value>>> {
+        let root = self.parent.root();
+        query_retrieval(0, query, root, self)
+    }
+
+    fn find_parameterized_rule(&mut self, rule_name: &str) -> Result<&'value ParameterizedRule<'loc>> {
+        self.parent.find_parameterized_rule(rule_name)
+    }
+
+    fn root(&mut self) -> &'value PathAwareValue {
+        self.parent.root()
+    }
+
+    fn options(&self) -> &EvaluationOptions {
+        self.parent.options()
+    }
+
+    fn rule_status(&mut self, rule_name: &'value str) -> Result<Status> {
+        self.parent.rule_status(rule_name)
+    }
+
+    fn resolve_variable(&mut self, variable_name: &'value str) -> Result<Vec<QueryResult<'value>>> {
+        match self.captured.get(variable_name) {
+            Some(values) => Ok(values.clone()),
+            None => self.parent.resolve_variable(variable_name)
+        }
+    }
+
+    fn add_variable_capture_key(&mut self, variable_name: &'value str, key: &'value PathAwareValue) -> Result<()> {
+        self.captured.entry(variable_name).or_default()
+            .push(QueryResult::Resolved(key));
+        Ok(())
+    }
+}
+
+impl<'value, 'loc: 'value, 'eval> RecordTracer<'value> for RuleScope<'value, 'loc, 'eval> {
+    fn start_record(&mut self, context: &str) -> Result<()> {
+        self.parent.start_record(context)
+    }
+
+    fn end_record(&mut self, context: &str, record: RecordType<'value>) -> Result<()> {
+        self.parent.end_record(context, record)
+    }
+}
+
 impl<'value, 'loc: 'value, 'eval> EvalContext<'value, 'loc> for BlockScope<'value, 'loc, 'eval> {
     fn query(&mut self, query: &'value [QueryPart<'loc>]) -> Result<Vec<QueryResult<'value>>> {
         query_retrieval(0, query, self.scope.root, self)
@@ -1038,6 +1337,10 @@ impl<'value, 'loc: 'value, 'eval> EvalContext<'value, 'loc> for BlockScope<'valu
         self.scope.root
     }
 
+    fn options(&self) -> &EvaluationOptions {
+        self.parent.options()
+    }
+
     fn rule_status(&mut self, rule_name: &'value str) -> Result<Status> {
         self.parent.rule_status(rule_name)
     }
@@ -1101,6 +1404,8 @@ pub(crate) struct FileReport<'value> {
    pub(crate) not_compliant: Vec<ClauseReport<'value>>,
    pub(crate) not_applicable: HashSet<String>,
    pub(crate) compliant: HashSet<String>,
+   #[serde(skip_serializing_if = "std::ops::Not::not")]
+   pub(crate) truncated: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Default)]
@@ -1322,18 +1627,27 @@ pub(crate) fn cmp_str(cmp: (CmpOperator, bool)) -> &'static str {
             CmpOperator::Empty => if not { "NOT EMPTY" } else { "EMPTY" },
             CmpOperator::IsList => if not { "NOT LIST" } else { "IS LIST" },
             CmpOperator::IsMap => if not { "NOT STRUCT" } else { "IS STRUCT" },
-            CmpOperator::IsString => if not { "NOT STRING" } else { "IS STRING" }
+            CmpOperator::IsString => if not { "NOT STRING" } else { "IS STRING" },
+            CmpOperator::IsBool => if not { "NOT BOOL" } else { "IS BOOL" },
+            CmpOperator::IsInt => if not { "NOT INT" } else { "IS INT" },
+            CmpOperator::IsNumber => if not { "NOT NUMBER" } else { "IS NUMBER" },
+            CmpOperator::Unique => if not { "NOT UNIQUE" } else { "UNIQUE" },
             _ => unreachable!()
         }
     }
     else {
         match cmp {
             CmpOperator::Eq => if not { "NOT EQUAL" } else { "EQUAL" },
+            CmpOperator::EqUnordered => if not { "NOT EQUAL UNORDERED" } else { "EQUAL UNORDERED" },
             CmpOperator::Le => if not { "NOT LESS THAN EQUAL" } else { "LESS THAN EQUAL" },
             CmpOperator::Lt => if not { "NOT LESS THAN" } else { "LESS THAN" },
             CmpOperator::Ge => if not { "NOT GREATER THAN EQUAL" } else { "GREATER THAN EQUAL" },
             CmpOperator::Gt => if not { "NOT GREATER THAN" } else { "GREATER THAN" },
             CmpOperator::In => if not { "NOT IN" } else { "IN" },
+            CmpOperator::AllIn => if not { "NOT ALL IN" } else { "ALL IN" },
+            CmpOperator::Subset => if not { "NOT SUBSET" } else { "SUBSET" },
+            CmpOperator::Superset => if not { "NOT SUPERSET" } else { "SUPERSET" },
+            CmpOperator::InCidr => if not { "NOT IN CIDR" } else { "IN CIDR" },
             _ => unreachable!()
         }
     }
@@ -1469,9 +1783,11 @@ fn report_all_failed_clauses_for_rules<'value>(checks: &[EventRecord<'value>]) -
                             IsMap => if *not { "was a struct" } else { "was not struct" },
                             IsString => if *not { "was a string " } else { "was not string" },
                             IsInt => if *not { "was int" } else { "was not int" },
+                            IsNumber => if *not { "was a number" } else { "was not a number" },
+                            Unique => if *not { "was unique" } else { "was not unique" },
                             IsBool => if *not { "was bool" } else { "was not bool" },
                             IsFloat => if *not { "was float" } else { "was not float" },
-                            Eq | In | Gt | Lt | Le | Ge => unreachable!()
+                            Eq | EqUnordered | In | AllIn | Gt | Lt | Le | Ge | Subset | Superset | InCidr => unreachable!()
                         };
 
                         let custom_message = custom_message.as_ref()
@@ -1487,9 +1803,14 @@ fn report_all_failed_clauses_for_rules<'value>(checks: &[EventRecord<'value>]) -
                             QueryResult::Resolved(res) => {
                                 (
                                     format!(
-                                        "Check was not compliant as property [{prop}] {cmp_msg}.{err}",
+                                        "Check was not compliant as property [{prop}] {cmp_msg}{found}.{err}",
                                         prop=res.self_path(),
                                         cmp_msg=cmp_msg,
+                                        found=if cmp.is_type_check() && !*not {
+                                            format!(", found {}", res.type_info())
+                                        } else {
+                                            "".to_string()
+                                        },
                                         err=error_message
                                     ),
                                     UnaryCheck::Resolved(UnaryComparison {
@@ -1581,11 +1902,16 @@ fn report_all_failed_clauses_for_rules<'value>(checks: &[EventRecord<'value>]) -
                                                 to=to_res,
                                                 op_msg=match cmp {
                                                     CmpOperator::Eq => if *not { "equal to" } else { "not equal to" },
+                                                    CmpOperator::EqUnordered => if *not { "equal in any order to" } else { "not equal in any order to" },
                                                     CmpOperator::Le => if *not { "less than equal to" } else { "less than equal to" },
                                                     CmpOperator::Lt => if *not { "less than" } else { "not less than" },
                                                     CmpOperator::Ge => if *not { "greater than equal to" } else { "not greater than equal" },
                                                     CmpOperator::Gt => if *not { "greater than" } else { "not greater than" },
                                                     CmpOperator::In => if *not { "in" } else { "not in" },
+                                                    CmpOperator::AllIn => if *not { "in" } else { "not in" },
+                                                    CmpOperator::Subset => if *not { "a subset of" } else { "not a subset of" },
+                                                    CmpOperator::Superset => if *not { "a superset of" } else { "not a superset of" },
+                                                    CmpOperator::InCidr => if *not { "within CIDR" } else { "not within CIDR" },
                                                     _ => unreachable!()
                                                 },
                                                 err=error_message
@@ -1645,11 +1971,33 @@ fn report_all_failed_clauses_for_rules<'value>(checks: &[EventRecord<'value>]) -
                         to,
                         custom_message,
                         comparison, ..}) => {
-                        let error_message = format!(
-                            "Check was not compliant as property [{}] was not present in [{}]",
-                            from.resolved().unwrap().self_path(),
-                            SliceDisplay(to)
-                        );
+                        let error_message = match comparison {
+                            (CmpOperator::Superset, false) => format!(
+                                "Check was not compliant as property [{}] was missing values [{}]",
+                                from.resolved().unwrap().self_path(),
+                                SliceDisplay(to)
+                            ),
+                            (CmpOperator::Superset, true) => format!(
+                                "Check was not compliant as property [{}] contained values [{}]",
+                                from.resolved().unwrap().self_path(),
+                                SliceDisplay(to)
+                            ),
+                            (CmpOperator::Subset, true) => format!(
+                                "Check was not compliant as property [{}] was present in [{}]",
+                                from.resolved().unwrap().self_path(),
+                                SliceDisplay(to)
+                            ),
+                            (CmpOperator::InCidr, true) => format!(
+                                "Check was not compliant as property [{}] was within CIDR [{}]",
+                                from.resolved().unwrap().self_path(),
+                                SliceDisplay(to)
+                            ),
+                            _ => format!(
+                                "Check was not compliant as property [{}] was not present in [{}]",
+                                from.resolved().unwrap().self_path(),
+                                SliceDisplay(to)
+                            ),
+                        };
                         clauses.push(
                             ClauseReport::Clause(
                                 GuardClauseReport::Binary(
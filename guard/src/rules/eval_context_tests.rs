@@ -48,6 +48,7 @@ impl<'record, 'value, 'loc: 'value> EvalContext<'value, 'loc> for BasicQueryTest
     }
 
     fn root(&mut self) -> &'value PathAwareValue { self.root }
+    fn options(&self) -> &EvaluationOptions { &DEFAULT_EVALUATION_OPTIONS }
     fn rule_status(&mut self, rule_name: &str) -> Result<Status> { todo!() }
     fn resolve_variable(&mut self, variable_name: &str) -> Result<Vec<QueryResult<'value>>> { todo!() }
     fn add_variable_capture_key(&mut self, variable_name: &'value str, key: &'value PathAwareValue) -> Result<()> {
@@ -392,4 +393,49 @@ fn test_with_converter() -> Result<()> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn query_assignment_resolved_once_and_reused() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    let volumes := Resources.*.Properties.BlockDeviceMappings[*]
+    rule ebs_volumes_protected {
+        %volumes.Ebs EXISTS
+        %volumes.Ebs.Encrypted == true
+        %volumes.Ebs.DeleteOnTermination == true
+    }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "instance": {
+                "Type": "AWS::EC2::Instance",
+                "Properties": {
+                    "BlockDeviceMappings": [
+                        { "DeviceName": "/dev/sdm", "Ebs": { "Encrypted": true, "DeleteOnTermination": true } },
+                        { "DeviceName": "/dev/sdk", "Ebs": { "Encrypted": true, "DeleteOnTermination": true } }
+                    ]
+                }
+            }
+        }
+    }))?;
+    let mut root = root_scope(&rules, &value)?;
+    assert_eq!(crate::rules::eval::eval_rules_file(&rules, &mut root)?, Status::PASS);
+
+    let captured = root.scope.resolved_variables.get("volumes").cloned().unwrap();
+    assert_eq!(captured.len(), 2);
+
+    //
+    // With the query gone, every reference must be served from the captured values
+    //
+    root.scope.variable_queries.clear();
+    let resolved = root.resolve_variable("volumes")?;
+    assert_eq!(resolved.len(), 2);
+    for (first, second) in captured.iter().zip(resolved.iter()) {
+        match (first, second) {
+            (QueryResult::Resolved(first), QueryResult::Resolved(second)) =>
+                assert!(std::ptr::eq(*first, *second)),
+            rest => unreachable!("{:?}", rest),
+        }
+    }
+    assert_eq!(crate::rules::eval::eval_rule(&rules.guard_rules[0], &mut root)?, Status::PASS);
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use super::*;
-use crate::rules::eval_context::{root_scope, RecordTracker, EventRecord};
+use crate::rules::eval_context::{root_scope, reset_with, RecordTracker, EventRecord};
 use crate::rules::eval_context::eval_context_tests::BasicQueryTesting;
+use crate::rules::path_value::DEFAULT_EVALUATION_OPTIONS;
 use std::collections::HashMap;
 use grep_searcher::{SinkMatch, SearcherBuilder, LineStep};
 use grep_matcher::Match;
@@ -402,7 +403,7 @@ fn each_lhs_value_not_comparable() -> Result<()> {
     let rhs_query = AccessQuery::try_from("Parameters.allowed_images")?.query;
     let rhs = eval.query(&rhs_query)?;
     let result = each_lhs_compare(
-        compare_eq,
+        |lhs, rhs| compare_eq(lhs, rhs, &DEFAULT_EVALUATION_OPTIONS),
         lhs,
         &rhs
     )?;
@@ -422,7 +423,7 @@ fn each_lhs_value_not_comparable() -> Result<()> {
     }
 
     let result = each_lhs_compare(
-        in_cmp(true), // not in operation
+        in_cmp(true, &DEFAULT_EVALUATION_OPTIONS), // not in operation
         lhs,
         &rhs
     )?;
@@ -438,7 +439,7 @@ fn each_lhs_value_not_comparable() -> Result<()> {
     }
 
     let result = each_lhs_compare(
-        in_cmp(false), // in operation
+        in_cmp(false, &DEFAULT_EVALUATION_OPTIONS), // in operation
         lhs,
         &rhs
     )?;
@@ -488,7 +489,7 @@ fn each_lhs_value_eq_compare() -> Result<()> {
     let rhs = eval.query(&rhs_query)?;
     assert_eq!(rhs.len(), 2);
     let result = each_lhs_compare(
-        compare_eq,
+        |lhs, rhs| compare_eq(lhs, rhs, &DEFAULT_EVALUATION_OPTIONS),
         lhs,
         &rhs
     )?;
@@ -566,7 +567,7 @@ fn each_lhs_value_eq_compare_mixed_comparable() -> Result<()> {
         match each_lhs {
             QueryResult::Resolved(lhs) => {
                 for cmp_result in each_lhs_compare(
-                    not_compare(compare_eq, true),
+                    not_compare(compare_eq, true, &DEFAULT_EVALUATION_OPTIONS),
                     lhs,
                     &rhs_query_result)? {
                     match cmp_result {
@@ -629,7 +630,7 @@ fn each_lhs_value_eq_compare_mixed_single_plus_array_form_correct_exec() -> Resu
         match each_lhs {
             QueryResult::Resolved(lhs) => {
                 for cmp_result in each_lhs_compare(
-                    compare_eq,
+                    |lhs, rhs| compare_eq(lhs, rhs, &DEFAULT_EVALUATION_OPTIONS),
                     lhs,
                     &rhs_query_result)? {
                     match cmp_result {
@@ -670,7 +671,7 @@ macro_rules! test_case {
             match each_lhs {
                 QueryResult::Resolved(res) => {
                     for cmp_result in each_lhs_compare(
-                        $func,
+                        |lhs, rhs| $func(lhs, rhs, &DEFAULT_EVALUATION_OPTIONS),
                         res,
                         &[QueryResult::Resolved(&rhs_value)]
                     )? {
@@ -3157,7 +3158,10 @@ rule check_rest_api_private {
         }
     }"###;
 
-    let values = PathAwareValue::try_from(resources)?;
+    //
+    // repeats the Bool key, read as data the last one wins
+    //
+    let values = PathAwareValue::try_from(crate::rules::values::read_from(resources)?)?;
     let mut eval = BasicQueryTesting { root: &values, recorder: None };
     let status = eval_rule(&rule, &mut eval)?;
     assert_eq!(status, Status::FAIL);
@@ -3715,6 +3719,52 @@ fn test_string_in_comparison() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_captured_keys_are_local_to_rule() -> Result<()> {
+    let resources = r###"
+    Resources:
+      bucket:
+        Type: AWS::S3::Bucket
+      table:
+        Type: AWS::DynamoDB::Table
+    "###;
+    let value = PathAwareValue::try_from(
+        serde_yaml::from_str::<serde_yaml::Value>(resources)?)?;
+
+    //
+    // each rule sees only the keys it captured itself
+    //
+    let rules = r###"
+    rule buckets {
+        Resources[ name | Type == 'AWS::S3::Bucket' ] not empty
+        %name == 'bucket'
+    }
+    rule tables {
+        Resources[ name | Type == 'AWS::DynamoDB::Table' ] not empty
+        %name == 'table'
+    }
+    "###;
+
+    let rules_files = RulesFile::try_from(rules)?;
+    let mut eval = root_scope(&rules_files, &value)?;
+    let status = eval_rules_file(&rules_files, &mut eval)?;
+    assert_eq!(status, Status::PASS);
+
+    let rules = r###"
+    rule buckets {
+        Resources[ name | Type == 'AWS::S3::Bucket' ] not empty
+    }
+    rule uses_buckets_capture {
+        %name == 'bucket'
+    }
+    "###;
+    let rules_files = RulesFile::try_from(rules)?;
+    let mut eval = root_scope(&rules_files, &value)?;
+    assert!(eval_rules_file(&rules_files, &mut eval).is_err());
+
+    Ok(())
+}
+
 #[test]
 fn test_searcher() -> Result<()> {
     let resources = r###"
@@ -3769,3 +3819,1433 @@ fn yaml_loader() -> Result<()> {
     Ok(())
 }
 
+
+#[test]
+fn test_keys_in_map_variable() -> Result<()> {
+    let value = PathAwareValue::try_from(serde_yaml::from_str::<serde_yaml::Value>(r#"
+    Tags:
+      CostCenter: "1234"
+      Owner: team
+    "#)?)?;
+    let rules = RulesFile::try_from(r#"
+    let allowed = { CostCenter: "cc", Owner: "owner" }
+    let allowed_list = ["CostCenter"]
+    rule tags_allowed {
+        Tags[ keys IN %allowed ] !empty
+        Tags[ keys NOT_IN %allowed ] empty
+    }
+    rule tags_not_all_allowed {
+        Tags[ keys NOT_IN %allowed_list ] empty
+    }
+    "#)?;
+    let mut root = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) => (*name, *status),
+        _ => unreachable!()
+    }).collect::<Vec<_>>();
+    assert_eq!(statuses, vec![("tags_allowed", Status::PASS), ("tags_not_all_allowed", Status::FAIL)]);
+    Ok(())
+}
+
+#[test]
+fn test_compare_with_null() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule ebs_is_null { Volumes[*].Ebs == null }
+    rule ebs_not_null { Volumes[*].Ebs != null }
+    rule name_not_null { Name != null }
+    rule name_is_null { Name == null }
+    "#)?;
+    //
+    // data files are read with the YAML loader, plain null, Null and ~ are null values there
+    // while a quoted "null" is a string
+    //
+    let values = vec![
+        PathAwareValue::try_from(serde_json::json!({
+            "Name": "bucket",
+            "Volumes": [ { "Ebs": null } ]
+        }))?,
+        PathAwareValue::try_from(crate::rules::values::read_from(
+            r#"{ "Name": "bucket", "Volumes": [ { "Ebs": null } ] }"#)?)?,
+        PathAwareValue::try_from(crate::rules::values::read_from(r#"
+        Name: "null"
+        Volumes:
+          - Ebs: ~
+          - Ebs: Null
+        "#)?)?,
+    ];
+    for value in &values {
+        let mut root = root_scope(&rules, value)?;
+        eval_rules_file(&rules, &mut root)?;
+        let top = root.reset_recorder().extract();
+        let statuses = top.children.iter().map(|rule| match &rule.container {
+            Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) => (*name, *status),
+            _ => unreachable!()
+        }).collect::<Vec<_>>();
+        assert_eq!(statuses, vec![
+            ("ebs_is_null", Status::PASS),
+            ("ebs_not_null", Status::FAIL),
+            ("name_not_null", Status::PASS),
+            ("name_is_null", Status::FAIL),
+        ]);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_type_check_operators() -> Result<()> {
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "SecurityGroupIds": "sg-1",
+        "Tags": { "Env": "prod" },
+        "Port": 443,
+        "Ratio": 0.5,
+        "Name": "web"
+    }))?;
+    let rules = RulesFile::try_from(r#"
+    rule sg_is_list { SecurityGroupIds IS_LIST }
+    rule tags_is_map { Tags IS_MAP }
+    rule port_is_number { Port IS_NUMBER }
+    rule ratio_is_number { Ratio is_number }
+    rule name_not_number { Name !IS_NUMBER }
+    rule name_is_number { Name IS_NUMBER }
+    "#)?;
+    let mut root = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) => (*name, *status),
+        _ => unreachable!()
+    }).collect::<Vec<_>>();
+    assert_eq!(statuses, vec![
+        ("sg_is_list", Status::FAIL),
+        ("tags_is_map", Status::PASS),
+        ("port_is_number", Status::PASS),
+        ("ratio_is_number", Status::PASS),
+        ("name_not_number", Status::PASS),
+        ("name_is_number", Status::FAIL),
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_recursive_descent_query() -> Result<()> {
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "bucket": {
+                "Properties": {
+                    "Encryption": { "KmsKeyId": "key-1" }
+                }
+            },
+            "volumes": {
+                "Properties": {
+                    "Volumes": [ { "KmsKeyId": "key-2" }, { "Size": 10 } ]
+                }
+            }
+        }
+    }))?;
+    let rules = RulesFile::try_from(r#"
+    rule kms_keys_exist { Resources..KmsKeyId EXISTS }
+    rule kms_keys_are_one { Resources..KmsKeyId == "key-1" }
+    rule no_iops { Resources..Iops EXISTS }
+    "#)?;
+    let mut root = root_scope(&rules, &value)?;
+    let query = AccessQuery::try_from("Resources..KmsKeyId")?;
+    let selected = root.query(&query.query)?;
+    assert_eq!(selected.len(), 2);
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) => (*name, *status),
+        _ => unreachable!()
+    }).collect::<Vec<_>>();
+    assert_eq!(statuses, vec![
+        ("kms_keys_exist", Status::PASS),
+        ("kms_keys_are_one", Status::FAIL),
+        ("no_iops", Status::FAIL),
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_filter_not_query() -> Result<()> {
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "bucket": { "Type": "AWS::S3::Bucket", "Properties": { "Name": "b" } },
+            "queue": { "Type": "AWS::SQS::Queue", "Properties": { "Name": "q" } },
+            "topic": { "Type": "AWS::SNS::Topic", "Properties": { "Name": "t" } }
+        },
+        "Names": [ "a", "b", "c" ]
+    }))?;
+    let rules = RulesFile::try_from("rule names { Names EXISTS }")?;
+    let mut root = root_scope(&rules, &value)?;
+
+    let query = AccessQuery::try_from(r#"Resources.*[! Type == "AWS::S3::Bucket" ].Properties.Name"#)?;
+    let selected = root.query(&query.query)?.into_iter().map(|each| match each {
+        QueryResult::Resolved(PathAwareValue::String((_, name))) => name.as_str(),
+        rest => unreachable!("{:?}", rest),
+    }).collect::<Vec<_>>();
+    assert_eq!(selected, vec!["q", "t"]);
+
+    let query = AccessQuery::try_from(r#"Resources.*[! Type IN ["AWS::S3::Bucket", "AWS::SQS::Queue"] ]"#)?;
+    assert_eq!(root.query(&query.query)?.len(), 1);
+
+    let query = AccessQuery::try_from(r#"Names[! this == "b" ]"#)?;
+    assert_eq!(root.query(&query.query)?.len(), 2);
+    Ok(())
+}
+
+fn rule_checks(record: &EventRecord<'_>, rule_name: &str) -> usize {
+    let this = match &record.container {
+        Some(RecordType::RuleCheck(NamedStatus { name, .. })) if *name == rule_name => 1,
+        _ => 0,
+    };
+    this + record.children.iter().map(|child| rule_checks(child, rule_name)).sum::<usize>()
+}
+
+fn cached_rule_checks<'a>(record: &EventRecord<'a>, cached: &mut Vec<&'a str>) {
+    if let Some(RecordType::CachedRuleCheck(NamedStatus { name, .. })) = &record.container {
+        cached.push(*name);
+    }
+    for child in &record.children {
+        cached_rule_checks(child, cached);
+    }
+}
+
+#[test]
+fn test_named_rule_status_cached_per_document() -> Result<()> {
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Names": [ "a", "b" ]
+    }))?;
+    let rules = RulesFile::try_from(r#"
+    rule names_exist { Names EXISTS }
+    rule first_check {
+        names_exist
+    }
+    rule second_check {
+        names_exist
+        Names[*] IN ["a", "b"]
+    }
+    "#)?;
+    let mut root = root_scope(&rules, &value)?;
+    assert_eq!(eval_rules_file(&rules, &mut root)?, Status::PASS);
+    let top = root.reset_recorder().extract();
+    assert_eq!(rule_checks(&top, "names_exist"), 1);
+    let mut cached = Vec::new();
+    cached_rule_checks(&top, &mut cached);
+    assert_eq!(cached, vec!["names_exist", "names_exist"]);
+
+    let mut root = reset_with(root, &value);
+    assert_eq!(root.rule_status("second_check")?, Status::PASS);
+    let top = root.reset_recorder().extract();
+    assert_eq!(rule_checks(&top, "names_exist"), 1);
+    let mut cached = Vec::new();
+    cached_rule_checks(&top, &mut cached);
+    assert!(cached.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_named_rule_cycle_detected() -> Result<()> {
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Names": [ "a", "b" ]
+    }))?;
+    let rules = RulesFile::try_from(r#"
+    rule depends_on_second {
+        depends_on_first
+    }
+    rule depends_on_first {
+        depends_on_second
+    }
+    "#)?;
+    let mut root = root_scope(&rules, &value)?;
+    let error = eval_rules_file(&rules, &mut root).unwrap_err();
+    let message = format!("{}", error);
+    assert!(message.contains(
+        "Cycle detected between rules depends_on_first -> depends_on_second -> depends_on_first"),
+        "{}", message);
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    let error = rules.evaluate(&value, &scope).unwrap_err();
+    assert!(format!("{}", error).contains("Cycle detected between rules"));
+    Ok(())
+}
+
+#[test]
+fn test_when_guarded_block_skipped_when_condition_fails() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    let volumes = Resources.*.Properties.BlockDeviceMappings
+    rule ebs_volumes_encrypted {
+        when %volumes.*.Ebs != null {
+            %volumes.*.Ebs.Encrypted == true
+        }
+    }
+    "#)?;
+    let no_ebs = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "instance": {
+                "Type": "AWS::EC2::Instance",
+                "Properties": { "BlockDeviceMappings": [{ "DeviceName": "/dev/sdm", "Ebs": null }] }
+            }
+        }
+    }))?;
+    let mut root = root_scope(&rules, &no_ebs)?;
+    assert_eq!(eval_rules_file(&rules, &mut root)?, Status::SKIP);
+
+    let unencrypted = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "instance": {
+                "Type": "AWS::EC2::Instance",
+                "Properties": { "BlockDeviceMappings": [{ "DeviceName": "/dev/sdm", "Ebs": { "Encrypted": false } }] }
+            }
+        }
+    }))?;
+    let mut root = root_scope(&rules, &unencrypted)?;
+    assert_eq!(eval_rules_file(&rules, &mut root)?, Status::FAIL);
+    Ok(())
+}
+
+#[test]
+fn test_assignment_from_filtered_query() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    let buckets = Resources.*[ Type == "AWS::S3::Bucket" ]
+    rule buckets_encrypted_and_versioned when %buckets !empty {
+        %buckets.Properties.BucketEncryption EXISTS
+        %buckets.Properties.VersioningConfiguration.Status == "Enabled"
+    }
+    "#)?;
+    match &rules.assignments[0].value {
+        LetValue::AccessClause(query) => assert!(matches!(query.query[..], [_, _, QueryPart::Filter(..)])),
+        rest => unreachable!("{:?}", rest),
+    }
+
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "logs": {
+                "Type": "AWS::S3::Bucket",
+                "Properties": {
+                    "BucketEncryption": { "ServerSideEncryptionConfiguration": [] },
+                    "VersioningConfiguration": { "Status": "Enabled" }
+                }
+            },
+            "queue": { "Type": "AWS::SQS::Queue", "Properties": {} }
+        }
+    }))?;
+    let mut root = root_scope(&rules, &value)?;
+    assert_eq!(root.resolve_variable("buckets")?.len(), 1);
+    assert_eq!(eval_rules_file(&rules, &mut root)?, Status::PASS);
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    assert_eq!(rules.evaluate(&value, &scope)?, Status::PASS);
+
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "logs": {
+                "Type": "AWS::S3::Bucket",
+                "Properties": { "VersioningConfiguration": { "Status": "Suspended" } }
+            },
+            "queue": { "Type": "AWS::SQS::Queue", "Properties": {} }
+        }
+    }))?;
+    let mut root = root_scope(&rules, &value)?;
+    assert_eq!(eval_rules_file(&rules, &mut root)?, Status::FAIL);
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    assert_eq!(rules.evaluate(&value, &scope)?, Status::FAIL);
+    Ok(())
+}
+
+#[test]
+fn test_rule_when_condition_skips_instead_of_failing() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule lambda_checks when Transform EXISTS {
+        Resources.*[ Type == "AWS::Serverless::Function" ].Properties.Tracing == "Active"
+    }
+    rule has_resources {
+        Resources EXISTS
+    }
+    "#)?;
+    let counts = |value: &PathAwareValue| -> Result<(Vec<(String, Status)>, Vec<Status>)> {
+        let mut root = root_scope(&rules, value)?;
+        eval_rules_file(&rules, &mut root)?;
+        let top = root.reset_recorder().extract();
+        let mut statuses = Vec::new();
+        let mut conditions = Vec::new();
+        for rule in &top.children {
+            if let Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) = &rule.container {
+                statuses.push((name.to_string(), *status));
+            }
+            for child in &rule.children {
+                if let Some(RecordType::RuleCondition(status)) = &child.container {
+                    conditions.push(*status);
+                }
+            }
+        }
+        Ok((statuses, conditions))
+    };
+
+    let plain = PathAwareValue::try_from(serde_json::json!({
+        "Resources": { "bucket": { "Type": "AWS::S3::Bucket", "Properties": {} } }
+    }))?;
+    let (statuses, conditions) = counts(&plain)?;
+    assert_eq!(statuses, vec![
+        ("lambda_checks".to_string(), Status::SKIP),
+        ("has_resources".to_string(), Status::PASS),
+    ]);
+    assert_eq!(conditions, vec![Status::FAIL]);
+
+    let serverless = PathAwareValue::try_from(serde_json::json!({
+        "Transform": "AWS::Serverless-2016-10-31",
+        "Resources": {
+            "function": { "Type": "AWS::Serverless::Function", "Properties": { "Tracing": "PassThrough" } }
+        }
+    }))?;
+    let (statuses, conditions) = counts(&serverless)?;
+    assert_eq!(statuses, vec![
+        ("lambda_checks".to_string(), Status::FAIL),
+        ("has_resources".to_string(), Status::PASS),
+    ]);
+    assert_eq!(conditions, vec![Status::PASS]);
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &plain)?;
+    assert_eq!(rules.guard_rules[0].evaluate(&plain, &scope)?, Status::SKIP);
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &serverless)?;
+    assert_eq!(rules.guard_rules[0].evaluate(&serverless, &scope)?, Status::FAIL);
+    Ok(())
+}
+
+#[test]
+fn test_negated_type_block() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule no_allow_policies {
+        not AWS::IAM::Policy {
+            Properties.PolicyDocument.Statement[*].Effect == "Allow"
+        }
+    }
+    "#)?;
+    let policy = |effect: &str| serde_json::json!({
+        "Type": "AWS::IAM::Policy",
+        "Properties": { "PolicyDocument": { "Statement": [ { "Effect": effect } ] } }
+    });
+    let allowed = PathAwareValue::try_from(serde_json::json!({ "Resources": { "policy": policy("Allow") } }))?;
+    let denied = PathAwareValue::try_from(serde_json::json!({ "Resources": { "policy": policy("Deny") } }))?;
+    let no_policies = PathAwareValue::try_from(serde_json::json!({
+        "Resources": { "bucket": { "Type": "AWS::S3::Bucket", "Properties": {} } }
+    }))?;
+
+    for (value, expected) in [(&allowed, Status::FAIL), (&denied, Status::PASS), (&no_policies, Status::SKIP)] {
+        let mut root = root_scope(&rules, value)?;
+        assert_eq!(eval_rules_file(&rules, &mut root)?, expected);
+        let top = root.reset_recorder().extract();
+        let type_block = &top.children[0].children[0];
+        assert_eq!(type_block.context, "TypeBlock#not AWS::IAM::Policy");
+        assert!(matches!(&type_block.container,
+            Some(RecordType::TypeCheck(TypeBlockCheck { block: BlockCheck { status, .. }, .. })) if *status == expected));
+
+        let scope = crate::rules::evaluate::RootScope::new(&rules, value)?;
+        let type_block = &rules.guard_rules[0].block.conjunctions[0][0];
+        assert_eq!(type_block.evaluate(value, &scope)?, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_type_exists_checks() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule no_users {
+        AWS::IAM::User !EXISTS
+    }
+    rule has_vpc {
+        AWS::EC2::VPC EXISTS
+    }
+    "#)?;
+    let with_users = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "alice": { "Type": "AWS::IAM::User", "Properties": {} },
+            "bob": { "Type": "AWS::IAM::User", "Properties": {} }
+        }
+    }))?;
+    let with_vpc = PathAwareValue::try_from(serde_json::json!({
+        "Resources": { "vpc": { "Type": "AWS::EC2::VPC", "Properties": {} } }
+    }))?;
+
+    for (value, expected) in [(&with_users, [Status::FAIL, Status::FAIL]), (&with_vpc, [Status::PASS, Status::PASS])] {
+        let mut root = root_scope(&rules, value)?;
+        eval_rules_file(&rules, &mut root)?;
+        let top = root.reset_recorder().extract();
+        let statuses = top.children.iter().map(|rule| match &rule.container {
+            Some(RecordType::RuleCheck(NamedStatus { status, .. })) => *status,
+            rest => unreachable!("{:?}", rest),
+        }).collect::<Vec<_>>();
+        assert_eq!(statuses, expected);
+
+        let scope = crate::rules::evaluate::RootScope::new(&rules, value)?;
+        for (rule, status) in rules.guard_rules.iter().zip(expected.iter()) {
+            assert_eq!(rule.block.conjunctions[0][0].evaluate(value, &scope)?, *status);
+        }
+    }
+
+    let mut root = root_scope(&rules, &with_users)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let no_users = &top.children[0].children[0];
+    let failures = no_users.children.iter().filter_map(|each| match &each.container {
+        Some(RecordType::ClauseValueCheck(ClauseCheck::Unary(check))) => check.value.message.clone(),
+        _ => None,
+    }).collect::<Vec<_>>();
+    assert_eq!(failures, vec!["2 resource(s) of type AWS::IAM::User matched"; 2]);
+    Ok(())
+}
+
+#[test]
+fn test_max_depth_exceeded_is_an_error() -> Result<()> {
+    use crate::rules::path_value::EvaluationOptions;
+    let rules = RulesFile::try_from(r#"
+    rule tagged {
+        Resources.*.Properties.Tags[*].Key == "Owner"
+    }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "bucket": {
+                "Type": "AWS::S3::Bucket",
+                "Properties": { "Tags": [ { "Key": "Owner", "Value": "team" } ] }
+            }
+        }
+    }))?;
+
+    let mut root = root_scope(&rules, &value)?;
+    assert_eq!(eval_rules_file(&rules, &mut root)?, Status::PASS);
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    assert_eq!(rules.evaluate(&value, &scope)?, Status::PASS);
+
+    let mut options = EvaluationOptions::new();
+    options.max_depth = 3;
+    let mut root = root_scope(&rules, &value)?.with_options(options.clone());
+    match eval_rules_file(&rules, &mut root) {
+        Err(Error(ErrorKind::IncompatibleError(msg))) => {
+            assert_eq!(msg.contains("Maximum traversal depth 3 exceeded at path = /Resources/bucket/Properties/Tags"), true, "{}", msg);
+        },
+        rest => unreachable!("{:?}", rest),
+    }
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?.with_options(options);
+    match rules.evaluate(&value, &scope) {
+        Err(Error(ErrorKind::IncompatibleError(msg))) => {
+            assert_eq!(msg.contains("Maximum traversal depth 3 exceeded"), true, "{}", msg);
+        },
+        rest => unreachable!("{:?}", rest),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_count_comparisons() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    let roles = Resources.*[ Type == 'AWS::IAM::Role' ]
+
+    rule multi_az_subnets {
+        count(Resources.*[ Type == /Subnet/ ]) >= 2
+    }
+
+    rule single_statement_roles when count(%roles) > 0 {
+        %roles {
+            count(Properties.AssumeRolePolicyDocument.Statement[*]) == 1
+        }
+    }
+
+    rule no_admin_policies {
+        count(Resources.*[ Type == 'AWS::IAM::Role' ].Properties.ManagedPolicyArns[ this == /AdministratorAccess/ ]) == 0
+    }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_yaml::from_str::<serde_yaml::Value>(r#"
+    Resources:
+      subnetA:
+        Type: AWS::EC2::Subnet
+        Properties:
+          CidrBlock: 10.0.0.0/24
+      lambdaRole:
+        Type: AWS::IAM::Role
+        Properties:
+          AssumeRolePolicyDocument:
+            Statement:
+              - Effect: Allow
+                Principal:
+                  Service: lambda.amazonaws.com
+                Action: sts:AssumeRole
+          ManagedPolicyArns:
+            - arn:aws:iam::aws:policy/service-role/AWSLambdaBasicExecutionRole
+      adminRole:
+        Type: AWS::IAM::Role
+        Properties:
+          AssumeRolePolicyDocument:
+            Statement:
+              - Effect: Allow
+                Principal:
+                  Service: ec2.amazonaws.com
+                Action: sts:AssumeRole
+              - Effect: Allow
+                Principal:
+                  AWS: arn:aws:iam::123456789012:root
+                Action: sts:AssumeRole
+          ManagedPolicyArns:
+            - arn:aws:iam::aws:policy/AdministratorAccess
+    "#)?)?;
+
+    let expected = [Status::FAIL, Status::FAIL, Status::FAIL];
+    let mut root = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus { status, .. })) => *status,
+        rest => unreachable!("{:?}", rest),
+    }).collect::<Vec<_>>();
+    assert_eq!(statuses, expected);
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    for (rule, status) in rules.guard_rules.iter().zip(expected.iter()) {
+        assert_eq!(rule.evaluate(&value, &scope)?, *status);
+    }
+
+    //
+    // The failed comparison reports the count that was found
+    //
+    let multi_az = &top.children[0];
+    let mut records = vec![multi_az];
+    let mut found = None;
+    while let Some(record) = records.pop() {
+        if let Some(RecordType::ClauseValueCheck(ClauseCheck::Comparison(check))) = &record.container {
+            if check.comparison == (CmpOperator::Ge, false) {
+                found = check.from.resolved().map(|value| value.clone());
+            }
+        }
+        records.extend(record.children.iter());
+    }
+    assert_eq!(found, Some(PathAwareValue::Int((crate::rules::path_value::Path::root(), 1))));
+
+    let compliant = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "subnetA": { "Type": "AWS::EC2::Subnet", "Properties": {} },
+            "subnetB": { "Type": "AWS::EC2::Subnet", "Properties": {} }
+        }
+    }))?;
+    let expected = [Status::PASS, Status::SKIP, Status::PASS];
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &compliant)?;
+    for (rule, status) in rules.guard_rules.iter().zip(expected.iter()) {
+        assert_eq!(rule.evaluate(&compliant, &scope)?, *status);
+    }
+    let mut root = root_scope(&rules, &compliant)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus { status, .. })) => *status,
+        rest => unreachable!("{:?}", rest),
+    }).collect::<Vec<_>>();
+    assert_eq!(statuses, expected);
+    Ok(())
+}
+
+#[test]
+fn test_empty_on_literal_variable() -> Result<()> {
+    let value = PathAwareValue::try_from(serde_json::json!({ "spec": { "replicas": 1 } }))?;
+    let rules = r###"
+    let none = []
+    let some = [1, 2]
+    let nothing = {}
+    rule skipped when %none not empty {
+        spec.replicas == 2
+    }
+    rule checked when %some not empty {
+        spec.replicas == 1
+    }
+    rule empty_map {
+        %nothing empty
+        %none empty
+    }
+    "###;
+    let rules_files = RulesFile::try_from(rules)?;
+    let mut eval = root_scope(&rules_files, &value)?;
+    assert_eq!(eval_rules_file(&rules_files, &mut eval)?, Status::PASS);
+    let top = eval.reset_recorder().extract();
+    let statuses = top.children.iter().map(|each| match &each.container {
+        Some(RecordType::RuleCheck(NamedStatus { status, .. })) => *status,
+        rest => unreachable!("{:?}", rest),
+    }).collect::<Vec<Status>>();
+    assert_eq!(statuses, vec![Status::SKIP, Status::PASS, Status::PASS]);
+    Ok(())
+}
+
+#[test]
+fn test_case_insensitive_keys() -> Result<()> {
+    use crate::rules::path_value::EvaluationOptions;
+    let rules = RulesFile::try_from(r#"
+    rule versioned {
+        Resources.*.Properties.versioningconfiguration.Status == "Enabled"
+    }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "bucket": {
+                "Type": "AWS::S3::Bucket",
+                "Properties": { "VersioningConfiguration": { "Status": "Enabled" } }
+            }
+        }
+    }))?;
+
+    let mut root = root_scope(&rules, &value)?;
+    assert_eq!(eval_rules_file(&rules, &mut root)?, Status::FAIL);
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    assert_eq!(rules.evaluate(&value, &scope)?, Status::FAIL);
+
+    let mut options = EvaluationOptions::new();
+    options.case_insensitive_keys = true;
+    let mut root = root_scope(&rules, &value)?.with_options(options.clone());
+    assert_eq!(eval_rules_file(&rules, &mut root)?, Status::PASS);
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?.with_options(options);
+    assert_eq!(rules.evaluate(&value, &scope)?, Status::PASS);
+    Ok(())
+}
+
+#[test]
+fn test_let_capture_region_from_arn() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    let region := Resources.*[ Type == 'AWS::SNS::Subscription' ].Properties.TopicArn MATCH /arn:aws:[^:]+:([^:]+):/
+    let unmatched := Resources.*[ Type == 'AWS::SQS::QueuePolicy' ].Properties.TopicArn MATCH /arn:aws:[^:]+:([^:]+):/
+
+    rule in_us_east_1 {
+        %region == "us-east-1"
+    }
+    rule in_eu {
+        %region IN ["eu-west-1", "eu-central-1"]
+    }
+    rule unmatched_skips {
+        %unmatched == "us-east-1"
+    }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "alerts": {
+                "Type": "AWS::SNS::Subscription",
+                "Properties": { "TopicArn": "arn:aws:sns:us-east-1:123456789012:alerts" }
+            },
+            "policy": {
+                "Type": "AWS::SQS::QueuePolicy",
+                "Properties": { "TopicArn": "not-an-arn" }
+            }
+        }
+    }))?;
+
+    let mut root = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|each| match &each.container {
+        Some(RecordType::RuleCheck(NamedStatus { status, .. })) => *status,
+        rest => unreachable!("{:?}", rest),
+    }).collect::<Vec<Status>>();
+    assert_eq!(statuses, vec![Status::PASS, Status::FAIL, Status::SKIP]);
+
+    let region = root.resolve_variable("region")?;
+    assert_eq!(region.len(), 1);
+    let region = region[0].resolved().unwrap();
+    assert_eq!(region.as_string(), Some("us-east-1"));
+    assert_eq!(region.self_path().0, "/Resources/alerts/Properties/TopicArn");
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    assert_eq!(scope.resolve_variable("region")?.iter().map(|each| each.as_string()).collect::<Vec<_>>(),
+               vec![Some("us-east-1")]);
+    assert!(scope.resolve_variable("unmatched")?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_root_anchor_from_type_block() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule tagged_with_env {
+        Resources.*[ Type == 'AWS::S3::Bucket' ] {
+            Properties.Tags[*].Value == $.Parameters.Env.Default
+            $.Resources.vpc exists
+        }
+    }
+    "#)?;
+    let template = |tag: &str| PathAwareValue::try_from(serde_json::json!({
+        "Parameters": { "Env": { "Type": "String", "Default": "prod" } },
+        "Resources": {
+            "vpc": { "Type": "AWS::EC2::VPC", "Properties": {} },
+            "bucket": {
+                "Type": "AWS::S3::Bucket",
+                "Properties": { "Tags": [{ "Key": "Env", "Value": tag }] }
+            }
+        }
+    }));
+
+    for (tag, expected) in [("prod", Status::PASS), ("dev", Status::FAIL)] {
+        let value = template(tag)?;
+        let mut root = root_scope(&rules, &value)?;
+        assert_eq!(eval_rules_file(&rules, &mut root)?, expected);
+
+        let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+        assert_eq!(rules.evaluate(&value, &scope)?, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_resource_ref_across_resources() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule profiles_use_known_roles {
+        Resources.*[ Type == 'AWS::IAM::InstanceProfile' ] {
+            Properties.Roles[*] == RESOURCE_REF(AWS::IAM::Role, Properties.RoleName)
+        }
+    }
+    "#)?;
+    let template = |role: &str| PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "app": { "Type": "AWS::IAM::Role", "Properties": { "RoleName": "app-role" } },
+            "batch": { "Type": "AWS::IAM::Role", "Properties": { "RoleName": "batch-role" } },
+            "bucket": { "Type": "AWS::S3::Bucket", "Properties": { "RoleName": "bucket-role" } },
+            "profile": {
+                "Type": "AWS::IAM::InstanceProfile",
+                "Properties": { "Roles": ["app-role", role] }
+            }
+        }
+    }));
+
+    for (role, expected) in [("batch-role", Status::PASS), ("bucket-role", Status::FAIL)] {
+        let value = template(role)?;
+        let mut root = root_scope(&rules, &value)?;
+        assert_eq!(eval_rules_file(&rules, &mut root)?, expected);
+
+        let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+        assert_eq!(rules.evaluate(&value, &scope)?, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_in_range_literals() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule sizes { Resources.*.Properties.Size IN r[1, 100] }
+    rule ratios { Resources.*.Properties.Ratio IN r[0, 1.5) }
+    rule offsets { Resources.*.Properties.Offset NOT IN r(-10, 0] }
+    rule ratios_in_ints { Resources.*.Properties.Ratio IN r[0, 1] }
+    "#)?;
+    //
+    // plain decimal and negative numbers in data load as numbers
+    //
+    let template = |size: i64| -> Result<PathAwareValue> {
+        PathAwareValue::try_from(crate::rules::values::read_from(&format!(r#"
+        Resources:
+          a:
+            Properties:
+              Size: {}
+              Ratio: 0.5
+              Offset: -12
+          b:
+            Properties:
+              Size: 1
+              Ratio: 1.25
+              Offset: 3
+        "#, size))?)
+    };
+
+    for (size, sizes) in [(100, Status::PASS), (101, Status::FAIL)] {
+        let expected = vec![
+            ("sizes", sizes),
+            ("ratios", Status::PASS),
+            ("offsets", Status::PASS),
+            ("ratios_in_ints", Status::FAIL),
+        ];
+        let value = template(size)?;
+        let mut root = root_scope(&rules, &value)?;
+        eval_rules_file(&rules, &mut root)?;
+        let top = root.reset_recorder().extract();
+        let statuses = top.children.iter().map(|rule| match &rule.container {
+            Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) => (*name, *status),
+            _ => unreachable!()
+        }).collect::<Vec<_>>();
+        assert_eq!(statuses, expected);
+
+        let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+        for (rule, (_, status)) in rules.guard_rules.iter().zip(expected) {
+            assert_eq!(rule.evaluate(&value, &scope)?, status, "{}", rule.rule_name);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_unique_operator() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule cidrs_unique { Resources.*.Properties.CidrBlock UNIQUE }
+    rule cidrs_repeat { Resources.*.Properties.CidrBlock !UNIQUE }
+    rule not_cidrs_unique { NOT Resources.*.Properties.CidrBlock UNIQUE }
+    rule ports_unique { Resources.sg.Properties.Ports unique }
+    rule ingress_unique { Resources.sg.Properties.Ingress[*] UNIQUE }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "a": { "Type": "AWS::EC2::VPC", "Properties": { "CidrBlock": "10.0.0.0/16" } },
+            "b": { "Type": "AWS::EC2::VPC", "Properties": { "CidrBlock": "10.1.0.0/16" } },
+            "c": { "Type": "AWS::EC2::VPC", "Properties": { "CidrBlock": "10.0.0.0/16" } },
+            "sg": {
+                "Type": "AWS::EC2::SecurityGroup",
+                "Properties": {
+                    "Ports": [80, 443],
+                    "Ingress": [
+                        { "CidrIp": "0.0.0.0/0", "FromPort": 443 },
+                        { "CidrIp": "10.0.0.0/8", "FromPort": 443 },
+                        { "FromPort": 443, "CidrIp": "0.0.0.0/0" }
+                    ]
+                }
+            }
+        }
+    }))?;
+    let expected = vec![
+        ("cidrs_unique", Status::FAIL),
+        ("cidrs_repeat", Status::PASS),
+        ("not_cidrs_unique", Status::PASS),
+        ("ports_unique", Status::PASS),
+        ("ingress_unique", Status::FAIL),
+    ];
+
+    let mut root = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) => (*name, *status),
+        _ => unreachable!()
+    }).collect::<Vec<_>>();
+    assert_eq!(statuses, expected);
+
+    //
+    // the repeated value fails, naming the value it repeats
+    //
+    let failed = top.children[0].children[0].children.iter()
+        .filter_map(|each| match &each.container {
+            Some(RecordType::ClauseValueCheck(ClauseCheck::Unary(check))) => Some(check),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(failed.len(), 1);
+    match &failed[0].value.from {
+        QueryResult::Resolved(repeated) => assert_eq!(repeated.self_path().0, "/Resources/c/Properties/CidrBlock"),
+        rest => unreachable!("{:?}", rest),
+    }
+    let message = failed[0].value.message.as_deref().unwrap_or("");
+    assert!(message.contains(r#""10.0.0.0/16" repeats the value at /Resources/a/Properties/CidrBlock"#), "{}", message);
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    for (rule, (_, status)) in rules.guard_rules.iter().zip(expected) {
+        assert_eq!(rule.evaluate(&value, &scope)?, status, "{}", rule.rule_name);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_missing_as_for_empty() -> Result<()> {
+    use crate::rules::path_value::MissingAs;
+    let rules = RulesFile::try_from(r#"
+    rule tags_empty { Resources.bucket.Properties.Tags EMPTY }
+    rule tags_not_empty { Resources.bucket.Properties.Tags !EMPTY }
+    "#)?;
+    let absent = serde_json::json!({ "Type": "AWS::S3::Bucket", "Properties": {} });
+    let empty = serde_json::json!({ "Type": "AWS::S3::Bucket", "Properties": { "Tags": [] } });
+    let tagged = serde_json::json!({
+        "Type": "AWS::S3::Bucket",
+        "Properties": { "Tags": [{ "Key": "team", "Value": "storage" }] }
+    });
+    let cases = [
+        (absent.clone(), MissingAs::Skip, [Status::SKIP, Status::FAIL]),
+        (absent, MissingAs::Fail, [Status::FAIL, Status::FAIL]),
+        (empty.clone(), MissingAs::Skip, [Status::PASS, Status::FAIL]),
+        (empty, MissingAs::Fail, [Status::PASS, Status::FAIL]),
+        (tagged.clone(), MissingAs::Skip, [Status::FAIL, Status::PASS]),
+        (tagged, MissingAs::Fail, [Status::FAIL, Status::PASS]),
+    ];
+    for (bucket, missing_as, expected) in cases {
+        let value = PathAwareValue::try_from(serde_json::json!({ "Resources": { "bucket": bucket } }))?;
+        let mut options = EvaluationOptions::new();
+        options.missing_as = missing_as;
+        let mut root = root_scope(&rules, &value)?.with_options(options.clone());
+        eval_rules_file(&rules, &mut root)?;
+        let top = root.reset_recorder().extract();
+        let statuses = top.children.iter().map(|rule| match &rule.container {
+            Some(RecordType::RuleCheck(NamedStatus { status, .. })) => *status,
+            _ => unreachable!()
+        }).collect::<Vec<_>>();
+        assert_eq!(statuses, expected, "{:?} {:?}", missing_as, value);
+
+        let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?.with_options(options);
+        for (rule, status) in rules.guard_rules.iter().zip(expected) {
+            assert_eq!(rule.evaluate(&value, &scope)?, status, "{} {:?}", rule.rule_name, missing_as);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_subset_superset_operators() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    let required_azs = ["us-east-1a", "us-east-1b"]
+    rule protocols_subset { Resources.sg.Properties.SecurityGroupIngress[*].IpProtocol SUBSET ["tcp", "udp", "icmp"] }
+    rule protocols_not_subset { Resources.sg.Properties.SecurityGroupIngress[*].IpProtocol NOT SUBSET ["tcp", "udp"] }
+    rule ports_subset { Resources.sg.Properties.Ports subset [22, 443] }
+    rule azs_superset { Resources.*.Properties.AvailabilityZones SUPERSET %required_azs }
+    rule azs_not_superset { Resources.b.Properties.AvailabilityZones !SUPERSET %required_azs }
+    rule scalar_subset { Resources.a.Type SUBSET ["AWS::EC2::Subnet"] }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "a": { "Type": "AWS::EC2::Subnet", "Properties": { "AvailabilityZones": ["us-east-1a", "us-east-1b", "us-east-1c"] } },
+            "b": { "Type": "AWS::EC2::Subnet", "Properties": { "AvailabilityZones": ["us-east-1a"] } },
+            "sg": {
+                "Type": "AWS::EC2::SecurityGroup",
+                "Properties": {
+                    "Ports": [22, 80],
+                    "SecurityGroupIngress": [{ "IpProtocol": "tcp" }, { "IpProtocol": "icmp" }]
+                }
+            }
+        }
+    }))?;
+    let expected = vec![
+        ("protocols_subset", Status::PASS),
+        ("protocols_not_subset", Status::PASS),
+        ("ports_subset", Status::FAIL),
+        ("azs_superset", Status::FAIL),
+        ("azs_not_superset", Status::PASS),
+        ("scalar_subset", Status::PASS),
+    ];
+
+    let mut root = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) => (*name, *status),
+        _ => unreachable!()
+    }).collect::<Vec<_>>();
+    assert_eq!(statuses, expected);
+
+    //
+    // SUBSET reports the values that are not in the RHS, SUPERSET the collection and the
+    // values it is missing
+    //
+    let failed = |index: usize| top.children[index].children[0].children.iter()
+        .filter_map(|each| match &each.container {
+            Some(RecordType::ClauseValueCheck(ClauseCheck::InComparison(check))) => Some(check.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let ports = failed(2);
+    assert_eq!(ports.len(), 1);
+    assert_eq!(ports[0].from.resolved().unwrap().self_path().0, "/Resources/sg/Properties/Ports/1");
+
+    let azs = failed(3);
+    assert_eq!(azs.len(), 1);
+    assert_eq!(azs[0].from.resolved().unwrap().self_path().0, "/Resources/b/Properties/AvailabilityZones");
+    assert_eq!(azs[0].to.iter().map(|each| format!("{}", ValueOnlyDisplay(each.resolved().unwrap()))).collect::<Vec<_>>(),
+               vec![r#""us-east-1b""#]);
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    for (rule, (_, status)) in rules.guard_rules.iter().zip(expected) {
+        assert_eq!(rule.evaluate(&value, &scope)?, status, "{}", rule.rule_name);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_all_in_operator() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    let allowed = ["tcp", "udp"]
+    let protocols = ["tcp", "udp"]
+    rule all_in { Resources.*.Properties.Protocols ALL IN %allowed }
+    rule none_in { Resources.sg.Properties.Protocols NOT ALL IN ["tcp", "icmp"] }
+    rule scalar_all_in { Resources.sg.Properties.Single all in %allowed }
+    rule literal_all_in { %protocols ALL IN ["tcp", "udp", "icmp"] }
+    rule keys_all_in { Resources[ keys ALL IN ["sg", "web"] ] EXISTS }
+    rule list_rhs_all_in { Resources.web.Properties.Protocols ALL IN [["tcp", "udp"]] }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "sg": { "Type": "AWS::EC2::SecurityGroup", "Properties": { "Protocols": ["tcp", "tcp"], "Single": "udp" } },
+            "web": { "Type": "AWS::EC2::SecurityGroup", "Properties": { "Protocols": ["tcp", "icmp", "udp"] } }
+        }
+    }))?;
+    let expected = vec![
+        ("all_in", Status::FAIL),
+        ("none_in", Status::FAIL),
+        ("scalar_all_in", Status::PASS),
+        ("literal_all_in", Status::PASS),
+        ("keys_all_in", Status::PASS),
+        ("list_rhs_all_in", Status::FAIL),
+    ];
+
+    let mut root = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) => (*name, *status),
+        _ => unreachable!()
+    }).collect::<Vec<_>>();
+    assert_eq!(statuses, expected);
+
+    //
+    // each element is checked on its own, only the one not in the RHS fails
+    //
+    let failed = top.children[0].children[0].children.iter()
+        .filter_map(|each| match &each.container {
+            Some(RecordType::ClauseValueCheck(ClauseCheck::InComparison(check))) => Some(check),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].from.resolved().unwrap().self_path().0, "/Resources/web/Properties/Protocols/1");
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    for (rule, (_, status)) in rules.guard_rules.iter().zip(expected) {
+        assert_eq!(rule.evaluate(&value, &scope)?, status, "{}", rule.rule_name);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_equals_unordered_operator() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule ordered_equal { Resources.a.Properties.Zones EQUALS_UNORDERED ["us-east-1a", "us-east-1b"] }
+    rule reordered_equal { Resources.b.Properties.Zones EQUALS_UNORDERED ["us-east-1a", "us-east-1b"] }
+    rule reordered_positional { Resources.b.Properties.Zones == ["us-east-1a", "us-east-1b"] }
+    rule differing_length { Resources.c.Properties.Zones equals_unordered ["us-east-1a", "us-east-1b"] }
+    rule not_equal_unordered { Resources.c.Properties.Zones NOT EQUALS_UNORDERED ["us-east-1a", "us-east-1b"] }
+    rule query_unordered { Resources.a.Properties.Zones EQUALS_UNORDERED Resources.b.Properties.Zones }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "a": { "Type": "AWS::EC2::Subnet", "Properties": { "Zones": ["us-east-1a", "us-east-1b"] } },
+            "b": { "Type": "AWS::EC2::Subnet", "Properties": { "Zones": ["us-east-1b", "us-east-1a"] } },
+            "c": { "Type": "AWS::EC2::Subnet", "Properties": { "Zones": ["us-east-1b", "us-east-1a", "us-east-1a"] } }
+        }
+    }))?;
+    let expected = vec![
+        ("ordered_equal", Status::PASS),
+        ("reordered_equal", Status::PASS),
+        ("reordered_positional", Status::FAIL),
+        ("differing_length", Status::FAIL),
+        ("not_equal_unordered", Status::PASS),
+        ("query_unordered", Status::PASS),
+    ];
+
+    let mut root = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) => (*name, *status),
+        _ => unreachable!()
+    }).collect::<Vec<_>>();
+    assert_eq!(statuses, expected);
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    for (rule, (_, status)) in rules.guard_rules.iter().zip(expected) {
+        assert_eq!(rule.evaluate(&value, &scope)?, status, "{}", rule.rule_name);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_this_cross_field_comparison() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    let asgs = Resources.*[ Type == "AWS::AutoScaling::AutoScalingGroup" ]
+    rule block_all { Resources.*[ Type == "AWS::AutoScaling::AutoScalingGroup" ] { this.Properties.MinSize <= this.Properties.MaxSize } }
+    rule variable_block { %asgs.Properties { this.MinSize <= this.MaxSize } }
+    rule compliant_only { Resources.compliant.Properties { this.MinSize <= this.MaxSize } }
+    rule filter_compliant { Resources.*[ this.Properties.MinSize <= this.Properties.MaxSize ] NOT EMPTY }
+    rule filter_none_swapped { Resources.*[ Type == "AWS::AutoScaling::AutoScalingGroup"
+                                            this.Properties.MinSize > this.Properties.MaxSize ] EMPTY }
+    "#)?;
+    //
+    // Each element compares its own MinSize against its own MaxSize, swapped fails even though
+    // its MaxSize is larger than the MinSize of compliant
+    //
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "compliant": { "Type": "AWS::AutoScaling::AutoScalingGroup", "Properties": { "MinSize": 1, "MaxSize": 5 } },
+            "swapped": { "Type": "AWS::AutoScaling::AutoScalingGroup", "Properties": { "MinSize": 10, "MaxSize": 2 } }
+        }
+    }))?;
+    let expected = vec![
+        ("block_all", Status::FAIL),
+        ("variable_block", Status::FAIL),
+        ("compliant_only", Status::PASS),
+        ("filter_compliant", Status::PASS),
+        ("filter_none_swapped", Status::FAIL),
+    ];
+
+    let mut root = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) => (*name, *status),
+        _ => unreachable!()
+    }).collect::<Vec<_>>();
+    assert_eq!(statuses, expected);
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    for (rule, (_, status)) in rules.guard_rules.iter().zip(expected) {
+        assert_eq!(rule.evaluate(&value, &scope)?, status, "{}", rule.rule_name);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_negated_keys_filter() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    let allowed = ["Name", "Owner"]
+    rule keys_not_in_token { Tags[ KEYS NOT_IN ["Name", "Owner"] ] NOT EMPTY }
+    rule keys_not_in_spaced { Tags[ keys not in %allowed ] NOT EMPTY }
+    rule keys_bang_in { Tags[ KEYS !IN ["Name", "Owner", "CostCenter"] ] EMPTY }
+    rule keys_not_eq { Tags[ KEYS != /^[A-Z]/ ] EMPTY }
+    rule keys_in { Tags[ KEYS IN %allowed ] NOT EMPTY }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Tags": { "Name": "app", "Owner": "team", "CostCenter": "42" }
+    }))?;
+    let expected = vec![
+        ("keys_not_in_token", Status::PASS),
+        ("keys_not_in_spaced", Status::PASS),
+        ("keys_bang_in", Status::PASS),
+        ("keys_not_eq", Status::PASS),
+        ("keys_in", Status::PASS),
+    ];
+
+    let mut root = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) => (*name, *status),
+        _ => unreachable!()
+    }).collect::<Vec<_>>();
+    assert_eq!(statuses, expected);
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    for (rule, (_, status)) in rules.guard_rules.iter().zip(expected) {
+        assert_eq!(rule.evaluate(&value, &scope)?, status, "{}", rule.rule_name);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_in_cidr_operator() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    let private = ["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16"]
+    rule private_only { Ingress[*].CidrIp IN_CIDR %private }
+    rule internal_only { Internal[*].CidrIp IN_CIDR %private }
+    rule not_open { Internal[*].CidrIp NOT IN_CIDR "0.0.0.0/1" }
+    rule narrow { cidr_prefix(Internal[*].CidrIp) >= 16 }
+    rule wide_ingress { cidr_prefix(Ingress[*].CidrIp) >= 16 }
+    rule v6_inside { Ingress[*].CidrIpv6 in_cidr "2001:db8::/32" }
+    rule invalid { Invalid[*].CidrIp IN_CIDR "0.0.0.0/0" }
+    rule invalid_negated { Invalid[*].CidrIp !IN_CIDR "10.0.0.0/8" }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Ingress": [
+            { "CidrIp": "10.1.2.0/24", "CidrIpv6": "2001:db8:1::/48" },
+            { "CidrIp": "0.0.0.0/0", "CidrIpv6": "2001:db8::1" }
+        ],
+        "Internal": [ { "CidrIp": "192.168.10.0/24" }, { "CidrIp": "172.16.0.1" } ],
+        "Invalid": [ { "CidrIp": "10.0.0.300/8" } ]
+    }))?;
+    let expected = vec![
+        ("private_only", Status::FAIL),
+        ("internal_only", Status::PASS),
+        ("not_open", Status::PASS),
+        ("narrow", Status::PASS),
+        ("wide_ingress", Status::FAIL),
+        ("v6_inside", Status::PASS),
+        ("invalid", Status::FAIL),
+        ("invalid_negated", Status::FAIL),
+    ];
+
+    let mut root = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) => (*name, *status),
+        _ => unreachable!()
+    }).collect::<Vec<_>>();
+    assert_eq!(statuses, expected);
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    for (rule, (_, status)) in rules.guard_rules.iter().zip(expected) {
+        assert_eq!(rule.evaluate(&value, &scope)?, status, "{}", rule.rule_name);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_regex_flags() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule substring { Env == /prod/ }
+    rule full_match { Env == /prod/f }
+    rule case_sensitive { Flag == /true/ }
+    rule case_insensitive { Flag == /true/i }
+    rule full_case_insensitive { Flag == /true/if }
+    rule line_anchored { Script == /^set -e$/m }
+    rule line_anchored_without_m { Script == /^set -e$/ }
+    rule dot_all { Script == /bash.set/s }
+    rule dot_all_without_s { Script == /bash.set/ }
+    rule keys_case_insensitive { Tags[ KEYS == /^name$/i ] NOT EMPTY }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Env": "preprod-1",
+        "Flag": "True",
+        "Script": "#!/bin/bash\nset -e\necho done",
+        "Tags": { "Name": "app" }
+    }))?;
+    let expected = vec![
+        ("substring", Status::PASS),
+        ("full_match", Status::FAIL),
+        ("case_sensitive", Status::FAIL),
+        ("case_insensitive", Status::PASS),
+        ("full_case_insensitive", Status::PASS),
+        ("line_anchored", Status::PASS),
+        ("line_anchored_without_m", Status::FAIL),
+        ("dot_all", Status::PASS),
+        ("dot_all_without_s", Status::FAIL),
+        ("keys_case_insensitive", Status::PASS),
+    ];
+
+    let mut root = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) => (*name, *status),
+        _ => unreachable!()
+    }).collect::<Vec<_>>();
+    assert_eq!(statuses, expected);
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    for (rule, (_, status)) in rules.guard_rules.iter().zip(expected) {
+        assert_eq!(rule.evaluate(&value, &scope)?, status, "{}", rule.rule_name);
+    }
+    Ok(())
+}
+
+
+#[test]
+fn test_resource_count() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule single_vpc { AWS::EC2::VPC RESOURCE_COUNT == 1 }
+    rule at_most_two_balancers { AWS::ElasticLoadBalancingV2::LoadBalancer RESOURCE_COUNT <= 2 }
+    rule no_topics { AWS::SNS::Topic RESOURCE_COUNT == 0 }
+    rule counted_in_type_block { AWS::EC2::Subnet { AWS::EC2::VPC RESOURCE_COUNT > 1 } }
+    rule guarded when AWS::EC2::VPC RESOURCE_COUNT == 1 { Resources NOT EMPTY }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "vpc": { "Type": "AWS::EC2::VPC" },
+            "subnet": { "Type": "AWS::EC2::Subnet" },
+            "lb1": { "Type": "AWS::ElasticLoadBalancingV2::LoadBalancer" },
+            "lb2": { "Type": "AWS::ElasticLoadBalancingV2::LoadBalancer" },
+            "lb3": { "Type": "AWS::ElasticLoadBalancingV2::LoadBalancer" }
+        }
+    }))?;
+    let expected = vec![
+        ("single_vpc", Status::PASS),
+        ("at_most_two_balancers", Status::FAIL),
+        ("no_topics", Status::PASS),
+        ("counted_in_type_block", Status::FAIL),
+        ("guarded", Status::PASS),
+    ];
+
+    let mut root = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) => (*name, *status),
+        _ => unreachable!()
+    }).collect::<Vec<_>>();
+    assert_eq!(statuses, expected);
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    assert_eq!(scope.resource_count("AWS::ElasticLoadBalancingV2::LoadBalancer"), 3);
+    assert_eq!(scope.resource_count("AWS::SNS::Topic"), 0);
+    for (rule, (_, status)) in rules.guard_rules.iter().zip(expected) {
+        assert_eq!(rule.evaluate(&value, &scope)?, status, "{}", rule.rule_name);
+    }
+
+    let empty = PathAwareValue::try_from(serde_json::json!({}))?;
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &empty)?;
+    assert_eq!(scope.resource_count("AWS::EC2::VPC"), 0);
+    Ok(())
+}
+
+#[test]
+fn test_quoted_property_names() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule source_vpc { Condition.StringEquals.'aws:SourceVpc' == "vpc-123" }
+    rule dotted_tag { Tags."my.dotted.key" EXISTS }
+    rule not_split { Tags.my.dotted.key EXISTS }
+    rule spaced_keys { Settings.*."Max Size" <= 10 }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Condition": { "StringEquals": { "aws:SourceVpc": "vpc-123" } },
+        "Tags": { "my.dotted.key": "yes" },
+        "Settings": { "first": { "Max Size": 5 }, "second": { "Max Size": 20 } }
+    }))?;
+    let expected = vec![
+        ("source_vpc", Status::PASS),
+        ("dotted_tag", Status::PASS),
+        ("not_split", Status::FAIL),
+        ("spaced_keys", Status::FAIL),
+    ];
+
+    let mut root = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) => (*name, *status),
+        _ => unreachable!()
+    }).collect::<Vec<_>>();
+    assert_eq!(statuses, expected);
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    for (rule, (_, status)) in rules.guard_rules.iter().zip(expected) {
+        assert_eq!(rule.evaluate(&value, &scope)?, status, "{}", rule.rule_name);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_key_regex_segments() -> Result<()> {
+    let rules = RulesFile::try_from(r#"
+    rule source_keys { Condition./aws:[sS]ource(Vpc|Vpce)$/ EXISTS }
+    rule source_values { Condition./aws:[sS]ource(Vpc|Vpce)$/ == /^vpc/ }
+    rule source_vpc_only { Condition./aws:[sS]ourceVpc$/ == /^vpc-/ }
+    rule no_key_matches { Condition./^nothing/ EXISTS }
+    rule nested { Statement[*].Condition.*./^aws:/ == "o-123" }
+    "#)?;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Condition": { "aws:SourceVpc": "vpc-1", "aws:sourceVpce": "vpce-1", "aws:SourceIp": "10.0.0.1" },
+        "Statement": [
+            { "Condition": { "StringEquals": { "aws:PrincipalOrgID": "o-123" } } },
+            { "Condition": { "StringLike": { "aws:PrincipalOrgID": "o-123", "s3:prefix": "home/" } } }
+        ]
+    }))?;
+    let expected = vec![
+        ("source_keys", Status::PASS),
+        ("source_values", Status::PASS),
+        ("source_vpc_only", Status::PASS),
+        ("no_key_matches", Status::FAIL),
+        ("nested", Status::PASS),
+    ];
+
+    let mut root = root_scope(&rules, &value)?;
+    eval_rules_file(&rules, &mut root)?;
+    let top = root.reset_recorder().extract();
+    let statuses = top.children.iter().map(|rule| match &rule.container {
+        Some(RecordType::RuleCheck(NamedStatus { name, status, .. })) => (*name, *status),
+        _ => unreachable!()
+    }).collect::<Vec<_>>();
+    assert_eq!(statuses, expected);
+
+    let scope = crate::rules::evaluate::RootScope::new(&rules, &value)?;
+    for (rule, (_, status)) in rules.guard_rules.iter().zip(expected) {
+        assert_eq!(rule.evaluate(&value, &scope)?, status, "{}", rule.rule_name);
+    }
+    Ok(())
+}
@@ -4,9 +4,11 @@ use std::fmt::Formatter;
 
 use crate::rules::{Evaluate, EvaluationContext, EvaluationType, Result, Status};
 use crate::rules::errors::{Error, ErrorKind};
+use crate::rules::display::{interpolate_message, ValueOnlyDisplay};
 use crate::rules::exprs::{GuardClause, GuardNamedRuleClause, QueryPart, RuleClause, TypeBlock, BlockGuardClause, WhenGuardClause};
-use crate::rules::exprs::{AccessQuery, Block, Conjunctions, GuardAccessClause, LetExpr, LetValue, Rule, RulesFile, SliceDisplay};
-use crate::rules::path_value::{PathAwareValue, QueryResolver};
+use crate::rules::exprs::{AccessQuery, FileLocation, ROOT_VARIABLE, Block, Conjunctions, GuardAccessClause, LetExpr, LetValue, Rule, RulesFile, SliceDisplay};
+use crate::rules::path_value::{capture_value, cidr_prefix_value, count_value, earlier_duplicates, set_collections, set_difference, EvaluationOptions, MissingAs, PathAwareValue, QueryResolver};
+use crate::rules::parser::type_query;
 use crate::rules::values::*;
 
 //////////////////////////////////////////////////////////////////////////////////////////////////
@@ -44,12 +46,44 @@ fn resolve_query<'s, 'loc>(all: bool,
                            query: &[QueryPart<'loc>],
                            context: &'s PathAwareValue,
                            var_resolver: &'s dyn EvaluationContext) -> Result<Vec<&'s PathAwareValue>> {
+    if let [QueryPart::ResourceCount(resource_type)] = query {
+        return Ok(vec![count_value(var_resolver.resource_count(resource_type))])
+    }
+    if let Some((QueryPart::Count, query)) = query.split_last() {
+        let matched = resolve_query(false, query, context, var_resolver)?;
+        return Ok(vec![count_value(matched.len())])
+    }
+    if let Some((QueryPart::Capture(regex), query)) = query.split_last() {
+        let matched = resolve_query(false, query, context, var_resolver)?;
+        return capture_values(matched, Some(regex))
+    }
+    if let Some((QueryPart::CidrPrefix, query)) = query.split_last() {
+        let matched = resolve_query(false, query, context, var_resolver)?;
+        let mut prefixes = Vec::with_capacity(matched.len());
+        for each in matched {
+            prefixes.push(cidr_prefix_value(each)?);
+        }
+        return Ok(prefixes)
+    }
     match query[0].variable() {
         Some(var) => resolve_variable_query(all, var, query, var_resolver),
         None => context.select(all, query, var_resolver)
     }
 }
 
+fn capture_values<'v>(values: Vec<&'v PathAwareValue>, capture: Option<&String>) -> Result<Vec<&'v PathAwareValue>> {
+    match capture {
+        Some(regex) => {
+            let mut captured = Vec::with_capacity(values.len());
+            for each in values {
+                captured.extend(capture_value(each, regex)?);
+            }
+            Ok(captured)
+        },
+        None => Ok(values)
+    }
+}
+
 fn invert_status(status: Status, not: bool) -> Status {
     if not {
         return match status {
@@ -278,11 +312,11 @@ fn compare<F>(lhs: &Vec<&PathAwareValue>,
 //    }
 //}
 
-pub(super) fn invert_closure<F>(f: F, clause_not: bool, not: bool) -> impl Fn(&PathAwareValue, &PathAwareValue) -> Result<bool>
-    where F: Fn(&PathAwareValue, &PathAwareValue) -> Result<bool>
+pub(super) fn invert_closure<'o, F>(f: F, clause_not: bool, not: bool, options: &'o EvaluationOptions) -> impl Fn(&PathAwareValue, &PathAwareValue) -> Result<bool> + 'o
+    where F: 'o + Fn(&PathAwareValue, &PathAwareValue, &EvaluationOptions) -> Result<bool>
 {
     move |first, second| {
-        let r = f(first, second)?;
+        let r = f(first, second, options)?;
         let r = if clause_not { !r } else { r };
         let r = if not { !r } else { r };
         Ok(r)
@@ -290,6 +324,105 @@ pub(super) fn invert_closure<F>(f: F, clause_not: bool, not: bool) -> impl Fn(&P
 }
 
 
+impl<'loc> GuardAccessClause<'loc> {
+    //
+    // UNIQUE compares the values against each other, a query that resolves to a single list
+    // checks its elements. A failure is reported on the first value that repeats an earlier one
+    //
+    fn unique_status(&self,
+                     lhs: &[&PathAwareValue],
+                     not: bool,
+                     context: &PathAwareValue,
+                     var_resolver: &dyn EvaluationContext) -> Status {
+        let values = match lhs {
+            [PathAwareValue::List((_, list))] => list.iter().collect::<Vec<&PathAwareValue>>(),
+            rest => rest.to_vec(),
+        };
+        let repeated = values.iter().zip(earlier_duplicates(&values, var_resolver.options()))
+            .find_map(|(each, first)| first.map(|first| (*each, first)));
+        let status = negation_status(repeated.is_none(), not, self.negation);
+
+        let guard_loc = format!("{}", self);
+        let mut auto_reporter = AutoReport::new(EvaluationType::Clause, var_resolver, &guard_loc);
+        auto_reporter.cmp(self.access_clause.comparator).status(status);
+        match repeated {
+            Some((each, first)) => auto_reporter.from(Some(each.clone())).to(Some(first.clone())),
+            None => auto_reporter.from(Some(values.first().map_or(context, |first| *first).clone())),
+        };
+        if status == Status::FAIL {
+            let repeat = repeated.map(|(each, first)|
+                format!("value {} repeats the value at {}", ValueOnlyDisplay(each), first.self_path()));
+            auto_reporter.message(match (&self.access_clause.custom_message, repeat) {
+                (Some(msg), Some(repeat)) => format!("{}, {}", interpolate_message(msg, context, var_resolver.options()), repeat),
+                (Some(msg), None) => interpolate_message(msg, context, var_resolver.options()),
+                (None, Some(repeat)) => repeat,
+                (None, None) => "(DEFAULT: NO_MESSAGE)".to_string(),
+            });
+        }
+        status
+    }
+
+    fn set_status(&self,
+                  lhs: &[&PathAwareValue],
+                  rhs: &[&PathAwareValue],
+                  context: &PathAwareValue,
+                  var_resolver: &dyn EvaluationContext) -> Status {
+        let (cmp, not) = self.access_clause.comparator;
+        let superset = cmp == CmpOperator::Superset;
+        let rhs = set_collections(rhs).into_iter()
+            .flat_map(|(_, elements)| elements)
+            .collect::<Vec<&PathAwareValue>>();
+        let displayed = |values: &[&PathAwareValue]| values.iter()
+            .map(|each| format!("{} at {}", ValueOnlyDisplay(each), each.self_path()))
+            .collect::<Vec<String>>().join(", ");
+        let guard_loc = format!("{}", self);
+        let mut statuses = Vec::new();
+        for (collection, elements) in set_collections(lhs) {
+            let diff = set_difference(&elements, &rhs, superset, var_resolver.options());
+            let status = negation_status(diff.is_empty(), not, self.negation);
+            statuses.push(status);
+            if status == Status::PASS {
+                let mut auto_reporter = AutoReport::new(EvaluationType::Clause, var_resolver, &guard_loc);
+                auto_reporter.cmp(self.access_clause.comparator).status(status).from(Some(collection.clone()));
+                continue
+            }
+            //
+            // Either the values that violate the relation or, when it is negated, those it held for.
+            // SUBSET reports each value of the collection, SUPERSET the collection with the values
+            //
+            let (violations, held) = if diff.is_empty() {
+                (if superset { rhs.clone() } else { elements.clone() }, true)
+            } else {
+                (diff, false)
+            };
+            let reported = if superset {
+                vec![(collection, format!("{} {} values [{}]",
+                    collection.self_path(), if held { "contained" } else { "was missing" }, displayed(&violations)))]
+            } else {
+                violations.iter().map(|each| (*each, format!("value {} at {} was {}present in [{}]",
+                    ValueOnlyDisplay(each), each.self_path(), if held { "" } else { "not " },
+                    rhs.iter().map(|each| ValueOnlyDisplay(each).to_string()).collect::<Vec<String>>().join(", "))))
+                    .collect()
+            };
+            for (from, violation) in reported {
+                let mut auto_reporter = AutoReport::new(EvaluationType::Clause, var_resolver, &guard_loc);
+                auto_reporter.cmp(self.access_clause.comparator).status(status).from(Some(from.clone()))
+                    .message(match &self.access_clause.custom_message {
+                        Some(msg) => format!("{}, {}", interpolate_message(msg, context, var_resolver.options()), violation),
+                        None => violation,
+                    });
+            }
+        }
+        if self.access_clause.query.match_all {
+            if statuses.contains(&Status::FAIL) { Status::FAIL } else { Status::PASS }
+        } else if statuses.contains(&Status::PASS) || statuses.is_empty() {
+            Status::PASS
+        } else {
+            Status::FAIL
+        }
+    }
+}
+
 impl<'loc> Evaluate for GuardAccessClause<'loc> {
     fn evaluate<'s>(&self,
                 context: &'s PathAwareValue,
@@ -312,14 +445,35 @@ impl<'loc> Evaluate for GuardAccessClause<'loc> {
                 Err(e) => return Err(e),
             };
 
+        if let (CmpOperator::Unique, not) = clause.access_clause.comparator {
+            //
+            // Values that do not resolve never repeat, they are left out rather than failing
+            //
+            let values = match lhs {
+                Some(values) => values,
+                None => match resolve_query(false, &clause.access_clause.query.query, context, var_resolver) {
+                    Ok(values) => values,
+                    Err(Error(ErrorKind::RetrievalError(_))) |
+                    Err(Error(ErrorKind::IncompatibleRetrievalError(_))) => vec![],
+                    Err(e) => return Err(e),
+                }
+            };
+            return Ok(self.unique_status(&values, not, context, var_resolver))
+        }
+
         let result = match clause.access_clause.comparator {
             (CmpOperator::Empty, not) =>
                 //
                 // Retrieval Error is considered the same as an empty or !exists
                 // When using "SOME" keyword in the clause, then IncompatibleError is trapped to be none
-                // This is okay as long as the checks are for empty, exists
+                // This is okay as long as the checks are for empty, exists. EMPTY SKIPs or FAILs
+                // with --missing-as, !EMPTY FAILs
                 //
                 match &lhs {
+                    None if not == clause.negation => Some(match var_resolver.options().missing_as {
+                        MissingAs::Skip => Status::SKIP,
+                        MissingAs::Fail => Status::FAIL,
+                    }),
                     None => Some(negation_status(true, not, clause.negation)),
                     Some(l) => {
                         Some(
@@ -363,7 +517,8 @@ impl<'loc> Evaluate for GuardAccessClause<'loc> {
                     Some(LetValue::Value(PathAwareValue::Null(_))) =>
                         match &lhs {
                             None => Some(negation_status(true, not, clause.negation)),
-                            Some(_) => Some(negation_status(false, not, clause.negation)),
+                            Some(l) => Some(negation_status(
+                                l.iter().all(|p| matches!(**p, PathAwareValue::Null(_))), not, clause.negation)),
                         }
                     _ => None
                 },
@@ -407,6 +562,15 @@ impl<'loc> Evaluate for GuardAccessClause<'loc> {
                         ).map_or(true, |_i| false), not, clause.negation))
                 },
 
+            (CmpOperator::IsNumber, not) =>
+                match &lhs {
+                    None => Some(negation_status(false, not, clause.negation)),
+                    Some(l) => Some(
+                        negation_status(l.iter().all(|p|
+                            matches!(**p, PathAwareValue::Int(_) | PathAwareValue::Float(_))
+                        ), not, clause.negation))
+                },
+
             _ => None
         };
 
@@ -414,8 +578,8 @@ impl<'loc> Evaluate for GuardAccessClause<'loc> {
             let guard_loc = format!("{}", self);
             let mut auto_reporter = AutoReport::new(EvaluationType::Clause, var_resolver, &guard_loc);
             let message = match &clause.access_clause.custom_message {
-                Some(msg) => msg,
-                None => "(DEFAULT: NO_MESSAGE)"
+                Some(msg) => interpolate_message(msg, context, var_resolver.options()),
+                None => "(DEFAULT: NO_MESSAGE)".to_string()
             };
             auto_reporter.cmp(self.access_clause.comparator).status(r).from(
                 match &lhs {
@@ -426,7 +590,7 @@ impl<'loc> Evaluate for GuardAccessClause<'loc> {
                 }
             );
             if r == Status::FAIL {
-                auto_reporter.message(message.to_string());
+                auto_reporter.message(message);
             }
             return Ok(r)
         }
@@ -481,6 +645,10 @@ impl<'loc> Evaluate for GuardAccessClause<'loc> {
             }
         };
 
+        if let CmpOperator::Subset | CmpOperator::Superset = clause.access_clause.comparator.0 {
+            return Ok(self.set_status(&lhs, &rhs, context, var_resolver))
+        }
+
         let (result, outcomes) =
             match &clause.access_clause.comparator.0 {
             //
@@ -491,7 +659,19 @@ impl<'loc> Evaluate for GuardAccessClause<'loc> {
                         &clause.access_clause.query.query,
                         &rhs,
                         rhs_query,
-                        invert_closure(super::path_value::compare_eq, clause.access_clause.comparator.1, clause.negation),
+                        invert_closure(super::path_value::compare_eq, clause.access_clause.comparator.1, clause.negation, var_resolver.options()),
+                        false,
+                        !all)?,
+
+            //
+            // EQUALS_UNORDERED, lists compare equal in any order
+            //
+            CmpOperator::EqUnordered =>
+                compare(&lhs,
+                        &clause.access_clause.query.query,
+                        &rhs,
+                        rhs_query,
+                        invert_closure(super::path_value::compare_list_set_eq, clause.access_clause.comparator.1, clause.negation, var_resolver.options()),
                         false,
                         !all)?,
 
@@ -503,7 +683,7 @@ impl<'loc> Evaluate for GuardAccessClause<'loc> {
                         &clause.access_clause.query.query,
                         &rhs,
                         rhs_query,
-                        invert_closure(super::path_value::compare_gt, clause.access_clause.comparator.1, clause.negation),
+                        invert_closure(super::path_value::compare_gt, clause.access_clause.comparator.1, clause.negation, var_resolver.options()),
                         false,
                         !all)?,
 
@@ -515,7 +695,7 @@ impl<'loc> Evaluate for GuardAccessClause<'loc> {
                         &clause.access_clause.query.query,
                         &rhs,
                         rhs_query,
-                        invert_closure(super::path_value::compare_ge, clause.access_clause.comparator.1, clause.negation),
+                        invert_closure(super::path_value::compare_ge, clause.access_clause.comparator.1, clause.negation, var_resolver.options()),
                         false,
                         !all)?,
 
@@ -527,7 +707,7 @@ impl<'loc> Evaluate for GuardAccessClause<'loc> {
                         &clause.access_clause.query.query,
                         &rhs,
                         rhs_query,
-                        invert_closure(super::path_value::compare_lt, clause.access_clause.comparator.1, clause.negation),
+                        invert_closure(super::path_value::compare_lt, clause.access_clause.comparator.1, clause.negation, var_resolver.options()),
                         false,
                         !all)?,
 
@@ -539,7 +719,7 @@ impl<'loc> Evaluate for GuardAccessClause<'loc> {
                         &clause.access_clause.query.query,
                         &rhs,
                         rhs_query,
-                        invert_closure(super::path_value::compare_le, clause.access_clause.comparator.1, clause.negation),
+                        invert_closure(super::path_value::compare_le, clause.access_clause.comparator.1, clause.negation, var_resolver.options()),
                         false,
                         !all)?,
 
@@ -556,7 +736,7 @@ impl<'loc> Evaluate for GuardAccessClause<'loc> {
                             &rhs,
                             rhs_query,
                             |lhs, rhs| {
-                                Ok(!super::path_value::compare_eq(lhs, rhs)?)
+                                Ok(!super::path_value::compare_eq(lhs, rhs, var_resolver.options())?)
                             },
                             false,
                             !all)?
@@ -565,7 +745,7 @@ impl<'loc> Evaluate for GuardAccessClause<'loc> {
                             &clause.access_clause.query.query,
                             &rhs,
                             rhs_query,
-                            super::path_value::compare_eq,
+                            |lhs, rhs| super::path_value::compare_eq(lhs, rhs, var_resolver.options()),
                             true,
                             !all)?
                 };
@@ -573,6 +753,52 @@ impl<'loc> Evaluate for GuardAccessClause<'loc> {
                 result
             },
 
+            //
+            // ALL IN, ! ALL IN, each element of the LHS lists on its own against all RHS values
+            //
+            CmpOperator::AllIn => {
+                let elements = merge_mixed_results(&lhs);
+                if elements.is_empty() {
+                    (invert_status(Status::PASS, clause.negation), vec![])
+                } else {
+                    let not_in = clause.access_clause.comparator.1;
+                    let mut result = compare(&elements,
+                            &clause.access_clause.query.query,
+                            &merge_mixed_results(&rhs),
+                            rhs_query,
+                            |lhs, rhs| {
+                                Ok(super::path_value::compare_eq(lhs, rhs, var_resolver.options())? != not_in)
+                            },
+                            !not_in,
+                            !all)?;
+                    result.0 = invert_status(result.0, clause.negation);
+                    result
+                }
+            },
+
+            //
+            // IN_CIDR, ! IN_CIDR, each LHS address or block against all RHS blocks. Values that are
+            // not valid addresses or blocks fail either way
+            //
+            CmpOperator::InCidr => {
+                let not_in = clause.access_clause.comparator.1;
+                let mut result = compare(&lhs,
+                        &clause.access_clause.query.query,
+                        &merge_mixed_results(&rhs),
+                        rhs_query,
+                        |lhs, rhs| {
+                            match super::path_value::compare_in_cidr(lhs, rhs) {
+                                Ok(contained) => Ok(contained != not_in),
+                                Err(Error(ErrorKind::NotComparable(_))) => Ok(false),
+                                Err(e) => Err(e),
+                            }
+                        },
+                        !not_in,
+                        !all)?;
+                result.0 = invert_status(result.0, clause.negation);
+                result
+            },
+
             _ => unreachable!()
 
         };
@@ -584,7 +810,7 @@ impl<'loc> Evaluate for GuardAccessClause<'loc> {
             auto_reporter.cmp(clause.access_clause.comparator);
             if !outcome {
                 auto_reporter.from(from).to(to).message(match &clause.access_clause.custom_message {
-                    Some(msg) => msg.clone(),
+                    Some(msg) => interpolate_message(msg, context, var_resolver.options()),
                     None => "DEFAULT MESSAGE(FAIL)".to_string()
                 });
             }
@@ -601,7 +827,7 @@ impl<'loc> std::fmt::Display for GuardNamedRuleClause<'loc> {
 
 impl<'loc> Evaluate for GuardNamedRuleClause<'loc> {
     fn evaluate<'s>(&self,
-                _context: &'s PathAwareValue,
+                context: &'s PathAwareValue,
                 var_resolver: &'s dyn EvaluationContext) -> Result<Status> {
         let guard_loc = format!("{}", self);
         let mut auto_reporter = AutoReport::new(EvaluationType::Clause, var_resolver, &guard_loc);
@@ -612,11 +838,11 @@ impl<'loc> Evaluate for GuardNamedRuleClause<'loc> {
 
         Ok(if status == Status::FAIL {
             let msg = if let Some(msg) = &self.custom_message {
-                msg
+                interpolate_message(msg, context, var_resolver.options())
             } else {
-                "DEFAULT FAIL"
+                "DEFAULT FAIL".to_string()
             };
-            auto_reporter.status(status).message(msg.to_string()).get_status()
+            auto_reporter.status(status).message(msg).get_status()
         } else {
             auto_reporter.status(status).get_status()
         })
@@ -683,7 +909,10 @@ impl<'loc, T: Evaluate + 'loc> Evaluate for Conjunctions<T> {
             'conjunction:
             for conjunction in self {
                 let mut num_of_disjunction_fails = 0;
-                let mut report = if "cfn_guard::rules::exprs::GuardClause" == item_name {
+                //
+                // or branches are always reported so that the branch that passed is visible
+                //
+                let mut report = if "cfn_guard::rules::exprs::GuardClause" == item_name || conjunction.len() > 1 {
                     Some(AutoReport::new(
                         EvaluationType::Conjunction,
                         var_resolver,
@@ -775,10 +1004,11 @@ impl<'loc> Evaluate for WhenGuardClause<'loc> {
 
 impl<'loc> Evaluate for TypeBlock<'loc> {
     fn evaluate<'s>(&self, context: &'s PathAwareValue, var_resolver: &'s dyn EvaluationContext) -> Result<Status> {
+        let type_context = if self.negation { format!("not {}", self.type_name) } else { self.type_name.clone() };
         let mut type_report = AutoReport::new(
             EvaluationType::Type,
             var_resolver,
-            &self.type_name
+            &type_context
         );
 
         if let Some(conditions) = &self.conditions {
@@ -797,6 +1027,13 @@ impl<'loc> Evaluate for TypeBlock<'loc> {
 
         let query = format!("Resources.*[ Type == \"{}\" ]", self.type_name);
         let cfn_query = AccessQuery::try_from(query.as_str())?;
+        if let Some(exists) = self.exists {
+            let matched = context.select(cfn_query.match_all, &cfn_query.query, var_resolver)
+                .map_or(0, |values| values.len());
+            let status = if (matched > 0) == (exists != self.negation) { Status::PASS } else { Status::FAIL };
+            return Ok(type_report.message(format!("{} resource(s) of type {} matched", matched, self.type_name))
+                .status(status).get_status())
+        }
         let values = match context.select(cfn_query.match_all, &cfn_query.query, var_resolver) {
             Ok(v) => if v.is_empty() {
                 return Ok(type_report.message(format!("There are no {} types present in context", self.type_name))
@@ -821,8 +1058,8 @@ impl<'loc> Evaluate for TypeBlock<'loc> {
                     Status::SKIP => {},
                 }
             }
-            if num_fail > 0 { break Status::FAIL }
-            if num_pass > 0 { break Status::PASS }
+            if num_fail > 0 { break if self.negation { Status::PASS } else { Status::FAIL } }
+            if num_pass > 0 { break if self.negation { Status::FAIL } else { Status::PASS } }
             break Status::SKIP
         };
         Ok(match overall {
@@ -895,7 +1132,10 @@ impl<'loc> Evaluate for RulesFile<'loc> {
             EvaluationType::File, var_resolver, "");
         for rule in &self.guard_rules {
             if Status::FAIL == rule.evaluate(context, var_resolver)? {
-                overall = Status::FAIL
+                overall = Status::FAIL;
+                if var_resolver.options().fail_fast {
+                    break
+                }
             }
         }
         auto_report.status(overall);
@@ -937,6 +1177,8 @@ pub(crate) struct RootScope<'s, 'loc> {
     literals: HashMap<&'s str, &'s PathAwareValue>,
     rule_by_name: HashMap<&'s str, &'s Rule<'loc>>,
     rule_statues: std::cell::RefCell<HashMap<&'s str, Status>>,
+    rules_in_progress: std::cell::RefCell<Vec<&'s str>>,
+    options: EvaluationOptions,
 }
 
 impl<'s, 'loc> RootScope<'s, 'loc> {
@@ -959,12 +1201,31 @@ impl<'s, 'loc> RootScope<'s, 'loc> {
             variables: std::cell::RefCell::new(HashMap::new()),
             rule_by_name: lookup_cache,
             rule_statues: std::cell::RefCell::new(HashMap::with_capacity(rules.guard_rules.len())),
+            rules_in_progress: std::cell::RefCell::new(Vec::new()),
+            options: EvaluationOptions::default(),
         })
     }
+
+    pub(crate) fn with_defines(mut self, defines: &'s HashMap<String, PathAwareValue>) -> Self {
+        for (name, value) in defines {
+            self.pending_queries.remove(name.as_str());
+            self.literals.insert(name.as_str(), value);
+        }
+        self
+    }
+
+    pub(crate) fn with_options(mut self, options: EvaluationOptions) -> Self {
+        self.options = options;
+        self
+    }
 }
 
 impl<'s, 'loc> EvaluationContext for RootScope<'s, 'loc> {
     fn resolve_variable(&self, variable: &str) -> Result<Vec<&PathAwareValue>> {
+        if variable == ROOT_VARIABLE {
+            return Ok(vec![self.input_context])
+        }
+
         if let Some(literal) = self.literals.get(variable) {
             return Ok(vec![literal])
         }
@@ -975,10 +1236,14 @@ impl<'s, 'loc> EvaluationContext for RootScope<'s, 'loc> {
         return if let Some((key, query)) = self.pending_queries.get_key_value(variable) {
             let all = (*query).match_all;
             let query: &[QueryPart<'_>] = &(*query).query;
+            let (query, capture) = match query.split_last() {
+                Some((QueryPart::Capture(regex), query)) => (query, Some(regex)),
+                _ => (query, None)
+            };
             let values = match query[0].variable() {
-                Some(var) => resolve_variable_query(all, var, query, self)?,
+                Some(var) => capture_values(resolve_variable_query(all, var, query, self)?, capture)?,
                 None => {
-                    let values = self.input_context.select(all, query, self)?;
+                    let values = capture_values(self.input_context.select(all, query, self)?, capture)?;
                     self.variables.borrow_mut().insert(*key, values.clone());
                     values
                 }
@@ -997,7 +1262,19 @@ impl<'s, 'loc> EvaluationContext for RootScope<'s, 'loc> {
         }
 
         if let Some((name, rule)) = self.rule_by_name.get_key_value(rule_name) {
-            let status = (*rule).evaluate(self.input_context, self)?;
+            let cycle = {
+                let in_progress = self.rules_in_progress.borrow();
+                in_progress.iter().position(|each| each == name)
+                    .map(|pos| in_progress[pos..].join(" -> "))
+            };
+            if let Some(cycle) = cycle {
+                return Err(Error::new(ErrorKind::IncompatibleError(
+                    format!("Cycle detected between rules {} -> {}", cycle, name))))
+            }
+            self.rules_in_progress.borrow_mut().push(*name);
+            let status = (*rule).evaluate(self.input_context, self);
+            self.rules_in_progress.borrow_mut().pop();
+            let status = status?;
             self.rule_statues.borrow_mut().insert(*name, status);
             return Ok(status)
         }
@@ -1007,6 +1284,16 @@ impl<'s, 'loc> EvaluationContext for RootScope<'s, 'loc> {
         )))
     }
 
+    fn resource_count(&self, resource_type: &str) -> usize {
+        let location = FileLocation { file_name: "", line: 0, column: 0 };
+        let query = type_query(location, resource_type);
+        self.input_context.select(false, &query, self).map_or(0, |resources| resources.len())
+    }
+
+    fn options(&self) -> &EvaluationOptions {
+        &self.options
+    }
+
     fn end_evaluation(&self,
                       eval_type: EvaluationType,
                       context: &str,
@@ -1066,10 +1353,14 @@ impl<'s, T> EvaluationContext for BlockScope<'s, T> {
         return if let Some((key, query)) = self.pending_queries.get_key_value(variable) {
             let all = (*query).match_all;
             let query: &[QueryPart<'_>] = &(*query).query;
+            let (query, capture) = match query.split_last() {
+                Some((QueryPart::Capture(regex), query)) => (query, Some(regex)),
+                _ => (query, None)
+            };
             let values = match query[0].variable() {
-                Some(var) => resolve_variable_query(all, var, query, self)?,
+                Some(var) => capture_values(resolve_variable_query(all, var, query, self)?, capture)?,
                 None => {
-                    let values = self.input_context.select(all, query, self)?;
+                    let values = capture_values(self.input_context.select(all, query, self)?, capture)?;
                     self.variables.borrow_mut().insert(*key, values.clone());
                     values
                 }
@@ -1084,6 +1375,14 @@ impl<'s, T> EvaluationContext for BlockScope<'s, T> {
         self.parent.rule_status(rule_name)
     }
 
+    fn resource_count(&self, resource_type: &str) -> usize {
+        self.parent.resource_count(resource_type)
+    }
+
+    fn options(&self) -> &EvaluationOptions {
+        self.parent.options()
+    }
+
 
 
     fn end_evaluation(
@@ -1,6 +1,6 @@
 use super::*;
 use super::super::path_value;
-use super::super::path_value::Path;
+use super::super::path_value::{Path, DEFAULT_EVALUATION_OPTIONS};
 use crate::rules::parser::{rules_file, Span};
 use crate::commands::files::read_file_content;
 use std::fs::File;
@@ -55,6 +55,14 @@ impl EvaluationContext for DummyEval {
         unimplemented!()
     }
 
+    fn resource_count(&self, _resource_type: &str) -> usize {
+        unimplemented!()
+    }
+
+    fn options(&self) -> &EvaluationOptions {
+        &DEFAULT_EVALUATION_OPTIONS
+    }
+
     fn end_evaluation(&self, _eval_type: EvaluationType, _context: &str, _msg: String, _from: Option<PathAwareValue>, _to: Option<PathAwareValue>, _status: Option<Status>, _cmp: Option<(CmpOperator, bool)>) {
     }
 
@@ -148,6 +156,14 @@ impl<'r> EvaluationContext for Reporter<'r> {
         self.0.rule_status(rule_name)
     }
 
+    fn resource_count(&self, resource_type: &str) -> usize {
+        self.0.resource_count(resource_type)
+    }
+
+    fn options(&self) -> &EvaluationOptions {
+        self.0.options()
+    }
+
     fn end_evaluation(&self, eval_type: EvaluationType, context: &str, msg: String, from: Option<PathAwareValue>, to: Option<PathAwareValue>, status: Option<Status>, cmp: Option<(CmpOperator, bool)>) {
         println!("{} {} {:?}", eval_type, context, status);
         self.0.end_evaluation(
@@ -898,6 +914,14 @@ impl<'a, 'b> EvaluationContext for VariableResolver<'a, 'b> {
         self.0.rule_status(rule_name)
     }
 
+    fn resource_count(&self, resource_type: &str) -> usize {
+        self.0.resource_count(resource_type)
+    }
+
+    fn options(&self) -> &EvaluationOptions {
+        self.0.options()
+    }
+
     fn end_evaluation(&self, eval_type: EvaluationType, context: &str, msg: String, from: Option<PathAwareValue>, to: Option<PathAwareValue>, status: Option<Status>, cmp: Option<(CmpOperator, bool)>) {
         self.0.end_evaluation(eval_type, context, msg, from, to, status, cmp);
     }
@@ -1374,7 +1398,7 @@ fn test_compare_loop_atleast_one_eq() -> Result<()> {
     // match any one rhs = false, at-least-one = false
     //
     let (result, _results) = compare_loop(
-        &lhs_values, &rhs_values, path_value::compare_eq, false, false
+        &lhs_values, &rhs_values, |lhs, rhs| path_value::compare_eq(lhs, rhs, &DEFAULT_EVALUATION_OPTIONS), false, false
     )?;
     assert_eq!(result, false);
 
@@ -1382,7 +1406,7 @@ fn test_compare_loop_atleast_one_eq() -> Result<()> {
     // match any one rhs = false, at-least-one = true
     //
     let (result, _results) = compare_loop(
-        &lhs_values, &rhs_values, path_value::compare_eq, false, true
+        &lhs_values, &rhs_values, |lhs, rhs| path_value::compare_eq(lhs, rhs, &DEFAULT_EVALUATION_OPTIONS), false, true
     )?;
     assert_eq!(result, true);
 
@@ -1390,7 +1414,7 @@ fn test_compare_loop_atleast_one_eq() -> Result<()> {
     // match any one rhs = true, at-least-one = false
     //
     let (result, _results) = compare_loop(
-        &lhs_values, &rhs_values, path_value::compare_eq, true, false
+        &lhs_values, &rhs_values, |lhs, rhs| path_value::compare_eq(lhs, rhs, &DEFAULT_EVALUATION_OPTIONS), true, false
     )?;
     assert_eq!(result, false);
 
@@ -1411,7 +1435,7 @@ fn test_compare_loop_all() -> Result<()> {
     let lhs_values = lhs.iter().collect::<Vec<&PathAwareValue>>();
     let rhs_values = rhs.iter().collect::<Vec<&PathAwareValue>>();
 
-    let results = super::compare_loop_all(&lhs_values, &rhs_values, path_value::compare_eq, false)?;
+    let results = super::compare_loop_all(&lhs_values, &rhs_values, |lhs, rhs| path_value::compare_eq(lhs, rhs, &DEFAULT_EVALUATION_OPTIONS), false)?;
     //
     // One result for each LHS value
     //
@@ -1447,7 +1471,7 @@ fn test_compare_lists() -> Result<()> {
         &query,
         &rhs,
         None,
-        super::super::path_value::compare_eq,
+        |lhs, rhs| super::super::path_value::compare_eq(lhs, rhs, &DEFAULT_EVALUATION_OPTIONS),
         false,
         false
     )?;
@@ -1800,6 +1824,14 @@ impl<'a> EvaluationContext for Tracker<'a> {
         self.root.rule_status(rule_name)
     }
 
+    fn resource_count(&self, resource_type: &str) -> usize {
+        self.root.resource_count(resource_type)
+    }
+
+    fn options(&self) -> &EvaluationOptions {
+        self.root.options()
+    }
+
     fn end_evaluation(&self, eval_type: EvaluationType, context: &str, msg: String, from: Option<PathAwareValue>, to: Option<PathAwareValue>, status: Option<Status>, cmp: Option<(CmpOperator, bool)>) {
         self.root.end_evaluation(eval_type, context, msg, from, to, status.clone(), cmp);
         if eval_type == EvaluationType::Rule {
@@ -2095,6 +2127,14 @@ fn test_multiple_valued_clause_reporting() -> Result<()> {
             todo!()
         }
 
+        fn resource_count(&self, _resource_type: &str) -> usize {
+            todo!()
+        }
+
+        fn options(&self) -> &EvaluationOptions {
+            &DEFAULT_EVALUATION_OPTIONS
+        }
+
         fn end_evaluation(&self, eval_type: EvaluationType, context: &str, msg: String, from: Option<PathAwareValue>, to: Option<PathAwareValue>, status: Option<Status>, _cmp: Option<(CmpOperator, bool)>) {
             if eval_type == EvaluationType::Clause {
                 match &status {
@@ -2160,6 +2200,14 @@ fn test_multiple_valued_clause_reporting_var_access() -> Result<()> {
             self.root.rule_status(rule_name)
         }
 
+        fn resource_count(&self, resource_type: &str) -> usize {
+            self.root.resource_count(resource_type)
+        }
+
+        fn options(&self) -> &EvaluationOptions {
+            self.root.options()
+        }
+
         fn end_evaluation(&self, eval_type: EvaluationType, context: &str, msg: String, from: Option<PathAwareValue>, to: Option<PathAwareValue>, status: Option<Status>, cmp: Option<(CmpOperator, bool)>) {
             if eval_type == EvaluationType::Clause {
                 match &status {
@@ -2262,3 +2310,24 @@ fn test_in_comparison_operator_for_list_of_lists() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_compare_with_null_from_data_file() -> Result<()> {
+    let value = PathAwareValue::try_from(crate::rules::values::read_from(r#"
+    Nullable: null
+    Other: ~
+    Name: "null"
+    "#)?)?;
+    for (rule, expected) in [
+        ("rule r { Nullable == null }", Status::PASS),
+        ("rule r { Other == null }", Status::PASS),
+        ("rule r { Missing == null }", Status::PASS),
+        ("rule r { Nullable != null }", Status::FAIL),
+        ("rule r { Name == null }", Status::FAIL),
+    ] {
+        let rules = RulesFile::try_from(rule)?;
+        let root = RootScope::new(&rules, &value)?;
+        assert_eq!(rules.evaluate(&value, &root)?, expected, "{}", rule);
+    }
+    Ok(())
+}
@@ -6,6 +6,12 @@ use serde::{Serialize, Deserialize};
 use crate::rules::path_value::PathAwareValue;
 use crate::rules::display::ValueOnlyDisplay;
 
+//
+// Reserved variable a query starting with $ resolves against, it is always the root of the
+// data being evaluated, e.g. $.Parameters.Env from inside a type block
+//
+pub(crate) const ROOT_VARIABLE: &str = "$root";
+
 #[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize, Hash)]
 pub(crate) struct FileLocation<'loc> {
     pub(crate) line: u32,
@@ -54,16 +60,47 @@ pub(crate) struct LetExpr<'loc> {
 /// use the structural key matches. E.g. to select all resources from an CFN template that match the
 /// DynamoDB Table we can use the following `resources.*[type=/AWS::Dynamo/]`
 ///
+/// A key followed by an index, e.g. `Resources.Subnets[0]` or `Subnets[*]`, is two parts, a
+/// `Key` then an `Index` or `AllIndices`. There are no combined key and index parts, each part
+/// resolves on its own against the values the previous one selected, so every resolver handles
+/// an index the same way whether it follows a key, another index or a filter
 ///
 #[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize, Hash)]
 pub(crate) enum QueryPart<'loc> {
     This,
     Key(String),
+    //
+    // From a /regex/ segment in dotted access, selects the values of every key in a map that
+    // matches regex
+    //
+    KeyRegex(String),
     MapKeyFilter(Option<String>, MapKeyFilterClause<'loc>),
     AllValues(Option<String>),
+    RecursiveDescent(String),
     AllIndices(Option<String>),
     Index(i32),
     Filter(Option<String>, Conjunctions<GuardClause<'loc>>),
+    FilterNot(Option<String>, Conjunctions<GuardClause<'loc>>),
+    //
+    // Always the last part, from count(query). The query resolves to the number of values
+    // it matched instead of the values themselves
+    //
+    Count,
+    //
+    // Always the last part, from let x := query MATCH /regex/. The query resolves to the first
+    // capture group of regex for each string value it matched
+    //
+    Capture(String),
+    //
+    // Always the last part, from cidr_prefix(query). The query resolves to the prefix length of
+    // each IP address or CIDR block it matched
+    //
+    CidrPrefix,
+    //
+    // Always the only part, from type_name RESOURCE_COUNT. The query resolves to the number of
+    // resources of the type in the template being evaluated, wherever the clause appears
+    //
+    ResourceCount(String),
 }
 
 impl<'loc> QueryPart<'loc> {
@@ -92,7 +129,10 @@ impl<'loc> std::fmt::Display for QueryPart<'loc> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             QueryPart::Key(s) => {
-                f.write_str(s.as_str())?;
+                match self.variable() {
+                    Some(ROOT_VARIABLE) => f.write_str("$")?,
+                    _ => f.write_str(s.as_str())?,
+                }
             },
 
             QueryPart::AllIndices(_name) => {
@@ -103,6 +143,10 @@ impl<'loc> std::fmt::Display for QueryPart<'loc> {
                 f.write_str("*")?;
             },
 
+            QueryPart::RecursiveDescent(key) => {
+                write!(f, "..{}", key)?;
+            },
+
             QueryPart::Index(idx) => {
                 write!(f, "{}", idx.to_string())?;
             },
@@ -111,12 +155,36 @@ impl<'loc> std::fmt::Display for QueryPart<'loc> {
                 f.write_fmt(format_args!("{} (filter-clauses)", name.as_ref().map_or("", String::as_str)))?;
             },
 
+            QueryPart::FilterNot(name, _c) => {
+                f.write_fmt(format_args!("{} (not-filter-clauses)", name.as_ref().map_or("", String::as_str)))?;
+            },
+
             QueryPart::MapKeyFilter(name, _clause) => {
                 f.write_fmt(format_args!("{} (map-key-filter-clauses)", name.as_ref().map_or("", String::as_str)))?;
             },
 
+            QueryPart::KeyRegex(regex) => {
+                write!(f, "/{}/", regex)?;
+            },
+
             QueryPart::This => {
                 f.write_str("_")?;
+            },
+
+            QueryPart::Count => {
+                f.write_str("count()")?;
+            },
+
+            QueryPart::Capture(regex) => {
+                write!(f, "MATCH /{}/", regex)?;
+            },
+
+            QueryPart::CidrPrefix => {
+                f.write_str("cidr_prefix()")?;
+            },
+
+            QueryPart::ResourceCount(type_name) => {
+                write!(f, "{} RESOURCE_COUNT", type_name)?;
             }
         }
         Ok(())
@@ -238,6 +306,8 @@ pub(crate) struct Block<'loc, T> {
 #[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct TypeBlock<'loc> {
     pub(crate) type_name: String,
+    pub(crate) negation: bool, // inverts the aggregate status of the block, SKIP stays SKIP
+    pub(crate) exists: Option<bool>, // Type EXISTS or Type !EXISTS, the block is empty
     pub(crate) conditions: Option<WhenConditions<'loc>>,
     pub(crate) block: Block<'loc, GuardClause<'loc>>, // only contains access clauses
     pub(crate) query: Vec<QueryPart<'loc>>,
@@ -255,6 +325,8 @@ pub(crate) struct Rule<'loc> {
     pub(crate) rule_name: String,
     pub(crate) conditions: Option<WhenConditions<'loc>>,
     pub(crate) block: Block<'loc, RuleClause<'loc>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) tags: Vec<String>,
 }
 
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
@@ -294,27 +366,70 @@ impl<'loc> std::fmt::Display for GuardClause<'loc> {
         match self {
             GuardClause::Clause(individual) => individual.fmt(f)?,
             GuardClause::BlockClause(block) => block.fmt(f)?,
-            _ => unimplemented!()
+            GuardClause::NamedRule(named) => named.fmt(f)?,
+            GuardClause::ParameterizedNamedRule(parameterized) => parameterized.fmt(f)?,
+            GuardClause::WhenBlock(conditions, block) => {
+                write!(f, "when ")?;
+                display_conjunctions(f, conditions)?;
+                write!(f, " {{ ")?;
+                display_conjunctions(f, &block.conjunctions)?;
+                write!(f, " }}")?;
+            }
         }
         Ok(())
     }
 }
 
-impl<'loc> std::fmt::Display for BlockGuardClause<'loc> {
+impl<'loc> std::fmt::Display for WhenGuardClause<'loc> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} {{ ", self.query)?;
-        for each in &self.block.conjunctions {
-            let len = each.len();
-            for idx in 0..len-2 {
-                write!(f, "{} or ", each[idx])?;
+        match self {
+            WhenGuardClause::Clause(individual) => individual.fmt(f),
+            WhenGuardClause::NamedRule(named) => named.fmt(f),
+            WhenGuardClause::ParameterizedNamedRule(parameterized) => parameterized.fmt(f),
+        }
+    }
+}
+
+impl<'loc> std::fmt::Display for ParameterizedNamedRuleClause<'loc> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}(", self.named_rule)?;
+        for (idx, each) in self.parameters.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
             }
-            write!(f, "{}; ", each[len])?;
+            write!(f, "{}", each)?;
         }
+        write!(f, ")")
+    }
+}
+
+impl<'loc> std::fmt::Display for BlockGuardClause<'loc> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {{ ", self.query)?;
+        display_conjunctions(f, &self.block.conjunctions)?;
         write!(f, " }}")?;
         Ok(())
     }
 }
 
+//
+// Clauses joined by "or" within a disjunction, conjunctions separated by "; "
+//
+fn display_conjunctions<T: std::fmt::Display>(f: &mut Formatter<'_>, conjunctions: &Conjunctions<T>) -> std::fmt::Result {
+    for (idx, disjunctions) in conjunctions.iter().enumerate() {
+        if idx > 0 {
+            write!(f, "; ")?;
+        }
+        for (each_idx, each) in disjunctions.iter().enumerate() {
+            if each_idx > 0 {
+                write!(f, " or ")?;
+            }
+            write!(f, "{}", each)?;
+        }
+    }
+    Ok(())
+}
+
 impl<'loc> std::fmt::Display for GuardAccessClause<'loc> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} {}",
@@ -341,7 +456,12 @@ impl<'loc> std::fmt::Display for AccessClause<'loc> {
 
 impl<'loc> std::fmt::Display for AccessQuery<'loc> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", SliceDisplay(&self.query))?;
+        match self.query.split_last() {
+            Some((QueryPart::Count, query)) => write!(f, "count({})", SliceDisplay(query))?,
+            Some((QueryPart::Capture(regex), query)) => write!(f, "{} MATCH /{}/", SliceDisplay(query), regex)?,
+            Some((QueryPart::CidrPrefix, query)) => write!(f, "cidr_prefix({})", SliceDisplay(query))?,
+            _ => write!(f, "{}", SliceDisplay(&self.query))?,
+        }
         Ok(())
     }
 }
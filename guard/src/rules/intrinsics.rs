@@ -0,0 +1,408 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::rules::errors::{Error, ErrorKind};
+use crate::rules::path_value::{MapValue, Path, PathAwareValue};
+use crate::rules::Result;
+
+//
+// Each undetermined condition doubles the number of template variants evaluated
+//
+const MAX_UNDETERMINED_CONDITIONS: usize = 8;
+
+///
+/// Resolves Fn::If in a CloudFormation template using its Conditions section. Conditions are
+/// decided from literal Fn::Equals comparisons, Fn::And, Fn::Or, Fn::Not and Condition references,
+/// with values in overrides taking precedence. Resources whose Condition is false are removed
+/// and properties that resolve to AWS::NoValue are dropped.
+///
+/// Conditions that cannot be decided, typically those that depend on a Ref to a parameter,
+/// yield one variant per combination of outcomes. Each variant is returned with a label such
+/// as "IsProd=true,UseKms=false", the label is empty when all conditions were decided.
+///
+pub(crate) fn resolve_conditions(template: &PathAwareValue, overrides: &HashMap<String, bool>) -> Result<Vec<(String, PathAwareValue)>> {
+    let known = evaluate_conditions(template, overrides)?;
+    let mut undetermined = BTreeSet::new();
+    collect_undetermined(template, &known, &mut undetermined);
+    if let Some(PathAwareValue::Map((_, resources))) = top_level(template, "Resources") {
+        for each in resources.values.values() {
+            if let Some(PathAwareValue::String((_, name))) = top_level(each, "Condition") {
+                if !matches!(known.get(name.as_str()), Some(Some(_))) {
+                    undetermined.insert(name.as_str());
+                }
+            }
+        }
+    }
+
+    if undetermined.len() > MAX_UNDETERMINED_CONDITIONS {
+        return Err(Error::new(ErrorKind::IncompatibleError(
+            format!("Conditions {} could not be determined, which is more than the {} that can be evaluated in all combinations. Use --condition to set some of them",
+                    undetermined.iter().cloned().collect::<Vec<&str>>().join(", "), MAX_UNDETERMINED_CONDITIONS)
+        )))
+    }
+
+    let undetermined = undetermined.into_iter().collect::<Vec<&str>>();
+    let mut variants = Vec::with_capacity(1 << undetermined.len());
+    for combination in 0..(1usize << undetermined.len()) {
+        let mut selected = known.iter()
+            .filter_map(|(name, value)| value.map(|v| (name.as_str(), v)))
+            .collect::<HashMap<&str, bool>>();
+        let mut label = Vec::with_capacity(undetermined.len());
+        for (index, name) in undetermined.iter().enumerate() {
+            let value = combination & (1 << index) != 0;
+            selected.insert(*name, value);
+            label.push(format!("{}={}", name, value));
+        }
+        let resolved = resolve_value(template, &selected).unwrap_or_else(|| template.clone());
+        variants.push((label.join(","), remove_inactive_resources(resolved, &selected)));
+    }
+    Ok(variants)
+}
+
+///
+/// Replaces each {"Ref": "Name"} to a parameter declared in the template's Parameters section
+/// with the value supplied in values, or else the parameter's Default. The path of a substituted
+/// value is that of the Ref and names the parameter. Refs to parameters without a value or
+/// Default, to resources and to pseudo parameters like AWS::Region are left as they are.
+///
+/// CommaDelimitedList and List<...> parameters are substituted as lists and supplied values
+/// for Number parameters as numbers.
+///
+/// Fn::FindInMap is then replaced with the value from the template's Mappings section when its
+/// map name and keys are strings, literally or after substitution. Other intrinsic functions are
+/// left as they are.
+///
+pub(crate) fn resolve_parameters(template: &PathAwareValue, values: &HashMap<String, String>) -> PathAwareValue {
+    let mut resolved = HashMap::new();
+    if let Some(PathAwareValue::Map((_, parameters))) = top_level(template, "Parameters") {
+        for (name, parameter) in parameters.values.iter() {
+            let type_name = match top_level(parameter, "Type") {
+                Some(PathAwareValue::String((_, type_name))) => type_name.as_str(),
+                _ => "String"
+            };
+            let is_list = type_name == "CommaDelimitedList" || type_name.starts_with("List<");
+            let value = match (values.get(name), top_level(parameter, "Default")) {
+                (Some(value), _) => parameter_value(value, type_name, is_list),
+                (None, Some(PathAwareValue::String((_, value)))) if is_list => parameter_value(value, type_name, is_list),
+                (None, Some(default)) => default.clone(),
+                (None, None) => continue,
+            };
+            resolved.insert(name.as_str(), value);
+        }
+    }
+    let mappings = top_level(template, "Mappings");
+    substitute_parameters(template, &resolved, mappings)
+}
+
+fn parameter_value(value: &str, type_name: &str, is_list: bool) -> PathAwareValue {
+    let path = Path::root();
+    if is_list {
+        let elements = value.split(',').enumerate()
+            .map(|(index, each)| PathAwareValue::String((path.extend_usize(index), each.trim().to_string())))
+            .collect::<Vec<PathAwareValue>>();
+        return PathAwareValue::List((path, elements))
+    }
+    if type_name == "Number" {
+        if let Ok(i) = value.parse::<i64>() {
+            return PathAwareValue::Int((path, i))
+        }
+        if let Ok(f) = value.parse::<f64>() {
+            return PathAwareValue::Float((path, f))
+        }
+    }
+    PathAwareValue::String((path, value.to_string()))
+}
+
+fn substitute_parameters(
+    value: &PathAwareValue,
+    resolved: &HashMap<&str, PathAwareValue>,
+    mappings: Option<&PathAwareValue>,
+) -> PathAwareValue {
+    match value {
+        PathAwareValue::Map((path, map)) => {
+            if let Some(("Ref", PathAwareValue::String((_, name)))) = intrinsic(value) {
+                if let Some(parameter) = resolved.get(name.as_str()) {
+                    let mut substituted = parameter.clone();
+                    relocate(&mut substituted, path.via_parameter(name));
+                    return substituted
+                }
+            }
+            let mut substituted = MapValue { keys: map.keys.clone(), values: indexmap::IndexMap::with_capacity(map.values.len()) };
+            for (name, each) in map.values.iter() {
+                substituted.values.insert(name.clone(), substitute_parameters(each, resolved, mappings));
+            }
+            let substituted = PathAwareValue::Map((path.clone(), substituted));
+            match find_in_map(&substituted, mappings) {
+                Some(found) => {
+                    let mut found = found.clone();
+                    relocate(&mut found, path.clone());
+                    found
+                },
+                None => substituted
+            }
+        },
+
+        PathAwareValue::List((path, list)) => PathAwareValue::List((
+            path.clone(),
+            list.iter().map(|each| substitute_parameters(each, resolved, mappings)).collect()
+        )),
+
+        rest => rest.clone()
+    }
+}
+
+//
+// The value {"Fn::FindInMap": [map, top level key, second level key]} selects from mappings
+//
+fn find_in_map<'value>(value: &PathAwareValue, mappings: Option<&'value PathAwareValue>) -> Option<&'value PathAwareValue> {
+    let keys = match intrinsic(value) {
+        Some(("Fn::FindInMap", PathAwareValue::List((_, keys)))) if keys.len() == 3 => keys,
+        _ => return None
+    };
+    let mut current = mappings?;
+    for key in keys {
+        current = top_level(current, key.as_string()?)?;
+    }
+    Some(current)
+}
+
+//
+// Moves value and everything inside it to path
+//
+fn relocate(value: &mut PathAwareValue, path: Path) {
+    match value {
+        PathAwareValue::List((_, list)) => {
+            for (index, each) in list.iter_mut().enumerate() {
+                relocate(each, path.extend_usize(index));
+            }
+        },
+        PathAwareValue::Map((_, map)) => {
+            for key in map.keys.iter_mut() {
+                if let PathAwareValue::String((_, name)) = key {
+                    let key_path = path.extend_string(name);
+                    *key.self_path_mut() = key_path;
+                }
+            }
+            for (name, each) in map.values.iter_mut() {
+                relocate(each, path.extend_string(name));
+            }
+        },
+        _ => {}
+    }
+    *value.self_path_mut() = path;
+}
+
+fn top_level<'value>(value: &'value PathAwareValue, key: &str) -> Option<&'value PathAwareValue> {
+    match value {
+        PathAwareValue::Map((_, map)) => map.values.get(key),
+        _ => None
+    }
+}
+
+//
+// Returns the function name and its argument when value is a single key map like
+// {"Fn::If": [...]} or {"Ref": "..."}
+//
+fn intrinsic(value: &PathAwareValue) -> Option<(&str, &PathAwareValue)> {
+    match value {
+        PathAwareValue::Map((_, map)) if map.values.len() == 1 =>
+            map.values.iter().next().map(|(key, arg)| (key.as_str(), arg)),
+        _ => None
+    }
+}
+
+fn evaluate_conditions(template: &PathAwareValue, overrides: &HashMap<String, bool>) -> Result<HashMap<String, Option<bool>>> {
+    let mut evaluated = overrides.iter()
+        .map(|(name, value)| (name.clone(), Some(*value)))
+        .collect::<HashMap<String, Option<bool>>>();
+    if let Some(PathAwareValue::Map((_, conditions))) = top_level(template, "Conditions") {
+        for name in conditions.values.keys() {
+            let mut in_progress = Vec::new();
+            evaluate_named(name, conditions, &mut evaluated, &mut in_progress)?;
+        }
+    }
+    Ok(evaluated)
+}
+
+fn evaluate_named(
+    name: &str,
+    conditions: &MapValue,
+    evaluated: &mut HashMap<String, Option<bool>>,
+    in_progress: &mut Vec<String>) -> Result<Option<bool>> {
+    if let Some(value) = evaluated.get(name) {
+        return Ok(*value)
+    }
+
+    if in_progress.iter().any(|each| each == name) {
+        return Err(Error::new(ErrorKind::IncompatibleError(
+            format!("Cycle detected between conditions {} -> {}", in_progress.join(" -> "), name)
+        )))
+    }
+
+    let expr = match conditions.values.get(name) {
+        Some(expr) => expr,
+        None => return Err(Error::new(ErrorKind::MissingValue(
+            format!("Condition {} is not defined in the Conditions section", name)
+        )))
+    };
+    in_progress.push(name.to_string());
+    let value = evaluate_expr(expr, conditions, evaluated, in_progress)?;
+    in_progress.pop();
+    evaluated.insert(name.to_string(), value);
+    Ok(value)
+}
+
+//
+// Three valued evaluation, None when the outcome depends on something only known at deployment
+//
+fn evaluate_expr(
+    expr: &PathAwareValue,
+    conditions: &MapValue,
+    evaluated: &mut HashMap<String, Option<bool>>,
+    in_progress: &mut Vec<String>) -> Result<Option<bool>> {
+    match intrinsic(expr) {
+        Some(("Condition", PathAwareValue::String((_, name)))) =>
+            evaluate_named(name, conditions, evaluated, in_progress),
+
+        Some(("Fn::Not", PathAwareValue::List((_, args)))) if args.len() == 1 =>
+            Ok(evaluate_expr(&args[0], conditions, evaluated, in_progress)?.map(|value| !value)),
+
+        Some(("Fn::And", PathAwareValue::List((_, args)))) => {
+            let mut result = Some(true);
+            for each in args {
+                match evaluate_expr(each, conditions, evaluated, in_progress)? {
+                    Some(false) => return Ok(Some(false)),
+                    None => result = None,
+                    Some(true) => {}
+                }
+            }
+            Ok(result)
+        },
+
+        Some(("Fn::Or", PathAwareValue::List((_, args)))) => {
+            let mut result = Some(false);
+            for each in args {
+                match evaluate_expr(each, conditions, evaluated, in_progress)? {
+                    Some(true) => return Ok(Some(true)),
+                    None => result = None,
+                    Some(false) => {}
+                }
+            }
+            Ok(result)
+        },
+
+        Some(("Fn::Equals", PathAwareValue::List((_, args)))) if args.len() == 2 =>
+            Ok(match (literal(&args[0]), literal(&args[1])) {
+                (Some(first), Some(second)) => Some(first == second),
+                _ => None
+            }),
+
+        _ => Ok(None)
+    }
+}
+
+//
+// CloudFormation compares Fn::Equals arguments as strings
+//
+fn literal(value: &PathAwareValue) -> Option<String> {
+    match value {
+        PathAwareValue::String((_, s)) => Some(s.clone()),
+        PathAwareValue::Int((_, i)) => Some(i.to_string()),
+        PathAwareValue::Float((_, f)) => Some(f.to_string()),
+        PathAwareValue::Bool((_, b)) => Some(b.to_string()),
+        _ => None
+    }
+}
+
+fn collect_undetermined<'value>(
+    value: &'value PathAwareValue,
+    known: &HashMap<String, Option<bool>>,
+    undetermined: &mut BTreeSet<&'value str>) {
+    match value {
+        PathAwareValue::Map((_, map)) => {
+            if let Some(("Fn::If", PathAwareValue::List((_, args)))) = intrinsic(value) {
+                if let Some(PathAwareValue::String((_, name))) = args.first() {
+                    if !matches!(known.get(name.as_str()), Some(Some(_))) {
+                        undetermined.insert(name.as_str());
+                    }
+                }
+            }
+            for each in map.values.values() {
+                collect_undetermined(each, known, undetermined);
+            }
+        },
+        PathAwareValue::List((_, list)) => {
+            for each in list {
+                collect_undetermined(each, known, undetermined);
+            }
+        },
+        _ => {}
+    }
+}
+
+//
+// None when the value resolved to AWS::NoValue and must be removed from its parent
+//
+fn resolve_value(value: &PathAwareValue, conditions: &HashMap<&str, bool>) -> Option<PathAwareValue> {
+    match value {
+        PathAwareValue::Map((path, map)) => {
+            match intrinsic(value) {
+                Some(("Fn::If", PathAwareValue::List((_, args)))) if args.len() == 3 => {
+                    if let PathAwareValue::String((_, name)) = &args[0] {
+                        let branch = if conditions.get(name.as_str()).copied().unwrap_or(false) {
+                            &args[1]
+                        } else {
+                            &args[2]
+                        };
+                        return resolve_value(branch, conditions)
+                    }
+                },
+                Some(("Ref", PathAwareValue::String((_, name)))) if name == "AWS::NoValue" => return None,
+                _ => {}
+            }
+
+            let mut resolved = MapValue { keys: Vec::with_capacity(map.keys.len()), values: indexmap::IndexMap::with_capacity(map.values.len()) };
+            for (key, (name, each)) in map.keys.iter().zip(map.values.iter()) {
+                if let Some(each) = resolve_value(each, conditions) {
+                    resolved.keys.push(key.clone());
+                    resolved.values.insert(name.clone(), each);
+                }
+            }
+            Some(PathAwareValue::Map((path.clone(), resolved)))
+        },
+
+        PathAwareValue::List((path, list)) => {
+            let resolved = list.iter()
+                .filter_map(|each| resolve_value(each, conditions))
+                .collect::<Vec<PathAwareValue>>();
+            Some(PathAwareValue::List((path.clone(), resolved)))
+        },
+
+        rest => Some(rest.clone())
+    }
+}
+
+fn remove_inactive_resources(mut template: PathAwareValue, conditions: &HashMap<&str, bool>) -> PathAwareValue {
+    if let PathAwareValue::Map((_, root)) = &mut template {
+        if let Some(PathAwareValue::Map((_, resources))) = root.values.get_mut("Resources") {
+            let inactive = resources.values.iter()
+                .filter(|(_, resource)| match top_level(resource, "Condition") {
+                    Some(PathAwareValue::String((_, name))) => !conditions.get(name.as_str()).copied().unwrap_or(true),
+                    _ => false
+                })
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<String>>();
+            for name in &inactive {
+                resources.values.shift_remove(name);
+            }
+            resources.keys.retain(|key| match key {
+                PathAwareValue::String((_, name)) => !inactive.contains(name),
+                _ => true
+            });
+        }
+    }
+    template
+}
+
+#[cfg(test)]
+#[path = "intrinsics_tests.rs"]
+mod intrinsics_tests;
@@ -0,0 +1,260 @@
+use super::*;
+use std::convert::TryFrom;
+
+fn template() -> Result<PathAwareValue> {
+    PathAwareValue::try_from(serde_json::json!({
+        "Parameters": { "Env": { "Type": "String" } },
+        "Conditions": {
+            "IsProd": { "Fn::Equals": [{ "Ref": "Env" }, "prod"] },
+            "UseKms": { "Fn::Equals": ["yes", "yes"] },
+            "NoKms": { "Fn::Not": [{ "Condition": "UseKms" }] },
+            "ProdWithKms": { "Fn::And": [{ "Condition": "IsProd" }, { "Condition": "UseKms" }] },
+            "ProdOrKms": { "Fn::Or": [{ "Condition": "IsProd" }, { "Condition": "UseKms" }] }
+        },
+        "Resources": {
+            "bucket": {
+                "Type": "AWS::S3::Bucket",
+                "Properties": {
+                    "BucketEncryption": {
+                        "Fn::If": ["UseKms",
+                            { "SSEAlgorithm": { "Fn::If": ["IsProd", "aws:kms", "AES256"] } },
+                            { "Ref": "AWS::NoValue" }]
+                    },
+                    "Tags": [
+                        { "Fn::If": ["IsProd", { "Key": "Tier", "Value": "prod" }, { "Ref": "AWS::NoValue" }] },
+                        { "Key": "Owner", "Value": "team" }
+                    ]
+                }
+            },
+            "plain": {
+                "Type": "AWS::S3::Bucket",
+                "Condition": "NoKms",
+                "Properties": {}
+            }
+        }
+    }))
+}
+
+#[test]
+fn test_evaluate_conditions() -> Result<()> {
+    let template = template()?;
+    let evaluated = evaluate_conditions(&template, &HashMap::new())?;
+    assert_eq!(evaluated.get("IsProd"), Some(&None));
+    assert_eq!(evaluated.get("UseKms"), Some(&Some(true)));
+    assert_eq!(evaluated.get("NoKms"), Some(&Some(false)));
+    assert_eq!(evaluated.get("ProdWithKms"), Some(&None));
+    assert_eq!(evaluated.get("ProdOrKms"), Some(&Some(true)));
+
+    let overrides = HashMap::from([("IsProd".to_string(), true)]);
+    let evaluated = evaluate_conditions(&template, &overrides)?;
+    assert_eq!(evaluated.get("ProdWithKms"), Some(&Some(true)));
+
+    let cyclic = PathAwareValue::try_from(serde_json::json!({
+        "Conditions": {
+            "A": { "Condition": "B" },
+            "B": { "Fn::Not": [{ "Condition": "A" }] }
+        }
+    }))?;
+    match evaluate_conditions(&cyclic, &HashMap::new()) {
+        Err(Error(ErrorKind::IncompatibleError(msg))) => {
+            assert_eq!(msg, "Cycle detected between conditions A -> B -> A");
+        },
+        rest => unreachable!("{:?}", rest),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_resolve_nested_fn_if() -> Result<()> {
+    let template = template()?;
+    let variants = resolve_conditions(&template, &HashMap::new())?;
+    assert_eq!(variants.iter().map(|(label, _)| label.as_str()).collect::<Vec<&str>>(),
+               vec!["IsProd=false", "IsProd=true"]);
+
+    let expected_prod = PathAwareValue::try_from(serde_json::json!({
+        "SSEAlgorithm": "aws:kms"
+    }))?;
+    let expected_dev = PathAwareValue::try_from(serde_json::json!({
+        "SSEAlgorithm": "AES256"
+    }))?;
+    for ((_, resolved), (expected, tags)) in variants.iter().zip([(&expected_dev, 1), (&expected_prod, 2)]) {
+        let resources = match top_level(resolved, "Resources") {
+            Some(PathAwareValue::Map((_, resources))) => resources,
+            rest => unreachable!("{:?}", rest),
+        };
+        //
+        // plain is only created when UseKms is false
+        //
+        assert_eq!(resources.values.len(), 1);
+        assert_eq!(resources.keys.len(), 1);
+        let properties = top_level(&resources.values["bucket"], "Properties").unwrap();
+        assert_eq!(top_level(properties, "BucketEncryption"), Some(expected));
+        match top_level(properties, "Tags") {
+            Some(PathAwareValue::List((_, list))) => assert_eq!(list.len(), tags),
+            rest => unreachable!("{:?}", rest),
+        }
+    }
+
+    let overrides = HashMap::from([("IsProd".to_string(), true), ("UseKms".to_string(), false)]);
+    let variants = resolve_conditions(&template, &overrides)?;
+    assert_eq!(variants.len(), 1);
+    let (label, resolved) = &variants[0];
+    assert_eq!(label, "");
+    let resources = match top_level(resolved, "Resources") {
+        Some(PathAwareValue::Map((_, resources))) => resources,
+        rest => unreachable!("{:?}", rest),
+    };
+    assert_eq!(resources.values.len(), 2);
+    let properties = top_level(&resources.values["bucket"], "Properties").unwrap();
+    assert_eq!(top_level(properties, "BucketEncryption"), None);
+    Ok(())
+}
+
+fn parameterized_template() -> Result<PathAwareValue> {
+    PathAwareValue::try_from(serde_json::json!({
+        "Parameters": {
+            "InstanceTypeParam": { "Type": "String", "Default": "t3.micro" },
+            "Subnets": { "Type": "CommaDelimitedList", "Default": "subnet-1, subnet-2" },
+            "SecurityGroups": { "Type": "List<AWS::EC2::SecurityGroup::Id>" },
+            "KeyName": { "Type": "AWS::EC2::KeyPair::KeyName" },
+            "Volumes": { "Type": "Number" }
+        },
+        "Resources": {
+            "vpc": { "Type": "AWS::EC2::VPC", "Properties": {} },
+            "instance": {
+                "Type": "AWS::EC2::Instance",
+                "Properties": {
+                    "InstanceType": { "Ref": "InstanceTypeParam" },
+                    "SubnetIds": { "Ref": "Subnets" },
+                    "SecurityGroupIds": { "Ref": "SecurityGroups" },
+                    "KeyName": { "Ref": "KeyName" },
+                    "VolumeCount": { "Ref": "Volumes" },
+                    "VpcId": { "Ref": "vpc" },
+                    "AvailabilityZone": { "Ref": "AWS::Region" }
+                }
+            }
+        }
+    }))
+}
+
+fn instance_property<'value>(template: &'value PathAwareValue, name: &str) -> &'value PathAwareValue {
+    let resources = top_level(template, "Resources").unwrap();
+    let properties = top_level(top_level(resources, "instance").unwrap(), "Properties").unwrap();
+    top_level(properties, name).unwrap()
+}
+
+#[test]
+fn test_resolve_parameters_with_defaults() -> Result<()> {
+    let template = parameterized_template()?;
+    let resolved = resolve_parameters(&template, &HashMap::new());
+
+    let instance_type = instance_property(&resolved, "InstanceType");
+    assert_eq!(instance_type.as_string(), Some("t3.micro"));
+    assert_eq!(instance_type.self_path().0, "/Resources/instance/Properties/InstanceType");
+    assert_eq!(instance_type.self_path().2.as_deref(), Some("InstanceTypeParam"));
+    assert!(format!("{}", instance_type.self_path()).ends_with("via Parameter InstanceTypeParam"));
+
+    let subnets = instance_property(&resolved, "SubnetIds").as_list().unwrap();
+    assert_eq!(subnets.iter().map(|each| each.as_string().unwrap()).collect::<Vec<&str>>(),
+               vec!["subnet-1", "subnet-2"]);
+    assert_eq!(subnets[1].self_path().0, "/Resources/instance/Properties/SubnetIds/1");
+    assert_eq!(subnets[1].self_path().2.as_deref(), Some("Subnets"));
+
+    //
+    // no default and no value supplied, resources and pseudo parameters are left as is
+    //
+    for name in ["SecurityGroupIds", "KeyName", "VolumeCount", "VpcId", "AvailabilityZone"] {
+        assert_eq!(instance_property(&resolved, name), instance_property(&template, name));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_resolve_parameters_with_supplied_values() -> Result<()> {
+    let template = parameterized_template()?;
+    let values = HashMap::from([
+        ("InstanceTypeParam".to_string(), "m5.large".to_string()),
+        ("SecurityGroups".to_string(), "sg-1,sg-2,sg-3".to_string()),
+        ("Volumes".to_string(), "2".to_string()),
+        ("vpc".to_string(), "vpc-123".to_string()),
+    ]);
+    let resolved = resolve_parameters(&template, &values);
+    assert_eq!(instance_property(&resolved, "InstanceType").as_string(), Some("m5.large"));
+    assert_eq!(instance_property(&resolved, "SecurityGroupIds").as_list().map(|list| list.len()), Some(3));
+    assert_eq!(instance_property(&resolved, "VolumeCount").as_int(), Some(2));
+    assert_eq!(instance_property(&resolved, "VpcId"), instance_property(&template, "VpcId"));
+    Ok(())
+}
+
+#[test]
+fn test_resolve_find_in_map() -> Result<()> {
+    let template = PathAwareValue::try_from(serde_json::json!({
+        "Parameters": { "Env": { "Type": "String" } },
+        "Mappings": {
+            "EnvSettings": {
+                "prod": { "Monitoring": true, "Subnets": ["subnet-1", "subnet-2"] },
+                "dev": { "Monitoring": false }
+            }
+        },
+        "Resources": {
+            "instance": {
+                "Type": "AWS::EC2::Instance",
+                "Properties": {
+                    "Monitoring": { "Fn::FindInMap": ["EnvSettings", { "Ref": "Env" }, "Monitoring"] },
+                    "SubnetIds": { "Fn::FindInMap": ["EnvSettings", "prod", "Subnets"] },
+                    "ImageId": { "Fn::FindInMap": ["EnvSettings", "prod", "ImageId"] },
+                    "Region": { "Fn::FindInMap": ["EnvSettings", { "Ref": "AWS::Region" }, "Monitoring"] }
+                }
+            }
+        }
+    }))?;
+
+    //
+    // Env has no value, only the map with literal keys resolves
+    //
+    let resolved = resolve_parameters(&template, &HashMap::new());
+    assert_eq!(instance_property(&resolved, "Monitoring"), instance_property(&template, "Monitoring"));
+    let subnets = instance_property(&resolved, "SubnetIds").as_list().unwrap();
+    assert_eq!(subnets.len(), 2);
+    assert_eq!(subnets[1].self_path().0, "/Resources/instance/Properties/SubnetIds/1");
+
+    let resolved = resolve_parameters(&template, &HashMap::from([("Env".to_string(), "dev".to_string())]));
+    let monitoring = instance_property(&resolved, "Monitoring");
+    assert_eq!(monitoring, &PathAwareValue::Bool((monitoring.self_path().clone(), false)));
+    assert_eq!(monitoring.self_path().0, "/Resources/instance/Properties/Monitoring");
+
+    //
+    // unknown keys and Refs that are not parameters are left as is
+    //
+    for name in ["ImageId", "Region"] {
+        assert_eq!(instance_property(&resolved, name), instance_property(&template, name));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_failure_on_substituted_value_names_parameter() -> Result<()> {
+    use crate::rules::eval::eval_rules_file;
+    use crate::rules::eval_context::root_scope;
+    use crate::rules::exprs::RulesFile;
+    use crate::rules::{ClauseCheck, RecordType, Status};
+
+    let rules = RulesFile::try_from(r#"
+    rule large_instances {
+        Resources.instance.Properties.InstanceType == "m5.large"
+    }
+    "#)?;
+    let resolved = resolve_parameters(&parameterized_template()?, &HashMap::new());
+    let mut root = root_scope(&rules, &resolved)?;
+    assert_eq!(eval_rules_file(&rules, &mut root)?, Status::FAIL);
+    let top = root.reset_recorder().extract();
+    let clause = &top.children[0].children[0].children[0];
+    match &clause.container {
+        Some(RecordType::ClauseValueCheck(ClauseCheck::Comparison(check))) => {
+            let from = check.from.resolved().unwrap();
+            assert!(format!("{}", from.self_path()).contains("InstanceType[L:0,C:0] via Parameter InstanceTypeParam"));
+        },
+        rest => unreachable!("{:?}", rest),
+    }
+    Ok(())
+}
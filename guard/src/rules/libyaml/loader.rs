@@ -74,6 +74,8 @@ impl Loader {
                 }
             } else if style != ScalarStyle::Plain {
                 MarkedValue::String(val, location)
+            } else if matches!(val.as_str(), "null" | "Null" | "NULL" | "~") {
+                MarkedValue::Null(location)
             } else {
                 if !val.is_empty() && Self::is_number(&val) {
                     match val.parse::<i64>() {
@@ -168,13 +170,17 @@ impl Loader {
         self.last_container_index.push(self.stack.len() - 1);
     }
 
+    //
+    // Plain decimal numbers, e.g. 10, -3 or 0.5. Other forms like 1e3 or .5 stay strings
+    //
     fn is_number(val: &str) -> bool {
-        for c in val.chars() {
-            if !c.is_numeric() {
-                return false;
-            }
-        }
-        return true;
+        let digits = val.strip_prefix('-').unwrap_or(val);
+        let (whole, fraction) = match digits.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (digits, None),
+        };
+        !whole.is_empty() && whole.chars().all(|c| c.is_ascii_digit()) &&
+            fraction.is_none_or(|f| !f.is_empty() && f.chars().all(|c| c.is_ascii_digit()))
     }
 
     fn short_form_to_long(fn_ref: &str) -> &'static str {
@@ -8,22 +8,24 @@ pub(crate) mod eval_context;
 pub(crate) mod eval;
 pub(crate) mod display;
 pub(crate) mod functions;
+pub(crate) mod references;
+pub(crate) mod intrinsics;
 mod libyaml;
 
 use errors::Error;
 
 use std::fmt::Formatter;
 use colored::*;
-use crate::rules::path_value::PathAwareValue;
+use crate::rules::path_value::{EvaluationOptions, PathAwareValue};
 use nom::lib::std::convert::TryFrom;
 use crate::rules::errors::ErrorKind;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use crate::rules::values::CmpOperator;
 use crate::rules::exprs::{ParameterizedRule, QueryPart};
 
 pub(crate) type Result<R> = std::result::Result<R, Error>;
 
-#[derive(Debug, Clone, PartialEq, Copy, Serialize)]
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
 pub(crate) enum Status {
     PASS,
     FAIL,
@@ -62,7 +64,7 @@ impl TryFrom<&str> for Status {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Copy, Serialize)]
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
 pub(crate) enum EvaluationType {
     File,
     Rule,
@@ -100,6 +102,21 @@ pub(crate) struct UnResolved<'value> {
     pub(crate) reason: Option<String>,
 }
 
+impl<'value> UnResolved<'value> {
+    //
+    // True when the query stopped selecting all entries of an empty list or map, e.g. Tags[*]
+    // for Tags: [], rather than at a key or index that does not exist
+    //
+    pub(crate) fn is_empty_collection(&self) -> bool {
+        let empty = match self.traversed_to {
+            PathAwareValue::List((_, list)) => list.is_empty(),
+            PathAwareValue::Map((_, map)) => map.is_empty(),
+            _ => false,
+        };
+        empty && (self.remaining_query.starts_with("[*]") || self.remaining_query.starts_with('*'))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub(crate) enum QueryResult<'value> {
     Literal(&'value PathAwareValue),
@@ -221,6 +238,12 @@ pub(crate) enum RecordType<'value> {
     //
     RuleCheck(NamedStatus<'value>),
 
+    //
+    // a rule referenced again after its status was computed for the document, has no child
+    // events, the evaluation is under its RuleCheck. It is not counted as a rule or a clause
+    //
+    CachedRuleCheck(NamedStatus<'value>),
+
     //
     // has as many child events for each GuardClauseBlockCheck | Disjunction
     //
@@ -300,6 +323,7 @@ pub(crate) trait EvalContext<'value, 'loc: 'value> : RecordTracer<'value> {
     //fn resolve(&self, guard_clause: &GuardAccessClause<'_>) -> Result<Vec<QueryResult<'value>>>;
     fn find_parameterized_rule(&mut self, rule_name: &str) -> Result<&'value ParameterizedRule<'loc>>;
     fn root(&mut self) -> &'value PathAwareValue;
+    fn options(&self) -> &EvaluationOptions;
     fn rule_status(&mut self, rule_name: &'value str) -> Result<Status>;
     fn resolve_variable(&mut self, variable_name: &'value str) -> Result<Vec<QueryResult<'value>>>;
     fn add_variable_capture_key(&mut self, variable_name: &'value str, key: &'value PathAwareValue) -> Result<()>;
@@ -312,6 +336,10 @@ pub(crate) trait EvaluationContext {
 
     fn rule_status(&self, rule_name: &str) -> Result<Status>;
 
+    fn resource_count(&self, resource_type: &str) -> usize;
+
+    fn options(&self) -> &EvaluationOptions;
+
     fn end_evaluation(
         &self,
         eval_type: EvaluationType,
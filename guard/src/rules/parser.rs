@@ -3,17 +3,17 @@ use std::fmt::Formatter;
 
 use indexmap::map::IndexMap;
 use nom::branch::alt;
-use nom::bytes::complete::{is_not, take_while, take_while1};
+use nom::bytes::complete::{is_a, is_not, take_while, take_while1};
 use nom::bytes::complete::{tag, take_till};
-use nom::character::complete::{alpha1, newline, space1};
+use nom::character::complete::{alpha1, alphanumeric1, newline, space1};
 use nom::character::complete::{anychar, digit1, one_of};
 use nom::character::complete::{char, multispace0, multispace1, space0};
-use nom::combinator::{all_consuming, cut, peek};
+use nom::combinator::{all_consuming, cut, peek, recognize};
 use nom::combinator::{map, value};
 use nom::combinator::{map_res, opt};
 use nom::error::context;
 use nom::error::ErrorKind;
-use nom::multi::{fold_many1, separated_list, separated_nonempty_list};
+use nom::multi::{fold_many0, fold_many1, separated_list, separated_nonempty_list};
 use nom::multi::{many0, many1};
 use nom::number::complete::double;
 use nom::sequence::{delimited, preceded};
@@ -85,18 +85,38 @@ impl<'a> nom::error::ParseError<Span<'a>> for ParserError<'a> {
     }
 }
 
+//
+// Renders the error in the style of rustc diagnostics, e.g.
+//
+// Error parsing file rules.guard at line 2 at column 24, when handling rule block
+//   --> rules.guard:2:24
+//    |
+//  2 |     Resources.*.Type == 
+//    |                        ^
+//
 impl<'a> std::fmt::Display for ParserError<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let message = format!(
-            "Error parsing file {} at line {} at column {}, when handling {}, fragment {}",
-            self.span.extra,
-            self.span.location_line(),
-            self.span.get_utf8_column(),
-            self.context,
-            *self.span.fragment()
-        );
-        f.write_str(&message)?;
-        Ok(())
+        let line = self.span.location_line();
+        let column = self.span.get_utf8_column();
+        write!(f, "Error parsing file {} at line {} at column {}", self.span.extra, line, column)?;
+        if !self.context.is_empty() {
+            write!(f, ", when handling {}", self.context)?;
+        }
+
+        //
+        // The span only holds the remaining input, the full line is read back from
+        // the original text. Tabs are kept in the caret line so that it lines up with the source
+        //
+        let source = String::from_utf8_lossy(self.span.get_line_beginning());
+        let caret = source.chars()
+            .take(column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        let gutter = " ".repeat(line.to_string().len());
+        write!(f, "\n{} --> {}:{}:{}", gutter, self.span.extra, line, column)?;
+        write!(f, "\n{}  |", gutter)?;
+        write!(f, "\n {} | {}", line, source.trim_end())?;
+        write!(f, "\n{}  | {}^", gutter, caret)
     }
 }
 
@@ -164,14 +184,18 @@ pub(in crate::rules) fn followed_by(ch: char) -> impl Fn(Span) -> IResult<Span,
 //                                                                                                //
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
+//
+// Digits with an optional leading sign, e.g. -1, +5. Only used for numeric values, the sign
+// must be immediately followed by the digits
+//
+fn signed_digits(input: Span) -> IResult<Span, Span> {
+    recognize(preceded(opt(one_of("+-")), digit1))(input)
+}
+
 pub(in crate::rules) fn parse_int_value(input: Span) -> IResult<Span, Value> {
-    let negative = map_res(preceded(tag("-"), digit1), |s: Span| {
-        s.fragment().parse::<i64>().map(|i| Value::Int(-i))
-    });
-    let positive = map_res(digit1, |s: Span| {
+    map_res(signed_digits, |s: Span| {
         s.fragment().parse::<i64>().map(Value::Int)
-    });
-    alt((positive, negative))(input)
+    })(input)
 }
 
 fn parse_string_inner(ch: char) -> impl Fn(Span) -> IResult<Span, Value> {
@@ -219,7 +243,7 @@ fn parse_bool(input: Span) -> IResult<Span, Value> {
 }
 
 fn parse_float(input: Span) -> IResult<Span, Value> {
-    let whole = digit1(input)?;
+    let whole = signed_digits(input)?;
     let fraction = opt(preceded(char('.'), digit1))(whole.0)?;
     let exponent = opt(tuple((one_of("eE"), one_of("+-"), digit1)))(fraction.0)?;
     if (fraction.1).is_some() || (exponent.1).is_some() {
@@ -264,8 +288,24 @@ fn parse_regex_inner(input: Span) -> IResult<Span, Value> {
     }
 }
 
+//
+// Flags directly after the closing '/', i case-insensitive, m multi-line (^ and $ match at line
+// boundaries), s dot matches newline and f the whole string must match rather than a substring.
+// They become inline flags of the regular expression, hence /abc/if is the same as /\A(?i:abc)\z/
+// everywhere it is compiled
+//
 fn parse_regex(input: Span) -> IResult<Span, Value> {
-    delimited(char('/'), parse_regex_inner, char('/'))(input)
+    let (input, regex) = delimited(char('/'), parse_regex_inner, char('/'))(input)?;
+    let (input, flags) = terminated(opt(is_a("imsf")), nom::combinator::not(alt((alphanumeric1, tag("_")))))(input)?;
+    let (regex, flags) = match (regex, flags) {
+        (Value::Regex(regex), Some(flags)) => (regex, *flags.fragment()),
+        (regex, None) => return Ok((input, regex)),
+        _ => unreachable!(),
+    };
+    let inline = ['i', 'm', 's'].iter().filter(|flag| flags.contains(**flag)).collect::<String>();
+    let regex = if inline.is_empty() { regex } else { format!("(?{}:{})", inline, regex) };
+    let regex = if flags.contains('f') { format!("\\A(?:{})\\z", regex) } else { regex };
+    Ok((input, Value::Regex(regex)))
 }
 
 fn parse_char(input: Span) -> IResult<Span, Value> {
@@ -305,6 +345,21 @@ fn parse_range(input: Span) -> IResult<Span, Value> {
             inclusive,
         }),
 
+        //
+        // r[1, 10.5) mixes bounds, the range is a float range
+        //
+        (Value::Int(s), Value::Float(e)) => Value::RangeFloat(RangeType {
+            upper: e,
+            lower: s as f64,
+            inclusive,
+        }),
+
+        (Value::Float(s), Value::Int(e)) => Value::RangeFloat(RangeType {
+            upper: e as f64,
+            lower: s,
+            inclusive,
+        }),
+
         (Value::Char(s), Value::Char(e)) => Value::RangeChar(RangeType {
             upper: e,
             lower: s,
@@ -370,24 +425,37 @@ fn key_part(input: Span) -> IResult<Span, String> {
     ))(input)
 }
 
-fn key_value(input: Span) -> IResult<Span, (String, Value)> {
-    separated_pair(
-        preceded(zero_or_more_ws_or_comment, key_part),
-        followed_by(':'),
-        parse_value,
-    )(input)
+//
+// The key along with where it starts, to report duplicates at
+//
+fn key_value(input: Span) -> IResult<Span, (Span, String, Value)> {
+    let (input, _ws) = zero_or_more_ws_or_comment(input)?;
+    let (rest, (key, value)) = separated_pair(key_part, followed_by(':'), parse_value)(input)?;
+    Ok((rest, (input, key, value)))
 }
 
+//
+// Keys are bare like bare, or quoted like 'work' or "a:b" when they contain other characters.
+// A key that repeats an earlier one is an error, instead of replacing its value
+//
 fn parse_map(input: Span) -> IResult<Span, Value> {
-    let result = delimited(
+    let (input, entries) = delimited(
         char('{'),
         separated_list(separated_by(','), key_value),
         followed_by('}'),
     )(input)?;
-    Ok((
-        result.0,
-        Value::Map(result.1.into_iter().collect::<IndexMap<String, Value>>()),
-    ))
+    let mut map = IndexMap::with_capacity(entries.len());
+    for (location, key, value) in entries {
+        if map.contains_key(&key) {
+            return Err(nom::Err::Failure(ParserError {
+                span: location,
+                kind: ErrorKind::Verify,
+                context: format!("Duplicate key {} in map", key),
+            }))
+        }
+        map.insert(key, value);
+    }
+    Ok((input, Value::Map(map)))
 }
 
 fn parse_null(input: Span) -> IResult<Span, Value> {
@@ -455,11 +523,13 @@ pub(crate) fn parse_value(input: Span) -> IResult<Span, Value> {
 ///  ```ABNF
 ///
 ///  or_term                    = "or" / "OR" / "|OR|"
+///  and_term                   = "and" / "AND" / "|AND|"
 ///
 ///  var_name                   = 1*CHAR [ 1*(CHAR/ALPHA/_) ]
 ///  var_name_access            = "%" var_name
 ///
-///  dotted_access              = "." (var_name / var_name_access / "*")
+///  quoted_name                = "'" *CHAR "'" / DQUOTE *CHAR DQUOTE
+///  dotted_access              = "." (var_name / quoted_name / var_name_access / "*" / regex)
 ///
 ///  property_access            = var_name [ dotted_access ]
 ///  variable_access            = var_name_access [ dotted_access ]
@@ -472,17 +542,20 @@ pub(crate) fn parse_value(input: Span) -> IResult<Span, Value> {
 ///  other_operators            = "IN" / "EXISTS" / "EMPTY"
 ///  not_other_operators        = not_keyword 1*SP other_operators
 ///  not_cmp                    = "!=" / not_other_operators / "NOT_IN"
-///  special_operators          = "KEYS" 1*SP ("==" / other_operators / not_other_operators)
+///  special_operators          = "KEYS" 1*SP ("==" / other_operators / not_other_operators / "NOT_IN")
 ///
 ///  cmp                        = basic_cmp / other_operators / not_cmp / special_operators
 ///
-///  clause                     = access 1*(LWSP/comment) cmp 1*(LWSP/comment) [(access/value)]
+///  resource_ref               = "RESOURCE_REF" "(" type_name "," access ")"
+///  resource_count             = type_name 1*(LWSP/comment) "RESOURCE_COUNT"
+///  clause                     = (access/resource_count) 1*(LWSP/comment) cmp 1*(LWSP/comment) [(access/resource_ref/value)]
 ///  rule_clause                = rule_name / not_keyword rule_name / clause
 ///  rule_disjunction_clauses   = rule_clause 1*(or_term 1*(LWSP/comment) rule_clause)
-///  rule_conjunction_clauses   = rule_clause 1*( (LSWP/comment) rule_clause )
+///  rule_conjunction_clauses   = rule_clause 1*( (LSWP/comment) [and_term 1*(LWSP/comment)] rule_clause )
 ///
 ///  type_clause                = type_name 1*SP clause
-///  type_block                 = type_name *SP [when] "{" *(LWSP/comment) 1*clause "}"
+///  type_block                 = [not_keyword 1*SP] type_name *SP [when] "{" *(LWSP/comment) 1*clause "}"
+///  type_exists                = [not_keyword 1*SP] type_name 1*SP [not_keyword] "EXISTS"
 ///
 ///  type_expr                  = type_clause / type_block
 ///
@@ -562,6 +635,28 @@ fn in_keyword(input: Span) -> IResult<Span, CmpOperator> {
     value(CmpOperator::In, alt((tag("in"), tag("IN"))))(input)
 }
 
+//
+// IN_CIDR, the LHS address or block lies within the RHS CIDR block
+//
+fn in_cidr_keyword(input: Span) -> IResult<Span, CmpOperator> {
+    value(CmpOperator::InCidr, alt((tag("IN_CIDR"), tag("in_cidr"))))(input)
+}
+
+//
+// ALL IN, every element of a list is IN the RHS
+//
+fn all_in_keyword(input: Span) -> IResult<Span, CmpOperator> {
+    let (input, _all) = terminated(alt((tag("ALL"), tag("all"))), space1)(input)?;
+    value(CmpOperator::AllIn, in_keyword)(input)
+}
+
+//
+// Contiguous form of NOT IN
+//
+fn not_in_keyword(input: Span) -> IResult<Span, (CmpOperator, bool)> {
+    value((CmpOperator::In, true), alt((tag("NOT_IN"), tag("not_in"))))(input)
+}
+
 fn not(input: Span) -> IResult<Span, ()> {
     match alt((preceded(tag("not"), space1), preceded(tag("NOT"), space1)))(input) {
         Ok((remainder, _not)) => Ok((remainder, ())),
@@ -594,9 +689,32 @@ fn empty(input: Span) -> IResult<Span, CmpOperator> {
     value(CmpOperator::Empty, alt((tag("EMPTY"), tag("empty"))))(input)
 }
 
+fn unique(input: Span) -> IResult<Span, CmpOperator> {
+    value(
+        CmpOperator::Unique,
+        alt((tag("UNIQUE"), tag("unique"), tag("DISTINCT"), tag("distinct"))),
+    )(input)
+}
+
+fn equals_unordered(input: Span) -> IResult<Span, CmpOperator> {
+    value(CmpOperator::EqUnordered, alt((tag("EQUALS_UNORDERED"), tag("equals_unordered"))))(input)
+}
+
+fn subset(input: Span) -> IResult<Span, CmpOperator> {
+    value(CmpOperator::Subset, alt((tag("SUBSET"), tag("subset"))))(input)
+}
+
+fn superset(input: Span) -> IResult<Span, CmpOperator> {
+    value(CmpOperator::Superset, alt((tag("SUPERSET"), tag("superset"))))(input)
+}
+
 fn other_operations(input: Span) -> IResult<Span, (CmpOperator, bool)> {
+    alt((not_in_keyword, negatable_operations))(input)
+}
+
+fn negatable_operations(input: Span) -> IResult<Span, (CmpOperator, bool)> {
     let (input, not) = opt(not)(input)?;
-    let (input, operation) = alt((in_keyword, exists, empty, is_type_operations))(input)?;
+    let (input, operation) = alt((in_cidr_keyword, in_keyword, all_in_keyword, exists, empty, unique, equals_unordered, subset, superset, is_type_operations))(input)?;
     Ok((input, (operation, not.is_some())))
 }
 
@@ -607,7 +725,7 @@ fn is_list(input: Span) -> IResult<Span, CmpOperator> {
 fn is_struct(input: Span) -> IResult<Span, CmpOperator> {
     value(
         CmpOperator::IsMap,
-        alt((tag("IS_STRUCT"), tag("is_struct"))),
+        alt((tag("IS_STRUCT"), tag("is_struct"), tag("IS_MAP"), tag("is_map"))),
     )(input)
 }
 
@@ -632,8 +750,15 @@ fn is_int(input: Span) -> IResult<Span, CmpOperator> {
         )))(input)
 }
 
+fn is_number(input: Span) -> IResult<Span, CmpOperator> {
+    value( CmpOperator::IsNumber, alt((
+        tag("IS_NUMBER"),
+        tag("is_number"),
+        )))(input)
+}
+
 fn is_type_operations(input: Span) -> IResult<Span, CmpOperator> {
-    alt(( is_string, is_list, is_struct, is_bool, is_int))(input)
+    alt(( is_string, is_list, is_struct, is_bool, is_int, is_number))(input)
 }
 
 pub(crate) fn value_cmp(input: Span) -> IResult<Span, (CmpOperator, bool)> {
@@ -669,24 +794,41 @@ pub(crate) fn value_cmp(input: Span) -> IResult<Span, (CmpOperator, bool)> {
     ))(input)
 }
 
-fn extract_message(input: Span) -> IResult<Span, &str> {
-    match input.find_substring(">>") {
-        None => Err(nom::Err::Failure(ParserError {
-            span: input,
-            kind: ErrorKind::Tag,
-            context: "Unable to find a closing >> tag for message".to_string(),
-        })),
-        Some(v) => {
-            let split = input.take_split(v);
-            Ok((split.0, *split.1.fragment()))
+//
+//   custom_message   =   "<<" *(<any char> / "\>>") ">>"
+//
+// A \>> inside the message is a literal >> that does not close it, and is stored unescaped.
+// A message that is never closed is reported at its opening <<
+//
+fn custom_message(input: Span) -> IResult<Span, String> {
+    let (mut remaining, _open) = tag("<<")(input)?;
+    let mut message = String::new();
+    loop {
+        match remaining.find_substring(">>") {
+            None => return Err(nom::Err::Failure(ParserError {
+                span: input,
+                kind: ErrorKind::Tag,
+                context: "Unable to find a closing >> for the message that starts here, write \\>> for a >> inside the message".to_string(),
+            })),
+            Some(v) => {
+                let (rest, part) = remaining.take_split(v);
+                let part = *part.fragment();
+                match part.strip_suffix('\\') {
+                    Some(escaped) => {
+                        message.push_str(escaped);
+                        message.push_str(">>");
+                    },
+                    None => {
+                        message.push_str(part);
+                        return Ok((rest.slice(2..), message))
+                    }
+                }
+                remaining = rest.slice(2..);
+            }
         }
     }
 }
 
-fn custom_message(input: Span) -> IResult<Span, &str> {
-    delimited(tag("<<"), extract_message, tag(">>"))(input)
-}
-
 pub(crate) fn does_comparator_have_rhs(op: &CmpOperator) -> bool {
     !op.is_unary()
 }
@@ -697,12 +839,21 @@ fn variable_capture_in_map_or_index(input: Span) -> IResult<Span, String> {
     Ok((input, var))
 }
 
+//
+//  predicate_filter_clauses   = "[" ["!"] [var_name "|"] clauses "]"
+//
+//  A "!" immediately following the "[" selects the elements for which the clauses do not PASS
+//
 fn predicate_filter_clauses(input: Span) -> IResult<Span, QueryPart> {
     let (input, _open) = open_array(input)?;
+    let (input, negated) = opt(char('!'))(input)?;
     let (input, var) = opt(variable_capture_in_map_or_index)(input)?;
     let (input, filters) = cnf_clauses(input, clause, std::convert::identity, true)?;
     let (input, _close) = cut(close_array)(input)?;
-    Ok((input, QueryPart::Filter(var, filters)))
+    Ok((input, match negated {
+        Some(_) => QueryPart::FilterNot(var, filters),
+        None => QueryPart::Filter(var, filters),
+    }))
 }
 
 fn dotted_property(input: Span) -> IResult<Span, QueryPart> {
@@ -721,11 +872,28 @@ fn dotted_property(input: Span) -> IResult<Span, QueryPart> {
                 map(property_name, QueryPart::Key),
                 map(var_name_access_inclusive, QueryPart::Key),
                 value(QueryPart::AllValues(None), char('*')),
+                map(parse_regex, |regex| match regex {
+                    Value::Regex(regex) => QueryPart::KeyRegex(regex),
+                    _ => unreachable!(),
+                }),
             )), // end alt
         ), // end preceded for char '.'
     )(input)
 }
 
+//
+//  recursive_descent          = ".." property_name
+//
+fn recursive_descent(input: Span) -> IResult<Span, QueryPart> {
+    preceded(
+        zero_or_more_ws_or_comment,
+        preceded(
+            tag(".."),
+            map(property_name, QueryPart::RecursiveDescent),
+        ),
+    )(input)
+}
+
 fn open_array(input: Span) -> IResult<Span, ()> {
     value((), preceded(zero_or_more_ws_or_comment, char('[')))(input)
 }
@@ -749,7 +917,11 @@ fn all_indices(input: Span) -> IResult<Span, QueryPart> {
 
 fn array_index(input: Span) -> IResult<Span, QueryPart> {
     map(
-        delimited(open_array, parse_int_value, cut(close_array)),
+        delimited(
+            open_array,
+            preceded(zero_or_more_ws_or_comment, parse_int_value),
+            cut(close_array),
+        ),
         |idx| {
             let idx = match idx {
                 Value::Int(i) => i as i32,
@@ -791,8 +963,11 @@ fn map_keys_match(input: Span) -> IResult<Span, QueryPart> {
         zero_or_more_ws_or_comment,
         alt((
             eq,
+            not_in_keyword,
             value((CmpOperator::In, false), in_keyword),
             map(tuple((not, in_keyword)), |_m| (CmpOperator::In, true)),
+            value((CmpOperator::AllIn, false), all_in_keyword),
+            map(tuple((not, all_in_keyword)), |_m| (CmpOperator::AllIn, true)),
         )),
     ))(input)?;
     let (input, with) = cut(preceded(
@@ -831,7 +1006,14 @@ fn predicate_or_index(input: Span) -> IResult<Span, QueryPart> {
 }
 
 //
-//  dotted_access              = "." (var_name / "*")
+//  dotted_access              = 1*( "." (property_name / var_name_access / "*" / int / regex) /
+//                                   ".." property_name / "[" (int / "*") "]" / predicate_or_index )
+//
+//  A list index can be written as ".0" or "[0]", every element of a list as ".*" or "[*]"
+//
+//  A key that var_name does not allow, e.g. 'aws:SourceVpc' or "my.dotted.key", is written
+//  quoted and is used verbatim as a single key. A regex, e.g. ./aws:[sS]ourceVpce?/, selects
+//  the values of all keys in a map that match it
 //
 // This combinator does not fail. It is the responsibility of the consumer to fail based
 // on error.
@@ -843,7 +1025,7 @@ fn predicate_or_index(input: Span) -> IResult<Span, QueryPart> {
 //
 fn dotted_access(input: Span) -> IResult<Span, Vec<QueryPart>> {
     fold_many1(
-        alt((dotted_property, predicate_or_index)),
+        alt((recursive_descent, dotted_property, predicate_or_index)),
         Vec::new(),
         |mut acc: Vec<QueryPart>, part| {
             acc.push(part);
@@ -852,6 +1034,9 @@ fn dotted_access(input: Span) -> IResult<Span, Vec<QueryPart>> {
     )(input)
 }
 
+//
+//  property_name              = var_name / string
+//
 fn property_name(input: Span) -> IResult<Span, String> {
     alt((
         var_name,
@@ -884,7 +1069,16 @@ fn this_keyword(input: Span) -> IResult<Span, QueryPart> {
 }
 
 //
-//   access     =   (var_name / var_name_access) [dotted_access]
+//   root_anchor    =   "$"
+//
+// Anchors the query to the root of the data, it is read as the reserved variable ROOT_VARIABLE
+//
+fn root_anchor(input: Span) -> IResult<Span, String> {
+    value(format!("%{}", ROOT_VARIABLE), char('$'))(input)
+}
+
+//
+//   access     =   (var_name / var_name_access / root_anchor) [dotted_access]
 //
 pub(crate) fn access(input: Span) -> IResult<Span, AccessQuery> {
     map(
@@ -893,7 +1087,7 @@ pub(crate) fn access(input: Span) -> IResult<Span, AccessQuery> {
             alt((
                 this_keyword,
                 map(
-                    alt((var_name_access_inclusive, property_name)),
+                    alt((var_name_access_inclusive, root_anchor, property_name)),
                     QueryPart::Key,
                 ),
             )),
@@ -926,6 +1120,107 @@ pub(crate) fn access(input: Span) -> IResult<Span, AccessQuery> {
     )(input)
 }
 
+//
+//   count_access   =   ("count" / "COUNT") "(" access ")"
+//
+fn count_access(input: Span) -> IResult<Span, AccessQuery> {
+    map(
+        preceded(
+            pair(zero_or_more_ws_or_comment, alt((tag("count"), tag("COUNT")))),
+            delimited(
+                pair(char('('), zero_or_more_ws_or_comment),
+                access,
+                pair(zero_or_more_ws_or_comment, char(')')),
+            ),
+        ),
+        |mut query| {
+            query.query.push(QueryPart::Count);
+            query
+        },
+    )(input)
+}
+
+//
+//   cidr_prefix_access   =   ("cidr_prefix" / "CIDR_PREFIX") "(" access ")"
+//
+fn cidr_prefix_access(input: Span) -> IResult<Span, AccessQuery> {
+    map(
+        preceded(
+            pair(zero_or_more_ws_or_comment, alt((tag("cidr_prefix"), tag("CIDR_PREFIX")))),
+            delimited(
+                pair(char('('), zero_or_more_ws_or_comment),
+                access,
+                pair(zero_or_more_ws_or_comment, char(')')),
+            ),
+        ),
+        |mut query| {
+            query.query.push(QueryPart::CidrPrefix);
+            query
+        },
+    )(input)
+}
+
+//
+//   resource_ref   =   "RESOURCE_REF" "(" type_name "," access ")"
+//
+// Refers to a property across all resources of a type, it is read as the query
+// $.Resources.*[ Type == <type_name> ].<access>
+//
+fn resource_ref(input: Span) -> IResult<Span, AccessQuery> {
+    let location = FileLocation {
+        file_name: input.extra,
+        line: input.location_line(),
+        column: input.get_utf8_column() as u32,
+    };
+    let (input, (name, property)) = preceded(
+        pair(zero_or_more_ws_or_comment, tag("RESOURCE_REF")),
+        delimited(
+            pair(char('('), zero_or_more_ws_or_comment),
+            cut(separated_pair(
+                type_name,
+                tuple((zero_or_more_ws_or_comment, char(','), zero_or_more_ws_or_comment)),
+                access,
+            )),
+            cut(pair(zero_or_more_ws_or_comment, char(')'))),
+        ),
+    )(input)?;
+    let mut query = vec![
+        QueryPart::Key(format!("%{}", ROOT_VARIABLE)),
+        QueryPart::AllIndices(None),
+    ];
+    query.extend(type_query(location, &name.type_name));
+    query.extend(property.query);
+    Ok((input, AccessQuery { query, match_all: true }))
+}
+
+fn resource_count_keyword(input: Span) -> IResult<Span, ()> {
+    value(
+        (),
+        terminated(
+            tag("RESOURCE_COUNT"),
+            nom::combinator::not(alt((alphanumeric1, tag("_")))),
+        ),
+    )(input)
+}
+
+//
+//   resource_count   =   type_name 1*(LWSP/comment) "RESOURCE_COUNT"
+//
+// The number of resources of a type in the template, e.g. AWS::EC2::VPC RESOURCE_COUNT == 1
+//
+fn resource_count_access(input: Span) -> IResult<Span, AccessQuery> {
+    map(
+        terminated(
+            preceded(zero_or_more_ws_or_comment, type_name),
+            pair(one_or_more_ws_or_comment, resource_count_keyword),
+        ),
+        |name| AccessQuery {
+            query: vec![QueryPart::ResourceCount(name.type_name)],
+            match_all: true,
+        },
+    )(input)
+}
+
 #[allow(clippy::redundant_closure)]
 fn clause_with_map<'loc, A, M, T: 'loc>(
     input: Span<'loc>,
@@ -954,10 +1249,7 @@ where
     })(rest)?;
 
     if !does_comparator_have_rhs(&cmp.0) {
-        let (rest, custom_message) = map(
-            preceded(zero_or_more_ws_or_comment, opt(custom_message)),
-            |msg| msg.map(String::from),
-        )(rest)?;
+        let (rest, custom_message) = preceded(zero_or_more_ws_or_comment, opt(custom_message))(rest)?;
         Ok((
             rest,
             mapper(GuardAccessClause {
@@ -972,7 +1264,7 @@ where
             }),
         ))
     } else {
-        let (rest, (compare_with, custom_message)) =
+        let (rest, (compare_with, custom_message, resource_ref)) =
             context("expecting either a property access \"engine.core\" or value like \"string\" or [\"this\", \"that\"]",
                     cut(alt((
                         //
@@ -981,15 +1273,28 @@ where
                         map(tuple((
                             parse_value, preceded(zero_or_more_ws_or_comment, opt(custom_message)))),
                             move |(rhs, msg)| {
-                                (Some(LetValue::Value(PathAwareValue::try_from(rhs).unwrap())), msg.map(String::from).or(None))
+                                (Some(LetValue::Value(PathAwareValue::try_from(rhs).unwrap())), msg, false)
+                            }),
+                        map(tuple((
+                            resource_ref,
+                            preceded(zero_or_more_ws_or_comment, opt(custom_message)))),
+                            |(rhs, msg)| {
+                                (Some(LetValue::AccessClause(rhs)), msg, true)
                             }),
                         map(tuple((
                             preceded(zero_or_more_ws_or_comment, access),
                             preceded(zero_or_more_ws_or_comment, opt(custom_message)))),
                             |(rhs, msg)| {
-                                (Some(LetValue::AccessClause(rhs)), msg.map(String::from).or(None))
+                                (Some(LetValue::AccessClause(rhs)), msg, false)
                             }),
                     ))))(rest)?;
+        //
+        // == and != against RESOURCE_REF check membership in the referenced values
+        //
+        let cmp = match cmp {
+            (CmpOperator::Eq, inverse) if resource_ref => (CmpOperator::In, inverse),
+            rest => rest,
+        };
         Ok((
             rest,
             mapper(GuardAccessClause {
@@ -1102,7 +1407,7 @@ pub(crate) fn parameterized_rule_call_clause(
             parameters: access_clauses,
             named_rule: GuardNamedRuleClause {
                 location,
-                custom_message: custom_message.map(|s| s.to_string()),
+                custom_message,
                 negation: not.map_or(false, |_| true),
                 dependent_rule: rule_name,
             },
@@ -1118,7 +1423,7 @@ pub(crate) fn parameterized_rule_call_clause(
 //
 //
 //  clause                     = access 1*SP unary_operators *(LWSP/comment) custom_message /
-//                               access 1*SP binary_operators 1*(LWSP/comment) (access/value) *(LWSP/comment) custom_message
+//                               access 1*SP binary_operators 1*(LWSP/comment) (access/resource_ref/value) *(LWSP/comment) custom_message
 //
 // Errors:
 //     nom::error::ErrorKind::Alpha, if var_name_access / var_name does not work out
@@ -1140,6 +1445,9 @@ fn clause(input: Span) -> IResult<Span, GuardClause> {
             )
         }),
         block_clause,
+        |i| clause_with(i, count_access),
+        |i| clause_with(i, cidr_prefix_access),
+        |i| clause_with(i, resource_count_access),
         map(
             parameterized_rule_call_clause,
             GuardClause::ParameterizedNamedRule,
@@ -1149,7 +1457,12 @@ fn clause(input: Span) -> IResult<Span, GuardClause> {
 }
 
 fn single_clause(input: Span) -> IResult<Span, WhenGuardClause> {
-    clause_with_map(input, access, WhenGuardClause::Clause)
+    alt((
+        |i| clause_with_map(i, count_access, WhenGuardClause::Clause),
+        |i| clause_with_map(i, cidr_prefix_access, WhenGuardClause::Clause),
+        |i| clause_with_map(i, resource_count_access, WhenGuardClause::Clause),
+        |i| clause_with_map(i, access, WhenGuardClause::Clause),
+    ))(input)
 }
 
 //
@@ -1216,7 +1529,7 @@ fn rule_clause(input: Span) -> IResult<Span, GuardClause> {
             dependent_rule: ct_type,
             location,
             negation: not.is_some(),
-            custom_message: Some(message.to_string()),
+            custom_message: Some(message),
         }),
     ))
 }
@@ -1240,7 +1553,7 @@ where
     let mut conjunctions = Conjunctions::new();
     let mut rest = input;
     loop {
-        match disjunction_clauses(rest, |i: Span| f(i), true) {
+        match joined_disjunction_clauses(rest, |i: Span| f(i)) {
             Err(nom::Err::Error(_)) => {
                 if conjunctions.is_empty() {
                     return Err(nom::Err::Failure(ParserError {
@@ -1290,6 +1603,40 @@ where
     }
 }
 
+//
+// Clauses are joined by and without a separator. An explicit and_term after a disjunction is
+// optional and lets several clauses share a line unambiguously, it must be followed by a clause
+//
+#[allow(clippy::redundant_closure)]
+fn joined_disjunction_clauses<'loc, E, F>(
+    input: Span<'loc>,
+    parser: F,
+) -> IResult<'loc, Span<'loc>, Disjunctions<E>>
+where
+    F: Fn(Span<'loc>) -> IResult<Span<'loc>, E>,
+    E: Clone + 'loc,
+{
+    let (input, disjunctions) = disjunction_clauses(input, |i: Span| parser(i), true)?;
+    let rest = match and_join(input) {
+        Ok((rest, _and)) => rest,
+        Err(_) => return Ok((input, disjunctions)),
+    };
+    match peek(|i: Span<'loc>| disjunction_clauses(i, |i: Span| parser(i), true))(rest) {
+        Ok(_) => Ok((rest, disjunctions)),
+        Err(nom::Err::Error(_)) => Err(nom::Err::Failure(ParserError {
+            span: rest,
+            context: format!(
+                "Expecting a clause after and {}#{}@{}",
+                rest.extra,
+                rest.location_line(),
+                rest.get_utf8_column()
+            ),
+            kind: ErrorKind::Many1,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
 fn single_clauses(input: Span) -> IResult<Span, Conjunctions<WhenGuardClause>> {
     cnf_clauses(
         input,
@@ -1374,6 +1721,23 @@ fn let_assignment_expr(input: Span) -> IResult<Span, String> {
     Ok((input, var_name))
 }
 
+//
+//   capture_regex  =  1*(LWSP/comment) ("MATCH" / "match") *(LWSP/comment) regex
+//
+// used as let region := Arn MATCH /arn:aws:[^:]+:([^:]+):/ to assign the first capture group
+//
+fn capture_regex(input: Span) -> IResult<Span, String> {
+    let (input, _match_keyword) = preceded(
+        one_or_more_ws_or_comment,
+        alt((tag("MATCH"), tag("match"))),
+    )(input)?;
+    let (input, regex) = preceded(zero_or_more_ws_or_comment, parse_regex)(input)?;
+    match regex {
+        Value::Regex(regex) => Ok((input, regex)),
+        _ => unreachable!(),
+    }
+}
+
 fn assignment(input: Span) -> IResult<Span, LetExpr> {
     let (input, var_name) = let_assignment_expr(input)?;
 
@@ -1391,7 +1755,11 @@ fn assignment(input: Span) -> IResult<Span, LetExpr> {
             // if we did not succeed in parsing a value object, then
             // if must be an access pattern, else it is a failure
             //
-            let (input, access) = cut(preceded(zero_or_more_ws_or_comment, access))(input)?;
+            let (input, mut access) = cut(preceded(zero_or_more_ws_or_comment, access))(input)?;
+            let (input, capture) = opt(capture_regex)(input)?;
+            if let Some(regex) = capture {
+                access.query.push(QueryPart::Capture(regex));
+            }
 
             Ok((
                 input,
@@ -1458,7 +1826,7 @@ where
                     (Some(s), None)
                 }),
                 map(
-                    |i: Span| disjunction_clauses(i, |i: Span| clause_parser(i), true),
+                    |i: Span| joined_disjunction_clauses(i, |i: Span| clause_parser(i)),
                     |c: Disjunctions<T>| (None, Some(c)),
                 ),
             )),
@@ -1527,13 +1895,43 @@ fn type_block(input: Span) -> IResult<Span, TypeBlock> {
         line: input.location_line(),
         column: input.get_utf8_column() as u32,
     };
+    let (input, negation) = opt(not)(input)?;
     let (input, name) = type_name(input)?;
 
+    //
+    // Type RESOURCE_COUNT starts a clause on the number of resources, not a block
+    //
+    let (input, _) = nom::combinator::not(preceded(one_or_more_ws_or_comment, resource_count_keyword))(input)?;
+
     //
     // There has to be a space following type name, else it is a failure
     //
     let (input, _space) = cut(one_or_more_ws_or_comment)(input)?;
 
+    //
+    // Type EXISTS or Type !EXISTS checks whether any resource of the type is present
+    //
+    let (input, exists_check) = opt(terminated(
+        pair(opt(not), exists),
+        nom::combinator::not(alt((nom::character::complete::alphanumeric1, tag("_")))),
+    ))(input)?;
+    if let Some((not_exists, _)) = exists_check {
+        return Ok((
+            input,
+            TypeBlock {
+                conditions: None,
+                negation: negation.is_some(),
+                exists: Some(not_exists.is_none()),
+                block: Block {
+                    assignments: vec![],
+                    conjunctions: vec![],
+                },
+                query: type_query(location, &name.type_name),
+                type_name: name.type_name,
+            },
+        ));
+    }
+
     let (input, when_conditions) = opt(when_conditions(single_clauses))(input)?;
 
     let (input, (assignments, clauses)) = if when_conditions.is_some() {
@@ -1556,40 +1954,49 @@ fn type_block(input: Span) -> IResult<Span, TypeBlock> {
         input,
         TypeBlock {
             conditions: when_conditions,
-            type_name: name.type_name.to_string(),
+            negation: negation.is_some(),
+            exists: None,
             block: Block {
                 assignments,
                 conjunctions: clauses,
             },
-            query: vec![
-                QueryPart::Key("Resources".to_string()),
-                QueryPart::AllValues(None),
-                QueryPart::Filter(
-                    None,
-                    Conjunctions::from([Disjunctions::from([GuardClause::Clause(
-                        GuardAccessClause {
-                            negation: false,
-                            access_clause: AccessClause {
-                                query: AccessQuery {
-                                    query: vec![QueryPart::Key("Type".to_string())],
-                                    match_all: true,
-                                },
-                                custom_message: None,
-                                location,
-                                compare_with: Some(LetValue::Value(PathAwareValue::String((
-                                    Path::root(),
-                                    name.type_name,
-                                )))),
-                                comparator: (CmpOperator::Eq, false),
-                            },
-                        },
-                    )])]),
-                ),
-            ],
+            query: type_query(location, &name.type_name),
+            type_name: name.type_name,
         },
     ))
 }
 
+//
+// Resources.*[ Type == <type_name> ]
+//
+pub(crate) fn type_query<'loc>(location: FileLocation<'loc>, type_name: &str) -> Vec<QueryPart<'loc>> {
+    vec![
+        QueryPart::Key("Resources".to_string()),
+        QueryPart::AllValues(None),
+        QueryPart::Filter(
+            None,
+            Conjunctions::from([Disjunctions::from([GuardClause::Clause(
+                GuardAccessClause {
+                    negation: false,
+                    access_clause: AccessClause {
+                        query: AccessQuery {
+                            query: vec![QueryPart::Key("Type".to_string())],
+                            match_all: true,
+                        },
+                        custom_message: None,
+                        location,
+                        compare_with: Some(LetValue::Value(PathAwareValue::String((
+                            Path::root(),
+                            type_name.to_string(),
+                        )))),
+                        comparator: (CmpOperator::Eq, false),
+                    },
+                },
+            )])]),
+        ),
+    ]
+}
+
 #[allow(clippy::redundant_closure)]
 fn when_block<'loc, C, B, M, T, R>(
     conditions: C,
@@ -1645,9 +2052,33 @@ fn rule_block_clause(input: Span) -> IResult<Span, RuleClause> {
     ))(input)
 }
 
+//
+//  rule_tags                  = "@tags" "(" string *( "," string ) ")"
+//
+fn rule_tags(input: Span) -> IResult<Span, Vec<String>> {
+    let (input, _at) = preceded(zero_or_more_ws_or_comment, char('@'))(input)?;
+    let (input, _tags) = cut(context("expecting @tags(\"tag\", ...) annotation", tag("tags")))(input)?;
+    cut(context(
+        "expecting one or more quoted tags inside @tags(...)",
+        delimited(
+            preceded(zero_or_more_ws_or_comment, char('(')),
+            separated_nonempty_list(
+                preceded(zero_or_more_ws_or_comment, char(',')),
+                map(preceded(zero_or_more_ws_or_comment, parse_string), |tag| match tag {
+                    Value::String(tag) => tag,
+                    _ => unreachable!(),
+                }),
+            ),
+            preceded(zero_or_more_ws_or_comment, char(')')),
+        ),
+    ))(input)
+}
+
 //
 // rule block
 //
+//  rule_block                 = "rule" rule_name [rule_tags] [when_conditions] block
+//
 fn rule_block(input: Span) -> IResult<Span, Rule> {
     //
     // rule is followed by space
@@ -1656,6 +2087,7 @@ fn rule_block(input: Span) -> IResult<Span, Rule> {
     let (input, _space) = one_or_more_ws_or_comment(input)?;
 
     let (input, rule_name) = cut(var_name)(input)?;
+    let (input, tags) = opt(rule_tags)(input)?;
     let (input, conditions) = opt(when_conditions(single_clauses))(input)?;
     let (input, (assignments, conjunctions)) = cut(block(rule_block_clause))(input)?;
 
@@ -1668,6 +2100,7 @@ fn rule_block(input: Span) -> IResult<Span, Rule> {
                 assignments,
                 conjunctions,
             },
+            tags: tags.unwrap_or_default(),
         },
     ))
 }
@@ -1717,6 +2150,7 @@ fn parameterized_rule_block(input: Span) -> IResult<Span, ParameterizedRule> {
                     conjunctions,
                 },
                 conditions: None,
+                tags: vec![],
             },
         },
     ))
@@ -1732,22 +2166,6 @@ fn type_block_clauses(input: Span) -> IResult<Span, Disjunctions<TypeBlock>> {
     Ok((input, disjunctions))
 }
 
-#[allow(clippy::redundant_closure)]
-fn remove_whitespace_comments<'loc, P, R>(
-    parser: P,
-) -> impl Fn(Span<'loc>) -> IResult<Span<'loc>, R>
-where
-    P: Fn(Span<'loc>) -> IResult<Span<'loc>, R>,
-{
-    move |input: Span| {
-        delimited(
-            zero_or_more_ws_or_comment,
-            |s| parser(s),
-            zero_or_more_ws_or_comment,
-        )(input)
-    }
-}
-
 #[derive(Clone, PartialEq, Debug)]
 enum Exprs<'loc> {
     Assignment(LetExpr<'loc>),
@@ -1761,9 +2179,175 @@ enum Exprs<'loc> {
 //
 // Rules File
 //
+fn expr_location(input: Span) -> IResult<Span, FileLocation> {
+    Ok((input, FileLocation {
+        file_name: input.extra,
+        line: input.location_line(),
+        column: input.get_utf8_column() as u32,
+    }))
+}
+
+//
+// Whitespace and comments before an expression. Comments of the form # @tags: security, cost
+// tag the rule that follows, in addition to any tags declared with @tags("...") after its name
+//
+fn comment_tags(input: Span) -> IResult<Span, Vec<String>> {
+    fold_many0(
+        alt((
+            value(None, multispace1),
+            map(comment2, |comment| {
+                comment.fragment().trim().strip_prefix("@tags:").map(|tags| {
+                    tags.split(',').map(str::trim).filter(|tag| !tag.is_empty())
+                        .map(String::from).collect::<Vec<String>>()
+                })
+            }),
+        )),
+        Vec::new(),
+        |mut acc: Vec<String>, tags| {
+            acc.extend(tags.into_iter().flatten());
+            acc
+        },
+    )(input)
+}
+
+//
+// Rule names and file level variable names must be unique within a rules file. Variables
+// assigned inside rule blocks are allowed to shadow file level ones, hence not checked here
+//
+fn check_duplicate_names(exprs: &[(FileLocation, Exprs)]) -> Result<(), Error> {
+    let mut rule_names: indexmap::IndexMap<&str, &FileLocation> = indexmap::IndexMap::new();
+    let mut var_names: indexmap::IndexMap<&str, &FileLocation> = indexmap::IndexMap::new();
+    let mut duplicates = Vec::new();
+    for (location, each) in exprs {
+        let (kind, name, seen) = match each {
+            Exprs::Rule(r) => ("rule", r.rule_name.as_str(), &mut rule_names),
+            Exprs::ParameterizedRule(p) => ("rule", p.rule.rule_name.as_str(), &mut rule_names),
+            Exprs::Assignment(l) => ("variable", l.var.as_str(), &mut var_names),
+            _ => continue,
+        };
+        match seen.get(name) {
+            Some(first) => duplicates.push(format!(
+                "Duplicate {} {} at {}, first defined at {}", kind, name, location, first)),
+            None => { seen.insert(name, location); }
+        }
+    }
+
+    if duplicates.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::new(crate::rules::errors::ErrorKind::ParseError(duplicates.join("; "))))
+    }
+}
+
+//
+// `this` resolves to the current value inside a block or filter. At the root of the rules file
+// there is no current value to refer to, so any rule level or default clause, block query, when
+// condition or assignment starting with `this` is rejected. Type blocks and parameterized rules
+// are not checked, their bodies always run against a selected value.
+//
+fn starts_with_this(query: &[QueryPart]) -> bool {
+    matches!(query.first(), Some(QueryPart::This))
+}
+
+fn let_value_uses_this(value: &LetValue) -> bool {
+    match value {
+        LetValue::AccessClause(query) => starts_with_this(&query.query),
+        LetValue::FunctionCall(call) => call.parameters.iter().any(let_value_uses_this),
+        LetValue::Value(_) => false,
+    }
+}
+
+fn access_clause_uses_this(clause: &AccessClause) -> bool {
+    starts_with_this(&clause.query.query) ||
+        matches!(&clause.compare_with, Some(value) if let_value_uses_this(value))
+}
+
+fn this_at_root_in_assignments(assignments: &[LetExpr], location: &FileLocation, errors: &mut Vec<String>) {
+    for each in assignments {
+        if let_value_uses_this(&each.value) {
+            errors.push(format!("`this` used at the root in assignment to {} at {}", each.var, location));
+        }
+    }
+}
+
+fn this_at_root_in_conditions(conditions: &WhenConditions, errors: &mut Vec<String>) {
+    for each in conditions.iter().flatten() {
+        if let WhenGuardClause::Clause(gac) = each {
+            if access_clause_uses_this(&gac.access_clause) {
+                errors.push(format!("`this` used at the root in when condition at {}", gac.access_clause.location));
+            }
+        }
+    }
+}
+
+fn this_at_root_in_guard_clause(clause: &GuardClause, location: &FileLocation, errors: &mut Vec<String>) {
+    match clause {
+        GuardClause::Clause(gac) => {
+            if access_clause_uses_this(&gac.access_clause) {
+                errors.push(format!("`this` used at the root in clause at {}", gac.access_clause.location));
+            }
+        },
+        GuardClause::BlockClause(block) => {
+            if starts_with_this(&block.query.query) {
+                errors.push(format!("`this` used at the root in block query at {}", block.location));
+            }
+        },
+        GuardClause::WhenBlock(conditions, block) =>
+            this_at_root_in_when_block(conditions, block, location, errors),
+        GuardClause::NamedRule(_) |
+        GuardClause::ParameterizedNamedRule(_) => {}
+    }
+}
+
+fn this_at_root_in_when_block(conditions: &WhenConditions, block: &Block<GuardClause>, location: &FileLocation, errors: &mut Vec<String>) {
+    this_at_root_in_conditions(conditions, errors);
+    this_at_root_in_assignments(&block.assignments, location, errors);
+    for each in block.conjunctions.iter().flatten() {
+        this_at_root_in_guard_clause(each, location, errors);
+    }
+}
+
+fn check_this_at_root(exprs: &[(FileLocation, Exprs)]) -> Result<(), Error> {
+    let mut errors = Vec::new();
+    for (location, each) in exprs {
+        match each {
+            Exprs::Assignment(l) => this_at_root_in_assignments(std::slice::from_ref(l), location, &mut errors),
+            Exprs::DefaultClause(clauses) => {
+                for each in clauses {
+                    this_at_root_in_guard_clause(each, location, &mut errors);
+                }
+            },
+            Exprs::DefaultWhenBlock(conditions, block) =>
+                this_at_root_in_when_block(conditions, block, location, &mut errors),
+            Exprs::Rule(rule) => {
+                if let Some(conditions) = &rule.conditions {
+                    this_at_root_in_conditions(conditions, &mut errors);
+                }
+                this_at_root_in_assignments(&rule.block.assignments, location, &mut errors);
+                for each in rule.block.conjunctions.iter().flatten() {
+                    match each {
+                        RuleClause::Clause(clause) => this_at_root_in_guard_clause(clause, location, &mut errors),
+                        RuleClause::WhenBlock(conditions, block) =>
+                            this_at_root_in_when_block(conditions, block, location, &mut errors),
+                        RuleClause::TypeBlock(_) => {}
+                    }
+                }
+            },
+            Exprs::DefaultTypeBlock(_) |
+            Exprs::ParameterizedRule(_) => {}
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::new(crate::rules::errors::ErrorKind::ParseError(errors.join("; "))))
+    }
+}
+
 pub(crate) fn rules_file(input: Span) -> Result<RulesFile, Error> {
-    let exprs = all_consuming(fold_many1(
-        remove_whitespace_comments(alt((
+    let exprs = all_consuming(terminated(fold_many1(
+        tuple((comment_tags, expr_location, alt((
             map(assignment, Exprs::Assignment),
             map(parameterized_rule_block, Exprs::ParameterizedRule),
             map(rule_block, Exprs::Rule),
@@ -1778,21 +2362,37 @@ pub(crate) fn rules_file(input: Span) -> Result<RulesFile, Error> {
                 )
             }),
             map(default_clauses, Exprs::DefaultClause),
-        ))),
+        )))),
         Vec::new(),
-        |mut acc, expr| {
-            acc.push(expr);
+        |mut acc, (tags, location, mut expr)| {
+            match &mut expr {
+                Exprs::Rule(rule) |
+                Exprs::ParameterizedRule(ParameterizedRule { rule, .. }) => {
+                    let mut merged = tags;
+                    for each in rule.tags.drain(..) {
+                        if !merged.contains(&each) {
+                            merged.push(each);
+                        }
+                    }
+                    rule.tags = merged;
+                },
+                _ => {}
+            }
+            acc.push((location, expr));
             acc
         },
-    ))(input)?
+    ), zero_or_more_ws_or_comment))(input)?
     .1;
 
+    check_duplicate_names(&exprs)?;
+    check_this_at_root(&exprs)?;
+
     let mut global_assignments = Vec::with_capacity(exprs.len());
     let mut default_rule_clauses = Vec::with_capacity(exprs.len());
     let mut named_rules = Vec::with_capacity(exprs.len());
     let mut parameterized_rules = Vec::with_capacity(exprs.len());
 
-    for each in exprs {
+    for (_location, each) in exprs {
         match each {
             Exprs::Rule(r) => named_rules.push(r),
             Exprs::ParameterizedRule(p) => parameterized_rules.push(p),
@@ -1823,6 +2423,7 @@ pub(crate) fn rules_file(input: Span) -> Result<RulesFile, Error> {
                 assignments: vec![],
                 conjunctions: default_rule_clauses,
             },
+            tags: vec![],
         };
         named_rules.insert(0, default_rule);
     }
@@ -1849,6 +2450,21 @@ fn or_join(input: Span) -> IResult<Span, Span> {
     )(input)
 }
 
+//
+//  ABNF        = "and" / "AND" / "|AND|"
+//
+fn and_term(input: Span) -> IResult<Span, Span> {
+    alt((tag("and"), tag("AND"), tag("|AND|")))(input)
+}
+
+fn and_join(input: Span) -> IResult<Span, Span> {
+    delimited(
+        zero_or_more_ws_or_comment,
+        and_term,
+        one_or_more_ws_or_comment,
+    )(input)
+}
+
 impl<'a> TryFrom<&'a str> for AccessQuery<'a> {
     type Error = Error;
 
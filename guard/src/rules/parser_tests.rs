@@ -1,6 +1,6 @@
 use std::convert::TryInto;
 
-use crate::rules::path_value::PathAwareValue;
+use crate::rules::path_value::{EvaluationOptions, PathAwareValue, DEFAULT_EVALUATION_OPTIONS};
 use crate::rules::values::WithinRange;
 use crate::rules::{EvaluationContext, EvaluationType, Status};
 
@@ -26,6 +26,45 @@ fn test_int_parse_pos() {
     )
 }
 
+#[rstest::rstest]
+#[case("-1", Value::Int(-1))]
+#[case("+5", Value::Int(5))]
+#[case("-9223372036854775808", Value::Int(i64::MIN))]
+#[case("-2.75", Value::Float(-2.75))]
+#[case("+2.5", Value::Float(2.5))]
+#[case("-1e+2", Value::Float(-100.0))]
+fn test_parse_signed_value(#[case] s: &str, #[case] expected: Value) {
+    let cmp = unsafe { Span::new_from_raw_offset(s.len(), 1, "", "") };
+    assert_eq!(parse_value(from_str2(s)), Ok((cmp, expected)));
+}
+
+#[test]
+fn test_parse_sign_without_digits() {
+    for s in ["-", "+", "- 1", "-abc"] {
+        assert!(parse_int_value(from_str2(s)).is_err(), "{}", s);
+        assert!(parse_float(from_str2(s)).is_err(), "{}", s);
+    }
+    assert!(parse_value(from_str2("-")).is_err());
+}
+
+#[test]
+fn test_clause_negative_rhs_with_comparators() {
+    for (rhs, expected) in [("== -1", -1i64), ("< -1", -1), (">= +5", 5), ("<= -12", -12)].iter() {
+        let access_pattern = format!("Threshold {}", rhs);
+        let (rest, parsed) = clause(from_str2(&access_pattern)).unwrap();
+        assert_eq!(*rest.fragment(), "");
+        match parsed {
+            GuardClause::Clause(GuardAccessClause { access_clause, .. }) => {
+                assert_eq!(
+                    access_clause.compare_with,
+                    Some(LetValue::Value(PathAwareValue::try_from(Value::Int(*expected)).unwrap()))
+                );
+            },
+            _ => unreachable!()
+        }
+    }
+}
+
 #[test]
 fn test_parse_string() {
     let s = "\"Hi there\"";
@@ -495,6 +534,31 @@ fn test_range_type_success() {
             })
         ))
     );
+
+    //
+    // mixed bounds make a float range
+    //
+    let s = "r[1, 10.5)";
+    let cmp = unsafe { Span::new_from_raw_offset(s.len(), 1, "", "") };
+    let v = parse_range(from_str2(s));
+    assert_eq!(
+        v,
+        Ok((
+            cmp,
+            Value::RangeFloat(RangeType {
+                upper: 10.5,
+                lower: 1.0,
+                inclusive: LOWER_INCLUSIVE
+            })
+        ))
+    );
+    let r = match v.unwrap().1 {
+        Value::RangeFloat(val) => val,
+        _ => unreachable!(),
+    };
+    assert!(r.contains(&1.0));
+    assert!(r.contains(&10.49));
+    assert!(!r.contains(&10.5));
 }
 
 #[test]
@@ -926,6 +990,108 @@ fn test_dotted_access() {
     }
 }
 
+#[test]
+fn test_dotted_access_recursive_descent() -> Result<(), Error> {
+    let span = from_str2("..KmsKeyId");
+    let (_rest, parts) = dotted_access(span)?;
+    assert_eq!(parts, vec![QueryPart::RecursiveDescent("KmsKeyId".to_string())]);
+
+    let span = from_str2(".Resources..Encryption.KmsKeyId");
+    let (_rest, parts) = dotted_access(span)?;
+    assert_eq!(parts, vec![
+        QueryPart::Key("Resources".to_string()),
+        QueryPart::RecursiveDescent("Encryption".to_string()),
+        QueryPart::Key("KmsKeyId".to_string()),
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_access_bracket_indices() -> Result<(), Error> {
+    let key = |name: &str| QueryPart::Key(name.to_string());
+    assert_eq!(AccessQuery::try_from("a.b[0].c")?.query, vec![key("a"), key("b"), QueryPart::Index(0), key("c")]);
+    assert_eq!(AccessQuery::try_from("a[*].b")?.query, vec![key("a"), QueryPart::AllIndices(None), key("b")]);
+    assert_eq!(AccessQuery::try_from("a.0[1]")?.query, vec![key("a"), QueryPart::Index(0), QueryPart::Index(1)]);
+    assert_eq!(AccessQuery::try_from("a[ 2 ][ * ]")?.query, vec![key("a"), QueryPart::Index(2), QueryPart::AllIndices(None)]);
+    assert_eq!(AccessQuery::try_from("a[-1]")?.query, vec![key("a"), QueryPart::Index(-1)]);
+
+    //
+    // both forms are the same query
+    //
+    assert_eq!(
+        AccessQuery::try_from("Resources.Subnets[0].CidrBlock")?.query,
+        AccessQuery::try_from("Resources.Subnets.0.CidrBlock")?.query
+    );
+    assert!(AccessQuery::try_from("a[0").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_access_quoted_segments() -> Result<(), Error> {
+    let key = |name: &str| QueryPart::Key(name.to_string());
+    assert_eq!(
+        AccessQuery::try_from("Condition.StringEquals.'aws:SourceVpc'")?.query,
+        vec![key("Condition"), key("StringEquals"), key("aws:SourceVpc")]
+    );
+    assert_eq!(
+        AccessQuery::try_from(r#"Tags."my.dotted.key""#)?.query,
+        vec![key("Tags"), key("my.dotted.key")]
+    );
+    assert_eq!(
+        AccessQuery::try_from(r#"Resources.*.Properties."Tag Key".*.'a[0]'"#)?.query,
+        vec![key("Resources"), QueryPart::AllValues(None), key("Properties"), key("Tag Key"),
+             QueryPart::AllValues(None), key("a[0]")]
+    );
+    assert_eq!(
+        AccessQuery::try_from(r#"%policies.Statement[*].Condition.'aws:SourceVpc'"#)?.query,
+        vec![key("%policies"), QueryPart::AllIndices(None), key("Statement"), QueryPart::AllIndices(None),
+             key("Condition"), key("aws:SourceVpc")]
+    );
+    assert_eq!(
+        AccessQuery::try_from(r#"Tags.%name.'it\'s'..'aws:PrincipalTag'"#)?.query,
+        vec![key("Tags"), key("%name"), key("it's"), QueryPart::RecursiveDescent("aws:PrincipalTag".to_string())]
+    );
+    assert_eq!(AccessQuery::try_from(r#"'a.b'.c"#)?.query, vec![key("a.b"), key("c")]);
+    assert!(AccessQuery::try_from("Tags.'unterminated").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_access_key_regex() -> Result<(), Error> {
+    let key = |name: &str| QueryPart::Key(name.to_string());
+    let key_regex = |regex: &str| QueryPart::KeyRegex(regex.to_string());
+    let access = AccessQuery::try_from("Condition./aws:[sS]ource(Vpc|Vpce)/")?;
+    assert_eq!(access.query, vec![key("Condition"), key_regex("aws:[sS]ource(Vpc|Vpce)")]);
+    assert_eq!(format!("{}", SliceDisplay(&access.query)), "Condition./aws:[sS]ource(Vpc|Vpce)/");
+    assert_eq!(
+        AccessQuery::try_from("Statement[*].Condition.*./^aws:/i.'aws:x'")?.query,
+        vec![key("Statement"), QueryPart::AllIndices(None), key("Condition"), QueryPart::AllValues(None),
+             key_regex("(?i:^aws:)"), key("aws:x")]
+    );
+    assert_eq!(
+        AccessQuery::try_from(r#"%conditions./a\/b/.Values[0]"#)?.query,
+        vec![key("%conditions"), QueryPart::AllIndices(None), key_regex("a/b"), key("Values"), QueryPart::Index(0)]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_filter_not_parse() -> Result<(), Error> {
+    let query = AccessQuery::try_from(r#"Resources.*[! Type == "AWS::S3::Bucket" ]"#)?.query;
+    assert_eq!(query.len(), 3);
+    match &query[2] {
+        QueryPart::FilterNot(None, conjunctions) => assert_eq!(conjunctions.len(), 1),
+        rest => unreachable!("{:?}", rest),
+    }
+
+    let query = AccessQuery::try_from(r#"Resources.*[ !Type == "AWS::S3::Bucket" ]"#)?.query;
+    match &query[2] {
+        QueryPart::Filter(None, _) => {},
+        rest => unreachable!("{:?}", rest),
+    }
+    Ok(())
+}
+
 #[test]
 fn test_access() {
     let examples = [
@@ -1210,6 +1376,8 @@ fn test_other_operations() {
         "! EMPTY", // 11 err
         "NOT EMPTY", // 12 ok
         "IN [\"t\", \"n\"]", // 13 ok
+        "NOT_IN [\"t\"]", // 14 ok
+        "not_in", // 15 ok
     ];
 
     let expectations = [
@@ -1292,6 +1460,16 @@ fn test_other_operations() {
             unsafe { Span::new_from_raw_offset(2, 1, " [\"t\", \"n\"]", "") },
             (CmpOperator::In, false),
         )),
+        // "NOT_IN [\"t\"]", // 14 ok
+        Ok((
+            unsafe { Span::new_from_raw_offset(6, 1, " [\"t\"]", "") },
+            (CmpOperator::In, true),
+        )),
+        // "not_in", // 15 ok
+        Ok((
+            unsafe { Span::new_from_raw_offset(examples[15].len(), 1, "", "") },
+            (CmpOperator::In, true),
+        )),
     ];
 
     for (idx, each) in examples.iter().enumerate() {
@@ -1491,6 +1669,107 @@ fn test_value_cmp() {
     }
 }
 
+#[test]
+fn test_value_cmp_not_in() {
+    let examples = [
+        "NOT_IN\n", // ok 0
+        "not_in ",  // ok 1
+        "NOT IN\n", // ok 2
+        "NOTIN",    // err 3
+    ];
+
+    let expectations = [
+        // "NOT_IN\n", // ok 0
+        Ok((
+            unsafe { Span::new_from_raw_offset(examples[0].len() - 1, 1, "\n", "") },
+            (CmpOperator::In, true),
+        )),
+        // "not_in ", // ok 1
+        Ok((
+            unsafe { Span::new_from_raw_offset(examples[1].len() - 1, 1, " ", "") },
+            (CmpOperator::In, true),
+        )),
+        // "NOT IN\n", // ok 2
+        Ok((
+            unsafe { Span::new_from_raw_offset(examples[2].len() - 1, 1, "\n", "") },
+            (CmpOperator::In, true),
+        )),
+        // "NOTIN", // err 3
+        Err(nom::Err::Error(ParserError {
+            span: from_str2(examples[3]),
+            context: "".to_string(),
+            kind: ErrorKind::Tag,
+        })),
+    ];
+
+    for (idx, each) in examples.iter().enumerate() {
+        let span = from_str2(*each);
+        let result = value_cmp(span);
+        assert_eq!(&result, &expectations[idx]);
+    }
+}
+
+#[test]
+fn test_keys_keyword_not_in() {
+    let examples = [
+        "[KEYS NOT_IN %var]",            // 0 Ok
+        "[keys not_in ['aws:IsSecure']]", // 1 Ok
+        "[ KEYS  NOT_IN  %var ]",         // 2 Ok
+    ];
+
+    let expectations = [
+        // "[KEYS NOT_IN %var]", // 0 Ok
+        Ok((
+            unsafe { Span::new_from_raw_offset(examples[0].len(), 1, "", "") },
+            QueryPart::MapKeyFilter(
+                None,
+                MapKeyFilterClause {
+                    comparator: (CmpOperator::In, true),
+                    compare_with: LetValue::AccessClause(AccessQuery {
+                        match_all: true,
+                        query: vec![QueryPart::Key("%var".to_string())],
+                    }),
+                },
+            ),
+        )),
+        // "[keys not_in ['aws:IsSecure']]", // 1 Ok
+        Ok((
+            unsafe { Span::new_from_raw_offset(examples[1].len(), 1, "", "") },
+            QueryPart::MapKeyFilter(
+                None,
+                MapKeyFilterClause {
+                    comparator: (CmpOperator::In, true),
+                    compare_with: LetValue::Value(
+                        PathAwareValue::try_from(Value::List(vec![
+                            Value::String("aws:IsSecure".to_string())
+                        ])).unwrap(),
+                    ),
+                },
+            ),
+        )),
+        // "[ KEYS  NOT_IN  %var ]", // 2 Ok
+        Ok((
+            unsafe { Span::new_from_raw_offset(examples[2].len(), 1, "", "") },
+            QueryPart::MapKeyFilter(
+                None,
+                MapKeyFilterClause {
+                    comparator: (CmpOperator::In, true),
+                    compare_with: LetValue::AccessClause(AccessQuery {
+                        match_all: true,
+                        query: vec![QueryPart::Key("%var".to_string())],
+                    }),
+                },
+            ),
+        )),
+    ];
+
+    for (idx, each) in examples.iter().enumerate() {
+        let span = from_str2(*each);
+        let result = map_keys_match(span);
+        assert_eq!(&result, &expectations[idx]);
+    }
+}
+
 #[test]
 fn test_clause_success() {
     let lhs = ["configuration.containers.*.image", "engine"];
@@ -1939,6 +2218,25 @@ fn test_clause_failures() {
     }
 }
 
+#[test]
+fn test_clause_bool_rhs_is_value() {
+    for (keyword, expected) in [("true", true), ("True", true), ("false", false), ("False", false)].iter() {
+        let access_pattern = format!("Properties.Encrypted == {}", keyword);
+        let (rest, parsed) = clause(from_str2(&access_pattern)).unwrap();
+        assert_eq!(*rest.fragment(), "");
+        match parsed {
+            GuardClause::Clause(GuardAccessClause { access_clause, .. }) => {
+                assert_eq!(access_clause.query.query, to_string_vec(&["Properties", "Encrypted"]));
+                assert_eq!(
+                    access_clause.compare_with,
+                    Some(LetValue::Value(PathAwareValue::try_from(Value::Bool(*expected)).unwrap()))
+                );
+            },
+            _ => unreachable!()
+        }
+    }
+}
+
 #[test]
 fn test_rule_clauses() {
     let examples = [
@@ -2240,6 +2538,39 @@ fn test_clauses() {
     }
 }
 
+#[test]
+fn test_clauses_and_term() -> Result<(), Error> {
+    let shape = |input: &str| -> Result<Vec<Vec<String>>, Error> {
+        let (_rest, conjunctions) = clauses(from_str2(input))?;
+        Ok(conjunctions.iter().map(|disjunctions| {
+            disjunctions.iter().map(|each| format!("{}", each)).collect()
+        }).collect())
+    };
+    let newline_separated = shape("Port == 443\nProtocol == 'tcp' or Protocol == 'udp'\nEncrypted EXISTS")?;
+    assert_eq!(newline_separated.len(), 3);
+    for each in [
+        "Port == 443 |AND| Protocol == 'tcp' or Protocol == 'udp' |AND| Encrypted EXISTS",
+        "Port == 443 and Protocol == 'tcp' OR Protocol == 'udp' and Encrypted EXISTS",
+        "Port == 443 AND\n    Protocol == 'tcp' |OR| Protocol == 'udp'\n    Encrypted EXISTS",
+    ] {
+        assert_eq!(shape(each)?, newline_separated, "{}", each);
+    }
+
+    //
+    // a property whose name starts with and is not mistaken for the and_term
+    //
+    assert_eq!(shape("Port == 443 android EXISTS")?.len(), 2);
+
+    let dangling = clauses(from_str2("Port == 443 |AND| "));
+    assert!(matches!(dangling, Err(nom::Err::Failure(ParserError { ref context, .. }))
+        if context.starts_with("Expecting a clause after and")), "{:?}", dangling);
+
+    let rules = rules_file(from_str2("rule single_line { Port == 443 |AND| Encrypted EXISTS and Protocol == 'tcp' }"))?;
+    assert_eq!(rules.guard_rules[0].block.conjunctions.len(), 3);
+    assert!(rules_file(from_str2("rule dangling { Port == 443 and }")).is_err());
+    Ok(())
+}
+
 #[rstest::rstest]
 #[case("letx", Err(nom::Err::Error(ParserError {
     span: unsafe {
@@ -2475,6 +2806,8 @@ fn test_type_block() {
         Ok((
             unsafe { Span::new_from_raw_offset(examples[0].len(), 6, "", "") },
             TypeBlock {
+                negation: false,
+                exists: None,
                 type_name: String::from("AWS::EC2::Instance"),
                 conditions: None,
                 block: Block {
@@ -2569,6 +2902,8 @@ fn test_type_block() {
         Ok((
             unsafe { Span::new_from_raw_offset(examples[1].len(), 1, "", "") },
             TypeBlock {
+                negation: false,
+                exists: None,
                 type_name: String::from("AWS::EC2::Instance"),
                 conditions: None,
                 block: Block {
@@ -2628,6 +2963,8 @@ fn test_type_block() {
         Ok((
             unsafe { Span::new_from_raw_offset(examples[2].len(), 3, "", "") },
             TypeBlock {
+                negation: false,
+                exists: None,
                 type_name: String::from("AWS::EC2::Instance"),
                 conditions: Some(vec![vec![WhenGuardClause::Clause(GuardAccessClause {
                     access_clause: AccessClause {
@@ -2744,6 +3081,7 @@ fn test_rule_block() {
         unsafe { Span::new_from_raw_offset(examples[0].len(), 24, "", "") },
         Rule {
             rule_name: String::from("example_rule"),
+            tags: vec![],
             conditions: Some(Conjunctions::from([Disjunctions::from([
                 WhenGuardClause::Clause(GuardAccessClause {
                     access_clause: AccessClause {
@@ -2790,6 +3128,8 @@ fn test_rule_block() {
                         },
                     ))]),
                     Disjunctions::from([RuleClause::TypeBlock(TypeBlock {
+                        negation: false,
+                        exists: None,
                         type_name: type_name.to_string(),
                         conditions: None,
                         block: Block {
@@ -2854,6 +3194,8 @@ fn test_rule_block() {
                     })]),
                     Disjunctions::from([
                         RuleClause::TypeBlock(TypeBlock {
+                            negation: false,
+                            exists: None,
                             type_name: type_name.to_string(),
                             conditions: None,
                             block: Block {
@@ -3009,6 +3351,8 @@ fn test_rule_block() {
                             ],
                         }),
                         RuleClause::TypeBlock(TypeBlock {
+                            negation: false,
+                            exists: None,
                             type_name: type_name.to_string(),
                             conditions: None,
                             block: Block {
@@ -3153,6 +3497,7 @@ fn test_try_from_rule_block() -> Result<(), Error> {
     let rule_statement = Rule::try_from(rule)?;
     let expected = Rule {
         rule_name: String::from("s3_secure_exception"),
+        tags: vec![],
         conditions: None,
         block: Block {
             assignments: vec![],
@@ -3168,6 +3513,8 @@ fn test_try_from_rule_block() -> Result<(), Error> {
                     custom_message: None,
                 })),
                 RuleClause::TypeBlock(TypeBlock {
+                    negation: false,
+                    exists: None,
                     type_name: String::from("AWS::S3::Bucket"),
                     conditions: None,
                     block: Block {
@@ -3315,6 +3662,14 @@ impl EvaluationContext for DummyEval {
         unimplemented!()
     }
 
+    fn resource_count(&self, _resource_type: &str) -> usize {
+        unimplemented!()
+    }
+
+    fn options(&self) -> &EvaluationOptions {
+        &DEFAULT_EVALUATION_OPTIONS
+    }
+
     fn end_evaluation(
         &self,
         _eval_type: EvaluationType,
@@ -3401,6 +3756,60 @@ fn select_any_one_from_list_clauses() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn test_rules_file_duplicate_names() -> Result<(), Error> {
+    let rules = r###"
+let ports = [80, 443]
+rule encrypted { Encrypted == true }
+let ports = [22]
+rule encrypted { Encrypted EXISTS }
+"###;
+    let result = rules_file(Span::new_extra(rules, "dups.guard"));
+    assert!(result.is_err());
+    let msg = format!("{}", result.unwrap_err());
+    assert!(msg.contains("Duplicate rule encrypted at Location[file:dups.guard, line:5, column:1], first defined at Location[file:dups.guard, line:3, column:1]"), "{}", msg);
+    assert!(msg.contains("Duplicate variable ports at Location[file:dups.guard, line:4, column:1], first defined at Location[file:dups.guard, line:2, column:1]"), "{}", msg);
+
+    let shadowed = r###"
+let ports = [80, 443]
+rule check_ports {
+    let ports = [22]
+    Port IN %ports
+}
+"###;
+    rules_file(Span::new_extra(shadowed, "shadowed.guard"))?;
+    Ok(())
+}
+
+#[test]
+fn test_rules_file_this_at_root() -> Result<(), Error> {
+    let rules = r###"
+let resources = this.Resources
+rule at_root { this.Resources EXISTS }
+rule compared_at_root { Resources.a.Properties.MinSize <= this.MaxSize }
+rule when_at_root when this.Resources EXISTS { Resources NOT EMPTY }
+this.Parameters EXISTS
+"###;
+    let result = rules_file(Span::new_extra(rules, "this.guard"));
+    assert!(result.is_err());
+    let msg = format!("{}", result.unwrap_err());
+    assert!(msg.contains("`this` used at the root in assignment to resources at Location[file:this.guard, line:2, column:1]"), "{}", msg);
+    assert!(msg.contains("`this` used at the root in clause at Location[file:this.guard, line:3, column:16]"), "{}", msg);
+    assert!(msg.contains("`this` used at the root in clause at Location[file:this.guard, line:4, column:25]"), "{}", msg);
+    assert!(msg.contains("`this` used at the root in when condition at Location[file:this.guard, line:5, column:24]"), "{}", msg);
+    assert!(msg.contains("`this` used at the root in clause at Location[file:this.guard, line:6, column:1]"), "{}", msg);
+
+    let scoped = r###"
+let asgs = Resources.*[ Type == "AWS::AutoScaling::AutoScalingGroup" ]
+rule in_block { %asgs.Properties { this.MinSize <= this.MaxSize } }
+rule in_filter { Resources.*[ this.Properties.MinSize <= this.Properties.MaxSize ] NOT EMPTY }
+rule in_type_block { AWS::AutoScaling::AutoScalingGroup { this.Properties.MinSize <= this.Properties.MaxSize } }
+rule in_parameterized(asg) { %asg { this.MinSize EXISTS } }
+"###;
+    rules_file(Span::new_extra(scoped, "scoped.guard"))?;
+    Ok(())
+}
+
 #[test]
 fn test_rules_file_default_rules() -> Result<(), Error> {
     let s = r###"
@@ -3410,12 +3819,15 @@ fn test_rules_file_default_rules() -> Result<(), Error> {
     "###;
     let default_rule = Rule {
         rule_name: String::from("default"),
+        tags: vec![],
         conditions: None,
         block: Block {
             assignments: vec![],
 
             conjunctions: vec![
                 vec![RuleClause::TypeBlock(TypeBlock {
+                    negation: false,
+                    exists: None,
                     type_name: String::from("AWS::AmazonMQ::Broker"),
                     conditions: None,
                     block: Block {
@@ -3469,6 +3881,8 @@ fn test_rules_file_default_rules() -> Result<(), Error> {
                     ]
                 })],
                 vec![RuleClause::TypeBlock(TypeBlock {
+                    negation: false,
+                    exists: None,
                     type_name: String::from("AWS::AmazonMQ::Broker"),
                     conditions: None,
                     block: Block {
@@ -3522,6 +3936,8 @@ fn test_rules_file_default_rules() -> Result<(), Error> {
                     ]
                 })],
                 vec![RuleClause::TypeBlock(TypeBlock {
+                    negation: false,
+                    exists: None,
                     type_name: String::from("AWS::ApiGateway::Method"),
                     conditions: None,
                     block: Block {
@@ -3575,6 +3991,8 @@ fn test_rules_file_default_rules() -> Result<(), Error> {
                     ]
                 }),
                  RuleClause::TypeBlock(TypeBlock {
+                     negation: false,
+                     exists: None,
                      type_name: String::from("AWS::ApiGateway::Method"),
                      conditions: None,
                      block: Block {
@@ -3754,6 +4172,7 @@ fn parameterized_rule_parse_test() -> Result<(), Error> {
         parameter_names: parameters,
         rule: Rule {
             rule_name: "policy_checks".to_string(),
+            tags: vec![],
             conditions: None,
             block: Block {
                 assignments: vec![],
@@ -4072,6 +4491,14 @@ fn does_this_work() -> Result<(), Error> {
 #[case("IS_BOOL", CmpOperator::IsBool)]
 #[case("is_int", CmpOperator::IsInt)]
 #[case("IS_INT", CmpOperator::IsInt)]
+#[case("is_map", CmpOperator::IsMap)]
+#[case("IS_MAP", CmpOperator::IsMap)]
+#[case("is_number", CmpOperator::IsNumber)]
+#[case("IS_NUMBER", CmpOperator::IsNumber)]
+#[case("unique", CmpOperator::Unique)]
+#[case("UNIQUE", CmpOperator::Unique)]
+#[case("distinct", CmpOperator::Unique)]
+#[case("DISTINCT", CmpOperator::Unique)]
 fn unary_parse(#[case] s: &str, #[case] expected : CmpOperator) -> Result<(), Error> {
     let parsed = value_cmp(LocatedSpan::new_extra(s, ""))?.1.0;
     assert_eq!(expected, parsed);
@@ -4079,6 +4506,26 @@ fn unary_parse(#[case] s: &str, #[case] expected : CmpOperator) -> Result<(), Er
     Ok(())
 }
 
+#[rstest::rstest]
+#[case("SUBSET", (CmpOperator::Subset, false))]
+#[case("subset", (CmpOperator::Subset, false))]
+#[case("NOT SUBSET", (CmpOperator::Subset, true))]
+#[case("SUPERSET", (CmpOperator::Superset, false))]
+#[case("superset", (CmpOperator::Superset, false))]
+#[case("!SUPERSET", (CmpOperator::Superset, true))]
+#[case("ALL IN", (CmpOperator::AllIn, false))]
+#[case("all in", (CmpOperator::AllIn, false))]
+#[case("NOT ALL IN", (CmpOperator::AllIn, true))]
+#[case("!ALL IN", (CmpOperator::AllIn, true))]
+#[case("EQUALS_UNORDERED", (CmpOperator::EqUnordered, false))]
+#[case("not equals_unordered", (CmpOperator::EqUnordered, true))]
+fn set_operator_parse(#[case] s: &str, #[case] expected : (CmpOperator, bool)) -> Result<(), Error> {
+    let parsed = value_cmp(LocatedSpan::new_extra(s, ""))?.1;
+    assert_eq!(expected, parsed);
+    assert_eq!(expected.0.is_binary(), true);
+    Ok(())
+}
+
 #[test]
 fn parameterized_rule_block() -> Result<(), Error> {
     let parameterized_rule = r###"
@@ -4095,6 +4542,7 @@ fn parameterized_rule_block() -> Result<(), Error> {
         parameter_names,
         rule: Rule {
             rule_name: "iam_disallowed_attributes_check".to_string(),
+            tags: vec![],
             block: Block {
                 assignments: vec![],
                 conjunctions: Conjunctions::from([Disjunctions::from([RuleClause::Clause(
@@ -4523,6 +4971,142 @@ fn test_parse_regex_inner_when_regex_is_valid() {
     assert!(parse_regex_inner(valid_cmp).is_ok())
 }
 
+#[test]
+fn test_parse_regex_flags() {
+    fn regex(s: &str) -> Result<Value, String> {
+        parse_regex(from_str2(s)).map(|(_, value)| value).map_err(|e| format!("{:?}", e))
+    }
+    assert_eq!(regex("/true/i"), Ok(Value::Regex("(?i:true)".to_string())));
+    assert_eq!(regex("/^a.b$/sm"), Ok(Value::Regex("(?ms:^a.b$)".to_string())));
+    assert_eq!(regex("/prod/f"), Ok(Value::Regex("\\A(?:prod)\\z".to_string())));
+    assert_eq!(regex("/prod/fi"), Ok(Value::Regex("\\A(?:(?i:prod))\\z".to_string())));
+    assert_eq!(regex("/a\\/b/i"), Ok(Value::Regex("(?i:a/b)".to_string())));
+
+    //
+    // flags must directly follow the regex, anything else after it is left for the caller
+    //
+    let (remainder, value) = parse_regex(from_str2("/prod/ i")).unwrap();
+    assert_eq!(value, Value::Regex("prod".to_string()));
+    assert_eq!(*remainder.fragment(), " i");
+    let (remainder, _) = parse_regex(from_str2("/prod/i]")).unwrap();
+    assert_eq!(*remainder.fragment(), "]");
+    assert!(regex("/prod/x").is_err());
+    assert!(regex("/prod/in").is_err());
+
+    let clause = GuardClause::try_from(r#"Name == /PROD/if"#).unwrap();
+    match clause {
+        GuardClause::Clause(GuardAccessClause { access_clause, .. }) => assert_eq!(
+            access_clause.compare_with,
+            Some(LetValue::Value(PathAwareValue::Regex((Path::root(), "\\A(?:(?i:PROD))\\z".to_string()))))
+        ),
+        rest => unreachable!("{:?}", rest),
+    }
+}
+
+#[test]
+fn test_resource_count_clause() -> Result<(), Error> {
+    let clause = GuardClause::try_from("AWS::EC2::VPC RESOURCE_COUNT == 1")?;
+    match &clause {
+        GuardClause::Clause(GuardAccessClause { access_clause, .. }) => {
+            assert_eq!(access_clause.query.query, vec![QueryPart::ResourceCount("AWS::EC2::VPC".to_string())]);
+            assert_eq!(access_clause.comparator, (CmpOperator::Eq, false));
+            assert_eq!(format!("{}", access_clause.query), "AWS::EC2::VPC RESOURCE_COUNT");
+        },
+        rest => unreachable!("{:?}", rest),
+    }
+
+    let rules = RulesFile::try_from(r#"
+    rule single_vpc {
+        AWS::EC2::VPC RESOURCE_COUNT == 1
+        AWS::ElasticLoadBalancingV2::LoadBalancer RESOURCE_COUNT <= 2 or
+        Custom::Balancer RESOURCE_COUNT == 0
+        AWS::EC2::VPC EXISTS
+        AWS::EC2::VPC { Properties.CidrBlock EXISTS }
+    }
+    "#)?;
+    let conjunctions = &rules.guard_rules[0].block.conjunctions;
+    assert_eq!(conjunctions.len(), 4);
+    assert!(matches!(&conjunctions[0][0], RuleClause::Clause(_)));
+    assert_eq!(conjunctions[1].len(), 2);
+    assert!(matches!(&conjunctions[2][0], RuleClause::TypeBlock(TypeBlock { exists: Some(true), .. })));
+    assert!(matches!(&conjunctions[3][0], RuleClause::TypeBlock(TypeBlock { exists: None, .. })));
+
+    //
+    // the keyword must stand on its own
+    //
+    assert!(GuardClause::try_from("AWS::EC2::VPC RESOURCE_COUNTS == 1").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_custom_message_escaped_delimiter() {
+    let (remainder, message) = custom_message(from_str2(r#"<<arn:aws:iam::123:role/X \>> see wiki>> or"#)).unwrap();
+    assert_eq!(message, "arn:aws:iam::123:role/X >> see wiki");
+    assert_eq!(*remainder.fragment(), " or");
+
+    let (remainder, message) = custom_message(from_str2(r#"<<\>>\>>>>"#)).unwrap();
+    assert_eq!(message, ">>>>");
+    assert_eq!(*remainder.fragment(), "");
+
+    let clause = GuardClause::try_from(r#"Name exists <<escaped \>> still the message>>"#).unwrap();
+    match clause {
+        GuardClause::Clause(GuardAccessClause { access_clause, .. }) =>
+            assert_eq!(access_clause.custom_message.as_deref(), Some("escaped >> still the message")),
+        rest => unreachable!("{:?}", rest),
+    }
+
+    //
+    // an unclosed message is reported where it opens
+    //
+    let input = from_str2("Name exists\n    <<never closed \\>>");
+    match custom_message(input.slice(16..)) {
+        Err(nom::Err::Failure(error)) => {
+            assert_eq!(error.span.location_line(), 2);
+            assert_eq!(error.span.get_utf8_column(), 5);
+            assert!(error.context.starts_with("Unable to find a closing >>"), "{}", error.context);
+        },
+        rest => unreachable!("{:?}", rest),
+    }
+}
+
+#[test]
+fn test_parse_map_key_forms() {
+    let (remainder, value) = parse_value(from_str2(
+        r#"{ bare: 1, with-dash_2: 2, 'single': 3, "double": 4, 'arn:aws:iam': 5, "a b": 6 }"#)).unwrap();
+    assert_eq!(*remainder.fragment(), "");
+    let keys = match value {
+        Value::Map(map) => map.keys().cloned().collect::<Vec<_>>(),
+        rest => unreachable!("{:?}", rest),
+    };
+    assert_eq!(keys, vec!["bare", "with-dash_2", "single", "double", "arn:aws:iam", "a b"]);
+
+    //
+    // a colon needs the key quoted
+    //
+    assert!(parse_value(from_str2("{ arn:aws: 5 }")).is_err());
+}
+
+#[test]
+fn test_parse_map_duplicate_keys() {
+    for map in [
+        "{ key: 1, key: 2 }",
+        r#"{ key: 1, 'key': 2 }"#,
+        "{ key: 1,\n  other: { key: 1 },\n  \"key\": 2 }",
+    ] {
+        match parse_value(from_str2(map)) {
+            Err(nom::Err::Failure(error)) => {
+                assert_eq!(error.context, "Duplicate key key in map", "{}", map);
+                assert!(error.span.fragment().starts_with("key") || error.span.fragment().starts_with("'key'") ||
+                        error.span.fragment().starts_with("\"key\""), "{}", map);
+            },
+            rest => unreachable!("{} {:?}", map, rest),
+        }
+    }
+
+    let error = RulesFile::try_from("let tags = { Env: 'dev', Env: 'prod' }").unwrap_err();
+    assert!(format!("{}", error).contains("Duplicate key Env in map"), "{}", error);
+}
+
 #[test]
 fn test_parse_value_when_strings_are_randomly_generated() {
     let values = vec!["weifhasidhhfasidf77627&^&*^**", "IiI+L1w="];
@@ -4532,3 +5116,368 @@ fn test_parse_value_when_strings_are_randomly_generated() {
         assert!(parse_value(cmp).is_err())
     }
 }
+
+#[test]
+fn test_when_guarded_type_block_and_clause_parse() -> Result<(), Error> {
+    let type_block_str = r#"AWS::EC2::Instance when %volumes.*.Ebs != null {
+        %volumes.*.Ebs.encrypted == true
+        %volumes.*.Ebs.delete_on_termination == true
+    }"#;
+    let (rest, parsed) = type_block(from_str2(type_block_str))?;
+    assert_eq!(*rest.fragment(), "");
+    assert_eq!(parsed.type_name, "AWS::EC2::Instance");
+    let conditions = parsed.conditions.expect("when conditions on type block");
+    assert_eq!(conditions.len(), 1);
+    match &conditions[0][..] {
+        [WhenGuardClause::Clause(GuardAccessClause { access_clause, negation: false })] => {
+            assert_eq!(access_clause.comparator, (CmpOperator::Eq, true));
+            assert_eq!(format!("{}", SliceDisplay(&access_clause.query.query)), "%volumes[*].*.Ebs");
+            assert_eq!(access_clause.compare_with, Some(LetValue::Value(PathAwareValue::Null(Path::root()))));
+        },
+        rest => unreachable!("{:?}", rest),
+    }
+    assert_eq!(parsed.block.conjunctions.len(), 2);
+
+    let clause_str = r#"when %volumes.*.Ebs != null {
+        %volumes.*.Ebs.encrypted == true
+    }"#;
+    let (rest, parsed) = clause(from_str2(clause_str))?;
+    assert_eq!(*rest.fragment(), "");
+    match parsed {
+        GuardClause::WhenBlock(conditions, block) => {
+            assert_eq!(conditions.len(), 1);
+            assert_eq!(block.conjunctions.len(), 1);
+        },
+        rest => unreachable!("{:?}", rest),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_negated_type_block_parse() -> Result<(), Error> {
+    let negated = r#"not AWS::IAM::Policy {
+        Properties.PolicyDocument.Statement[*].Effect == "Allow"
+    }"#;
+    let (rest, parsed) = type_block(from_str2(negated))?;
+    assert_eq!(*rest.fragment(), "");
+    assert!(parsed.negation);
+    assert_eq!(parsed.type_name, "AWS::IAM::Policy");
+
+    let (_, parsed) = type_block(from_str2(r#"!AWS::IAM::Policy Properties.PolicyName EXISTS"#))?;
+    assert!(parsed.negation);
+    let (_, parsed) = type_block(from_str2(r#"AWS::IAM::Policy Properties.PolicyName EXISTS"#))?;
+    assert!(!parsed.negation);
+
+    let rule = Rule::try_from(r#"rule no_allow_policies {
+        not AWS::IAM::Policy {
+            Properties.PolicyDocument.Statement[*].Effect == "Allow"
+        }
+        not Resources.*.Properties.PolicyName EXISTS
+    }"#)?;
+    let clauses = rule.block.conjunctions.iter().flatten().collect::<Vec<_>>();
+    assert!(matches!(clauses[0], RuleClause::TypeBlock(TypeBlock { negation: true, .. })));
+    assert!(matches!(clauses[1], RuleClause::Clause(GuardClause::Clause(GuardAccessClause { negation: true, .. }))));
+    Ok(())
+}
+
+#[test]
+fn test_type_exists_parse() -> Result<(), Error> {
+    let (rest, parsed) = type_block(from_str2("AWS::IAM::User !EXISTS"))?;
+    assert_eq!(*rest.fragment(), "");
+    assert_eq!(parsed.exists, Some(false));
+    assert!(parsed.block.conjunctions.is_empty());
+
+    let (_, parsed) = type_block(from_str2("AWS::EC2::VPC EXISTS"))?;
+    assert_eq!(parsed.exists, Some(true));
+    let (_, parsed) = type_block(from_str2("not AWS::EC2::VPC exists"))?;
+    assert_eq!((parsed.negation, parsed.exists), (true, Some(true)));
+
+    //
+    // A property starting with exists is still a clause on the type
+    //
+    let (_, parsed) = type_block(from_str2("AWS::EC2::VPC exists_flag == true"))?;
+    assert_eq!(parsed.exists, None);
+    assert_eq!(parsed.block.conjunctions.len(), 1);
+
+    let rules = RulesFile::try_from(r#"
+    AWS::IAM::User !EXISTS
+    rule no_users {
+        AWS::IAM::User !EXISTS
+    }
+    "#)?;
+    assert_eq!(rules.guard_rules.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_parser_error_display_points_at_source() {
+    let rules = "rule check_bucket {\n    Resources.*.Type == \n}\n";
+    let error = match rules_file(Span::new_extra(rules, "rules.guard")) {
+        Err(e) => format!("{}", e),
+        Ok(_) => unreachable!(),
+    };
+    assert!(error.contains("Error parsing file rules.guard at line 2 at column 24"));
+    assert!(error.contains(concat!(
+        "  --> rules.guard:2:24\n",
+        "   |\n",
+        " 2 |     Resources.*.Type ==\n",
+        "   |                        ^"
+    )), "{}", error);
+
+    let source = Span::new_extra("rule r {\n\tb == \n}", "data.guard");
+    let error = ParserError {
+        context: "".to_string(),
+        span: source.slice(10..),
+        kind: nom::error::ErrorKind::Tag,
+    };
+    assert_eq!(format!("{}", error), concat!(
+        "Error parsing file data.guard at line 2 at column 2\n",
+        "  --> data.guard:2:2\n",
+        "   |\n",
+        " 2 | \tb ==\n",
+        "   | \t^"
+    ));
+}
+
+#[test]
+fn test_cidr_clause_parse() -> Result<(), Error> {
+    for (clause, comparator) in [
+        ("CidrIp IN_CIDR \"10.0.0.0/8\"", (CmpOperator::InCidr, false)),
+        ("CidrIp in_cidr [\"10.0.0.0/8\", \"fd00::/8\"]", (CmpOperator::InCidr, false)),
+        ("CidrIp NOT IN_CIDR \"0.0.0.0/0\"", (CmpOperator::InCidr, true)),
+        ("CidrIp !IN_CIDR \"0.0.0.0/0\"", (CmpOperator::InCidr, true)),
+        ("CidrIp IN [\"10.0.0.0/8\"]", (CmpOperator::In, false)),
+    ] {
+        match GuardClause::try_from(clause)? {
+            GuardClause::Clause(GuardAccessClause { access_clause, .. }) =>
+                assert_eq!(access_clause.comparator, comparator, "{}", clause),
+            rest => unreachable!("{:?}", rest),
+        }
+    }
+
+    let clause = GuardClause::try_from(r#"CIDR_PREFIX(Properties.SecurityGroupIngress[*].CidrIp) >= 24"#)?;
+    match &clause {
+        GuardClause::Clause(GuardAccessClause { access_clause, negation: false }) => {
+            assert_eq!(access_clause.query.query.last(), Some(&QueryPart::CidrPrefix));
+            assert_eq!(access_clause.comparator, (CmpOperator::Ge, false));
+            assert_eq!(format!("{}", access_clause.query), "cidr_prefix(Properties.SecurityGroupIngress[*].CidrIp)");
+        },
+        rest => unreachable!("{:?}", rest),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_count_clause_parse() -> Result<(), Error> {
+    let clause = GuardClause::try_from(r#"count(Resources.*[ Type == /Subnet/ ]) >= 2"#)?;
+    match &clause {
+        GuardClause::Clause(GuardAccessClause { access_clause, negation: false }) => {
+            assert_eq!(access_clause.query.query.len(), 4);
+            assert_eq!(access_clause.query.query.last(), Some(&QueryPart::Count));
+            assert_eq!(access_clause.comparator, (CmpOperator::Ge, false));
+            assert_eq!(format!("{}", access_clause.query), "count(Resources.*. (filter-clauses))");
+        },
+        rest => unreachable!("{:?}", rest),
+    }
+
+    //
+    // count without parentheses remains a property name, Terraform resources have one
+    //
+    let clause = GuardClause::try_from(r#"count >= 2"#)?;
+    match &clause {
+        GuardClause::Clause(GuardAccessClause { access_clause, .. }) => {
+            assert_eq!(access_clause.query.query, vec![QueryPart::Key("count".to_string())]);
+        },
+        rest => unreachable!("{:?}", rest),
+    }
+
+    let rules = RulesFile::try_from(r#"
+    let subnets = Resources.*[ Type == /Subnet/ ]
+    rule multi_az when COUNT(%subnets) > 1 {
+        count(%subnets) <= 6
+    }
+    "#)?;
+    assert_eq!(rules.guard_rules.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_let_capture_parse() -> Result<(), Error> {
+    let rules = RulesFile::try_from(r#"
+    let region := Resources.*.Properties.TopicArn MATCH /arn:aws:[^:]+:([^:]+):/
+    let topics = Resources.*[ Type == 'AWS::SNS::Topic' ]
+    rule in_region {
+        let account = %topics.Properties.TopicArn match /:(\d{12}):/
+        %region == 'us-east-1'
+        %account exists
+    }
+    "#)?;
+    match &rules.assignments[0].value {
+        LetValue::AccessClause(query) => {
+            assert_eq!(query.query.last(), Some(&QueryPart::Capture("arn:aws:[^:]+:([^:]+):".to_string())));
+            assert_eq!(format!("{}", query), "Resources.*.Properties.TopicArn MATCH /arn:aws:[^:]+:([^:]+):/");
+        },
+        rest => unreachable!("{:?}", rest),
+    }
+    match &rules.assignments[1].value {
+        LetValue::AccessClause(query) => assert!(!matches!(query.query.last(), Some(QueryPart::Capture(_)))),
+        rest => unreachable!("{:?}", rest),
+    }
+    assert_eq!(rules.guard_rules[0].block.assignments[0].value, LetValue::AccessClause(AccessQuery {
+        query: vec![
+            QueryPart::Key("%topics".to_string()),
+            QueryPart::AllIndices(None),
+            QueryPart::Key("Properties".to_string()),
+            QueryPart::Key("TopicArn".to_string()),
+            QueryPart::Capture(r":(\d{12}):".to_string()),
+        ],
+        match_all: true,
+    }));
+    Ok(())
+}
+
+#[test]
+fn test_root_anchor_parse() -> Result<(), Error> {
+    let (_, query) = access(from_str2("$.Parameters.Env"))?;
+    assert_eq!(query.query, vec![
+        QueryPart::Key(format!("%{}", ROOT_VARIABLE)),
+        QueryPart::AllIndices(None),
+        QueryPart::Key("Parameters".to_string()),
+        QueryPart::Key("Env".to_string()),
+    ]);
+    assert_eq!(query.query[0].variable(), Some(ROOT_VARIABLE));
+    assert_eq!(format!("{}", query.query[0]), "$");
+
+    let (_, query) = access(from_str2("$"))?;
+    assert_eq!(query.query, vec![QueryPart::Key(format!("%{}", ROOT_VARIABLE))]);
+    Ok(())
+}
+
+#[test]
+fn test_resource_ref_parse() -> Result<(), Error> {
+    let (_, parsed) = clause(from_str2(
+        "Properties.Roles[*] != RESOURCE_REF(AWS::IAM::Role, Properties.RoleName)"))?;
+    let access_clause = match parsed {
+        GuardClause::Clause(GuardAccessClause { access_clause, .. }) => access_clause,
+        rest => unreachable!("{:?}", rest),
+    };
+    assert_eq!(access_clause.comparator, (CmpOperator::In, true));
+    let query = match access_clause.compare_with {
+        Some(LetValue::AccessClause(query)) => query.query,
+        rest => unreachable!("{:?}", rest),
+    };
+    assert_eq!(query[0].variable(), Some(ROOT_VARIABLE));
+    assert_eq!(&query[2..4], &[QueryPart::Key("Resources".to_string()), QueryPart::AllValues(None)]);
+    assert!(matches!(query[4], QueryPart::Filter(..)));
+    assert_eq!(&query[5..], &[QueryPart::Key("Properties".to_string()), QueryPart::Key("RoleName".to_string())]);
+
+    let (_, parsed) = clause(from_str2(
+        "Properties.Roles[*] > RESOURCE_REF(AWS::IAM::Role, Properties.RoleName)"))?;
+    match parsed {
+        GuardClause::Clause(GuardAccessClause { access_clause, .. }) =>
+            assert_eq!(access_clause.comparator, (CmpOperator::Gt, false)),
+        rest => unreachable!("{:?}", rest),
+    }
+
+    assert!(clause(from_str2("Properties.Roles[*] == RESOURCE_REF(Properties.RoleName)")).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_guard_clause_display() -> Result<(), Error> {
+    let expectations = [
+        ("a == 1", " a EQUALS  1"),
+        ("a != 'x'", " a not EQUALS  \"x\""),
+        ("a in [1, 2]", " a IN  [1,2]"),
+        ("a > 1", " a GREATER THAN  1"),
+        ("a < 1", " a LESS THAN  1"),
+        ("a >= 1", " a GREATER THAN EQUALS  1"),
+        ("a <= 1", " a LESS THAN EQUALS  1"),
+        ("a.b[0].c exists", " a.b.0.c EXISTS  "),
+        ("a not empty", " a not EMPTY  "),
+        ("a is_string", " a IS STRING  "),
+        ("a is_bool", " a IS BOOL  "),
+        ("a is_int", " a IS INT  "),
+        ("a is_number", " a IS NUMBER  "),
+        ("a is_list", " a IS LIST  "),
+        ("a is_struct", " a IS MAP  "),
+        ("a[*] !unique", " a[*] not UNIQUE  "),
+        ("a[*] SUBSET ['tcp', 'udp']", " a[*] SUBSET  [\"tcp\",\"udp\"]"),
+        ("a not superset %azs", " a not SUPERSET  %azs"),
+        ("a ALL IN [1, 2]", " a ALL IN  [1,2]"),
+        ("a EQUALS_UNORDERED [1, 2]", " a EQUALS UNORDERED  [1,2]"),
+    ];
+    for (clause, expected) in expectations {
+        assert_eq!(GuardClause::try_from(clause)?.to_string(), expected, "{}", clause);
+    }
+
+    let block = GuardClause::try_from("a { b == 1 or c exists\n d == 2 }")?;
+    assert_eq!(block.to_string(), "a {  b EQUALS  1 or  c EXISTS  ;  d EQUALS  2 }");
+    let named = GuardClause::NamedRule(GuardNamedRuleClause {
+        dependent_rule: "s3_encrypted".to_string(),
+        negation: false,
+        custom_message: None,
+        location: FileLocation { line: 3, column: 5, file_name: "s3.guard" },
+    });
+    assert_eq!(named.to_string(), "Rule(s3_encrypted@Location[file:s3.guard, line:3, column:5])");
+    let parameterized = ParameterizedNamedRuleClause::try_from("check_tags(%tags, 'env')")?;
+    assert_eq!(parameterized.to_string(), r#"Rule(check_tags@Location[file:, line:1, column:1])(%tags, "env")"#);
+    Ok(())
+}
+
+#[test]
+fn test_rule_tags_parse() -> Result<(), Error> {
+    let rules = RulesFile::try_from(r#"
+rule iam_no_wildcards @tags("iam", "cis-1.16") when Resources exists {
+    Resources.*.Properties.PolicyDocument.Statement[*].Action != '*'
+}
+
+rule untagged {
+    Resources exists
+}
+"#)?;
+    assert_eq!(rules.guard_rules[0].tags, vec!["iam".to_string(), "cis-1.16".to_string()]);
+    assert!(rules.guard_rules[0].conditions.is_some());
+    assert!(rules.guard_rules[1].tags.is_empty());
+
+    let serialized = serde_json::to_value(&rules.guard_rules)?;
+    assert_eq!(serialized[0]["tags"], serde_json::json!(["iam", "cis-1.16"]));
+    assert!(serialized[1].get("tags").is_none());
+
+    let rules = RulesFile::try_from(r#"
+let buckets = Resources.*[ Type == 'AWS::S3::Bucket' ]
+# @tags: security, cost
+# checks that every bucket is encrypted
+rule encrypted @tags("s3", "cost") {
+    %buckets.Properties.BucketEncryption exists
+}
+
+# @tags: cost
+rule parameterized_cost(buckets) {
+    %buckets.Properties.Tags exists
+}
+# a plain comment
+rule commented { Resources exists }
+
+# @tags: trailing
+"#)?;
+    assert_eq!(rules.guard_rules[0].tags, vec!["security".to_string(), "cost".to_string(), "s3".to_string()]);
+    assert_eq!(rules.parameterized_rules[0].rule.tags, vec!["cost".to_string()]);
+    assert!(rules.guard_rules[1].tags.is_empty());
+
+    for (malformed, column) in [
+        ("rule r @tag(\"iam\") { Resources exists }", 9),
+        ("rule r @tags() { Resources exists }", 13),
+        ("rule r @tags(\"iam\" { Resources exists }", 13),
+    ] {
+        let span = Span::new_extra(malformed, "tags.guard");
+        match rules_file(span) {
+            Err(e) => {
+                let msg = e.to_string();
+                assert!(msg.contains(&format!("tags.guard at line 1 at column {}", column)), "{}", msg);
+            },
+            rest => unreachable!("{:?}", rest),
+        }
+    }
+    Ok(())
+}
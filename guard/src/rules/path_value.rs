@@ -1,6 +1,7 @@
 pub(crate) mod traversal;
 
 use std::cmp::Ordering;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 use std::convert::{TryFrom, TryInto};
 //
 // Std Libraries
@@ -50,16 +51,23 @@ impl std::fmt::Display for Location {
     }
 }
 
+//
+// The last field names the template parameter when the value was substituted for a Ref to it
+//
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub(crate) struct Path(pub(crate) String, pub(crate) Location);
+pub(crate) struct Path(pub(crate) String, pub(crate) Location, #[serde(skip)] pub(crate) Option<String>);
 
 impl Path {
     pub(crate) fn new(path: String, line: usize, col: usize) -> Path {
-        Path(path, Location::new(line, col))
+        Path(path, Location::new(line, col), None)
     }
 
     pub(crate) fn with_location(&self, loc: Location) -> Self {
-        Path(self.0.clone(), loc)
+        Path(self.0.clone(), loc, self.2.clone())
+    }
+
+    pub(crate) fn via_parameter(&self, parameter: &str) -> Self {
+        Path(self.0.clone(), self.1.clone(), Some(parameter.to_string()))
     }
 }
 
@@ -71,13 +79,17 @@ impl std::fmt::Display for Path {
                 self.0,
                 self.1
             )
-        )
+        )?;
+        if let Some(parameter) = &self.2 {
+            write!(f, " via Parameter {}", parameter)?;
+        }
+        Ok(())
     }
 }
 
 impl Path {
     pub(crate) fn root() -> Self {
-        Path("".to_string(), Location::default())
+        Path("".to_string(), Location::default(), None)
     }
 
     pub(crate) fn relative(&self) -> &str {
@@ -92,7 +104,7 @@ impl TryFrom<&str> for Path {
     type Error = Error;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        Ok(Path(value.to_string(), Location::default()))
+        Ok(Path(value.to_string(), Location::default(), None))
     }
 }
 
@@ -108,7 +120,7 @@ impl TryFrom<&[&str]> for Path {
                             acc.push('/'); acc.push_str(part.as_str());
                         }
                         acc
-                    }), Location::default()))
+                    }), Location::default(), None))
     }
 }
 
@@ -122,18 +134,24 @@ impl TryFrom<&[String]> for Path {
 }
 
 impl Path {
+    //
+    // Paths are extended for every value visited when loading and querying data, so the
+    // extended path is allocated once at its final size instead of cloning and growing
+    //
+    fn joined(&self, part: &str) -> String {
+        let mut joined = String::with_capacity(self.0.len() + 1 + part.len());
+        joined.push_str(&self.0);
+        joined.push('/');
+        joined.push_str(part);
+        joined
+    }
+
     pub(crate) fn extend_str(&self, part: &str) -> Path {
-        let mut copy = self.0.clone();
-        copy.push('/');
-        copy.push_str(part);
-        Path(copy, self.1.clone())
+        Path(self.joined(part), self.1.clone(), self.2.clone())
     }
 
     pub(crate) fn extend_str_with_location(&self, part: &str, loc: Location) -> Path {
-        let mut copy = self.0.clone();
-        copy.push('/');
-        copy.push_str(part);
-        Path(copy, loc)
+        Path(self.joined(part), loc, self.2.clone())
     }
 
     pub(crate) fn extend_string(&self, part: &String) -> Path {
@@ -146,14 +164,36 @@ impl Path {
     }
 
     pub(crate) fn drop_last(&mut self) -> &mut Self {
-        let removed = match self.0.rfind('/') {
-            Some(idx) => self.0.as_str()[0..idx].to_string(),
-            None => return self
-        };
-        self.0 = removed;
+        if let Some(idx) = self.0.rfind('/') {
+            self.0.truncate(idx);
+        }
         self
     }
 
+    //
+    // The parent path has no location of its own, it only becomes known when the parent
+    // value is visited. Returns None for the root path
+    //
+    pub(crate) fn parent(&self) -> Option<Path> {
+        if self.0.is_empty() {
+            return None
+        }
+        let mut parent = Path(self.0.clone(), Location::default(), None);
+        match self.0.rfind('/') {
+            Some(_) => { parent.drop_last(); },
+            None => parent.0.clear(),
+        }
+        Some(parent)
+    }
+
+    pub(crate) fn components(&self) -> Vec<&str> {
+        self.0.split('/').filter(|each| !each.is_empty()).collect()
+    }
+
+    pub(crate) fn depth(&self) -> usize {
+        self.components().len()
+    }
+
     pub(crate) fn extend_with_value(&self, part: &Value) -> Result<Path, Error> {
         match part {
             Value::String(s) => Ok(self.extend_string(s)),
@@ -197,10 +237,58 @@ impl PartialEq for MapValue {
 
 impl Eq for MapValue {}
 
+lazy_static::lazy_static! {
+    static ref KEY_REGEXES: std::sync::Mutex<std::collections::HashMap<String, regex::Regex>> =
+        std::sync::Mutex::new(std::collections::HashMap::new());
+}
+
 impl MapValue {
     pub(crate) fn is_empty(&self) -> bool {
         self.values.is_empty()
     }
+
+    //
+    // Looks up key by exact match first and falls back to the first key that matches ignoring
+    // ASCII case, e.g. bucketName finds BucketName. Returns the key as it appears in the data
+    //
+    pub(crate) fn get_case_insensitive<'a>(&'a self, key: &str) -> Option<(&'a String, &'a PathAwareValue)> {
+        self.values.get_key_value(key).or_else(|| {
+            self.values.iter().find(|(each, _)| each.eq_ignore_ascii_case(key))
+        })
+    }
+
+    //
+    // The values of the keys that match regex, in the order of the keys, for a /regex/ segment
+    // in a query. Ignores case when case insensitive keys are enabled. The segment is selected
+    // for every map the query reaches, each pattern is compiled once
+    //
+    pub(crate) fn values_matching(&self, regex: &str, options: &EvaluationOptions) -> Result<Vec<&PathAwareValue>, Error> {
+        let pattern = if options.case_insensitive_keys { format!("(?i:{})", regex) } else { regex.to_string() };
+        let compiled = {
+            let mut compiled = KEY_REGEXES.lock().unwrap();
+            match compiled.get(&pattern) {
+                Some(each) => each.clone(),
+                None => {
+                    let each = regex::Regex::new(&pattern).map_err(|e| Error::new(ErrorKind::IncompatibleError(
+                        format!("Invalid regular expression /{}/ to match keys with, {}", regex, e))))?;
+                    compiled.insert(pattern, each.clone());
+                    each
+                }
+            }
+        };
+        Ok(self.values.iter().filter(|(key, _)| compiled.is_match(key)).map(|(_, value)| value).collect())
+    }
+
+    //
+    // Key lookup used by queries, exact unless case insensitive keys are enabled
+    //
+    pub(crate) fn get_key(&self, key: &str, options: &EvaluationOptions) -> Option<&PathAwareValue> {
+        if options.case_insensitive_keys {
+            self.get_case_insensitive(key).map(|(_, value)| value)
+        } else {
+            self.values.get(key)
+        }
+    }
 }
 
 
@@ -269,6 +357,10 @@ impl PathAwareValue {
             _ => None
         }
     }
+
+    pub(crate) fn to_json_value(&self) -> serde_json::Value {
+        serde_json::Value::from(self)
+    }
 }
 
 
@@ -360,13 +452,15 @@ impl PartialEq for PathAwareValue {
                 value.is_within(r)
             },
 
-            (rest, rest2) => match compare_values(rest, rest2) {
-                Ok(ordering) => match ordering {
-                    Ordering::Equal => true,
-                    _ => false
-                },
-                Err(_) => false
-            }
+            (PathAwareValue::Int((_, value)), PathAwareValue::RangeFloat((_, r))) => {
+                r.contains(&(*value as f64))
+            },
+
+            (PathAwareValue::Float((_, value)), PathAwareValue::RangeInt((_, r))) => {
+                r.as_float().contains(value)
+            },
+
+            (rest, rest2) => matches!(compare_values(rest, rest2, &DEFAULT_EVALUATION_OPTIONS), Ok(Ordering::Equal))
         }
     }
 }
@@ -402,14 +496,60 @@ impl TryFrom<(&serde_json::Value, Path)> for PathAwareValue {
     }
 }
 
+//
+// Converts YAML directly without going through Value. Core tags like !!int or !!float are
+// already resolved by serde_yaml into the scalar type, other tags (e.g. !Ref) are dropped and
+// the tagged value is used. Scalar mapping keys like 1 or true are converted to strings
+//
 impl TryFrom<(&serde_yaml::Value, Path)> for PathAwareValue {
     type Error = Error;
 
     fn try_from(incoming: (&serde_yaml::Value, Path)) -> Result<Self, Self::Error> {
         let root = incoming.0;
         let path = incoming.1;
-        let value = Value::try_from(root)?;
-        PathAwareValue::try_from((&value, path))
+
+        match root {
+            serde_yaml::Value::String(s) => Ok(PathAwareValue::String((path, s.to_owned()))),
+            serde_yaml::Value::Number(num) => {
+                if let Some(i) = num.as_i64() {
+                    Ok(PathAwareValue::Int((path, i)))
+                } else if let Some(u) = num.as_u64() {
+                    Ok(PathAwareValue::Float((path, u as f64)))
+                } else {
+                    Ok(PathAwareValue::Float((path, num.as_f64().unwrap_or(f64::NAN))))
+                }
+            },
+            serde_yaml::Value::Bool(b) => Ok(PathAwareValue::Bool((path, *b))),
+            serde_yaml::Value::Null => Ok(PathAwareValue::Null(path)),
+            serde_yaml::Value::Sequence(sequence) => {
+                let mut result: Vec<PathAwareValue> = Vec::with_capacity(sequence.len());
+                for (idx, each) in sequence.iter().enumerate() {
+                    let sub_path = path.extend_usize(idx);
+                    result.push(PathAwareValue::try_from((each, sub_path))?);
+                }
+                Ok(PathAwareValue::List((path, result)))
+            },
+            serde_yaml::Value::Mapping(mapping) => {
+                let mut keys = Vec::with_capacity(mapping.len());
+                let mut values = indexmap::IndexMap::with_capacity(mapping.len());
+                for (each_key, each_value) in mapping {
+                    let key = match each_key {
+                        serde_yaml::Value::String(s) => s.to_owned(),
+                        serde_yaml::Value::Number(n) => n.to_string(),
+                        serde_yaml::Value::Bool(b) => b.to_string(),
+                        serde_yaml::Value::Null => "null".to_string(),
+                        rest => return Err(Error::new(ErrorKind::IncompatibleError(
+                            format!("Map keys must be scalar values, found {:?} at path {}", rest, path)
+                        ))),
+                    };
+                    let sub_path = path.extend_string(&key);
+                    keys.push(PathAwareValue::String((sub_path.clone(), key.clone())));
+                    values.insert(key, PathAwareValue::try_from((each_value, sub_path))?);
+                }
+                Ok(PathAwareValue::Map((path, MapValue{keys, values})))
+            },
+            serde_yaml::Value::Tagged(tagged) => PathAwareValue::try_from((&tagged.value, path)),
+        }
     }
 }
 
@@ -532,8 +672,15 @@ impl TryFrom<(MarkedValue, Path)> for PathAwareValue {
                     let sub_path = path.extend_string(&each_key);
                     let sub_path = sub_path.with_location(each_value.location().clone());
                     let value = PathAwareValue::try_from((each_value, sub_path))?;
-                    values.insert(each_key.to_owned(), value);
-                    keys.push(PathAwareValue::String((path.with_location(loc.clone()), each_key.to_string())));
+                    let key = PathAwareValue::String((path.with_location(loc.clone()), each_key.to_string()));
+                    //
+                    // The loader keeps repeated keys apart by location, the last value wins in
+                    // the position of the first so that keys stay in step with values
+                    //
+                    match values.insert_full(each_key, value) {
+                        (index, Some(_)) => keys[index] = key,
+                        (_, None) => keys.push(key),
+                    }
                 }
                 Ok(PathAwareValue::Map((path.with_location(loc), MapValue{keys, values})))
             },
@@ -617,10 +764,111 @@ impl<'a> TryInto<(String, serde_json::Value)> for &'a PathAwareValue {
     }
 }
 
+//
+// Plain JSON form of the value without paths. Regular expressions are prefixed with "regex:"
+// to distinguish them from strings, ranges become [lower, upper] and floats that can not be
+// represented in JSON (NaN, infinity) become null
+//
+impl From<&PathAwareValue> for serde_json::Value {
+    fn from(value: &PathAwareValue) -> Self {
+        let float = |f: f64| serde_json::Number::from_f64(f)
+            .map_or(serde_json::Value::Null, serde_json::Value::Number);
+        match value {
+            PathAwareValue::Null(_) => serde_json::Value::Null,
+            PathAwareValue::String((_, s)) => serde_json::Value::String(s.clone()),
+            PathAwareValue::Regex((_, r)) => serde_json::Value::String(format!("regex:{}", r)),
+            PathAwareValue::Bool((_, b)) => serde_json::Value::Bool(*b),
+            PathAwareValue::Int((_, i)) => serde_json::Value::Number(serde_json::Number::from(*i)),
+            PathAwareValue::Float((_, f)) => float(*f),
+            PathAwareValue::Char((_, c)) => serde_json::Value::String(c.to_string()),
+            PathAwareValue::List((_, list)) =>
+                serde_json::Value::Array(list.iter().map(serde_json::Value::from).collect()),
+            PathAwareValue::Map((_, map)) =>
+                serde_json::Value::Object(map.values.iter()
+                    .map(|(key, value)| (key.clone(), serde_json::Value::from(value)))
+                    .collect()),
+            PathAwareValue::RangeInt((_, r)) => serde_json::json!([r.lower, r.upper]),
+            PathAwareValue::RangeFloat((_, r)) => serde_json::Value::Array(vec![float(r.lower), float(r.upper)]),
+            PathAwareValue::RangeChar((_, r)) =>
+                serde_json::Value::Array(vec![
+                    serde_json::Value::String(r.lower.to_string()),
+                    serde_json::Value::String(r.upper.to_string())
+                ]),
+        }
+    }
+}
+
 pub(crate) trait QueryResolver {
     fn select(&self, all: bool, query: &[QueryPart<'_>], eval: &dyn EvaluationContext) -> Result<Vec<&PathAwareValue>, Error>;
 }
 
+lazy_static::lazy_static! {
+    static ref COUNT_VALUES: std::sync::Mutex<std::collections::HashMap<usize, &'static PathAwareValue>> =
+        std::sync::Mutex::new(std::collections::HashMap::new());
+}
+
+//
+// The value count(query) resolves to. Counts are not part of the data being evaluated, so each
+// distinct count is allocated once and kept for the life of the process, which lets evaluation
+// records refer to it like any other value
+//
+pub(crate) fn count_value(count: usize) -> &'static PathAwareValue {
+    let mut values = COUNT_VALUES.lock().unwrap();
+    values.entry(count).or_insert_with(|| {
+        Box::leak(Box::new(PathAwareValue::Int((Path::root(), count as i64))))
+    })
+}
+
+lazy_static::lazy_static! {
+    static ref CAPTURED_VALUES: std::sync::Mutex<std::collections::HashMap<(String, String), &'static PathAwareValue>> =
+        std::sync::Mutex::new(std::collections::HashMap::new());
+}
+
+//
+// The value let x := query MATCH /regex/ resolves to for a value the query matched, the first
+// capture group of regex. Values that are not strings or do not match capture nothing. Like
+// count_value, each distinct capture is allocated once, keeping the path it was taken from
+//
+pub(crate) fn capture_value(value: &PathAwareValue, regex: &str) -> Result<Option<&'static PathAwareValue>, Error> {
+    let compiled = regex::Regex::new(regex).map_err(|e| Error::new(ErrorKind::IncompatibleError(
+        format!("Invalid regular expression /{}/ to capture from, {}", regex, e))))?;
+    if compiled.captures_len() < 2 {
+        return Err(Error::new(ErrorKind::IncompatibleError(
+            format!("Regular expression /{}/ has no capture group to assign", regex))))
+    }
+    let captured = match value {
+        PathAwareValue::String((path, s)) => compiled.captures(s)
+            .and_then(|captures: regex::Captures<'_>| captures.get(1))
+            .map(|group| (path, group.as_str().to_string())),
+        _ => None,
+    };
+    Ok(captured.map(|(path, captured)| {
+        let mut values = CAPTURED_VALUES.lock().unwrap();
+        *values.entry((format!("{}", path), captured.clone())).or_insert_with(|| {
+            Box::leak(Box::new(PathAwareValue::String((path.clone(), captured))))
+        })
+    }))
+}
+
+lazy_static::lazy_static! {
+    static ref CIDR_PREFIX_VALUES: std::sync::Mutex<std::collections::HashMap<(String, u8), &'static PathAwareValue>> =
+        std::sync::Mutex::new(std::collections::HashMap::new());
+}
+
+//
+// The value cidr_prefix(query) resolves to for a value the query matched, the prefix length of
+// the CIDR block, keeping the path it was taken from. Plain addresses are full length blocks.
+// Values that are not a valid IP address or CIDR block are NotComparable
+//
+pub(crate) fn cidr_prefix_value(value: &PathAwareValue) -> Result<&'static PathAwareValue, Error> {
+    let (_, prefix, _) = cidr_or_error(value)?;
+    let path = value.self_path();
+    let mut values = CIDR_PREFIX_VALUES.lock().unwrap();
+    Ok(*values.entry((format!("{}", path), prefix)).or_insert_with(|| {
+        Box::leak(Box::new(PathAwareValue::Int((path.clone(), prefix as i64))))
+    }))
+}
+
 impl QueryResolver for PathAwareValue {
     fn select(&self, all: bool, query: &[QueryPart<'_>], resolver: &dyn EvaluationContext) -> Result<Vec<&PathAwareValue>, Error> {
         if query.is_empty() {
@@ -702,7 +950,7 @@ impl QueryResolver for PathAwareValue {
                                 }
                                 Ok(acc)
                             }
-                            else if let Some(next) = map.values.get(key) {
+                            else if let Some(next) = map.get_key(key, resolver.options()) {
                                 next.select(all, &query[1..], resolver)
                             } else {
                                 self.map_some_or_error_all(all, query)
@@ -714,6 +962,27 @@ impl QueryResolver for PathAwareValue {
                 }
             },
 
+            QueryPart::KeyRegex(regex) => {
+                match self {
+                    PathAwareValue::Map((path, map)) => {
+                        let matched = map.values_matching(regex, resolver.options())?;
+                        if matched.is_empty() && all {
+                            return Err(Error::new(ErrorKind::RetrievalError(
+                                format!("Could not locate a key matching /{}/ inside object/map at path = {}, remaining query = {}",
+                                        regex, path, SliceDisplay(query))
+                            )))
+                        }
+                        let mut acc = Vec::with_capacity(matched.len());
+                        for each in matched {
+                            acc.extend(each.select(all, &query[1..], resolver)?);
+                        }
+                        Ok(acc)
+                    },
+
+                    _ => self.map_some_or_error_all(all, query)
+                }
+            },
+
             QueryPart::Index(array_idx) => {
                 match self {
                     PathAwareValue::List((_path, vec)) => {
@@ -754,6 +1023,7 @@ impl QueryResolver for PathAwareValue {
                     },
 
                     PathAwareValue::Map((_path, map)) => {
+                        check_max_depth(resolver.options().max_depth, self, &query[1..])?;
                         let values: Vec<&PathAwareValue> = map.values.values().collect();
                         let mut resolved = Vec::with_capacity(values.len());
                         for each in values {
@@ -774,40 +1044,51 @@ impl QueryResolver for PathAwareValue {
                 }
             },
 
+            QueryPart::RecursiveDescent(key) => {
+                let found = self.descendants_with_key(key);
+                if found.is_empty() {
+                    return self.map_some_or_error_all(all, query)
+                }
+                let mut resolved = Vec::with_capacity(found.len());
+                for each in found {
+                    resolved.extend(each.select(all, &query[1..], resolver)?);
+                }
+                Ok(resolved)
+            },
+
             QueryPart::MapKeyFilter(_name, filter) => {
                 match self {
                     PathAwareValue::Map((_, map)) => {
                         let mut selected = Vec::with_capacity(map.values.len());
-                        match &filter.compare_with {
-                            LetValue::AccessClause(query) => {
-                                let values = resolve_query(false, &query.query, self, resolver)?;
-                                for key in map.keys.iter() {
-                                    if values.contains(&key) {
-                                        match key {
-                                            PathAwareValue::String((_, v)) => {
-                                                selected.push(map.values.get(v).unwrap());
-                                            },
-                                            _ => unreachable!()
-                                        }
-                                    }
-                                }
-                            },
-
-                            LetValue::Value(path_value) => {
-                                for key in map.keys.iter() {
-                                    if key == path_value {
-                                        match key {
-                                            PathAwareValue::String((_, v)) => {
-                                                selected.push(map.values.get(v).unwrap());
-                                            },
-                                            _ => unreachable!()
-                                        }
-                                    }
-                                }
-                            },
-
+                        let values = match &filter.compare_with {
+                            LetValue::AccessClause(query) => resolve_query(false, &query.query, self, resolver)?,
+                            LetValue::Value(path_value) => vec![path_value],
                             LetValue::FunctionCall(_) => unreachable!(),
                         };
+                        let values = if filter.comparator.0 == CmpOperator::Eq {
+                            values
+                        } else {
+                            values.into_iter().flat_map(|each| match each {
+                                PathAwareValue::List((_, list)) => list.iter().collect(),
+                                _ => vec![each],
+                            }).collect::<Vec<_>>()
+                        };
+                        //
+                        // A key is selected when it matches any of the values, KEYS != and
+                        // KEYS NOT IN select the keys that match none of them
+                        //
+                        let (_, not) = filter.comparator;
+                        for key in map.keys.iter() {
+                            let matched = values.iter().any(|each| compare_eq(key, each, resolver.options()).unwrap_or(false));
+                            if matched != not {
+                                match key {
+                                    PathAwareValue::String((_, v)) => {
+                                        selected.push(map.values.get(v).unwrap());
+                                    },
+                                    _ => unreachable!()
+                                }
+                            }
+                        }
                         if query.len() > 1 {
                             let mut acc = Vec::with_capacity(selected.len());
                             for each in selected {
@@ -824,11 +1105,46 @@ impl QueryResolver for PathAwareValue {
                 }
             },
 
-            QueryPart::Filter(_name, conjunctions) => {
+            QueryPart::Count => {
+                Err(Error::new(ErrorKind::IncompatibleError(
+                    format!("count() can only be applied to a whole query, found it inside {}", SliceDisplay(query))
+                )))
+            },
+
+            QueryPart::Capture(_) => {
+                Err(Error::new(ErrorKind::IncompatibleError(
+                    format!("MATCH can only be applied to a whole query, found it inside {}", SliceDisplay(query))
+                )))
+            },
+
+            QueryPart::CidrPrefix => {
+                Err(Error::new(ErrorKind::IncompatibleError(
+                    format!("cidr_prefix() can only be applied to a whole query, found it inside {}", SliceDisplay(query))
+                )))
+            },
+
+            QueryPart::ResourceCount(_) => {
+                Err(Error::new(ErrorKind::IncompatibleError(
+                    format!("RESOURCE_COUNT can only follow a resource type, found it inside {}", SliceDisplay(query))
+                )))
+            },
+
+            QueryPart::Filter(_name, conjunctions) |
+            QueryPart::FilterNot(_name, conjunctions) => {
+                //
+                // FilterNot selects the elements for which the clauses did not PASS
+                //
+                let negated = matches!(&query[0], QueryPart::FilterNot(..));
                 match self {
                     PathAwareValue::List((path, vec)) => {
                         let mut selected = Vec::with_capacity(vec.len());
                         let context = format!("Path={},Type=Array", path);
+                        let index: usize = if query.len() > 1 {
+                            match &query[1] {
+                                QueryPart::AllIndices(_) => 2,
+                                _ => 1
+                            }
+                        } else { 1 };
                         for each in vec {
                             let mut filter = AutoReport::new(EvaluationType::Filter, resolver, &context);
                             match conjunctions.evaluate(each, resolver) {
@@ -837,27 +1153,24 @@ impl QueryResolver for PathAwareValue {
                                         return Err(Error::new(ErrorKind::RetrievalError(e)))
                                     }
                                     // Else treat is like a filter
+                                    if negated {
+                                        selected.extend(each.select(all, &query[index..], resolver)?);
+                                    }
                                 },
                                 Err(Error(ErrorKind::IncompatibleRetrievalError(e))) => {
                                     if all {
                                         return Err(Error::new(ErrorKind::IncompatibleRetrievalError(e)))
                                     }
                                     // Else treat is like a filter
+                                    if negated {
+                                        selected.extend(each.select(all, &query[index..], resolver)?);
+                                    }
                                 },
                                 Err(e) => return Err(e),
                                 Ok(status) => {
-                                    match status {
-                                        Status::PASS => {
-                                            filter.status(Status::PASS);
-                                            let index: usize = if query.len() > 1 {
-                                                match &query[1] {
-                                                    QueryPart::AllIndices(_) => 2,
-                                                    _ => 1
-                                                }
-                                            } else { 1 };
-                                            selected.extend(each.select(all, &query[index..], resolver)?);
-                                        },
-                                        rest => { filter.status(rest); }
+                                    filter.status(status);
+                                    if (status == Status::PASS) != negated {
+                                        selected.extend(each.select(all, &query[index..], resolver)?);
                                     }
                                 }
                             }
@@ -872,16 +1185,11 @@ impl QueryResolver for PathAwareValue {
                             .map_or_else(
                                 |e| self.map_error_or_empty(all, e),
                                 |status| {
-                                    match status {
-                                        Status::PASS => {
-                                            filter.status(Status::PASS);
-                                            self.select(all, &query[1..], resolver)
-                                        },
-                                        rest => {
-                                            filter.status(rest);
-                                            Ok(vec![])
-                                        }
-
+                                    filter.status(status);
+                                    if (status == Status::PASS) != negated {
+                                        self.select(all, &query[1..], resolver)
+                                    } else {
+                                        Ok(vec![])
                                     }
                                 }
                             )
@@ -947,6 +1255,92 @@ impl PathAwareValue {
         Ok(self)
     }
 
+    //
+    // Depth first collection of all scalar values, with their paths. Maps and lists are
+    // traversed but never returned themselves
+    //
+    pub(crate) fn flatten(&self) -> Vec<(&Path, &PathAwareValue)> {
+        let mut leaves = Vec::new();
+        self.collect_leaves(&mut leaves);
+        leaves
+    }
+
+    fn collect_leaves<'value>(&'value self, leaves: &mut Vec<(&'value Path, &'value PathAwareValue)>) {
+        match self {
+            PathAwareValue::Map((_, map)) => {
+                for each in map.values.values() {
+                    each.collect_leaves(leaves);
+                }
+            },
+            PathAwareValue::List((_, list)) => {
+                for each in list {
+                    each.collect_leaves(leaves);
+                }
+            },
+            rest => leaves.push(rest.self_value())
+        }
+    }
+
+    //
+    // Depth first search for all values associated with key at any depth
+    // below this value, including those nested inside other matches
+    //
+    pub(crate) fn descendants_with_key(&self, key: &str) -> Vec<&PathAwareValue> {
+        let mut found = Vec::new();
+        self.collect_with_key(key, &mut found);
+        found
+    }
+
+    fn collect_with_key<'value>(&'value self, key: &str, found: &mut Vec<&'value PathAwareValue>) {
+        match self {
+            PathAwareValue::Map((_, map)) => {
+                for (each_key, each) in map.values.iter() {
+                    if each_key == key {
+                        found.push(each);
+                    }
+                    each.collect_with_key(key, found);
+                }
+            },
+            PathAwareValue::List((_, list)) => {
+                for each in list {
+                    each.collect_with_key(key, found);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    //
+    // Deep merges other into this map. Values from other replace existing ones, except when
+    // both sides are maps, in which case they are merged recursively
+    //
+    pub(crate) fn deep_merge(&mut self, other: &PathAwareValue) -> crate::rules::Result<()> {
+        match (self, other) {
+            (PathAwareValue::Map((_, map)),
+                PathAwareValue::Map((path, other_map))) => {
+                for (key, value) in &other_map.values {
+                    match map.values.get_mut(key) {
+                        Some(existing) if existing.is_map() && value.is_map() => {
+                            existing.deep_merge(value)?
+                        },
+                        Some(existing) => *existing = value.clone(),
+                        None => {
+                            map.values.insert(key.clone(), value.clone());
+                            map.keys.push(PathAwareValue::String((path.extend_str(key), key.clone())));
+                        }
+                    }
+                }
+                Ok(())
+            }
+
+            (this, that) => {
+                Err(Error::new(ErrorKind::IncompatibleError(
+                    format!("Types are not compatible for merges {}, {}", this.type_info(), that.type_info())
+                )))
+            }
+        }
+    }
+
     pub(crate) fn is_list(&self) -> bool {
         match self {
             PathAwareValue::List((_, _)) => true,
@@ -1005,6 +1399,41 @@ impl PathAwareValue {
         self.self_value().0
     }
 
+    //
+    // Looks up the value at a "/" separated path relative to this value, e.g.
+    // "/Resources/logs/Properties" or "Tags/0/Key". Components index into lists by position
+    //
+    pub(crate) fn at_path(&self, path: &str) -> Result<&PathAwareValue, Error> {
+        let mut current = self;
+        for component in path.split('/').filter(|each| !each.is_empty()) {
+            current = match current {
+                PathAwareValue::Map((at, map)) => match map.values.get(component) {
+                    Some(value) => value,
+                    None => return Err(Error::new(ErrorKind::RetrievalError(format!(
+                        "Could not find key {} inside struct at path {} looking up {}", component, at, path
+                    )))),
+                },
+                PathAwareValue::List((at, list)) => match component.parse::<usize>() {
+                    Ok(index) => match list.get(index) {
+                        Some(value) => value,
+                        None => return Err(Error::new(ErrorKind::RetrievalError(format!(
+                            "Array index {} out of bounds for array of length {} at path {} looking up {}",
+                            index, list.len(), at, path
+                        )))),
+                    },
+                    Err(_) => return Err(Error::new(ErrorKind::IncompatibleRetrievalError(format!(
+                        "Expected an array index, found {} at path {} looking up {}", component, at, path
+                    )))),
+                },
+                rest => return Err(Error::new(ErrorKind::IncompatibleRetrievalError(format!(
+                    "Expected a map or array to look up {}, found {} at path {} looking up {}",
+                    component, rest.type_info(), rest.self_path(), path
+                )))),
+            };
+        }
+        Ok(current)
+    }
+
     pub(crate) fn self_path_mut(&mut self) -> &mut Path {
         match self {
             PathAwareValue::Null( path)              |
@@ -1071,6 +1500,7 @@ impl PathAwareValue {
     }
 
     pub(crate) fn accumulate<'v>(parent: &PathAwareValue, all: bool, query: &[QueryPart<'_>], elements: &'v Vec<PathAwareValue>, resolver: &dyn EvaluationContext) -> Result<Vec<&'v PathAwareValue>, Error>{
+        check_max_depth(resolver.options().max_depth, parent, query)?;
         if elements.is_empty() && !query.is_empty() && all {
             return Err(Error::new(ErrorKind::RetrievalError(
                 format!("No entries for path = {} . Remaining Query {}", parent.self_path(), SliceDisplay(query))
@@ -1091,25 +1521,329 @@ impl PathAwareValue {
     }
 }
 
-fn compare_values(first: &PathAwareValue, other: &PathAwareValue) -> Result<Ordering, Error> {
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 64;
+
+//
+// Options that change how queries select values and how values compare, set from the command
+// line for each evaluation. Evaluation contexts hand them out to the code that needs them,
+// the root scope owns them
+//
+#[derive(Debug)]
+pub(crate) struct EvaluationOptions {
+    //
+    // Maximum depth that AllValues/AllIndices queries may descend to. Templates nested deeper
+    // than this produce an error instead of recursing further
+    //
+    pub(crate) max_depth: usize,
+    //
+    // Lenient comparisons, strings "true"/"false" compare with booleans and numeric strings
+    // with ints and floats
+    //
+    pub(crate) type_coercion: bool,
+    //
+    // A string that parses as a number compares with an int or float for <, <=, > and >=.
+    // This is a narrower form of type coercion, coerced matches are counted along with its own
+    //
+    pub(crate) numeric_string_coercion: bool,
+    //
+    // Query keys match ignoring case when no key matches exactly. Rules that write bucketname
+    // instead of BucketName then select the property instead of failing to retrieve it
+    //
+    pub(crate) case_insensitive_keys: bool,
+    //
+    // Outcome of EMPTY for a value that the query could not resolve, --missing-as
+    //
+    pub(crate) missing_as: MissingAs,
+    //
+    // Key lookups on lists apply to each element. Terraform plans hold resources of a type and
+    // nested blocks in lists, with this enabled aws_s3_bucket { versioning.enabled == true }
+    // checks every bucket and its versioning block
+    //
+    pub(crate) list_key_expansion: bool,
+    //
+    // How <, >, <= and >= order two strings, --string-order
+    //
+    pub(crate) string_order: StringOrder,
+    //
+    // Stop evaluating a rules file at the first rule that FAILs, --fail-fast. The rules after
+    // it are not evaluated or reported
+    //
+    pub(crate) fail_fast: bool,
+    //
+    // Number of comparisons that succeeded only via coercion
+    //
+    coerced_matches: AtomicUsize,
+}
+
+impl EvaluationOptions {
+    pub(crate) const fn new() -> Self {
+        EvaluationOptions {
+            max_depth: DEFAULT_MAX_DEPTH,
+            type_coercion: false,
+            numeric_string_coercion: false,
+            case_insensitive_keys: false,
+            missing_as: MissingAs::Skip,
+            list_key_expansion: false,
+            string_order: StringOrder::Lexical,
+            fail_fast: false,
+            coerced_matches: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn coerced_matches(&self) -> usize {
+        self.coerced_matches.load(AtomicOrdering::Relaxed)
+    }
+}
+
+impl Clone for EvaluationOptions {
+    fn clone(&self) -> Self {
+        EvaluationOptions {
+            max_depth: self.max_depth,
+            type_coercion: self.type_coercion,
+            numeric_string_coercion: self.numeric_string_coercion,
+            case_insensitive_keys: self.case_insensitive_keys,
+            missing_as: self.missing_as,
+            list_key_expansion: self.list_key_expansion,
+            string_order: self.string_order,
+            fail_fast: self.fail_fast,
+            coerced_matches: AtomicUsize::new(self.coerced_matches()),
+        }
+    }
+}
+
+impl Default for EvaluationOptions {
+    fn default() -> Self {
+        EvaluationOptions::new()
+    }
+}
+
+//
+// Options for contexts that evaluate without any set from the command line
+//
+pub(crate) static DEFAULT_EVALUATION_OPTIONS: EvaluationOptions = EvaluationOptions::new();
+
+//
+// Outcome of EMPTY for a value that the query could not resolve, e.g. Tags EMPTY when there
+// is no Tags key. !EMPTY FAILs for such values either way
+//
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum MissingAs {
+    Skip,
+    Fail,
+}
+
+//
+// How <, >, <= and >= order two strings. Lexical compares them byte by byte, so "1.10.0" < "1.9.0".
+// Semver compares strings that are both semantic versions by version precedence, so
+// "1.10.0" > "1.9.0", and any other strings lexically
+//
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum StringOrder {
+    Lexical,
+    Semver,
+}
+
+fn compare_strings(first: &str, other: &str, string_order: StringOrder) -> Ordering {
+    if string_order == StringOrder::Semver {
+        if let (Ok(first), Ok(other)) = (semver::Version::parse(first), semver::Version::parse(other)) {
+            return first.cmp(&other)
+        }
+    }
+    first.cmp(other)
+}
+
+pub(crate) fn check_max_depth(max_depth: usize, parent: &PathAwareValue, query: &[QueryPart<'_>]) -> Result<(), Error> {
+    if parent.self_path().depth() >= max_depth {
+        return Err(Error::new(ErrorKind::IncompatibleError(
+            format!("Maximum traversal depth {} exceeded at path = {}, remaining query = {}. Use --max-depth to raise the limit",
+                    max_depth, parent.self_path(), SliceDisplay(query))
+        )))
+    }
+    Ok(())
+}
+
+fn coerce_string(value: &str, path: &Path, to: &PathAwareValue) -> Option<PathAwareValue> {
+    match to {
+        PathAwareValue::Bool(_) => match value {
+            "true" => Some(PathAwareValue::Bool((path.clone(), true))),
+            "false" => Some(PathAwareValue::Bool((path.clone(), false))),
+            _ => None
+        },
+        PathAwareValue::Int(_) => value.parse::<i64>().ok()
+            .map(|i| PathAwareValue::Int((path.clone(), i))),
+        PathAwareValue::Float(_) => value.parse::<f64>().ok()
+            .map(|f| PathAwareValue::Float((path.clone(), f))),
+        _ => None
+    }
+}
+
+fn coerced_pair(first: &PathAwareValue, second: &PathAwareValue, options: &EvaluationOptions) -> Option<(PathAwareValue, PathAwareValue)> {
+    if !options.type_coercion {
+        return None
+    }
+    match (first, second) {
+        (PathAwareValue::String((path, s)), other) =>
+            coerce_string(s, path, other).map(|coerced| (coerced, other.clone())),
+        (other, PathAwareValue::String((path, s))) =>
+            coerce_string(s, path, other).map(|coerced| (other.clone(), coerced)),
+        _ => None
+    }
+}
+
+fn numeric_pair(first: &PathAwareValue, second: &PathAwareValue, options: &EvaluationOptions) -> Option<(PathAwareValue, PathAwareValue)> {
+    if !options.numeric_string_coercion {
+        return None
+    }
+    match (first, second) {
+        (PathAwareValue::String((path, s)), other @ (PathAwareValue::Int(_) | PathAwareValue::Float(_))) =>
+            coerce_string(s, path, other).map(|coerced| (coerced, other.clone())),
+        (other @ (PathAwareValue::Int(_) | PathAwareValue::Float(_)), PathAwareValue::String((path, s))) =>
+            coerce_string(s, path, other).map(|coerced| (other.clone(), coerced)),
+        _ => None
+    }
+}
+
+fn note_if_coerced(first: &PathAwareValue, second: &PathAwareValue, matched: bool, options: &EvaluationOptions) -> bool {
+    if matched && coerced_pair(first, second, options).is_some() {
+        options.coerced_matches.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+    matched
+}
+
+fn note_if_ordering_coerced(first: &PathAwareValue, second: &PathAwareValue, matched: bool, options: &EvaluationOptions) -> bool {
+    if matched && (coerced_pair(first, second, options).is_some() || numeric_pair(first, second, options).is_some()) {
+        options.coerced_matches.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+    matched
+}
+
+//
+// Ordering used by <, <=, > and >=, falls back to numeric string coercion when the values
+// are not otherwise comparable
+//
+fn compare_ordering(first: &PathAwareValue, other: &PathAwareValue, options: &EvaluationOptions) -> Result<Ordering, Error> {
+    match compare_values(first, other, options) {
+        Err(Error(ErrorKind::NotComparable(msg))) => match numeric_pair(first, other, options) {
+            Some((first, other)) => compare_values(&first, &other, options),
+            None => Err(Error::new(ErrorKind::NotComparable(msg)))
+        },
+        rest => rest
+    }
+}
+
+fn compare_values(first: &PathAwareValue, other: &PathAwareValue, options: &EvaluationOptions) -> Result<Ordering, Error> {
     match (first, other) {
         //
         // scalar values
         //
         (PathAwareValue::Null(_), PathAwareValue::Null(_)) => Ok(Ordering::Equal),
         (PathAwareValue::Int((_, i)), PathAwareValue::Int((_, o))) => Ok(i.cmp(o)),
-        (PathAwareValue::String((_, s)), PathAwareValue::String((_, o))) => Ok(s.cmp(o)),
+        (PathAwareValue::String((_, s)), PathAwareValue::String((_, o))) => Ok(compare_strings(s, o, options.string_order)),
         (PathAwareValue::Float((_, f)), PathAwareValue::Float((_, s))) => match f.partial_cmp(s) {
             Some(o) => Ok(o),
             None => Err(Error::new(ErrorKind::NotComparable("Float values are not comparable".to_owned())))
         },
         (PathAwareValue::Char((_, f)), PathAwareValue::Char((_, s))) => Ok(f.cmp(s)),
-        (_, _) => Err(Error::new(ErrorKind::NotComparable(
-            format!("PathAwareValues are not comparable {}, {}", first.type_info(), other.type_info()))))
+        (_, _) => match coerced_pair(first, other, options) {
+            Some((first, other)) => compare_values(&first, &other, options),
+            None => Err(Error::new(ErrorKind::NotComparable(
+                format!("PathAwareValues are not comparable {}, {}", first.type_info(), other.type_info()))))
+        }
     }
 }
 
-pub(crate) fn compare_eq(first: &PathAwareValue, second: &PathAwareValue) -> Result<bool, Error> {
+//
+// For each value the earlier value that it repeats, if any, as used by UNIQUE. Values are equal
+// as with compare_eq, except that a string never repeats a regex it matches
+//
+pub(crate) fn earlier_duplicates<'value>(values: &[&'value PathAwareValue], options: &EvaluationOptions) -> Vec<Option<&'value PathAwareValue>> {
+    let repeats = |first: &PathAwareValue, second: &PathAwareValue| match (first, second) {
+        (PathAwareValue::String(_), PathAwareValue::Regex(_)) |
+        (PathAwareValue::Regex(_), PathAwareValue::String(_)) => false,
+        _ => compare_eq(first, second, options).unwrap_or(false)
+    };
+    values.iter().enumerate()
+        .map(|(index, each)| values[..index].iter().find(|earlier| repeats(earlier, each)).copied())
+        .collect()
+}
+
+//
+// The collections that SUBSET and SUPERSET compare, each list on its own and all other values
+// together as one, so that a scalar is treated as a single element list. Each collection comes
+// with the value that it is reported at, the list or the first of the other values
+//
+pub(crate) fn set_collections<'value>(values: &[&'value PathAwareValue]) -> Vec<(&'value PathAwareValue, Vec<&'value PathAwareValue>)> {
+    let mut collections = Vec::with_capacity(values.len());
+    let mut rest = Vec::new();
+    for each in values {
+        match each {
+            PathAwareValue::List((_, list)) => collections.push((*each, list.iter().collect())),
+            single => rest.push(*single),
+        }
+    }
+    if let Some(first) = rest.first() {
+        collections.push((*first, rest));
+    }
+    collections
+}
+
+//
+// The elements that violate SUBSET, values in the collection that are not in the other, or
+// SUPERSET, values in the other that are not in the collection. Values are equal as with
+// compare_eq, values that are not comparable are not equal
+//
+pub(crate) fn set_difference<'value>(
+    collection: &[&'value PathAwareValue],
+    other: &[&'value PathAwareValue],
+    superset: bool,
+    options: &EvaluationOptions) -> Vec<&'value PathAwareValue>
+{
+    let (from, within) = if superset { (other, collection) } else { (collection, other) };
+    from.iter()
+        .filter(|each| !within.iter().any(|candidate| compare_eq(each, candidate, options).unwrap_or(false)))
+        .copied()
+        .collect()
+}
+
+//
+// Lists are equal as multisets, each element of one is equal to a different element of the other
+// with compare_eq, in any order, elements that are not comparable are not equal. Lists nested in
+// them, and all other values, compare as with compare_eq. Used by EQUALS_UNORDERED, == stays
+// positional
+//
+pub(crate) fn compare_list_set_eq(first: &PathAwareValue, second: &PathAwareValue, options: &EvaluationOptions) -> Result<bool, Error> {
+    match (first, second) {
+        (PathAwareValue::List((_, list)), PathAwareValue::List((_, list2))) => {
+            if list.len() != list2.len() {
+                return Ok(false)
+            }
+            let mut unmatched = list2.iter().collect::<Vec<&PathAwareValue>>();
+            for each in list {
+                let mut matched = None;
+                for (idx, other) in unmatched.iter().enumerate() {
+                    let equal = match compare_eq(each, other, options) {
+                        Ok(equal) => equal,
+                        Err(Error(ErrorKind::NotComparable(_))) => false,
+                        Err(e) => return Err(e),
+                    };
+                    if equal {
+                        matched = Some(idx);
+                        break;
+                    }
+                }
+                match matched {
+                    Some(idx) => { unmatched.swap_remove(idx); },
+                    None => return Ok(false),
+                }
+            }
+            Ok(true)
+        },
+
+        _ => compare_eq(first, second, options)
+    }
+}
+
+pub(crate) fn compare_eq(first: &PathAwareValue, second: &PathAwareValue, options: &EvaluationOptions) -> Result<bool, Error> {
     let (reg, s) = match (first, second) {
         (PathAwareValue::String((_, s)), PathAwareValue::Regex((_, r))) => (regex::Regex::new(r.as_str())?, s.as_str()),
         (PathAwareValue::Regex((_, r)), PathAwareValue::String((_, s))) => (regex::Regex::new(r.as_str())?, s.as_str()),
@@ -1122,7 +1856,7 @@ pub(crate) fn compare_eq(first: &PathAwareValue, second: &PathAwareValue) -> Res
                     for (key, value) in map.values.iter() {
                         match map2.values.get(key) {
                             Some(value2) => {
-                                if !compare_eq(value, value2)? {
+                                if !compare_eq(value, value2, options)? {
                                     break 'result false;
                                 }
                             },
@@ -1145,7 +1879,7 @@ pub(crate) fn compare_eq(first: &PathAwareValue, second: &PathAwareValue) -> Res
                 //
                 if list.len() == list2.len() {
                     for (left, right) in list.iter().zip(list2.iter()) {
-                        if !compare_eq(left, right)? {
+                        if !compare_eq(left, right, options)? {
                             break 'result false;
                         }
                     }
@@ -1178,54 +1912,197 @@ pub(crate) fn compare_eq(first: &PathAwareValue, second: &PathAwareValue) -> Res
             return Ok(value.is_within(r))
         },
 
-        (_, _) => return match compare_values(first, second)? {
-            Ordering::Equal => Ok(true),
-            _ => Ok(false)
+        (PathAwareValue::Int((_, value)), PathAwareValue::RangeFloat((_, r))) => {
+            return Ok(r.contains(&(*value as f64)))
+        },
+
+        (PathAwareValue::Float((_, value)), PathAwareValue::RangeInt((_, r))) => {
+            return Ok(r.as_float().contains(value))
+        },
+
+        //
+        // null only ever equals null, comparing it against any other type is not an error
+        //
+        (PathAwareValue::Null(_), PathAwareValue::Null(_)) => return Ok(true),
+        (PathAwareValue::Null(_), _) | (_, PathAwareValue::Null(_)) => return Ok(false),
+
+        (_, _) => {
+            if let Some((coerced_first, coerced_second)) = coerced_pair(first, second, options) {
+                return Ok(note_if_coerced(first, second, compare_eq(&coerced_first, &coerced_second, options)?, options))
+            }
+            return match compare_values(first, second, options)? {
+                Ordering::Equal => Ok(true),
+                _ => Ok(false)
+            }
         }
     };
     Ok(reg.is_match(s))
 }
 
-pub(crate) fn compare_lt(first: &PathAwareValue, other: &PathAwareValue) -> Result<bool, Error> {
-    match compare_values(first, other) {
-        Ok(o) => match o {
-            Ordering::Equal | Ordering::Greater => Ok(false),
-            Ordering::Less => Ok(true)
+//
+// IN membership, true when first is equal to any element of the list. Equality is checked with
+// compare_eq, hence regular expressions in the list are matched against strings
+//
+pub(crate) fn compare_in(first: &PathAwareValue, list: &PathAwareValue, options: &EvaluationOptions) -> Result<bool, Error> {
+    match list {
+        PathAwareValue::List((_, elements)) => {
+            for each in elements {
+                if compare_eq(first, each, options)? {
+                    return Ok(true)
+                }
+            }
+            Ok(false)
         },
+
+        rest => Err(Error::new(ErrorKind::NotComparable(
+            format!("IN operator expects a list to compare against, found {} at {}",
+                    rest.type_info(), rest.self_path())
+        )))
+    }
+}
+
+//
+// An IP address or CIDR block parsed into (network bits, prefix length, is IPv6). A plain address
+// is a block of its own, /32 for IPv4 and /128 for IPv6. Host bits past the prefix are kept, the
+// comparisons below mask them out
+//
+pub(crate) fn parse_cidr(value: &str) -> Option<(u128, u8, bool)> {
+    let (address, prefix) = match value.trim().split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix)),
+        None => (value.trim(), None),
+    };
+    let (bits, max, v6) = match address.parse::<std::net::IpAddr>().ok()? {
+        std::net::IpAddr::V4(v4) => (u32::from(v4) as u128, 32u8, false),
+        std::net::IpAddr::V6(v6) => (u128::from(v6), 128u8, true),
+    };
+    let prefix = match prefix {
+        Some(prefix) if !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_digit()) =>
+            prefix.parse::<u8>().ok().filter(|p| *p <= max)?,
+        Some(_) => return None,
+        None => max,
+    };
+    Some((bits, prefix, v6))
+}
+
+fn cidr_or_error(value: &PathAwareValue) -> Result<(u128, u8, bool), Error> {
+    match value {
+        PathAwareValue::String((path, s)) => parse_cidr(s).ok_or_else(|| Error::new(ErrorKind::NotComparable(
+            format!("{} at {} is not a valid IP address or CIDR block", s, path)))),
+        rest => Err(Error::new(ErrorKind::NotComparable(
+            format!("Expecting an IP address or CIDR block string, found {} at {}",
+                    rest.type_info(), rest.self_path())))),
+    }
+}
+
+//
+// IN_CIDR containment, true when the address or block first lies within the block cidr. Both
+// must be of the same family, IPv4 addresses are never inside IPv6 blocks or the other way round
+//
+pub(crate) fn compare_in_cidr(first: &PathAwareValue, cidr: &PathAwareValue) -> Result<bool, Error> {
+    let (inner, inner_prefix, inner_v6) = cidr_or_error(first)?;
+    let (outer, outer_prefix, outer_v6) = cidr_or_error(cidr)?;
+    if inner_v6 != outer_v6 || inner_prefix < outer_prefix {
+        return Ok(false)
+    }
+    let width = if outer_v6 { 128 } else { 32 };
+    let mask = match width - outer_prefix as u32 {
+        128 => 0,
+        host => !0u128 << host,
+    };
+    Ok(inner & mask == outer & mask)
+}
+
+pub(crate) fn compare_lt(first: &PathAwareValue, other: &PathAwareValue, options: &EvaluationOptions) -> Result<bool, Error> {
+    match compare_ordering(first, other, options) {
+        Ok(o) => Ok(note_if_ordering_coerced(first, other, match o {
+            Ordering::Equal | Ordering::Greater => false,
+            Ordering::Less => true
+        }, options)),
         Err(e) => Err(e)
     }
 }
 
-pub(crate) fn compare_le(first: &PathAwareValue, other: &PathAwareValue) -> Result<bool, Error> {
-    match compare_values(first, other) {
-        Ok(o) => match o {
-            Ordering::Greater => Ok(false),
-            Ordering::Equal | Ordering::Less => Ok(true)
-        },
+pub(crate) fn compare_le(first: &PathAwareValue, other: &PathAwareValue, options: &EvaluationOptions) -> Result<bool, Error> {
+    match compare_ordering(first, other, options) {
+        Ok(o) => Ok(note_if_ordering_coerced(first, other, match o {
+            Ordering::Greater => false,
+            Ordering::Equal | Ordering::Less => true
+        }, options)),
         Err(e) => Err(e)
     }
 }
 
-pub(crate) fn compare_gt(first: &PathAwareValue, other: &PathAwareValue) -> Result<bool, Error> {
-    match compare_values(first, other) {
-        Ok(o) => match o {
-            Ordering::Greater => Ok(true),
-            Ordering::Less | Ordering::Equal => Ok(false)
-        },
+pub(crate) fn compare_gt(first: &PathAwareValue, other: &PathAwareValue, options: &EvaluationOptions) -> Result<bool, Error> {
+    match compare_ordering(first, other, options) {
+        Ok(o) => Ok(note_if_ordering_coerced(first, other, match o {
+            Ordering::Greater => true,
+            Ordering::Less | Ordering::Equal => false
+        }, options)),
         Err(e) => Err(e)
     }
 }
 
-pub(crate) fn compare_ge(first: &PathAwareValue, other: &PathAwareValue) -> Result<bool, Error> {
-    match compare_values(first, other) {
-        Ok(o) => match o {
-            Ordering::Greater | Ordering::Equal => Ok(true),
-            Ordering::Less => Ok(false)
-        },
+pub(crate) fn compare_ge(first: &PathAwareValue, other: &PathAwareValue, options: &EvaluationOptions) -> Result<bool, Error> {
+    match compare_ordering(first, other, options) {
+        Ok(o) => Ok(note_if_ordering_coerced(first, other, match o {
+            Ordering::Greater | Ordering::Equal => true,
+            Ordering::Less => false
+        }, options)),
         Err(e) => Err(e)
     }
 }
 
+//
+// Walks both trees and collects every position where they differ. Maps with different key
+// sets, lists of different lengths and mismatched types are each reported as a single entry
+// for the containing values, common keys and indices are compared recursively
+//
+pub(crate) fn diff<'value>(
+    first: &'value PathAwareValue,
+    second: &'value PathAwareValue) -> Vec<(Path, &'value PathAwareValue, &'value PathAwareValue)>
+{
+    let mut differences = Vec::new();
+    collect_diff(first, second, &mut differences);
+    differences
+}
+
+fn collect_diff<'value>(
+    first: &'value PathAwareValue,
+    second: &'value PathAwareValue,
+    differences: &mut Vec<(Path, &'value PathAwareValue, &'value PathAwareValue)>)
+{
+    match (first, second) {
+        (PathAwareValue::Map((path, first_map)), PathAwareValue::Map((_, second_map))) => {
+            let same_keys = first_map.values.len() == second_map.values.len() &&
+                first_map.values.keys().all(|key| second_map.values.contains_key(key));
+            if !same_keys {
+                differences.push((path.clone(), first, second));
+            }
+            for (key, each) in &first_map.values {
+                if let Some(other) = second_map.values.get(key) {
+                    collect_diff(each, other, differences);
+                }
+            }
+        },
+
+        (PathAwareValue::List((path, first_list)), PathAwareValue::List((_, second_list))) => {
+            if first_list.len() != second_list.len() {
+                differences.push((path.clone(), first, second));
+            } else {
+                for (each, other) in first_list.iter().zip(second_list.iter()) {
+                    collect_diff(each, other, differences);
+                }
+            }
+        },
+
+        (_, _) => {
+            if first != second {
+                differences.push((first.self_path().clone(), first, second));
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 #[path = "path_value_tests.rs"]
 mod path_value_tests;
@@ -121,6 +121,14 @@ impl EvaluationContext for DummyEval {
         unimplemented!()
     }
 
+    fn resource_count(&self, _resource_type: &str) -> usize {
+        unimplemented!()
+    }
+
+    fn options(&self) -> &EvaluationOptions {
+        &DEFAULT_EVALUATION_OPTIONS
+    }
+
     fn end_evaluation(&self, _eval_type: EvaluationType, _context: &str, _msg: String, _from: Option<PathAwareValue>, _to: Option<PathAwareValue>, _status: Option<Status>, _cmp: Option<(CmpOperator, bool)>) {
     }
 
@@ -321,6 +329,30 @@ fn it_support_evaluation_tests() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn all_values_over_map_test() -> Result<(), Error> {
+    let resources = r#"{
+        Resources: {
+            s3: { Type: "AWS::S3::Bucket" },
+            ec2: { Type: "AWS::EC2::Instance" }
+        }
+    }"#;
+    let value = PathAwareValue::try_from(resources)?;
+    let access = AccessQuery::try_from("Resources.*")?;
+    let dummy = DummyEval{};
+    let selected = value.select(access.match_all, &access.query, &dummy)?;
+    assert_eq!(selected.len(), 2);
+    assert_eq!(selected[0].self_path(), &Path::try_from("/Resources/s3")?);
+    assert_eq!(selected[1].self_path(), &Path::try_from("/Resources/ec2")?);
+
+    let access = AccessQuery::try_from("Resources.*.Type")?;
+    let selected = value.select(access.match_all, &access.query, &dummy)?;
+    assert_eq!(selected.len(), 2);
+    assert_eq!(selected[0].self_path(), &Path::try_from("/Resources/s3/Type")?);
+    assert_eq!(selected[1].self_path(), &Path::try_from("/Resources/ec2/Type")?);
+    Ok(())
+}
+
 #[test]
 fn map_keys_filter_test() -> Result<(), Error> {
     let condition_str = r#"{
@@ -349,6 +381,56 @@ fn map_keys_filter_test() -> Result<(), Error> {
     Ok(())
 }
 
+fn map_keys(value: &PathAwareValue) -> (Vec<&str>, Vec<&str>) {
+    match value {
+        PathAwareValue::Map((_, map)) => (
+            map.keys.iter().map(|key| key.as_string().unwrap()).collect(),
+            map.values.keys().map(String::as_str).collect(),
+        ),
+        rest => unreachable!("{:?}", rest),
+    }
+}
+
+#[test]
+fn map_keys_preserve_insertion_order_test() -> Result<(), Error> {
+    let condition = r#"{"Condition": {"zeta": 1, "alpha": 2, "mid": 3, "beta": 4}}"#;
+    let expected = vec!["zeta", "alpha", "mid", "beta"];
+    let json: serde_json::Value = serde_json::from_str(condition)?;
+    let yaml: serde_yaml::Value = serde_yaml::from_str(condition)?;
+    let parsed = [
+        PathAwareValue::try_from(condition)?,
+        PathAwareValue::try_from(crate::rules::values::read_from(condition)?)?,
+        PathAwareValue::try_from(json)?,
+        PathAwareValue::try_from(yaml)?,
+    ];
+    let access = AccessQuery::try_from("Condition[ keys == /.*/ ]")?;
+    let dummy = DummyEval{};
+    for value in &parsed {
+        let top = value.select(true, &AccessQuery::try_from("Condition")?.query, &dummy)?;
+        assert_eq!(map_keys(top[0]), (expected.clone(), expected.clone()));
+
+        let selected = value.select(access.match_all, &access.query, &dummy)?;
+        let ints = selected.iter().map(|each| each.as_int().unwrap()).collect::<Vec<i64>>();
+        assert_eq!(ints, vec![1, 2, 3, 4]);
+    }
+
+    //
+    // in data the last value for a repeated key wins, keys are not repeated and stay in step with
+    // values. As a rules literal a repeated key is an error
+    //
+    let repeated = r#"{"b": 1, "a": 2, "b": 3}"#;
+    assert!(PathAwareValue::try_from(repeated).is_err());
+    for value in [
+        PathAwareValue::try_from(crate::rules::values::read_from(repeated)?)?,
+        PathAwareValue::try_from(serde_json::from_str::<serde_json::Value>(repeated)?)?,
+    ] {
+        assert_eq!(map_keys(&value), (vec!["b", "a"], vec!["b", "a"]));
+        let selected = value.select(true, &AccessQuery::try_from("b")?.query, &dummy)?;
+        assert_eq!(selected[0].as_int(), Some(3));
+    }
+    Ok(())
+}
+
 #[test]
 fn merge_values_test() -> Result<(), Error> {
     let resources = PathAwareValue::try_from(
@@ -386,3 +468,671 @@ fn merge_values_test() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn deep_merge_override_test() -> Result<(), Error> {
+    let mut merged = PathAwareValue::try_from(
+        serde_yaml::from_str::<serde_yaml::Value>(r#"
+        allowed_regions: ["us-east-1"]
+        mandatory_tags: ["owner"]
+        "#)?
+    )?;
+    let second = PathAwareValue::try_from(
+        serde_json::from_str::<serde_json::Value>(r#"{"allowed_regions": ["eu-west-1"], "env": "prod"}"#)?
+    )?;
+    merged.deep_merge(&second)?;
+    let map = match &merged {
+        PathAwareValue::Map((_, map)) => map,
+        _ => unreachable!()
+    };
+    assert_eq!(map.values.len(), 3);
+    assert_eq!(map.keys.len(), 3);
+    assert_eq!(
+        map.values.get("allowed_regions"),
+        Some(&PathAwareValue::try_from(serde_json::json!(["eu-west-1"]))?)
+    );
+    assert_eq!(
+        map.values.get("mandatory_tags"),
+        Some(&PathAwareValue::try_from(serde_json::json!(["owner"]))?)
+    );
+
+    let list = PathAwareValue::try_from("[1, 2]")?;
+    assert_eq!(merged.deep_merge(&list).is_err(), true);
+    Ok(())
+}
+
+#[test]
+fn deep_merge_test() -> Result<(), Error> {
+    let mut lb = PathAwareValue::try_from(serde_json::json!({
+        "Type": "AWS::ElasticLoadBalancingV2::LoadBalancer",
+        "Properties": {
+            "Scheme": "internal",
+            "Subnets": ["subnet-1"],
+            "Tags": { "Owner": "team" }
+        }
+    }))?;
+    let tg = PathAwareValue::try_from(serde_json::json!({
+        "Type": "AWS::ElasticLoadBalancingV2::TargetGroup",
+        "Properties": {
+            "Port": 443,
+            "Subnets": ["subnet-2"],
+            "Tags": { "CostCenter": "1234" }
+        }
+    }))?;
+    lb.deep_merge(&tg)?;
+    let expected = PathAwareValue::try_from(serde_json::json!({
+        "Type": "AWS::ElasticLoadBalancingV2::TargetGroup",
+        "Properties": {
+            "Scheme": "internal",
+            "Subnets": ["subnet-2"],
+            "Tags": { "Owner": "team", "CostCenter": "1234" },
+            "Port": 443
+        }
+    }))?;
+    assert_eq!(lb, expected);
+    match &lb {
+        PathAwareValue::Map((_, map)) => match map.values.get("Properties") {
+            Some(PathAwareValue::Map((_, properties))) => assert_eq!(properties.keys.len(), 4),
+            _ => unreachable!()
+        },
+        _ => unreachable!()
+    }
+
+    let mut list = PathAwareValue::try_from("[1, 2]")?;
+    assert_eq!(list.deep_merge(&tg).is_err(), true);
+    assert_eq!(lb.deep_merge(&PathAwareValue::try_from("10")?).is_err(), true);
+    Ok(())
+}
+
+#[test]
+fn diff_test() -> Result<(), Error> {
+    let first = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "bucket": {
+                "Type": "AWS::S3::Bucket",
+                "Properties": { "Versioning": "Enabled", "Tags": ["a", "b"], "Name": "first" }
+            },
+            "topic": { "Type": "AWS::SNS::Topic", "Properties": ["x"] }
+        }
+    }))?;
+    let second = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "bucket": {
+                "Type": "AWS::S3::Bucket",
+                "Properties": { "Versioning": "Suspended", "Tags": ["a"], "Name": "first" }
+            },
+            "topic": { "Type": "AWS::SNS::Topic", "Properties": { "x": 1 } }
+        }
+    }))?;
+    let differences = diff(&first, &second);
+    let paths = differences.iter().map(|(path, _, _)| path.0.as_str()).collect::<Vec<_>>();
+    assert_eq!(paths, vec![
+        "/Resources/bucket/Properties/Versioning",
+        "/Resources/bucket/Properties/Tags",
+        "/Resources/topic/Properties",
+    ]);
+    let (_, in_first, in_second) = &differences[2];
+    assert_eq!(in_first.is_list(), true);
+    assert_eq!(in_second.is_map(), true);
+
+    assert_eq!(diff(&first, &first).is_empty(), true);
+
+    let extra_key = PathAwareValue::try_from(serde_json::json!({"Resources": {}}))?;
+    let differences = diff(&first, &extra_key);
+    assert_eq!(differences.len(), 1);
+    let (path, _, _) = &differences[0];
+    assert_eq!(path.0, "/Resources");
+    Ok(())
+}
+
+#[test]
+fn compare_eq_null_test() -> Result<(), Error> {
+    let null = PathAwareValue::try_from("null")?;
+    let string = PathAwareValue::try_from(r#""bucket""#)?;
+    assert_eq!(compare_eq(&null, &PathAwareValue::try_from(serde_json::Value::Null)?, &DEFAULT_EVALUATION_OPTIONS)?, true);
+    assert_eq!(compare_eq(&null, &string, &DEFAULT_EVALUATION_OPTIONS)?, false);
+    assert_eq!(compare_eq(&string, &null, &DEFAULT_EVALUATION_OPTIONS)?, false);
+    Ok(())
+}
+
+#[test]
+fn to_json_value_test() -> Result<(), Error> {
+    let json = serde_json::json!({
+        "Resources": {
+            "bucket": {
+                "Properties": { "Versioning": true, "Tags": [ { "Key": "env", "Value": null } ] }
+            }
+        },
+        "Count": 10,
+        "Ratio": 0.5
+    });
+    let value = PathAwareValue::try_from(json.clone())?;
+    assert_eq!(value.to_json_value(), json);
+
+    let value = PathAwareValue::try_from(r#"/^prod-/"#)?;
+    assert_eq!(serde_json::Value::from(&value), serde_json::json!("regex:^prod-"));
+
+    let path = Path::root();
+    let ranges = [
+        PathAwareValue::RangeInt((path.clone(), RangeType { lower: 10, upper: 20, inclusive: LOWER_INCLUSIVE })),
+        PathAwareValue::RangeFloat((path.clone(), RangeType { lower: 0.5, upper: 1.5, inclusive: 0 })),
+        PathAwareValue::RangeChar((path.clone(), RangeType { lower: 'a', upper: 'z', inclusive: UPPER_INCLUSIVE })),
+    ];
+    let ranges = ranges.iter().map(serde_json::Value::from).collect::<Vec<_>>();
+    assert_eq!(serde_json::Value::Array(ranges), serde_json::json!([[10, 20], [0.5, 1.5], ["a", "z"]]));
+    Ok(())
+}
+
+fn joined_segment_paths(value: &PathAwareValue, prefix: &str, paths: &mut Vec<String>) {
+    paths.push(prefix.to_string());
+    match value {
+        PathAwareValue::Map((_, map)) => {
+            for (key, each) in map.values.iter() {
+                joined_segment_paths(each, &format!("{}/{}", prefix, key), paths);
+            }
+        },
+        PathAwareValue::List((_, list)) => {
+            for (idx, each) in list.iter().enumerate() {
+                joined_segment_paths(each, &format!("{}/{}", prefix, idx), paths);
+            }
+        },
+        _ => {}
+    }
+}
+
+fn collect_paths(value: &PathAwareValue, paths: &mut Vec<String>) {
+    paths.push(value.self_path().0.clone());
+    match value {
+        PathAwareValue::Map((_, map)) => map.values.values().for_each(|each| collect_paths(each, paths)),
+        PathAwareValue::List((_, list)) => list.iter().for_each(|each| collect_paths(each, paths)),
+        _ => {}
+    }
+}
+
+#[test]
+fn path_extension_matches_joined_segments() -> Result<(), Error> {
+    let mut resources = serde_json::Map::new();
+    for idx in 0..500 {
+        resources.insert(format!("bucket{}", idx), serde_json::json!({
+            "Type": "AWS::S3::Bucket",
+            "Properties": {
+                "BucketEncryption": {
+                    "ServerSideEncryptionConfiguration": [
+                        { "ServerSideEncryptionByDefault": { "SSEAlgorithm": "aws:kms" } }
+                    ]
+                },
+                "Tags": [ { "Key": "env", "Value": "prod" }, { "Key": "team", "Value": idx } ]
+            }
+        }));
+    }
+    let value = PathAwareValue::try_from(serde_json::json!({ "Resources": resources }))?;
+
+    let mut expected = Vec::new();
+    joined_segment_paths(&value, "", &mut expected);
+    let mut actual = Vec::with_capacity(expected.len());
+    collect_paths(&value, &mut actual);
+    assert_eq!(actual.len(), 1 + 1 + 500 * 15);
+    assert_eq!(actual, expected);
+
+    let mut path = Path::root().extend_str("Resources").extend_usize(10).extend_string(&"Type".to_string());
+    assert_eq!(path.0, "/Resources/10/Type");
+    assert_eq!(path.drop_last().0, "/Resources/10");
+    assert_eq!(path.drop_last().drop_last().0, "");
+    assert_eq!(path.drop_last().0, "");
+    Ok(())
+}
+
+#[test]
+fn compare_in_test() -> Result<(), Error> {
+    let images = PathAwareValue::try_from(r#"["ami-1", "ami-2"]"#)?;
+    assert_eq!(compare_in(&PathAwareValue::try_from(r#""ami-2""#)?, &images, &DEFAULT_EVALUATION_OPTIONS)?, true);
+    assert_eq!(compare_in(&PathAwareValue::try_from(r#""ami-3""#)?, &images, &DEFAULT_EVALUATION_OPTIONS)?, false);
+
+    let prefixes = PathAwareValue::try_from(r#"[/^prod-/, /^stage-/]"#)?;
+    assert_eq!(compare_in(&PathAwareValue::try_from(r#""stage-web""#)?, &prefixes, &DEFAULT_EVALUATION_OPTIONS)?, true);
+    assert_eq!(compare_in(&PathAwareValue::try_from(r#""dev-web""#)?, &prefixes, &DEFAULT_EVALUATION_OPTIONS)?, false);
+
+    let not_list = PathAwareValue::try_from(r#""ami-1""#)?;
+    match compare_in(&not_list, &not_list, &DEFAULT_EVALUATION_OPTIONS) {
+        Err(Error(ErrorKind::NotComparable(_))) => {},
+        rest => unreachable!("{:?}", rest),
+    }
+    Ok(())
+}
+
+#[test]
+fn type_coercion_test() -> Result<(), Error> {
+    let enabled = PathAwareValue::try_from(r#""true""#)?;
+    let port = PathAwareValue::try_from(r#""443""#)?;
+    let ratio = PathAwareValue::try_from(r#""0.5""#)?;
+    let yes = PathAwareValue::try_from(r#""yes""#)?;
+    let bool_true = PathAwareValue::try_from("true")?;
+    let int_443 = PathAwareValue::try_from("443")?;
+    let float_half = PathAwareValue::try_from("0.5")?;
+
+    //
+    // strict by default
+    //
+    assert_eq!(compare_eq(&enabled, &bool_true, &DEFAULT_EVALUATION_OPTIONS).is_err(), true);
+    assert_eq!(compare_ge(&port, &int_443, &DEFAULT_EVALUATION_OPTIONS).is_err(), true);
+    assert_eq!(enabled == bool_true, false);
+
+    let options = EvaluationOptions { type_coercion: true, ..EvaluationOptions::default() };
+    assert_eq!(compare_eq(&enabled, &bool_true, &options)?, true);
+    assert_eq!(compare_eq(&bool_true, &enabled, &options)?, true);
+    assert_eq!(compare_eq(&port, &int_443, &options)?, true);
+    assert_eq!(compare_ge(&port, &int_443, &options)?, true);
+    assert_eq!(compare_lt(&port, &int_443, &options)?, false);
+    assert_eq!(compare_eq(&ratio, &float_half, &options)?, true);
+    assert_eq!(compare_eq(&port, &PathAwareValue::try_from(r#""443""#)?, &options)?, true);
+    assert_eq!(compare_eq(&yes, &bool_true, &options).is_err(), true);
+    assert_eq!(options.coerced_matches(), 5);
+
+    assert_eq!(compare_eq(&enabled, &bool_true, &DEFAULT_EVALUATION_OPTIONS).is_err(), true);
+    Ok(())
+}
+
+#[test]
+fn numeric_string_coercion_test() -> Result<(), Error> {
+    let port = PathAwareValue::try_from(r#""443""#)?;
+    let name = PathAwareValue::try_from(r#""abc""#)?;
+    let enabled = PathAwareValue::try_from(r#""true""#)?;
+    let int_100 = PathAwareValue::try_from("100")?;
+    let int_443 = PathAwareValue::try_from("443")?;
+    let bool_true = PathAwareValue::try_from("true")?;
+
+    assert_eq!(compare_gt(&port, &int_100, &DEFAULT_EVALUATION_OPTIONS).is_err(), true);
+
+    let options = EvaluationOptions { numeric_string_coercion: true, ..EvaluationOptions::default() };
+    assert_eq!(compare_gt(&port, &int_100, &options)?, true);
+    assert_eq!(compare_lt(&int_100, &port, &options)?, true);
+    assert_eq!(compare_le(&port, &int_100, &options)?, false);
+    assert_eq!(compare_ge(&port, &int_443, &options)?, true);
+    assert_eq!(compare_gt(&name, &int_100, &options).is_err(), true);
+    //
+    // only ordering comparisons against numbers are coerced
+    //
+    assert_eq!(compare_eq(&port, &int_443, &options).is_err(), true);
+    assert_eq!(compare_eq(&enabled, &bool_true, &options).is_err(), true);
+    assert_eq!(options.coerced_matches(), 3);
+
+    assert_eq!(compare_gt(&port, &int_100, &DEFAULT_EVALUATION_OPTIONS).is_err(), true);
+    Ok(())
+}
+
+#[test]
+fn flatten_test() -> Result<(), Error> {
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "bucket": {
+                "Type": "AWS::S3::Bucket",
+                "Properties": { "Tags": [ { "Key": "Owner", "Value": "team" } ], "Empty": {} }
+            }
+        },
+        "Count": 2,
+        "Enabled": null
+    }))?;
+    let leaves = value.flatten().into_iter()
+        .map(|(path, leaf)| (path.0.as_str(), leaf.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(leaves, vec![
+        ("/Resources/bucket/Type", PathAwareValue::try_from(r#""AWS::S3::Bucket""#)?),
+        ("/Resources/bucket/Properties/Tags/0/Key", PathAwareValue::try_from(r#""Owner""#)?),
+        ("/Resources/bucket/Properties/Tags/0/Value", PathAwareValue::try_from(r#""team""#)?),
+        ("/Count", PathAwareValue::try_from("2")?),
+        ("/Enabled", PathAwareValue::try_from("null")?),
+    ]);
+
+    let scalar = PathAwareValue::try_from("10")?;
+    assert_eq!(scalar.flatten().len(), 1);
+    assert_eq!(PathAwareValue::try_from("[]")?.flatten().is_empty(), true);
+    Ok(())
+}
+
+#[test]
+fn yaml_value_conversion_test() -> Result<(), Error> {
+    let yaml = serde_yaml::from_str::<serde_yaml::Value>(r#"
+    Count: !!int "42"
+    Ratio: !!float '1.5'
+    Enabled: !!bool 'true'
+    Version: !!str 2012
+    Mask: 0x1F
+    Bucket: !Ref BucketName
+    Ports:
+      80: http
+      true: enabled
+    "#)?;
+    let value = PathAwareValue::try_from(yaml)?;
+    let leaves = value.flatten().into_iter()
+        .map(|(path, leaf)| (path.0.as_str(), leaf.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(leaves, vec![
+        ("/Count", PathAwareValue::Int((Path::try_from("/Count")?, 42))),
+        ("/Ratio", PathAwareValue::Float((Path::try_from("/Ratio")?, 1.5))),
+        ("/Enabled", PathAwareValue::Bool((Path::try_from("/Enabled")?, true))),
+        ("/Version", PathAwareValue::String((Path::try_from("/Version")?, "2012".to_string()))),
+        ("/Mask", PathAwareValue::Int((Path::try_from("/Mask")?, 31))),
+        ("/Bucket", PathAwareValue::String((Path::try_from("/Bucket")?, "BucketName".to_string()))),
+        ("/Ports/80", PathAwareValue::String((Path::try_from("/Ports/80")?, "http".to_string()))),
+        ("/Ports/true", PathAwareValue::String((Path::try_from("/Ports/true")?, "enabled".to_string()))),
+    ]);
+
+    let yaml = serde_yaml::from_str::<serde_yaml::Value>("? [a, b]\n: value")?;
+    assert!(PathAwareValue::try_from(yaml).is_err());
+    Ok(())
+}
+
+#[test]
+fn path_parent_components_depth_test() -> Result<(), Error> {
+    let root = Path::root();
+    assert_eq!(root.parent(), None);
+    assert!(root.components().is_empty());
+    assert_eq!(root.depth(), 0);
+
+    let path = Path::new("/Resources/bucket/Properties/Tags/0".to_string(), 10, 4);
+    assert_eq!(path.components(), vec!["Resources", "bucket", "Properties", "Tags", "0"]);
+    assert_eq!(path.depth(), 5);
+    assert_eq!(Path::try_from("Resources")?.depth(), 1);
+
+    let parent = path.parent().unwrap();
+    assert_eq!(parent.0, "/Resources/bucket/Properties/Tags");
+    assert_eq!(parent.1, Location::default());
+    assert_eq!(path.0, "/Resources/bucket/Properties/Tags/0");
+
+    let top = Path::try_from("/Resources")?;
+    assert_eq!(top.parent(), Some(Path::root()));
+    assert_eq!(Path::try_from("Resources")?.parent(), Some(Path::root()));
+    Ok(())
+}
+
+#[test]
+fn value_only_display_test() -> Result<(), Error> {
+    use crate::rules::display::ValueOnlyDisplay;
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Name": "bucket",
+        "Versioned": true,
+        "Count": 2,
+        "Ratio": 0.5,
+        "Tags": [ { "Key": "Owner" }, null ]
+    }))?;
+    assert_eq!(
+        format!("{}", ValueOnlyDisplay(&value)),
+        r#"{"Name":"bucket","Versioned":true,"Count":2,"Ratio":0.5,"Tags":[{"Key":"Owner"},null]}"#
+    );
+    assert_eq!(format!("{}", ValueOnlyDisplay(&PathAwareValue::try_from("[]")?)), "[]");
+    assert_eq!(format!("{}", ValueOnlyDisplay(&PathAwareValue::try_from("{}")?)), "{}");
+    let range = PathAwareValue::RangeInt((Path::root(), RangeType {
+        upper: 20, lower: 10, inclusive: LOWER_INCLUSIVE
+    }));
+    assert_eq!(format!("{}", ValueOnlyDisplay(&range)), "[10,20)");
+    Ok(())
+}
+
+#[test]
+fn test_map_get_case_insensitive() -> Result<(), Error> {
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "bucketname": "lower",
+        "BucketName": "exact",
+        "Tags": []
+    }))?;
+    let map = match &value {
+        PathAwareValue::Map((_, map)) => map,
+        rest => unreachable!("{:?}", rest),
+    };
+    let (key, found) = map.get_case_insensitive("BucketName").unwrap();
+    assert_eq!((key.as_str(), found.as_string()), ("BucketName", Some("exact")));
+    let (key, _) = map.get_case_insensitive("TAGS").unwrap();
+    assert_eq!(key, "Tags");
+    assert!(map.get_case_insensitive("Tag").is_none());
+
+    let options = EvaluationOptions { case_insensitive_keys: true, ..EvaluationOptions::default() };
+    assert!(map.get_key("tags", &DEFAULT_EVALUATION_OPTIONS).is_none());
+    assert_eq!(map.get_key("tags", &options).is_some(), true);
+    Ok(())
+}
+
+#[test]
+fn test_capture_value() -> Result<(), Error> {
+    let arn = PathAwareValue::String((Path::root().extend_str("TopicArn"), "arn:aws:sns:eu-west-1:123456789012:alerts".to_string()));
+    let captured = capture_value(&arn, "arn:aws:[^:]+:([^:]+):")?.unwrap();
+    assert_eq!(captured.as_string(), Some("eu-west-1"));
+    assert_eq!(captured.self_path().0, "/TopicArn");
+
+    assert!(capture_value(&arn, "arn:aws-cn:[^:]+:([^:]+):")?.is_none());
+    assert!(capture_value(&PathAwareValue::Int((Path::root(), 10)), "(\\d+)")?.is_none());
+    match capture_value(&arn, "arn:aws") {
+        Err(Error(ErrorKind::IncompatibleError(msg))) =>
+            assert_eq!(msg, "Regular expression /arn:aws/ has no capture group to assign"),
+        rest => unreachable!("{:?}", rest),
+    }
+    assert!(capture_value(&arn, "(unclosed").is_err());
+    Ok(())
+}
+
+#[test]
+fn at_path_test() -> Result<(), Error> {
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Resources": {
+            "vpc": {
+                "Type": "AWS::EC2::VPC",
+                "Properties": { "Tags": [{ "Key": "env", "Value": "prod" }] }
+            }
+        }
+    }))?;
+    let tag = value.at_path("/Resources/vpc/Properties/Tags/0/Key")?;
+    assert_eq!(tag.as_string(), Some("env"));
+    assert_eq!(tag.self_path().0, "/Resources/vpc/Properties/Tags/0/Key");
+    let resource = value.at_path("Resources/vpc")?;
+    assert_eq!(resource.at_path("Type")?.as_string(), Some("AWS::EC2::VPC"));
+    assert_eq!(value.at_path("/")?, &value);
+
+    match value.at_path("/Resources/subnet/Type") {
+        Err(Error(ErrorKind::RetrievalError(msg))) => assert!(msg.contains("key subnet"), "{}", msg),
+        rest => unreachable!("{:?}", rest),
+    }
+    match value.at_path("/Resources/vpc/Properties/Tags/1") {
+        Err(Error(ErrorKind::RetrievalError(msg))) => assert!(msg.contains("index 1 out of bounds"), "{}", msg),
+        rest => unreachable!("{:?}", rest),
+    }
+    match value.at_path("/Resources/vpc/Properties/Tags/Key") {
+        Err(Error(ErrorKind::IncompatibleRetrievalError(msg))) => assert!(msg.contains("found Key"), "{}", msg),
+        rest => unreachable!("{:?}", rest),
+    }
+    match value.at_path("/Resources/vpc/Type/Name") {
+        Err(Error(ErrorKind::IncompatibleRetrievalError(msg))) => assert!(msg.contains("look up Name, found String"), "{}", msg),
+        rest => unreachable!("{:?}", rest),
+    }
+    Ok(())
+}
+
+#[test]
+fn earlier_duplicates_test() -> Result<(), Error> {
+    let value = PathAwareValue::try_from(serde_json::json!([
+        "10.0.0.0/16", 80, "10.1.0.0/16", { "Port": 80 }, "10.0.0.0/16", { "Port": 80 }, 80.5
+    ]))?;
+    let values = match &value {
+        PathAwareValue::List((_, list)) => list.iter().collect::<Vec<&PathAwareValue>>(),
+        _ => unreachable!(),
+    };
+    let duplicates = earlier_duplicates(&values, &DEFAULT_EVALUATION_OPTIONS).into_iter()
+        .map(|each| each.map(|first| first.self_path().0.as_str()))
+        .collect::<Vec<Option<&str>>>();
+    assert_eq!(duplicates, vec![None, None, None, None, Some("/0"), Some("/3"), None]);
+
+    //
+    // a string that matches a regex does not repeat it, unlike with compare_eq
+    //
+    let regex = PathAwareValue::Regex((Path::root(), "^10\\.".to_string()));
+    let regex_values = vec![&regex, values[0], &regex];
+    assert!(compare_eq(&regex, values[0], &DEFAULT_EVALUATION_OPTIONS)?);
+    assert_eq!(earlier_duplicates(&regex_values, &DEFAULT_EVALUATION_OPTIONS).iter().map(Option::is_some).collect::<Vec<bool>>(),
+               vec![false, false, true]);
+    Ok(())
+}
+
+#[test]
+fn set_collections_and_difference_test() -> Result<(), Error> {
+    let value = PathAwareValue::try_from(serde_json::json!([
+        ["us-east-1a", "us-east-1c"], "tcp", "icmp", []
+    ]))?;
+    let values = match &value {
+        PathAwareValue::List((_, list)) => list.iter().collect::<Vec<&PathAwareValue>>(),
+        _ => unreachable!(),
+    };
+    //
+    // each list is a collection of its own, the scalars are one collection reported at the first
+    //
+    let collections = set_collections(&values);
+    assert_eq!(collections.iter().map(|(at, elements)| (at.self_path().0.as_str(), elements.len()))
+                   .collect::<Vec<(&str, usize)>>(),
+               vec![("/0", 2), ("/3", 0), ("/1", 2)]);
+
+    let allowed = PathAwareValue::try_from(serde_json::json!(["tcp", "udp", "us-east-1a"]))?;
+    let allowed = match &allowed {
+        PathAwareValue::List((_, list)) => list.iter().collect::<Vec<&PathAwareValue>>(),
+        _ => unreachable!(),
+    };
+    let paths = |diff: Vec<&PathAwareValue>| diff.iter().map(|each| each.self_path().0.clone()).collect::<Vec<String>>();
+    assert_eq!(paths(set_difference(&collections[0].1, &allowed, false, &DEFAULT_EVALUATION_OPTIONS)), vec!["/0/1"]);
+    assert_eq!(paths(set_difference(&collections[0].1, &allowed, true, &DEFAULT_EVALUATION_OPTIONS)), vec!["/0", "/1"]);
+    assert!(set_difference(&collections[1].1, &allowed, false, &DEFAULT_EVALUATION_OPTIONS).is_empty());
+    assert_eq!(paths(set_difference(&collections[2].1, &allowed, false, &DEFAULT_EVALUATION_OPTIONS)), vec!["/2"]);
+
+    //
+    // values that are not comparable are not equal, a regex matches strings
+    //
+    let regex = PathAwareValue::Regex((Path::root(), "^us-east-1".to_string()));
+    assert!(set_difference(&collections[0].1, &[&regex], false, &DEFAULT_EVALUATION_OPTIONS).is_empty());
+    assert_eq!(set_difference(&[values[1]], &[collections[0].0], false, &DEFAULT_EVALUATION_OPTIONS).len(), 1);
+    Ok(())
+}
+
+#[test]
+fn compare_in_cidr_test() -> Result<(), Error> {
+    let value = |s: &str| PathAwareValue::String((Path::root(), s.to_string()));
+    assert!(compare_in_cidr(&value("10.1.2.3"), &value("10.0.0.0/8"))?);
+    assert!(compare_in_cidr(&value("10.1.2.0/24"), &value("10.0.0.0/8"))?);
+    assert!(compare_in_cidr(&value("10.0.0.0/8"), &value("10.0.0.0/8"))?);
+    assert!(!compare_in_cidr(&value("10.0.0.0/7"), &value("10.0.0.0/8"))?);
+    assert!(!compare_in_cidr(&value("11.0.0.1"), &value("10.0.0.0/8"))?);
+    assert!(compare_in_cidr(&value("192.168.1.1"), &value("0.0.0.0/0"))?);
+
+    assert!(compare_in_cidr(&value("2001:db8:1::/48"), &value("2001:db8::/32"))?);
+    assert!(!compare_in_cidr(&value("2001:db9::1"), &value("2001:db8::/32"))?);
+    assert!(compare_in_cidr(&value("::1"), &value("::/0"))?);
+
+    //
+    // families never contain each other
+    //
+    assert!(!compare_in_cidr(&value("10.0.0.1"), &value("::/0"))?);
+
+    for invalid in ["bogus", "10.0.0.0/33", "10.0.0.0/", "10.0.0/8", "2001:db8::/129"] {
+        match compare_in_cidr(&value(invalid), &value("10.0.0.0/8")) {
+            Err(Error(ErrorKind::NotComparable(msg))) => assert!(msg.contains(invalid), "{}", msg),
+            rest => unreachable!("{} {:?}", invalid, rest),
+        }
+    }
+    assert!(matches!(compare_in_cidr(&PathAwareValue::Int((Path::root(), 10)), &value("10.0.0.0/8")),
+                     Err(Error(ErrorKind::NotComparable(_)))));
+
+    assert_eq!(cidr_prefix_value(&value("10.1.0.0/16"))?, &PathAwareValue::Int((Path::root(), 16)));
+    assert_eq!(cidr_prefix_value(&value("2001:db8::1"))?, &PathAwareValue::Int((Path::root(), 128)));
+    assert!(cidr_prefix_value(&value("bogus")).is_err());
+    Ok(())
+}
+
+#[test]
+fn compare_string_order_test() -> Result<(), Error> {
+    let value = |s: &str| PathAwareValue::String((Path::root(), s.to_string()));
+    let semver = EvaluationOptions { string_order: StringOrder::Semver, ..EvaluationOptions::default() };
+    assert!(!compare_gt(&value("1.10.0"), &value("1.9.0"), &DEFAULT_EVALUATION_OPTIONS)?);
+    assert!(compare_gt(&value("1.10.0"), &value("1.9.0"), &semver)?);
+    assert!(compare_lt(&value("1.0.0-rc.1"), &value("1.0.0"), &semver)?);
+    assert!(compare_ge(&value("2.0.0"), &value("2.0.0"), &semver)?);
+
+    //
+    // strings that are not both versions stay lexical
+    //
+    assert!(compare_lt(&value("1.10"), &value("1.9"), &semver)?);
+    assert!(compare_lt(&value("1.10.0"), &value("latest"), &semver)?);
+    assert!(compare_lt(&value("v1.10.0"), &value("v1.9.0"), &semver)?);
+    Ok(())
+}
+
+#[test]
+fn compare_list_set_eq_test() -> Result<(), Error> {
+    let list = |value: serde_json::Value| PathAwareValue::try_from(value);
+    let ordered = list(serde_json::json!(["a", "b", "b"]))?;
+    let same = list(serde_json::json!(["a", "b", "b"]))?;
+    let reordered = list(serde_json::json!(["b", "a", "b"]))?;
+    let other_counts = list(serde_json::json!(["a", "a", "b"]))?;
+    let shorter = list(serde_json::json!(["a", "b"]))?;
+
+    assert!(compare_eq(&ordered, &same, &DEFAULT_EVALUATION_OPTIONS)?);
+    assert!(compare_list_set_eq(&ordered, &same, &DEFAULT_EVALUATION_OPTIONS)?);
+
+    assert!(!compare_eq(&ordered, &reordered, &DEFAULT_EVALUATION_OPTIONS)?);
+    assert!(compare_list_set_eq(&ordered, &reordered, &DEFAULT_EVALUATION_OPTIONS)?);
+
+    //
+    // multisets, the number of times each value appears matters
+    //
+    assert!(!compare_list_set_eq(&ordered, &other_counts, &DEFAULT_EVALUATION_OPTIONS)?);
+    assert!(!compare_list_set_eq(&ordered, &shorter, &DEFAULT_EVALUATION_OPTIONS)?);
+    assert!(!compare_list_set_eq(&shorter, &ordered, &DEFAULT_EVALUATION_OPTIONS)?);
+
+    //
+    // nested lists stay positional, other values compare as with compare_eq
+    //
+    let nested = list(serde_json::json!([["a", "b"], 1]))?;
+    assert!(compare_list_set_eq(&nested, &list(serde_json::json!([1, ["a", "b"]]))?, &DEFAULT_EVALUATION_OPTIONS)?);
+    assert!(!compare_list_set_eq(&nested, &list(serde_json::json!([1, ["b", "a"]]))?, &DEFAULT_EVALUATION_OPTIONS)?);
+    let scalar = list(serde_json::json!("a"))?;
+    assert!(compare_list_set_eq(&scalar, &list(serde_json::json!("a"))?, &DEFAULT_EVALUATION_OPTIONS)?);
+    Ok(())
+}
+
+#[test]
+fn test_select_key_regex() -> Result<(), Error> {
+    let value = PathAwareValue::try_from(serde_json::json!({
+        "Condition": {
+            "aws:SourceVpc": "vpc-123",
+            "aws:sourceVpce": "vpce-456",
+            "aws:SourceIp": "10.0.0.1",
+            "Nested": { "aws:SourceVpc": "vpc-789" }
+        }
+    }))?;
+    let eval = DummyEval{};
+    let paths = |query: &str, all: bool| -> Result<Vec<String>, Error> {
+        let query = AccessQuery::try_from(query)?.query;
+        Ok(value.select(all, &query, &eval)?.iter().map(|each| each.self_path().0.clone()).collect())
+    };
+    assert_eq!(paths("Condition./aws:[sS]ource(Vpc|Vpce)$/", true)?, vec![
+        "/Condition/aws:SourceVpc",
+        "/Condition/aws:sourceVpce",
+    ]);
+    assert_eq!(paths("Condition.*./Vpc$/", false)?, vec!["/Condition/Nested/aws:SourceVpc"]);
+    assert!(paths("Condition./Vpc$/./x/", true).is_err());
+
+    //
+    // no key matches, an error for all and nothing selected for SOME
+    //
+    match paths("Condition./^nothing/", true) {
+        Err(Error(ErrorKind::RetrievalError(_))) => {},
+        rest => unreachable!("{:?}", rest),
+    }
+    assert!(paths("Condition./^nothing/", false)?.is_empty());
+
+    let map = match &value {
+        PathAwareValue::Map((_, map)) => match map.values.get("Condition") {
+            Some(PathAwareValue::Map((_, condition))) => condition,
+            rest => unreachable!("{:?}", rest),
+        },
+        rest => unreachable!("{:?}", rest),
+    };
+    let options = EvaluationOptions { case_insensitive_keys: true, ..EvaluationOptions::default() };
+    assert_eq!(map.values_matching("^aws:sourcevpc$", &DEFAULT_EVALUATION_OPTIONS)?.len(), 0);
+    assert_eq!(map.values_matching("^aws:sourcevpc$", &options)?.len(), 1);
+    assert!(map.values_matching("(broken", &DEFAULT_EVALUATION_OPTIONS).is_err());
+    Ok(())
+}
@@ -0,0 +1,381 @@
+use std::collections::HashSet;
+use std::fmt::Formatter;
+
+use crate::rules::exprs::{Block, Conjunctions, FileLocation, GuardClause, LetExpr, LetValue, QueryPart, Rule, RuleClause, RulesFile, WhenConditions, WhenGuardClause, ROOT_VARIABLE};
+use crate::rules::path_value::PathAwareValue;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ReferenceKind {
+    Variable,
+    Rule,
+}
+
+///
+/// A variable or rule referenced in a rules file that is never declared. The location is
+/// absent when the reference was made inside a let assignment, as those do not carry one
+///
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct UndefinedReference<'loc> {
+    pub(crate) kind: ReferenceKind,
+    pub(crate) name: String,
+    pub(crate) rule_name: Option<String>,
+    pub(crate) location: Option<FileLocation<'loc>>,
+}
+
+impl<'loc> std::fmt::Display for UndefinedReference<'loc> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ReferenceKind::Variable => write!(f, "Undefined variable %{}", self.name)?,
+            ReferenceKind::Rule => write!(f, "Undefined rule {}", self.name)?,
+        }
+        if let Some(rule_name) = &self.rule_name {
+            write!(f, " in rule {}", rule_name)?;
+        }
+        if let Some(location) = &self.location {
+            write!(f, " at {}", location)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// A regular expression literal in a rules file that does not compile. Like undefined references,
+/// the location is absent for literals inside let assignments
+///
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct InvalidRegex<'loc> {
+    pub(crate) regex: String,
+    pub(crate) reason: String,
+    pub(crate) rule_name: Option<String>,
+    pub(crate) location: Option<FileLocation<'loc>>,
+}
+
+impl<'loc> std::fmt::Display for InvalidRegex<'loc> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid regular expression /{}/", self.regex)?;
+        if let Some(rule_name) = &self.rule_name {
+            write!(f, " in rule {}", rule_name)?;
+        }
+        if let Some(location) = &self.location {
+            write!(f, " at {}", location)?;
+        }
+        write!(f, ", {}", self.reason.split_whitespace().collect::<Vec<_>>().join(" "))?;
+        Ok(())
+    }
+}
+
+//
+// Walks all clauses and assignments in a scope, recording declared variables (let assignments,
+// parameters and variable captures inside queries), every %variable and rule referenced and
+// every regular expression literal
+//
+struct Scope<'r, 'loc> {
+    declared: HashSet<&'r str>,
+    variables: Vec<(&'r str, Option<&'r FileLocation<'loc>>)>,
+    rules: Vec<(&'r str, bool, &'r FileLocation<'loc>)>,
+    regexes: Vec<(&'r str, Option<&'r FileLocation<'loc>>)>,
+}
+
+impl<'r, 'loc> Scope<'r, 'loc> {
+    fn new() -> Self {
+        Scope { declared: HashSet::new(), variables: Vec::new(), rules: Vec::new(), regexes: Vec::new() }
+    }
+
+    fn assignments(&mut self, assignments: &'r [LetExpr<'loc>]) {
+        for each in assignments {
+            self.declared.insert(each.var.as_str());
+            self.let_value(&each.value, None);
+        }
+    }
+
+    fn let_value(&mut self, value: &'r LetValue<'loc>, location: Option<&'r FileLocation<'loc>>) {
+        match value {
+            LetValue::Value(value) => self.value(value, location),
+            LetValue::AccessClause(query) => self.query(&query.query, location),
+            LetValue::FunctionCall(function) => {
+                for each in &function.parameters {
+                    self.let_value(each, Some(&function.location));
+                }
+            }
+        }
+    }
+
+    fn value(&mut self, value: &'r PathAwareValue, location: Option<&'r FileLocation<'loc>>) {
+        match value {
+            PathAwareValue::Regex((_, regex)) => self.regexes.push((regex.as_str(), location)),
+            PathAwareValue::List((_, list)) => {
+                for each in list {
+                    self.value(each, location);
+                }
+            },
+            PathAwareValue::Map((_, map)) => {
+                for each in map.values.values() {
+                    self.value(each, location);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn query(&mut self, query: &'r [QueryPart<'loc>], location: Option<&'r FileLocation<'loc>>) {
+        for part in query {
+            match part {
+                QueryPart::Key(_) => {
+                    match part.variable() {
+                        Some(ROOT_VARIABLE) | None => {},
+                        Some(var) => self.variables.push((var, location)),
+                    }
+                },
+                QueryPart::AllValues(Some(name)) |
+                QueryPart::AllIndices(Some(name)) => {
+                    self.declared.insert(name.as_str());
+                },
+                QueryPart::Filter(name, conjunctions) |
+                QueryPart::FilterNot(name, conjunctions) => {
+                    if let Some(name) = name {
+                        self.declared.insert(name.as_str());
+                    }
+                    self.guard_clauses(conjunctions);
+                },
+                QueryPart::MapKeyFilter(name, filter) => {
+                    if let Some(name) = name {
+                        self.declared.insert(name.as_str());
+                    }
+                    self.let_value(&filter.compare_with, location);
+                },
+                QueryPart::Capture(regex) | QueryPart::KeyRegex(regex) => {
+                    self.regexes.push((regex.as_str(), location));
+                },
+                _ => {}
+            }
+        }
+    }
+
+    fn block(&mut self, block: &'r Block<'loc, GuardClause<'loc>>) {
+        self.assignments(&block.assignments);
+        self.guard_clauses(&block.conjunctions);
+    }
+
+    fn guard_clauses(&mut self, conjunctions: &'r Conjunctions<GuardClause<'loc>>) {
+        for each in conjunctions.iter().flatten() {
+            self.guard_clause(each);
+        }
+    }
+
+    fn guard_clause(&mut self, clause: &'r GuardClause<'loc>) {
+        match clause {
+            GuardClause::Clause(access) => {
+                let location = &access.access_clause.location;
+                self.query(&access.access_clause.query.query, Some(location));
+                if let Some(with) = &access.access_clause.compare_with {
+                    self.let_value(with, Some(location));
+                }
+            },
+            GuardClause::NamedRule(named) => {
+                self.rules.push((named.dependent_rule.as_str(), false, &named.location));
+            },
+            GuardClause::ParameterizedNamedRule(call) => {
+                let location = &call.named_rule.location;
+                self.rules.push((call.named_rule.dependent_rule.as_str(), true, location));
+                for each in &call.parameters {
+                    self.let_value(each, Some(location));
+                }
+            },
+            GuardClause::BlockClause(block) => {
+                self.query(&block.query.query, Some(&block.location));
+                self.block(&block.block);
+            },
+            GuardClause::WhenBlock(conditions, block) => {
+                self.when_conditions(conditions);
+                self.block(block);
+            }
+        }
+    }
+
+    fn when_conditions(&mut self, conditions: &'r WhenConditions<'loc>) {
+        for each in conditions.iter().flatten() {
+            match each {
+                WhenGuardClause::Clause(access) => {
+                    let location = &access.access_clause.location;
+                    self.query(&access.access_clause.query.query, Some(location));
+                    if let Some(with) = &access.access_clause.compare_with {
+                        self.let_value(with, Some(location));
+                    }
+                },
+                WhenGuardClause::NamedRule(named) => {
+                    self.rules.push((named.dependent_rule.as_str(), false, &named.location));
+                },
+                WhenGuardClause::ParameterizedNamedRule(call) => {
+                    let location = &call.named_rule.location;
+                    self.rules.push((call.named_rule.dependent_rule.as_str(), true, location));
+                    for each in &call.parameters {
+                        self.let_value(each, Some(location));
+                    }
+                }
+            }
+        }
+    }
+
+    fn rule(&mut self, rule: &'r Rule<'loc>) {
+        if let Some(conditions) = &rule.conditions {
+            self.when_conditions(conditions);
+        }
+        self.assignments(&rule.block.assignments);
+        for each in rule.block.conjunctions.iter().flatten() {
+            match each {
+                RuleClause::Clause(clause) => self.guard_clause(clause),
+                RuleClause::WhenBlock(conditions, block) => {
+                    self.when_conditions(conditions);
+                    self.block(block);
+                },
+                RuleClause::TypeBlock(type_block) => {
+                    if let Some(conditions) = &type_block.conditions {
+                        self.when_conditions(conditions);
+                    }
+                    self.query(&type_block.query, None);
+                    self.block(&type_block.block);
+                }
+            }
+        }
+    }
+}
+
+///
+/// Finds all %variable references that do not resolve to a variable declared at file scope,
+/// inside the enclosing rule or in known_variables (e.g. names provided with --define), and
+/// all rule clauses that do not refer to a rule defined in the file
+///
+pub(crate) fn undefined_references<'r, 'loc>(
+    rules_file: &'r RulesFile<'loc>,
+    known_variables: &[&'r str]) -> Vec<UndefinedReference<'loc>>
+{
+    let mut file_scope = Scope::new();
+    file_scope.assignments(&rules_file.assignments);
+    file_scope.declared.extend(known_variables.iter());
+
+    let mut undefined = Vec::new();
+    for (name, location) in &file_scope.variables {
+        if !file_scope.declared.contains(name) {
+            undefined.push(UndefinedReference {
+                kind: ReferenceKind::Variable,
+                name: name.to_string(),
+                rule_name: None,
+                location: location.cloned(),
+            });
+        }
+    }
+
+    let rule_names = rules_file.guard_rules.iter()
+        .map(|r| r.rule_name.as_str()).collect::<HashSet<_>>();
+    let parameterized_names = rules_file.parameterized_rules.iter()
+        .map(|r| r.rule.rule_name.as_str()).collect::<HashSet<_>>();
+    let undefined_rules = |scope: &Scope<'r, 'loc>, rule_name: Option<&String>| {
+        scope.rules.iter().filter(|(name, is_parameterized, _)| {
+            let defined = if *is_parameterized { &parameterized_names } else { &rule_names };
+            !defined.contains(name)
+        }).map(|(name, _, location)| UndefinedReference {
+            kind: ReferenceKind::Rule,
+            name: name.to_string(),
+            rule_name: rule_name.cloned(),
+            location: Some((*location).clone()),
+        }).collect::<Vec<_>>()
+    };
+    undefined.extend(undefined_rules(&file_scope, None));
+
+    let parameterized = rules_file.parameterized_rules.iter()
+        .map(|p| (&p.rule, Some(&p.parameter_names)));
+    let rules = rules_file.guard_rules.iter().map(|r| (r, None)).chain(parameterized);
+    for (rule, parameters) in rules {
+        let mut scope = Scope::new();
+        if let Some(parameters) = parameters {
+            scope.declared.extend(parameters.iter().map(String::as_str));
+        }
+        scope.rule(rule);
+        for (name, location) in &scope.variables {
+            if !scope.declared.contains(name) && !file_scope.declared.contains(name) {
+                undefined.push(UndefinedReference {
+                    kind: ReferenceKind::Variable,
+                    name: name.to_string(),
+                    rule_name: Some(rule.rule_name.clone()),
+                    location: location.cloned(),
+                });
+            }
+        }
+        undefined.extend(undefined_rules(&scope, Some(&rule.rule_name)));
+    }
+    undefined
+}
+
+///
+/// Finds all regular expression literals in the rules file, in clauses, let assignments, filters
+/// and MATCH captures, that do not compile
+///
+pub(crate) fn invalid_regexes<'loc>(rules_file: &RulesFile<'loc>) -> Vec<InvalidRegex<'loc>> {
+    let mut file_scope = Scope::new();
+    file_scope.assignments(&rules_file.assignments);
+    let mut scopes = vec![(None, file_scope)];
+
+    let parameterized = rules_file.parameterized_rules.iter().map(|p| &p.rule);
+    for rule in rules_file.guard_rules.iter().chain(parameterized) {
+        let mut scope = Scope::new();
+        scope.rule(rule);
+        scopes.push((Some(&rule.rule_name), scope));
+    }
+
+    let mut invalid = Vec::new();
+    for (rule_name, scope) in scopes {
+        for (regex, location) in scope.regexes {
+            if let Err(e) = regex::Regex::new(regex) {
+                invalid.push(InvalidRegex {
+                    regex: regex.to_string(),
+                    reason: e.to_string(),
+                    rule_name: rule_name.cloned(),
+                    location: location.cloned(),
+                });
+            }
+        }
+    }
+    invalid
+}
+
+///
+/// Keeps the rules for which selected returns true along with every rule they depend on through
+/// rule clauses, directly or through other rules and parameterized rules they call.
+/// Parameterized rules are kept as they only run when called
+///
+pub(crate) fn select_rules<'loc, F>(rules_file: RulesFile<'loc>, selected: F) -> RulesFile<'loc>
+    where F: Fn(&Rule<'loc>) -> bool
+{
+    let mut keep: HashSet<(String, bool)> = rules_file.guard_rules.iter()
+        .filter(|rule| selected(rule))
+        .map(|rule| (rule.rule_name.clone(), false))
+        .collect();
+    let mut pending = keep.iter().cloned().collect::<Vec<_>>();
+    while let Some((name, is_parameterized)) = pending.pop() {
+        let parameterized = rules_file.parameterized_rules.iter().map(|each| &each.rule);
+        let rules = rules_file.guard_rules.iter().filter(|_| !is_parameterized)
+            .chain(parameterized.filter(|_| is_parameterized))
+            .filter(|rule| rule.rule_name == name);
+        for rule in rules {
+            let mut scope = Scope::new();
+            scope.rule(rule);
+            for (dependency, is_parameterized, _) in scope.rules {
+                if keep.insert((dependency.to_string(), is_parameterized)) {
+                    pending.push((dependency.to_string(), is_parameterized));
+                }
+            }
+        }
+    }
+    let RulesFile { assignments, guard_rules, parameterized_rules } = rules_file;
+    RulesFile {
+        assignments,
+        guard_rules: guard_rules.into_iter()
+            .filter(|rule| keep.contains(&(rule.rule_name.clone(), false)))
+            .collect(),
+        parameterized_rules,
+    }
+}
+
+#[cfg(test)]
+#[path = "references_tests.rs"]
+mod references_tests;
@@ -0,0 +1,163 @@
+use super::*;
+use std::convert::TryFrom;
+
+fn undefined_names(rules: &RulesFile<'_>, known: &[&str]) -> Vec<(ReferenceKind, String, Option<String>)> {
+    undefined_references(rules, known).into_iter()
+        .map(|each| (each.kind, each.name, each.rule_name))
+        .collect()
+}
+
+#[test]
+fn test_undefined_variables_and_rules() -> crate::rules::Result<()> {
+    let rules = RulesFile::try_from(r###"
+let ec2_instance_types = ["t2.micro"]
+let from_other = %not_declared
+
+rule allowed_types {
+    let allowed = %ec2_instance_types
+    Resources.*.Properties.InstanceType IN %allowed
+    Resources.*.Properties.InstanceType IN %ec2_instance_typs
+}
+
+rule depends {
+    allowed_types
+    alowed_types
+}
+
+rule captures {
+    Resources[ name | Type == "AWS::S3::Bucket" ] {
+        %name EXISTS
+    }
+}
+"###)?;
+    assert_eq!(undefined_names(&rules, &[]), vec![
+        (ReferenceKind::Variable, "not_declared".to_string(), None),
+        (ReferenceKind::Variable, "ec2_instance_typs".to_string(), Some("allowed_types".to_string())),
+        (ReferenceKind::Rule, "alowed_types".to_string(), Some("depends".to_string())),
+    ]);
+
+    assert_eq!(undefined_names(&rules, &["not_declared", "ec2_instance_typs"]), vec![
+        (ReferenceKind::Rule, "alowed_types".to_string(), Some("depends".to_string())),
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_undefined_reference_display() -> crate::rules::Result<()> {
+    let content = r###"rule check { Name IN %names }
+rule uses {
+    check
+    other
+}
+"###;
+    let rules = crate::rules::parser::rules_file(crate::rules::parser::Span::new_extra(content, "check.guard"))?;
+    let messages = undefined_references(&rules, &[]).iter()
+        .map(|each| format!("{}", each)).collect::<Vec<_>>();
+    assert_eq!(messages, vec![
+        "Undefined variable %names in rule check at Location[file:check.guard, line:1, column:14]".to_string(),
+        "Undefined rule other in rule uses at Location[file:check.guard, line:4, column:5]".to_string(),
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_parameterized_rule_parameters_are_declared() -> crate::rules::Result<()> {
+    let rules = RulesFile::try_from(r###"
+rule allowed_ports(ports) {
+    %ports[*] IN [80, 443]
+}
+
+rule check {
+    allowed_ports(Resources.*.Properties.Port)
+    not_defined(%check_ports)
+}
+"###)?;
+    assert_eq!(undefined_names(&rules, &[]), vec![
+        (ReferenceKind::Variable, "check_ports".to_string(), Some("check".to_string())),
+        (ReferenceKind::Rule, "not_defined".to_string(), Some("check".to_string())),
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_root_anchor_is_always_declared() -> crate::rules::Result<()> {
+    let rules = RulesFile::try_from(r###"
+let env = $.Parameters.Env.Default
+
+rule tagged_with_env {
+    Resources.*[ Type == 'AWS::S3::Bucket' ] {
+        Properties.Tags[*].Value == $.Parameters.Env.Default
+    }
+}
+"###)?;
+    assert_eq!(undefined_names(&rules, &[]), vec![]);
+    Ok(())
+}
+
+#[test]
+fn test_select_rules_keeps_dependencies() -> crate::rules::Result<()> {
+    let rules = RulesFile::try_from(r###"
+rule buckets_exist { Resources.*[ Type == 'AWS::S3::Bucket' ] not empty }
+rule tags_present { Resources.*.Properties.Tags exists }
+rule check_tags(tags) {
+    buckets_exist
+    %tags not empty
+}
+
+rule buckets_encrypted @tags("s3") when buckets_exist {
+    Resources.*.Properties.BucketEncryption exists
+}
+rule buckets_tagged @tags("tagging") {
+    check_tags(Resources.*.Properties.Tags)
+}
+rule experimental @tags("s3", "experimental") {
+    tags_present
+}
+"###)?;
+    let names = |rules: &RulesFile<'_>| rules.guard_rules.iter()
+        .map(|rule| rule.rule_name.clone()).collect::<Vec<String>>();
+
+    let selected = select_rules(rules.clone(), |rule| rule.tags.contains(&"s3".to_string()) &&
+        !rule.tags.contains(&"experimental".to_string()));
+    assert_eq!(names(&selected), vec!["buckets_exist", "buckets_encrypted"]);
+    assert_eq!(selected.parameterized_rules.len(), 1);
+
+    //
+    // through the parameterized rule it calls
+    //
+    let selected = select_rules(rules.clone(), |rule| rule.tags.contains(&"tagging".to_string()));
+    assert_eq!(names(&selected), vec!["buckets_exist", "buckets_tagged"]);
+
+    let selected = select_rules(rules, |rule| rule.tags.contains(&"experimental".to_string()));
+    assert_eq!(names(&selected), vec!["tags_present", "experimental"]);
+    Ok(())
+}
+
+#[test]
+fn test_invalid_regexes() -> crate::rules::Result<()> {
+    let content = r###"let patterns = [/^ok$/, /[broken/]
+rule check {
+    Name == /(unclosed/
+    Tags[ KEYS == /+/ ] EMPTY
+    Condition./(bad/ EXISTS
+    Name == /^fine$/
+}
+rule capture {
+    let region := Arn MATCH /arn:(?P<bad/
+    %region EXISTS
+}
+"###;
+    let rules = crate::rules::parser::rules_file(crate::rules::parser::Span::new_extra(content, "check.guard"))?;
+    let invalid = invalid_regexes(&rules).into_iter()
+        .map(|each| (each.regex, each.rule_name, each.location.map(|l| (l.line, l.column))))
+        .collect::<Vec<_>>();
+    assert_eq!(invalid, vec![
+        ("[broken".to_string(), None, None),
+        ("(unclosed".to_string(), Some("check".to_string()), Some((3, 5))),
+        ("+".to_string(), Some("check".to_string()), Some((4, 5))),
+        ("(bad".to_string(), Some("check".to_string()), Some((5, 5))),
+        ("arn:(?P<bad".to_string(), Some("capture".to_string()), None),
+    ]);
+    Ok(())
+}
+
@@ -20,7 +20,9 @@ use serde::{Serialize, Deserialize};
 #[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize, Hash, Copy)]
 pub enum CmpOperator {
     Eq,
+    EqUnordered,
     In,
+    AllIn,
     Gt,
     Lt,
     Le,
@@ -33,6 +35,14 @@ pub enum CmpOperator {
     IsMap,
     IsBool,
     IsInt,
+    IsNumber,
+
+    Unique,
+
+    Subset,
+    Superset,
+
+    InCidr,
 }
 
 impl CmpOperator {
@@ -44,19 +54,27 @@ impl CmpOperator {
             CmpOperator::IsBool     |
             CmpOperator::IsList     |
             CmpOperator::IsInt      |
-            CmpOperator::IsMap          => true,
+            CmpOperator::IsNumber   |
+            CmpOperator::IsMap      |
+            CmpOperator::Unique         => true,
             _                           => false
         }
     }
 
     pub(crate) fn is_binary(&self) -> bool { !self.is_unary() }
+
+    pub(crate) fn is_type_check(&self) -> bool {
+        self.is_unary() && !matches!(self, CmpOperator::Exists | CmpOperator::Empty | CmpOperator::Unique)
+    }
 }
 
 impl Display for CmpOperator {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             CmpOperator::Eq => f.write_str("EQUALS")?,
+            CmpOperator::EqUnordered => f.write_str("EQUALS UNORDERED")?,
             CmpOperator::In => f.write_str("IN")?,
+            CmpOperator::AllIn => f.write_str("ALL IN")?,
             CmpOperator::Gt=> f.write_str("GREATER THAN")?,
             CmpOperator::Lt=> f.write_str("LESS THAN")?,
             CmpOperator::Ge => f.write_str("GREATER THAN EQUALS")?,
@@ -66,8 +84,13 @@ impl Display for CmpOperator {
             CmpOperator::IsString => f.write_str("IS STRING")?,
             CmpOperator::IsBool => f.write_str("IS BOOL")?,
             CmpOperator::IsInt => f.write_str("IS INT")?,
+            CmpOperator::IsNumber => f.write_str("IS NUMBER")?,
             CmpOperator::IsList => f.write_str("IS LIST")?,
             CmpOperator::IsMap => f.write_str("IS MAP")?,
+            CmpOperator::Unique => f.write_str("UNIQUE")?,
+            CmpOperator::Subset => f.write_str("SUBSET")?,
+            CmpOperator::Superset => f.write_str("SUPERSET")?,
+            CmpOperator::InCidr => f.write_str("IN CIDR")?,
         }
         Ok(())
     }
@@ -229,36 +252,42 @@ pub(crate) trait WithinRange<RHS: PartialOrd = Self> {
 
 impl WithinRange for i64 {
     fn is_within(&self, range: &RangeType<i64>) -> bool {
-        is_within(range, self)
+        range.contains(self)
     }
 }
 
 impl WithinRange for f64 {
     fn is_within(&self, range: &RangeType<f64>) -> bool {
-        is_within(range, self)
+        range.contains(self)
     }
 }
 
 impl WithinRange for char {
     fn is_within(&self, range: &RangeType<char>) -> bool {
-        is_within(range, self)
+        range.contains(self)
     }
 }
 
-//impl WithinRange for
-
-fn is_within<T: PartialOrd>(range: &RangeType<T>, other: &T) -> bool {
-    let lower = if (range.inclusive & LOWER_INCLUSIVE) > 0 {
-        range.lower.le(other)
-    } else {
-        range.lower.lt(other)
-    };
-    let upper = if (range.inclusive & UPPER_INCLUSIVE) > 0 {
-        range.upper.ge(other)
-    } else {
-        range.upper.gt(other)
-    };
-    lower && upper
+impl<T: PartialOrd> RangeType<T> {
+    pub(crate) fn contains(&self, other: &T) -> bool {
+        let lower = if (self.inclusive & LOWER_INCLUSIVE) > 0 {
+            self.lower.le(other)
+        } else {
+            self.lower.lt(other)
+        };
+        let upper = if (self.inclusive & UPPER_INCLUSIVE) > 0 {
+            self.upper.ge(other)
+        } else {
+            self.upper.gt(other)
+        };
+        lower && upper
+    }
+}
+
+impl RangeType<i64> {
+    pub(crate) fn as_float(&self) -> RangeType<f64> {
+        RangeType { upper: self.upper as f64, lower: self.lower as f64, inclusive: self.inclusive }
+    }
 }
 
 impl <'a> TryFrom<&'a serde_yaml::Value> for Value {
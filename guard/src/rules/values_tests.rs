@@ -7,7 +7,7 @@ use std::collections::HashMap;
 use crate::rules::exprs::{TypeBlock, Rule};
 
 use crate::rules::{ Result, EvaluationContext, EvaluationType, Evaluate, Status, Error, errors::ErrorKind };
-use crate::rules::path_value::{PathAwareValue, QueryResolver};
+use crate::rules::path_value::{EvaluationOptions, PathAwareValue, QueryResolver, DEFAULT_EVALUATION_OPTIONS};
 use crate::rules::path_value::traversal::{Traversal, TraversalResult};
 
 #[test]
@@ -136,6 +136,14 @@ fn test_query_on_value() -> Result<()> {
             unimplemented!()
         }
 
+        fn resource_count(&self, _resource_type: &str) -> usize {
+            unimplemented!()
+        }
+
+        fn options(&self) -> &EvaluationOptions {
+            &DEFAULT_EVALUATION_OPTIONS
+        }
+
         fn end_evaluation(&self, _eval_type: EvaluationType, _context: &str, _msg: String, _from: Option<PathAwareValue>, _to: Option<PathAwareValue>, _status: Option<Status>, _cmp: Option<(CmpOperator, bool)>) {
         }
 
@@ -225,6 +233,14 @@ fn test_type_block_with_var_query_evaluation() -> Result<()> {
             unimplemented!()
         }
 
+        fn resource_count(&self, _resource_type: &str) -> usize {
+            unimplemented!()
+        }
+
+        fn options(&self) -> &EvaluationOptions {
+            &DEFAULT_EVALUATION_OPTIONS
+        }
+
         fn end_evaluation(&self, _eval_type: EvaluationType, _context: &str, _msg: String, _from: Option<PathAwareValue>, _to: Option<PathAwareValue>, _status: Option<Status>, _cmp: Option<(CmpOperator, bool)>) {
         }
 
@@ -153,7 +153,7 @@ mod tests {
         }, ValidateInput {
             content: &rule,
             file_name: "functional_test.rule",
-        }, verbose).unwrap();
+        }, verbose, &[], false).unwrap();
         let result = serde_json::from_str::<serde_json::Value>(&serialized)
             .ok()
             .unwrap();
@@ -163,6 +163,93 @@ mod tests {
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn test_run_checks_structured() {
+        let data = r#"
+            {
+                "Resources": {
+                    "VPC" : {
+                        "Type" : "AWS::ApiGateway::Method",
+                        "Properties" : {
+                            "AuthorizationType" : "10.0.0.0/24"
+                        }
+                    }
+                }
+            }
+        "#;
+        let rule = "AWS::ApiGateway::Method { Properties.AuthorizationType == \"NONE\" <<Methods must not require authorization>> }";
+        use cfn_guard::*;
+        let failures = run_checks_structured(ValidateInput {
+            content: data,
+            file_name: "functional_test.json",
+        }, ValidateInput {
+            content: rule,
+            file_name: "functional_test.rule",
+        }, &[], false).unwrap();
+        assert_eq!(failures.len(), 1);
+        let failure = &failures[0];
+        assert_eq!(failure.rule, "default");
+        assert_eq!(failure.path, "/Resources/VPC/Properties/AuthorizationType");
+        assert_eq!(failure.from, Some(serde_json::Value::String("10.0.0.0/24".to_string())));
+        assert_eq!(failure.to, Some(serde_json::Value::String("NONE".to_string())));
+        assert_eq!(failure.message.as_deref(), Some("Methods must not require authorization"));
+        assert_eq!(failure.location, Some((6, 50)));
+    }
+
+    #[test]
+    fn test_run_checks_k8s() {
+        use cfn_guard::*;
+        let review = std::fs::read_to_string(
+            utils::get_full_path_for_resource_file("resources/k8s-admission-review.json")).unwrap();
+        let rules = std::fs::read_to_string(
+            utils::get_full_path_for_resource_file("resources/k8s_deployment_rule.guard")).unwrap();
+        let result = run_checks_k8s(&review, ValidateInput {
+            content: &rules,
+            file_name: "k8s_deployment_rule.guard",
+        }).unwrap();
+        assert_eq!(result.failures.len(), 2);
+        assert_eq!(result.response.uid, "705ab4f5-6393-11e8-b7cc-42010a800002");
+        assert!(!result.response.allowed);
+        let status = result.response.status.unwrap();
+        assert_eq!(status.code, 403);
+        assert!(status.message.contains("Container images must be pinned to a version"));
+        assert!(status.message.contains("Replicas must not be reduced"));
+
+        //
+        // CREATE has no oldObject, %previous is empty and the replicas rule is skipped
+        //
+        let create = review
+            .replace("\"nginx:latest\"", "\"nginx:1.25.3\"")
+            .replace("\"oldObject\"", "\"unused\"");
+        let result = run_checks_k8s(&create, ValidateInput {
+            content: &rules,
+            file_name: "k8s_deployment_rule.guard",
+        }).unwrap();
+        assert!(result.failures.is_empty(), "{:?}", result.failures);
+        assert!(result.response.allowed);
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = r#"
+        let buckets = Resources.*[ Type == 'AWS::S3::Bucket' ]
+        rule s3_encrypted when %buckets !empty {
+            %buckets.Properties.BucketEncryption exists
+        }
+        "#;
+        assert_eq!(cfn_guard::parse_rules(rules), Ok(()));
+
+        let malformed = r#"
+        rule s3_encrypted {
+            Properties.BucketEncryption ===
+        }
+        "#;
+        let errors = cfn_guard::parse_rules(malformed).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("at line"));
+        assert!(errors[0].contains("at column"));
+    }
+
     #[test]
     fn test_single_data_file_single_rules_file_compliant() {
         let data_arg = utils::get_full_path_for_resource_file(
@@ -225,6 +312,115 @@ mod tests {
         assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
     }
 
+    #[test]
+    fn test_report_options_without_report_by() {
+        let data_arg = utils::get_full_path_for_resource_file(
+            "resources/data-dir/s3-public-read-prohibited-template-non-compliant.yaml",
+        );
+        let rules_arg = utils::get_full_path_for_resource_file("resources/rules-dir/s3_bucket_logging_enabled.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        for option in ["--show-clause-failures", "--summary-only", "--explain"] {
+            let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, option, "-E"];
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args), "{}", option);
+        }
+    }
+
+    #[test]
+    fn test_data_dir_rules_dir_fail_fast() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/data-dir/");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/rules-dir/");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        let dir = std::env::temp_dir().join(format!("cfn-guard-fail-fast-{}", std::process::id()));
+        let output_file = dir.join("results.txt");
+        let output_arg = output_file.to_str().unwrap();
+        for engine in [None, Some("-E")] {
+            let mut args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, "--fail-fast",
+                                "--output-file", output_arg];
+            args.extend(engine);
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+
+            //
+            // only the failing clause of the first rules file is shown, the evaluation stops
+            // before the remaining rules files and data files
+            //
+            let content = std::fs::read_to_string(&output_file).unwrap();
+            assert!(content.contains("Rule [s3_bucket_logging_enabled.guard/S3_BUCKET_LOGGING_ENABLED] FAIL"), "{}", content);
+            assert!(!content.contains("s3_bucket_public_read_prohibited"), "{}", content);
+            assert!(!content.contains("s3_bucket_server_side_encryption_enabled"), "{}", content);
+            assert!(!content.contains("Status ="), "{}", content);
+            assert!(content.contains("across 1 data file;"), "{}", content);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fail_fast_stops_at_first_failing_rule() {
+        let dir = std::env::temp_dir().join(format!("cfn-guard-fail-fast-rules-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let data_file = dir.join("template.yaml");
+        std::fs::write(&data_file, "Resources:\n  bucket:\n    Type: AWS::S3::Bucket\n    Properties: {}\n").unwrap();
+        let rules_file = dir.join("rules.guard");
+        std::fs::write(&rules_file, r#"
+rule passes { Resources.bucket.Type == "AWS::S3::Bucket" }
+rule first_failure { Resources.bucket.Properties.BucketName exists }
+rule second_failure { Resources.bucket.Properties.VersioningConfiguration exists }
+"#).unwrap();
+        let output_file = dir.join("results.txt");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        for engine in [None, Some("-E")] {
+            let mut args = vec![VALIDATE, &data_option, data_file.to_str().unwrap(), &rules_option,
+                                rules_file.to_str().unwrap(), "--fail-fast", "--output-file", output_file.to_str().unwrap()];
+            args.extend(engine);
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+
+            //
+            // the rules after the first one that fails are not evaluated, the report of the rules
+            // evaluated until then is still written
+            //
+            let content = std::fs::read_to_string(&output_file).unwrap();
+            assert!(content.contains("rules.guard/first_failure] FAIL"), "{}", content);
+            assert!(!content.contains("second_failure"), "{}", content);
+            assert!(content.contains("Rules: 2 total, 1 passed, 1 failed, 0 skipped"), "{}", content);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_data_dir_rules_dir_max_errors() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/data-dir/");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/rules-dir/");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        let dir = std::env::temp_dir().join(format!("cfn-guard-max-errors-{}", std::process::id()));
+        let output_file = dir.join("results.txt");
+        let output_arg = output_file.to_str().unwrap();
+        for engine in [None, Some("-E")] {
+            let mut args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, "-S", "none",
+                                "--max-errors", "1", "--output-file", output_arg];
+            args.extend(engine);
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+
+            //
+            // the limit is for the run, the one failure shown is from the first data file and rules
+            // file evaluated and the rest are counted once at the end
+            //
+            let content = std::fs::read_to_string(&output_file).unwrap();
+            let omitted = content.lines().filter(|line| line.contains("more failures omitted")).collect::<Vec<_>>();
+            assert_eq!(omitted, vec!["... and 9 more failures omitted. Use --max-errors 0 for all."], "{}", content);
+
+            let mut args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, "-S", "none",
+                                "--max-errors", "1", "-o", "json", "--output-file", output_arg];
+            args.extend(engine);
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+            let content = std::fs::read_to_string(&output_file).unwrap();
+            assert!(content.replace(" ", "").contains(r#""truncated":true"#), "{}", content);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_multiple_data_files_single_rules_file() {
         let data_arg1 = utils::get_full_path_for_resource_file(
@@ -438,6 +634,549 @@ mod tests {
         assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), args));
     }
 
+    fn validate_region_with_parameters(parameter_files: &[&str]) -> i32 {
+        let data_arg = utils::get_full_path_for_resource_file("resources/region_data.yaml");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/region_rule.guard");
+        let parameter_args: Vec<String> = parameter_files
+            .iter()
+            .map(|each| utils::get_full_path_for_resource_file(each))
+            .collect();
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        let input_parameters_option = format!("-{}", INPUT_PARAMETERS.1);
+        let mut args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
+        for each in &parameter_args {
+            args.push(&input_parameters_option);
+            args.push(each);
+        }
+        utils::cfn_guard_test_command(Validate::new(), args)
+    }
+
+    #[test]
+    fn test_input_parameters_yaml_keys_as_variables() {
+        assert_eq!(0, validate_region_with_parameters(&["resources/region_params.yaml"]));
+    }
+
+    #[test]
+    fn test_input_parameters_json_keys_as_variables() {
+        assert_eq!(5, validate_region_with_parameters(&["resources/region_params_override.json"]));
+    }
+
+    #[test]
+    fn test_input_parameters_later_file_wins_on_conflict() {
+        assert_eq!(
+            5,
+            validate_region_with_parameters(&[
+                "resources/region_params.yaml",
+                "resources/region_params_override.json"
+            ])
+        );
+        assert_eq!(
+            0,
+            validate_region_with_parameters(&[
+                "resources/region_params_override.json",
+                "resources/region_params.yaml"
+            ])
+        );
+    }
+
+    #[test]
+    fn test_undefined_variables_checked_before_evaluation() {
+        // -1 status code equates to Error being thrown for the undefined %allowed_regions
+        assert_eq!(-1, validate_region_with_parameters(&[]));
+
+        let data_arg = utils::get_full_path_for_resource_file("resources/region_data.yaml");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/region_rule.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        let args = vec![
+            VALIDATE,
+            &data_option,
+            &data_arg,
+            &rules_option,
+            &rules_arg,
+            "--define",
+            r#"allowed_regions=["us-west-2"]"#,
+        ];
+        assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), args));
+    }
+
+    #[test]
+    fn test_rule_when_condition_skips_rule() {
+        let rules_arg = utils::get_full_path_for_resource_file("resources/serverless_lambda_rule.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+
+        let data_arg = utils::get_full_path_for_resource_file("resources/non-serverless-template.yaml");
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
+        assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), args));
+
+        let data_arg = utils::get_full_path_for_resource_file("resources/serverless-template.yaml");
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+    }
+
+    #[test]
+    fn test_resolve_conditions_evaluates_each_outcome() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/conditional-encryption-template.yaml");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/conditional_encryption_rule.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+
+        //
+        // Without resolution the rule sees the Fn::If structure and fails
+        //
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+
+        //
+        // IsProd depends on a parameter, IsProd=false selects NONE
+        //
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, "--resolve-conditions"];
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+
+        let args = vec![
+            VALIDATE,
+            &data_option,
+            &data_arg,
+            &rules_option,
+            &rules_arg,
+            "--resolve-conditions",
+            "--condition",
+            "IsProd=true",
+        ];
+        assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), args));
+    }
+
+    #[test]
+    fn test_tf_plan_input_type() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/tf-s3-buckets-plan.json");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/tf_s3_bucket_encryption_rule.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+
+        //
+        // The plan as is has no aws_s3_bucket key, the rule is skipped
+        //
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
+        assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), args));
+
+        //
+        // aws_s3_bucket.assets is not encrypted, aws_s3_bucket.legacy is being deleted and is skipped
+        //
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, "--input-type", "tf-plan"];
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+
+        let template_arg = utils::get_full_path_for_resource_file("resources/conditional-encryption-template.yaml");
+        let args = vec![VALIDATE, &data_option, &template_arg, &rules_option, &rules_arg, "--input-type", "tf-plan"];
+        assert_eq!(3, utils::cfn_guard_test_command(Validate::new(), args));
+    }
+
+    #[test]
+    fn test_cdk_assembly_input_type() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/cdk-assembly");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/conditional_encryption_rule.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+
+        //
+        // AppStack and Prod/ApiStack encrypt their buckets, the nested stack of AppStack does not
+        //
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, "--input-type", "cdk-assembly"];
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+
+        //
+        // no manifest.json in the directory
+        //
+        let data_dir_arg = utils::get_full_path_for_resource_file("resources/data-dir");
+        let args = vec![VALIDATE, &data_option, &data_dir_arg, &rules_option, &rules_arg, "--input-type", "cdk-assembly"];
+        assert_eq!(3, utils::cfn_guard_test_command(Validate::new(), args));
+    }
+
+    #[test]
+    fn test_merge_rules_across_files() {
+        let data_arg = utils::get_full_path_for_resource_file(
+            "resources/data-dir/s3-server-side-encryption-template-compliant.yaml");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/merge-rules-dir");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+
+        //
+        // a.guard depends on s3_bucket_encrypted from b.guard
+        //
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
+        assert_eq!(-1, utils::cfn_guard_test_command(Validate::new(), args));
+
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, "--merge-rules"];
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, "--merge-rules", "-E"];
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+    }
+
+    #[test]
+    fn test_k8s_admission_input_type() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/k8s-admission-review.json");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/k8s_deployment_rule.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, "--input-type", "k8s-admission"];
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+
+        //
+        // %previous is only known with --input-type k8s-admission
+        //
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
+        assert_eq!(-1, utils::cfn_guard_test_command(Validate::new(), args));
+    }
+
+    #[test]
+    fn test_output_file() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/data-dir/s3-public-read-prohibited-template-non-compliant.yaml");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/rules-dir/s3_bucket_logging_enabled.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        let dir = std::env::temp_dir().join(format!("cfn-guard-output-file-{}", std::process::id()));
+        let output_file = dir.join("reports").join("results.json");
+        let output_arg = output_file.to_str().unwrap();
+
+        for _ in 0..2 {
+            let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, "-o", "json", "-S", "none", "--output-file", output_arg];
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+
+            //
+            // the report for the data file followed by the summary of the run. The second run
+            // overwrites the first
+            //
+            let content = std::fs::read_to_string(&output_file).unwrap();
+            let reports = serde_json::Deserializer::from_str(&content)
+                .into_iter::<serde_json::Value>()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(reports.len(), 2);
+            assert_eq!(reports[0]["status"], "FAIL");
+            assert_eq!(reports[1]["summary"]["rules"]["failed"], 1);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_report_by_resource() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/data-dir/s3-public-read-prohibited-template-non-compliant.yaml");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/rules-dir/s3_bucket_logging_enabled.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        for engine in [None, Some("-E")] {
+            let mut args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, "--report-by", "resource"];
+            args.extend(engine);
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+
+            let mut by_type = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, "--group-by", "type"];
+            by_type.extend(engine);
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), by_type));
+        }
+    }
+
+    #[test]
+    fn test_suppressed_rules_do_not_fail() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/suppressed-template.yaml");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/s3_bucket_encrypted_rule.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        for engine in [None, Some("-E")] {
+            let mut args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
+            args.extend(engine);
+            assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), args.clone()));
+
+            args.push("--no-suppressions");
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+        }
+    }
+
+    #[test]
+    fn test_baseline_only_fails_new_failures() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/data-dir/s3-public-read-prohibited-template-non-compliant.yaml");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/rules-dir/s3_bucket_logging_enabled.guard");
+        let other_data_arg = utils::get_full_path_for_resource_file("resources/data-dir/s3-server-side-encryption-template-non-compliant.yaml");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        let dir = std::env::temp_dir().join(format!("cfn-guard-baseline-{}", std::process::id()));
+        for (engine, name) in [(None, "baseline.json"), (Some("-E"), "previous-engine-baseline.json")] {
+            let baseline_file = dir.join(name);
+            let baseline_arg = baseline_file.to_str().unwrap();
+            let mut args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, "--baseline", baseline_arg];
+            args.extend(engine);
+
+            let mut write = args.clone();
+            write.push("--write-baseline");
+            assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), write));
+            let baseline: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&baseline_file).unwrap()).unwrap();
+            assert!(!baseline["findings"].as_array().unwrap().is_empty());
+
+            assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), args.clone()));
+
+            //
+            // failures in another data file are not in the baseline
+            //
+            args[2] = &other_data_arg;
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_incremental_reuses_cached_results() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/data-dir/s3-public-read-prohibited-template-non-compliant.yaml");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/rules-dir/s3_bucket_logging_enabled.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        let dir = std::env::temp_dir().join(format!("cfn-guard-cache-{}", std::process::id()));
+        let dir_arg = dir.to_str().unwrap();
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, "-E", "--incremental", "--cache-dir", dir_arg];
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args.clone()));
+        let entries = std::fs::read_dir(&dir).unwrap().map(|each| each.unwrap().path()).collect::<Vec<_>>();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args.clone()));
+
+        //
+        // the second run reported from the entry, a passing entry for the same files passes
+        //
+        let mut entry: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&entries[0]).unwrap()).unwrap();
+        entry["status"] = serde_json::json!("PASS");
+        entry["tree"][0]["status"] = serde_json::json!("PASS");
+        entry["tree"][0]["children"] = serde_json::json!([]);
+        std::fs::write(&entries[0], entry.to_string()).unwrap();
+        assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), args.clone()));
+
+        //
+        // an expired entry is evaluated again
+        //
+        entry["created"] = serde_json::json!(0);
+        std::fs::write(&entries[0], entry.to_string()).unwrap();
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args.clone()));
+
+        let mut ttl = args.clone();
+        ttl.extend(["--cache-ttl", "0"]);
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), ttl));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_tags_select_rules() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/data-dir/s3-public-read-prohibited-template-non-compliant.yaml");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/tagged_s3_rules.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        for engine in [None, Some("-E")] {
+            let mut args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
+            args.extend(engine);
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args.clone()));
+
+            let mut encryption = args.clone();
+            encryption.extend(["--tags", "encryption", "--report-by", "tag"]);
+            assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), encryption));
+
+            let mut no_logging = args.clone();
+            no_logging.extend(["--tags", "s3", "--exclude-tags", "logging"]);
+            assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), no_logging));
+
+            let mut unknown = args.clone();
+            unknown.extend(["--tags", "pci"]);
+            assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), unknown));
+
+            //
+            // s3_bucket_versioned is tagged with a # @tags: comment, + needs all of the tags
+            //
+            let mut versioned = args.clone();
+            versioned.extend(["--tags", "s3+versioning"]);
+            assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), versioned));
+
+            let mut all_of = args.clone();
+            all_of.extend(["--tags", "versioning+logging"]);
+            assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), all_of));
+
+            let mut any_of = args.clone();
+            any_of.extend(["--tags", "versioning+s3,logging"]);
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), any_of));
+
+            args.extend(["--tags", "logging,encryption", "--report-by", "tag"]);
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+        }
+    }
+
+    #[test]
+    fn test_schema_lints_rules_without_data() {
+        let rules_option = format!("-{}", RULES.1);
+        let lint_errors = utils::get_full_path_for_resource_file("resources/lint_errors.guard");
+        let args = vec![VALIDATE, &rules_option, &lint_errors, "--schema"];
+        assert_eq!(7, utils::cfn_guard_test_command(Validate::new(), args));
+
+        //
+        // undefined variables become warnings, the undefined rule and broken regexes still fail
+        //
+        let args = vec![VALIDATE, &rules_option, &lint_errors, "--schema", "--allow-undefined-variables", "-o", "json"];
+        assert_eq!(7, utils::cfn_guard_test_command(Validate::new(), args));
+
+        let clean = utils::get_full_path_for_resource_file("resources/tagged_s3_rules.guard");
+        let args = vec![VALIDATE, &rules_option, &clean, "--schema"];
+        assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), args));
+    }
+
+    #[test]
+    fn test_parameters_file_resolves_refs() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/parameterized-instance-template.yaml");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/instance_type_rule.guard");
+        let parameters_arg = utils::get_full_path_for_resource_file("resources/instance-parameters.json");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args.clone()));
+
+        //
+        // the Default t3.micro and no value for Env, Fn::FindInMap is left as is
+        //
+        let mut defaults = args.clone();
+        defaults.push("--resolve-parameters");
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), defaults));
+
+        for engine in [None, Some("-E")] {
+            let mut args = args.clone();
+            args.extend(engine);
+            let mut from_file = args.clone();
+            from_file.extend(["--resolve-parameters", "--parameters-file", &parameters_arg]);
+            assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), from_file.clone()));
+
+            from_file.extend(["--parameter", "Env=dev"]);
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), from_file));
+        }
+    }
+
+    #[test]
+    fn test_missing_as_for_empty_on_absent_key() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/s3-server-side-encryption-template-non-compliant-2.yaml");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/s3_bucket_no_tags_rule.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        for engine in [None, Some("-E")] {
+            let mut args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
+            args.extend(engine);
+            //
+            // MyBucket has no Tags, skipped by default
+            //
+            assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), args.clone()));
+
+            let mut fail = args.clone();
+            fail.extend(["--missing-as", "fail"]);
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), fail));
+        }
+    }
+
+    #[test]
+    fn test_string_order_semver() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/layer_versions.json");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/layer_version_rule.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        for engine in [None, Some("-E")] {
+            let mut args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
+            args.extend(engine);
+            //
+            // "1.10.0" sorts before "1.9.0" character by character
+            //
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args.clone()));
+
+            let mut semver = args.clone();
+            semver.extend(["--string-order", "semver"]);
+            assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), semver));
+        }
+    }
+
+    #[test]
+    fn test_root_path_evaluates_subtree() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/s3-server-side-encryption-template-non-compliant-2.yaml");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/resources_subtree_rule.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        for engine in [None, Some("-E")] {
+            let mut args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
+            args.extend(engine);
+            //
+            // against the whole template the rule selects no buckets and skips
+            //
+            assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), args.clone()));
+
+            let mut subtree = args.clone();
+            subtree.extend(["--root-path", "/Resources"]);
+            assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), subtree));
+
+            let mut missing = args.clone();
+            missing.extend(["--root-path", "/Outputs"]);
+            assert_eq!(-1, utils::cfn_guard_test_command(Validate::new(), missing));
+        }
+    }
+
+    #[test]
+    fn test_assert_all_pass_fails_skipped_rules() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/tf-s3-buckets-plan.json");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/tf_s3_bucket_encryption_rule.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+
+        //
+        // No aws_s3_bucket key in the plan as is, the only rule is skipped
+        //
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
+        assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), args));
+
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, "--assert-all-pass"];
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg, "--assert-all-pass", "-E"];
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+    }
+
+    #[test]
+    fn test_type_coercion_is_opt_in() {
+        let data_arg = utils::get_full_path_for_resource_file("resources/string_typed_data.json");
+        let rules_arg = utils::get_full_path_for_resource_file("resources/string_typed_rule.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+
+        let args = vec![
+            VALIDATE,
+            &data_option,
+            &data_arg,
+            &rules_option,
+            &rules_arg,
+            "--type-coercion",
+        ];
+        assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), args));
+    }
+
+    #[test]
+    fn test_data_format_json_rejects_yaml_only_syntax() {
+        let data_arg =
+            utils::get_full_path_for_resource_file("resources/single-quoted-template.json");
+        let rules_arg =
+            utils::get_full_path_for_resource_file("resources/s3_bucket_server_side_encryption_enabled_2.guard");
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
+        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+
+        let args = vec![
+            VALIDATE,
+            &data_option,
+            &data_arg,
+            &rules_option,
+            &rules_arg,
+            "--data-format",
+            "json",
+        ];
+        assert_eq!(3, utils::cfn_guard_test_command(Validate::new(), args));
+    }
+
     #[test]
     fn test_single_data_file_single_rules_file_input_parameters_dir() {
         let data_arg = utils::get_full_path_for_resource_file("resources/db_resource.yaml");
@@ -460,6 +1199,33 @@ mod tests {
         assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), args));
     }
 
+    #[test]
+    fn test_input_parameters_dir_merged_in_file_name_order() {
+        let dir = std::env::temp_dir().join(format!("cfn-guard-input-parameters-{}", std::process::id()));
+        let params_dir = dir.join("params");
+        std::fs::create_dir_all(&params_dir).unwrap();
+        std::fs::write(params_dir.join("b_params.yaml"), "port: 5432\n").unwrap();
+        std::fs::write(params_dir.join("a_params.yaml"), "port: 3306\n").unwrap();
+        let data_file = dir.join("template.yaml");
+        std::fs::write(&data_file, "Resources: {}\n").unwrap();
+        let rules_file = dir.join("rules.guard");
+        std::fs::write(&rules_file, "rule later_file_wins { %port == 5432 }\n").unwrap();
+        let data_option = format!("-{}", DATA.1);
+        let rules_option = format!("-{}", RULES.1);
+        let input_parameters_option = format!("-{}", INPUT_PARAMETERS.1);
+        let args = vec![
+            VALIDATE,
+            &data_option,
+            data_file.to_str().unwrap(),
+            &rules_option,
+            rules_file.to_str().unwrap(),
+            &input_parameters_option,
+            params_dir.to_str().unwrap(),
+        ];
+        assert_eq!(0, utils::cfn_guard_test_command(Validate::new(), args));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_single_data_file_malformed_rules_file() {
         let data_arg = utils::get_full_path_for_resource_file(
@@ -482,8 +1248,8 @@ mod tests {
         let data_option = format!("-{}", DATA.1);
         let rules_option = format!("-{}", RULES.1);
         let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
-        // -1 status code equates to Error being thrown
-        assert_eq!(-1, utils::cfn_guard_test_command(Validate::new(), args));
+        // 3 status code equates to a data file that cannot be read or parsed
+        assert_eq!(3, utils::cfn_guard_test_command(Validate::new(), args));
     }
 
     #[test]
@@ -505,13 +1271,13 @@ mod tests {
             &input_parameters_option,
             &input_parameters_arg,
         ];
-        // -1 status code equates to Error being thrown
-        assert_eq!(-1, utils::cfn_guard_test_command(Validate::new(), args));
+        // 3 status code equates to a data file that cannot be read or parsed
+        assert_eq!(3, utils::cfn_guard_test_command(Validate::new(), args));
     }
 
     #[test]
     fn test_single_data_file_blank_rules_file() {
-        // The parsing exits with status code 5 = FAIL for allowing other rules to get evaluated even when one of them fails to get parsed
+        // The parsing exits with status code 7 after allowing other rules to get evaluated even when one of them fails to get parsed
         let data_arg = utils::get_full_path_for_resource_file(
             "resources/s3-server-side-encryption-template-non-compliant-2.yaml",
         );
@@ -519,12 +1285,12 @@ mod tests {
         let data_option = format!("-{}", DATA.1);
         let rules_option = format!("-{}", RULES.1);
         let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
-        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+        assert_eq!(7, utils::cfn_guard_test_command(Validate::new(), args));
     }
 
     #[test]
     fn test_single_data_file_blank_and_valid_rules_file() {
-        // The parsing exits with status code 5 = FAIL for allowing other rules to get evaluated even when one of them fails to get parsed
+        // The parsing exits with status code 7 after allowing other rules to get evaluated even when one of them fails to get parsed
         let data_arg = utils::get_full_path_for_resource_file(
             "resources/s3-server-side-encryption-template-non-compliant-2.yaml",
         );
@@ -543,7 +1309,7 @@ mod tests {
             &rules_option,
             &rules_arg2,
         ];
-        assert_eq!(5, utils::cfn_guard_test_command(Validate::new(), args));
+        assert_eq!(7, utils::cfn_guard_test_command(Validate::new(), args));
     }
 
     #[test]
@@ -555,8 +1321,8 @@ mod tests {
         let data_option = format!("-{}", DATA.1);
         let rules_option = format!("-{}", RULES.1);
         let args = vec![VALIDATE, &data_option, &data_arg, &rules_option, &rules_arg];
-        // -1 status code equates to Error being thrown
-        assert_eq!(-1, utils::cfn_guard_test_command(Validate::new(), args));
+        // 3 status code equates to a data file that cannot be read or parsed
+        assert_eq!(3, utils::cfn_guard_test_command(Validate::new(), args));
     }
 
     #[test]
@@ -579,8 +1345,8 @@ mod tests {
             &rules_option,
             &rules_arg,
         ];
-        // -1 status code equates to Error being thrown
-        assert_eq!(-1, utils::cfn_guard_test_command(Validate::new(), args));
+        // 3 status code equates to a data file that cannot be read or parsed
+        assert_eq!(3, utils::cfn_guard_test_command(Validate::new(), args));
     }
 
     #[test]
@@ -602,8 +1368,8 @@ mod tests {
             &input_parameters_option,
             &input_parameters_arg,
         ];
-        // -1 status code equates to Error being thrown
-        assert_eq!(-1, utils::cfn_guard_test_command(Validate::new(), args));
+        // 3 status code equates to a data file that cannot be read or parsed
+        assert_eq!(3, utils::cfn_guard_test_command(Validate::new(), args));
     }
 
     #[test]
@@ -629,8 +1395,8 @@ mod tests {
             &input_parameters_option,
             &input_parameters_arg2,
         ];
-        // -1 status code equates to Error being thrown
-        assert_eq!(-1, utils::cfn_guard_test_command(Validate::new(), args));
+        // 3 status code equates to a data file that cannot be read or parsed
+        assert_eq!(3, utils::cfn_guard_test_command(Validate::new(), args));
     }
 
     #[test]
@@ -648,7 +1414,8 @@ mod tests {
             let data_option = &format!("-{}", DATA.1);
             let rules_option = &format!("-{}", RULES.1);
             let args = vec![VALIDATE, data_option, &arg.0, rules_option, &arg.1];
-            assert_eq!(-1, utils::cfn_guard_test_command(Validate::new(), args))
+            // 3 status code equates to a file that cannot be found or read
+            assert_eq!(3, utils::cfn_guard_test_command(Validate::new(), args))
         }
     }
 }
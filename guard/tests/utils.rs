@@ -8,7 +8,7 @@ use cfn_guard::commands::validate::Validate;
 use std::collections::HashMap;
 use clap::App;
 use cfn_guard::command::Command;
-use cfn_guard::commands::{DATA, RULES};
+use cfn_guard::commands::{DATA, RULES, exit_code_for_error};
 use cfn_guard::commands::test::Test;
 
 pub fn get_data_option() -> String {
@@ -67,7 +67,7 @@ pub fn cfn_guard_test_command<T: Command>(command: T, args: Vec<&str>) -> i32 {
                 match (*command).execute(value) {
                     Err(e) => {
                         println!("Error occurred {}", e);
-                        -1
+                        exit_code_for_error(&e)
                     },
                     Ok(code) => {
                         code